@@ -0,0 +1,131 @@
+//! The equivalent of ASM's `BasicInterpreter`, which only tells apart the kinds of values the JVM has,
+//! so every reference is just a `Reference`, whatever its class.
+
+use crate::*;
+use crate::analysis::{self, Interpreter, Problem};
+use crate::bytecode::{Instruction, Operand};
+use crate::descriptor::FieldType;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum BasicValue {
+	/// Locals that haven't been set, the second slot of longs and doubles,
+	/// and anything where paths with different kinds of values meet.
+	Uninitialised,
+	Int,
+	Float,
+	Long,
+	Double,
+	Reference,
+	ReturnAddress,
+}
+
+impl BasicValue {
+	/// Booleans, bytes, chars and shorts are all ints, as far as the JVM is concerned.
+	pub fn of(field_type: &FieldType) -> BasicValue {
+		match field_type {
+			FieldType::Boolean | FieldType::Byte | FieldType::Char | FieldType::Short | FieldType::Int => BasicValue::Int,
+			FieldType::Float => BasicValue::Float,
+			FieldType::Long => BasicValue::Long,
+			FieldType::Double => BasicValue::Double,
+			FieldType::Object(_) | FieldType::Array(_) => BasicValue::Reference,
+		}
+	}
+
+	pub fn size(self) -> usize {
+		match self {
+			BasicValue::Long | BasicValue::Double => 2,
+			_ => 1,
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Default)]
+pub struct BasicInterpreter;
+
+impl Interpreter for BasicInterpreter {
+	type Value = BasicValue;
+
+	fn size(&self, value: &BasicValue) -> usize {
+		value.size()
+	}
+
+	fn uninitialised(&self) -> BasicValue {
+		BasicValue::Uninitialised
+	}
+
+	fn this(&self, _class_name: &str) -> BasicValue {
+		BasicValue::Reference
+	}
+
+	fn parameter(&self, field_type: &FieldType) -> BasicValue {
+		BasicValue::of(field_type)
+	}
+
+	fn exception(&self, _catch_type: Option<&str>) -> BasicValue {
+		BasicValue::Reference
+	}
+
+	fn new_operation(&self, instruction: &Instruction, cp: &ConstantPool) -> Result<BasicValue, Problem> {
+		let value = match instruction.opcode {
+			ACONST_NULL | NEW => BasicValue::Reference,
+			ICONST_M1..=ICONST_5 | BIPUSH | SIPUSH => BasicValue::Int,
+			LCONST_0 | LCONST_1 => BasicValue::Long,
+			FCONST_0..=FCONST_2 => BasicValue::Float,
+			DCONST_0 | DCONST_1 => BasicValue::Double,
+			LDC | LDC_W | LDC2_W => match instruction.operand {
+				Operand::Constant(index) => BasicValue::of(&analysis::constant_type(cp, index)?),
+				_ => return Err(Problem::InvalidOperand),
+			},
+			GETSTATIC => match instruction.operand {
+				Operand::Constant(index) => BasicValue::of(&analysis::field_type(cp, index)?),
+				_ => return Err(Problem::InvalidOperand),
+			},
+			JSR | JSR_W => BasicValue::ReturnAddress,
+			opcode => return Err(Problem::IllegalInstruction(opcode)),
+		};
+		Ok(value)
+	}
+
+	fn unary_operation(&self, instruction: &Instruction, _value: &BasicValue, cp: &ConstantPool) -> Result<Option<BasicValue>, Problem> {
+		let value = match instruction.opcode {
+			INEG | IINC | L2I | F2I | D2I | I2B | I2C | I2S | ARRAYLENGTH | INSTANCEOF => BasicValue::Int,
+			FNEG | I2F | L2F | D2F => BasicValue::Float,
+			LNEG | I2L | F2L | D2L => BasicValue::Long,
+			DNEG | I2D | L2D | F2D => BasicValue::Double,
+			NEWARRAY | ANEWARRAY | CHECKCAST => BasicValue::Reference,
+			GETFIELD => match instruction.operand {
+				Operand::Constant(index) => BasicValue::of(&analysis::field_type(cp, index)?),
+				_ => return Err(Problem::InvalidOperand),
+			},
+			_ => return Ok(None),
+		};
+		Ok(Some(value))
+	}
+
+	fn binary_operation(&self, instruction: &Instruction, _first: &BasicValue, _second: &BasicValue) -> Result<Option<BasicValue>, Problem> {
+		let value = match instruction.opcode {
+			IALOAD | BALOAD | CALOAD | SALOAD | LCMP..=DCMPG => BasicValue::Int,
+			IADD | ISUB | IMUL | IDIV | IREM | ISHL | ISHR | IUSHR | IAND | IOR | IXOR => BasicValue::Int,
+			FALOAD | FADD | FSUB | FMUL | FDIV | FREM => BasicValue::Float,
+			LALOAD | LADD | LSUB | LMUL | LDIV | LREM | LSHL | LSHR | LUSHR | LAND | LOR | LXOR => BasicValue::Long,
+			DALOAD | DADD | DSUB | DMUL | DDIV | DREM => BasicValue::Double,
+			AALOAD => BasicValue::Reference,
+			_ => return Ok(None),
+		};
+		Ok(Some(value))
+	}
+
+	fn nary_operation(&self, instruction: &Instruction, _values: &[BasicValue], cp: &ConstantPool) -> Result<Option<BasicValue>, Problem> {
+		match instruction.operand {
+			Operand::MultiANewArray { .. } => Ok(Some(BasicValue::Reference)),
+			Operand::Constant(index) | Operand::InvokeInterface { index, .. } => {
+				Ok(analysis::return_type(cp, instruction.opcode, index)?.as_ref().map(BasicValue::of))
+			}
+			_ => Err(Problem::InvalidOperand),
+		}
+	}
+
+	fn merge(&self, first: &BasicValue, second: &BasicValue) -> BasicValue {
+		if first == second { *first } else { BasicValue::Uninitialised }
+	}
+}
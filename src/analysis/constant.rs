@@ -0,0 +1,224 @@
+//! Constant propagation for ints, longs, floats and doubles.
+//!
+//! Arithmetic, comparisons and conversions on constants are folded the way the JVM would do them,
+//! overflow and all, except for integer division by zero, which throws rather than producing anything.
+
+use std::cmp::Ordering;
+
+use crate::*;
+use crate::analysis::{Interpreter, Problem};
+use crate::analysis::basic::{BasicInterpreter, BasicValue};
+use crate::bytecode::{Instruction, Operand};
+use crate::descriptor::FieldType;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ConstantValue {
+	/// Not a constant, or not the same constant on every path.
+	Unknown(BasicValue),
+	Int(i32),
+	Long(i64),
+	Float(f32),
+	Double(f64),
+}
+
+impl ConstantValue {
+	pub fn basic(self) -> BasicValue {
+		match self {
+			ConstantValue::Unknown(value) => value,
+			ConstantValue::Int(_) => BasicValue::Int,
+			ConstantValue::Long(_) => BasicValue::Long,
+			ConstantValue::Float(_) => BasicValue::Float,
+			ConstantValue::Double(_) => BasicValue::Double,
+		}
+	}
+}
+
+/// Floats and doubles compare by their bits, so NaN is the same constant as itself, and `0.0` isn't `-0.0`.
+impl PartialEq for ConstantValue {
+	fn eq(&self, other: &ConstantValue) -> bool {
+		match (*self, *other) {
+			(ConstantValue::Unknown(first), ConstantValue::Unknown(second)) => first == second,
+			(ConstantValue::Int(first), ConstantValue::Int(second)) => first == second,
+			(ConstantValue::Long(first), ConstantValue::Long(second)) => first == second,
+			(ConstantValue::Float(first), ConstantValue::Float(second)) => first.to_bits() == second.to_bits(),
+			(ConstantValue::Double(first), ConstantValue::Double(second)) => first.to_bits() == second.to_bits(),
+			_ => false,
+		}
+	}
+}
+
+impl Eq for ConstantValue {}
+
+fn ordering(ordering: Ordering) -> i32 {
+	match ordering {
+		Ordering::Less => -1,
+		Ordering::Equal => 0,
+		Ordering::Greater => 1,
+	}
+}
+
+/// `fcmpl` and `dcmpl` push -1 if either is NaN, `fcmpg` and `dcmpg` push 1.
+fn compare<T: PartialOrd>(first: T, second: T, nan: i32) -> i32 {
+	first.partial_cmp(&second).map_or(nan, ordering)
+}
+
+fn ldc(cp: &ConstantPool, index: u16) -> Option<ConstantValue> {
+	let value = match cp.entries.get((index as usize).checked_sub(1)?)? {
+		CPEntry::Integer(info) => ConstantValue::Int(info.value as i32),
+		CPEntry::Float(info) => ConstantValue::Float(f32::from_bits(info.value)),
+		CPEntry::Long(info) => ConstantValue::Long(((info.high_bytes as u64) << 32 | info.low_bytes as u64) as i64),
+		CPEntry::Double(info) => ConstantValue::Double(f64::from_bits((info.high_bytes as u64) << 32 | info.low_bytes as u64)),
+		_ => return None,
+	};
+	Some(value)
+}
+
+fn unary(instruction: &Instruction, value: ConstantValue) -> Option<ConstantValue> {
+	use self::ConstantValue::*;
+
+	let result = match (instruction.opcode, value) {
+		(INEG, Int(value)) => Int(value.wrapping_neg()),
+		(IINC, Int(value)) => match instruction.operand {
+			Operand::Iinc { value: increment, .. } => Int(value.wrapping_add(increment as i32)),
+			_ => return None,
+		},
+		(LNEG, Long(value)) => Long(value.wrapping_neg()),
+		(FNEG, Float(value)) => Float(-value),
+		(DNEG, Double(value)) => Double(-value),
+		(I2L, Int(value)) => Long(value as i64),
+		(I2F, Int(value)) => Float(value as f32),
+		(I2D, Int(value)) => Double(value as f64),
+		(L2I, Long(value)) => Int(value as i32),
+		(L2F, Long(value)) => Float(value as f32),
+		(L2D, Long(value)) => Double(value as f64),
+		// Casting saturates and turns NaN into 0, just like the JVM.
+		(F2I, Float(value)) => Int(value as i32),
+		(F2L, Float(value)) => Long(value as i64),
+		(F2D, Float(value)) => Double(value as f64),
+		(D2I, Double(value)) => Int(value as i32),
+		(D2L, Double(value)) => Long(value as i64),
+		(D2F, Double(value)) => Float(value as f32),
+		(I2B, Int(value)) => Int(value as i8 as i32),
+		(I2C, Int(value)) => Int(value as u16 as i32),
+		(I2S, Int(value)) => Int(value as i16 as i32),
+		_ => return None,
+	};
+	Some(result)
+}
+
+fn binary(opcode: u8, first: ConstantValue, second: ConstantValue) -> Option<ConstantValue> {
+	use self::ConstantValue::*;
+
+	let result = match (opcode, first, second) {
+		(IADD, Int(a), Int(b)) => Int(a.wrapping_add(b)),
+		(ISUB, Int(a), Int(b)) => Int(a.wrapping_sub(b)),
+		(IMUL, Int(a), Int(b)) => Int(a.wrapping_mul(b)),
+		(IDIV, Int(a), Int(b)) if b != 0 => Int(a.wrapping_div(b)),
+		(IREM, Int(a), Int(b)) if b != 0 => Int(a.wrapping_rem(b)),
+		(ISHL, Int(a), Int(b)) => Int(a.wrapping_shl(b as u32)),
+		(ISHR, Int(a), Int(b)) => Int(a.wrapping_shr(b as u32)),
+		(IUSHR, Int(a), Int(b)) => Int((a as u32).wrapping_shr(b as u32) as i32),
+		(IAND, Int(a), Int(b)) => Int(a & b),
+		(IOR, Int(a), Int(b)) => Int(a | b),
+		(IXOR, Int(a), Int(b)) => Int(a ^ b),
+		(LADD, Long(a), Long(b)) => Long(a.wrapping_add(b)),
+		(LSUB, Long(a), Long(b)) => Long(a.wrapping_sub(b)),
+		(LMUL, Long(a), Long(b)) => Long(a.wrapping_mul(b)),
+		(LDIV, Long(a), Long(b)) if b != 0 => Long(a.wrapping_div(b)),
+		(LREM, Long(a), Long(b)) if b != 0 => Long(a.wrapping_rem(b)),
+		(LSHL, Long(a), Int(b)) => Long(a.wrapping_shl(b as u32)),
+		(LSHR, Long(a), Int(b)) => Long(a.wrapping_shr(b as u32)),
+		(LUSHR, Long(a), Int(b)) => Long((a as u64).wrapping_shr(b as u32) as i64),
+		(LAND, Long(a), Long(b)) => Long(a & b),
+		(LOR, Long(a), Long(b)) => Long(a | b),
+		(LXOR, Long(a), Long(b)) => Long(a ^ b),
+		(FADD, Float(a), Float(b)) => Float(a + b),
+		(FSUB, Float(a), Float(b)) => Float(a - b),
+		(FMUL, Float(a), Float(b)) => Float(a * b),
+		(FDIV, Float(a), Float(b)) => Float(a / b),
+		(FREM, Float(a), Float(b)) => Float(a % b),
+		(DADD, Double(a), Double(b)) => Double(a + b),
+		(DSUB, Double(a), Double(b)) => Double(a - b),
+		(DMUL, Double(a), Double(b)) => Double(a * b),
+		(DDIV, Double(a), Double(b)) => Double(a / b),
+		(DREM, Double(a), Double(b)) => Double(a % b),
+		(LCMP, Long(a), Long(b)) => Int(ordering(a.cmp(&b))),
+		(FCMPL, Float(a), Float(b)) => Int(compare(a, b, -1)),
+		(FCMPG, Float(a), Float(b)) => Int(compare(a, b, 1)),
+		(DCMPL, Double(a), Double(b)) => Int(compare(a, b, -1)),
+		(DCMPG, Double(a), Double(b)) => Int(compare(a, b, 1)),
+		_ => return None,
+	};
+	Some(result)
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Default)]
+pub struct ConstantInterpreter;
+
+impl Interpreter for ConstantInterpreter {
+	type Value = ConstantValue;
+
+	fn size(&self, value: &ConstantValue) -> usize {
+		value.basic().size()
+	}
+
+	fn uninitialised(&self) -> ConstantValue {
+		ConstantValue::Unknown(BasicValue::Uninitialised)
+	}
+
+	fn this(&self, class_name: &str) -> ConstantValue {
+		ConstantValue::Unknown(BasicInterpreter.this(class_name))
+	}
+
+	fn parameter(&self, field_type: &FieldType) -> ConstantValue {
+		ConstantValue::Unknown(BasicInterpreter.parameter(field_type))
+	}
+
+	fn exception(&self, catch_type: Option<&str>) -> ConstantValue {
+		ConstantValue::Unknown(BasicInterpreter.exception(catch_type))
+	}
+
+	fn new_operation(&self, instruction: &Instruction, cp: &ConstantPool) -> Result<ConstantValue, Problem> {
+		let value = match (instruction.opcode, &instruction.operand) {
+			(ICONST_M1..=ICONST_5, _) => ConstantValue::Int(instruction.opcode as i32 - ICONST_0 as i32),
+			(LCONST_0 | LCONST_1, _) => ConstantValue::Long((instruction.opcode - LCONST_0) as i64),
+			(FCONST_0..=FCONST_2, _) => ConstantValue::Float((instruction.opcode - FCONST_0) as f32),
+			(DCONST_0 | DCONST_1, _) => ConstantValue::Double((instruction.opcode - DCONST_0) as f64),
+			(BIPUSH, &Operand::Byte(value)) => ConstantValue::Int(value as i32),
+			(SIPUSH, &Operand::Short(value)) => ConstantValue::Int(value as i32),
+			(LDC | LDC_W | LDC2_W, &Operand::Constant(index)) => match ldc(cp, index) {
+				Some(value) => value,
+				None => ConstantValue::Unknown(BasicInterpreter.new_operation(instruction, cp)?),
+			},
+			_ => ConstantValue::Unknown(BasicInterpreter.new_operation(instruction, cp)?),
+		};
+		Ok(value)
+	}
+
+	fn unary_operation(&self, instruction: &Instruction, value: &ConstantValue, cp: &ConstantPool) -> Result<Option<ConstantValue>, Problem> {
+		match unary(instruction, *value) {
+			Some(value) => Ok(Some(value)),
+			None => Ok(BasicInterpreter.unary_operation(instruction, &value.basic(), cp)?.map(ConstantValue::Unknown)),
+		}
+	}
+
+	fn binary_operation(&self, instruction: &Instruction, first: &ConstantValue, second: &ConstantValue) -> Result<Option<ConstantValue>, Problem> {
+		match binary(instruction.opcode, *first, *second) {
+			Some(value) => Ok(Some(value)),
+			None => Ok(BasicInterpreter.binary_operation(instruction, &first.basic(), &second.basic())?.map(ConstantValue::Unknown)),
+		}
+	}
+
+	fn nary_operation(&self, instruction: &Instruction, values: &[ConstantValue], cp: &ConstantPool) -> Result<Option<ConstantValue>, Problem> {
+		let values: Vec<BasicValue> = values.iter().map(|value| value.basic()).collect();
+		Ok(BasicInterpreter.nary_operation(instruction, &values, cp)?.map(ConstantValue::Unknown))
+	}
+
+	fn merge(&self, first: &ConstantValue, second: &ConstantValue) -> ConstantValue {
+		if first == second {
+			*first
+		} else {
+			ConstantValue::Unknown(BasicInterpreter.merge(&first.basic(), &second.basic()))
+		}
+	}
+}
@@ -0,0 +1,551 @@
+//! A forward data-flow framework over decoded instructions, along the lines of ASM's `Analyzer`.
+//!
+//! The framework takes care of the stack and the locals, of following branches, switches and exception handlers,
+//! and of iterating until nothing changes, while an `Interpreter` decides what the values are,
+//! what each instruction makes of them, and how two of them merge where paths meet.
+//!
+//! There are a few ready-made interpreters: `basic` tracks roughly what the JVM's own types would be,
+//! `constant` propagates constants, and `nullness` tracks which references are definitely null or not.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug};
+
+use crate::*;
+use crate::attr::Code;
+use crate::bytecode::{self, DecodeError, Instruction, Operand};
+use crate::descriptor::{FieldType, MethodDescriptor};
+use crate::maxs;
+use crate::resolve::Resolve;
+use crate::utf8::MStrExt;
+use crate::verify::Location;
+
+pub mod basic;
+pub mod constant;
+pub mod nullness;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Problem {
+	Decode(DecodeError),
+	/// There's no method at the given index.
+	MissingMethod,
+	/// The method's descriptor couldn't be parsed.
+	InvalidDescriptor,
+	/// A branch or handler that doesn't point at the start of an instruction.
+	InvalidTarget(u32),
+	InvalidConstant(u16),
+	/// The `newarray` type or the `multianewarray` dimensions are invalid.
+	InvalidOperand,
+	/// An instruction the interpreter has no idea what to do with.
+	IllegalInstruction(u8),
+	StackUnderflow,
+	/// The stack grew beyond `max_stack`.
+	StackOverflow(u16),
+	/// The local variable doesn't exist.
+	InvalidLocal(u16),
+	/// An instruction that needs a value of the given category, 1 or 2, found one of the other.
+	WrongCategory(u8),
+	/// Two paths meet with a different number of slots on the stack.
+	StackHeightMismatch {
+		first: usize,
+		second: usize,
+	},
+	FallsOffEnd,
+}
+
+impl fmt::Display for Problem {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Problem::Decode(error) => write!(f, "{}", error),
+			Problem::MissingMethod => f.write_str("no such method"),
+			Problem::InvalidDescriptor => f.write_str("invalid method descriptor"),
+			Problem::InvalidTarget(pc) => write!(f, "{} is not the start of an instruction", pc),
+			Problem::InvalidConstant(index) => write!(f, "invalid constant #{}", index),
+			Problem::InvalidOperand => f.write_str("invalid operand"),
+			Problem::IllegalInstruction(opcode) => {
+				write!(f, "unexpected {}", bytecode::mnemonic(*opcode).unwrap_or("???"))
+			}
+			Problem::StackUnderflow => f.write_str("stack underflow"),
+			Problem::StackOverflow(max_stack) => write!(f, "stack exceeds max_stack of {}", max_stack),
+			Problem::InvalidLocal(index) => write!(f, "invalid local variable {}", index),
+			Problem::WrongCategory(expected) => write!(f, "expected a category {} value", expected),
+			Problem::StackHeightMismatch { first, second } => {
+				write!(f, "stack is {} slots high on one path, but {} on another", first, second)
+			}
+			Problem::FallsOffEnd => f.write_str("execution falls off the end of the code"),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct AnalysisError {
+	pub location: Location,
+	/// The offset of the failing instruction, if the problem is with one.
+	pub pc: Option<u32>,
+	pub problem: Problem,
+}
+
+impl fmt::Display for AnalysisError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.pc {
+			Some(pc) => write!(f, "{}, pc {}: {}", self.location, pc, self.problem),
+			None => write!(f, "{}: {}", self.location, self.problem),
+		}
+	}
+}
+
+/// What the values are, and what instructions do to them.
+///
+/// The framework handles loads, stores and stack shuffling such as `dup` and `swap` by itself,
+/// only asking for copies, and hands everything else over to one of the `*_operation` methods,
+/// depending on how many values the instruction pops.
+/// Those return `None` when the instruction doesn't push anything, such as for `ifeq` or `putfield`.
+pub trait Interpreter {
+	type Value: Clone + PartialEq + Debug;
+
+	/// How many slots the value takes up, 2 for longs and doubles, and 1 for everything else.
+	fn size(&self, value: &Self::Value) -> usize;
+
+	/// The value of a local that hasn't been set, and of the second slot of a long or double.
+	fn uninitialised(&self) -> Self::Value;
+
+	/// `this`, in instance methods.
+	fn this(&self, class_name: &str) -> Self::Value;
+
+	fn parameter(&self, field_type: &FieldType) -> Self::Value;
+
+	/// The exception on the stack at the start of a handler, `None` for handlers that catch anything.
+	fn exception(&self, catch_type: Option<&str>) -> Self::Value;
+
+	/// Instructions that push a value without popping any, such as constants, `getstatic`, `new` and `jsr`.
+	fn new_operation(&self, instruction: &Instruction, cp: &ConstantPool) -> Result<Self::Value, Problem>;
+
+	/// Loads, stores, and the extra values pushed by `dup` and friends.
+	fn copy_operation(&self, _instruction: &Instruction, value: &Self::Value) -> Self::Value {
+		value.clone()
+	}
+
+	/// Instructions that pop one value, including `iinc`, which gets the local instead.
+	fn unary_operation(&self, instruction: &Instruction, value: &Self::Value, cp: &ConstantPool) -> Result<Option<Self::Value>, Problem>;
+
+	fn binary_operation(&self, instruction: &Instruction, first: &Self::Value, second: &Self::Value) -> Result<Option<Self::Value>, Problem>;
+
+	/// Only the array stores, which don't push anything.
+	fn ternary_operation(&self, _instruction: &Instruction, _first: &Self::Value, _second: &Self::Value, _third: &Self::Value) -> Result<(), Problem> {
+		Ok(())
+	}
+
+	/// Invocations and `multianewarray`, with the values in the order they were pushed, receiver first.
+	fn nary_operation(&self, instruction: &Instruction, values: &[Self::Value], cp: &ConstantPool) -> Result<Option<Self::Value>, Problem>;
+
+	/// The value that covers both, where two paths meet.
+	fn merge(&self, first: &Self::Value, second: &Self::Value) -> Self::Value;
+}
+
+/// The values before an instruction runs.
+///
+/// Longs and doubles take up a single entry on the stack, but two in the locals, the second being `uninitialised`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame<V> {
+	pub locals: Vec<V>,
+	pub stack: Vec<V>,
+}
+
+/// The type of the field a `getfield`, `putfield`, `getstatic` or `putstatic` refers to.
+fn field_type(cp: &ConstantPool, index: u16) -> Result<FieldType, Problem> {
+	cp.resolve(CPIndex::<FieldRefInfo>::new(index))
+		.and_then(|field| FieldType::parse(&field.descriptor.decoded()))
+		.ok_or(Problem::InvalidConstant(index))
+}
+
+/// What the method an invoke instruction calls returns.
+fn return_type(cp: &ConstantPool, opcode: u8, index: u16) -> Result<Option<FieldType>, Problem> {
+	maxs::invoked_descriptor(cp, opcode, index)
+		.map(|descriptor| descriptor.return_type)
+		.ok_or(Problem::InvalidConstant(index))
+}
+
+/// The type of the constant an `ldc`, `ldc_w` or `ldc2_w` loads, with `Object` for any kind of reference.
+fn constant_type(cp: &ConstantPool, index: u16) -> Result<FieldType, Problem> {
+	let entry = (index as usize).checked_sub(1)
+		.and_then(|i| cp.entries.get(i))
+		.ok_or(Problem::InvalidConstant(index))?;
+	let field_type = match entry {
+		CPEntry::Integer(_) => FieldType::Int,
+		CPEntry::Float(_) => FieldType::Float,
+		CPEntry::Long(_) => FieldType::Long,
+		CPEntry::Double(_) => FieldType::Double,
+		CPEntry::String(_) => FieldType::Object("java/lang/String".to_string()),
+		CPEntry::Class(_) => FieldType::Object("java/lang/Class".to_string()),
+		CPEntry::MethodType(_) => FieldType::Object("java/lang/invoke/MethodType".to_string()),
+		CPEntry::MethodHandle(_) => FieldType::Object("java/lang/invoke/MethodHandle".to_string()),
+		CPEntry::Dynamic(info) => info.resolve(cp)
+			.and_then(|dynamic| FieldType::parse(&dynamic.descriptor.decoded()))
+			.ok_or(Problem::InvalidConstant(index))?,
+		_ => return Err(Problem::InvalidConstant(index)),
+	};
+	Ok(field_type)
+}
+
+fn constant_index(instruction: &Instruction) -> u16 {
+	match instruction.operand {
+		Operand::Constant(index) | Operand::InvokeInterface { index, .. } | Operand::MultiANewArray { index, .. } => index,
+		_ => 0,
+	}
+}
+
+struct Machine<'i, I: Interpreter> {
+	interpreter: &'i I,
+	max_stack: usize,
+}
+
+impl<'i, I: Interpreter> Machine<'i, I> {
+	fn pop(&self, frame: &mut Frame<I::Value>) -> Result<I::Value, Problem> {
+		frame.stack.pop().ok_or(Problem::StackUnderflow)
+	}
+
+	/// Pops a value that has to take up a single slot.
+	fn pop_single(&self, frame: &mut Frame<I::Value>) -> Result<I::Value, Problem> {
+		let value = self.pop(frame)?;
+		match self.interpreter.size(&value) {
+			1 => Ok(value),
+			_ => Err(Problem::WrongCategory(1)),
+		}
+	}
+
+	fn push(&self, frame: &mut Frame<I::Value>, value: I::Value) -> Result<(), Problem> {
+		frame.stack.push(value);
+		if self.height(frame) > self.max_stack {
+			return Err(Problem::StackOverflow(self.max_stack as u16));
+		}
+		Ok(())
+	}
+
+	fn push_all(&self, frame: &mut Frame<I::Value>, values: Vec<I::Value>) -> Result<(), Problem> {
+		for value in values {
+			self.push(frame, value)?;
+		}
+		Ok(())
+	}
+
+	/// The number of slots on the stack.
+	fn height(&self, frame: &Frame<I::Value>) -> usize {
+		frame.stack.iter()
+			.map(|value| self.interpreter.size(value))
+			.sum()
+	}
+
+	fn load(&self, frame: &Frame<I::Value>, index: u16) -> Result<I::Value, Problem> {
+		frame.locals.get(index as usize)
+			.cloned()
+			.ok_or(Problem::InvalidLocal(index))
+	}
+
+	fn store(&self, frame: &mut Frame<I::Value>, index: u16, value: I::Value) -> Result<(), Problem> {
+		let i = index as usize;
+		let size = self.interpreter.size(&value);
+		if i + size > frame.locals.len() {
+			return Err(Problem::InvalidLocal(index));
+		}
+		// Overwriting the second half of a long or double leaves the first half useless.
+		if i > 0 && self.interpreter.size(&frame.locals[i - 1]) == 2 {
+			frame.locals[i - 1] = self.interpreter.uninitialised();
+		}
+		frame.locals[i] = value;
+		if size == 2 {
+			frame.locals[i + 1] = self.interpreter.uninitialised();
+		}
+		Ok(())
+	}
+
+	/// Runs the instruction, turning the frame before it into the one after it.
+	fn execute(&self, instruction: &Instruction, frame: &mut Frame<I::Value>, cp: &ConstantPool) -> Result<(), Problem> {
+		let interpreter = self.interpreter;
+		let copy = |value: &I::Value| interpreter.copy_operation(instruction, value);
+		match instruction.opcode {
+			NOP | GOTO | GOTO_W | RETURN => {}
+			ACONST_NULL..=LDC2_W | GETSTATIC | NEW | JSR | JSR_W => {
+				let value = interpreter.new_operation(instruction, cp)?;
+				self.push(frame, value)?;
+			}
+			ILOAD..=ALOAD_3 => {
+				let index = match instruction.operand {
+					Operand::Local(index) => index,
+					_ => return Err(Problem::InvalidOperand),
+				};
+				let value = copy(&self.load(frame, index)?);
+				self.push(frame, value)?;
+			}
+			ISTORE..=ASTORE_3 => {
+				let index = match instruction.operand {
+					Operand::Local(index) => index,
+					_ => return Err(Problem::InvalidOperand),
+				};
+				let value = copy(&self.pop(frame)?);
+				self.store(frame, index, value)?;
+			}
+			IINC => {
+				let index = match instruction.operand {
+					Operand::Iinc { index, .. } => index,
+					_ => return Err(Problem::InvalidOperand),
+				};
+				let value = self.load(frame, index)?;
+				if let Some(value) = interpreter.unary_operation(instruction, &value, cp)? {
+					self.store(frame, index, value)?;
+				}
+			}
+			RET => {
+				let index = match instruction.operand {
+					Operand::Local(index) => index,
+					_ => return Err(Problem::InvalidOperand),
+				};
+				self.load(frame, index)?;
+			}
+			IASTORE..=SASTORE => {
+				let third = self.pop(frame)?;
+				let second = self.pop(frame)?;
+				let first = self.pop(frame)?;
+				interpreter.ternary_operation(instruction, &first, &second, &third)?;
+			}
+			POP => {
+				self.pop_single(frame)?;
+			}
+			POP2 => {
+				if self.interpreter.size(&self.pop(frame)?) == 1 {
+					self.pop_single(frame)?;
+				}
+			}
+			DUP => {
+				let value = self.pop_single(frame)?;
+				self.push_all(frame, vec![copy(&value), value])?;
+			}
+			DUP_X1 => {
+				let first = self.pop_single(frame)?;
+				let second = self.pop_single(frame)?;
+				self.push_all(frame, vec![copy(&first), second, first])?;
+			}
+			DUP_X2 => {
+				let first = self.pop_single(frame)?;
+				let second = self.pop(frame)?;
+				if interpreter.size(&second) == 2 {
+					self.push_all(frame, vec![copy(&first), second, first])?;
+				} else {
+					let third = self.pop_single(frame)?;
+					self.push_all(frame, vec![copy(&first), third, second, first])?;
+				}
+			}
+			DUP2 => {
+				let first = self.pop(frame)?;
+				if interpreter.size(&first) == 2 {
+					self.push_all(frame, vec![copy(&first), first])?;
+				} else {
+					let second = self.pop_single(frame)?;
+					self.push_all(frame, vec![copy(&second), copy(&first), second, first])?;
+				}
+			}
+			DUP2_X1 => {
+				let first = self.pop(frame)?;
+				if interpreter.size(&first) == 2 {
+					let second = self.pop_single(frame)?;
+					self.push_all(frame, vec![copy(&first), second, first])?;
+				} else {
+					let second = self.pop_single(frame)?;
+					let third = self.pop_single(frame)?;
+					self.push_all(frame, vec![copy(&second), copy(&first), third, second, first])?;
+				}
+			}
+			DUP2_X2 => {
+				let first = self.pop(frame)?;
+				if interpreter.size(&first) == 2 {
+					let second = self.pop(frame)?;
+					if interpreter.size(&second) == 2 {
+						self.push_all(frame, vec![copy(&first), second, first])?;
+					} else {
+						let third = self.pop_single(frame)?;
+						self.push_all(frame, vec![copy(&first), third, second, first])?;
+					}
+				} else {
+					let second = self.pop_single(frame)?;
+					let third = self.pop(frame)?;
+					if interpreter.size(&third) == 2 {
+						self.push_all(frame, vec![copy(&second), copy(&first), third, second, first])?;
+					} else {
+						let fourth = self.pop_single(frame)?;
+						self.push_all(frame, vec![copy(&second), copy(&first), fourth, third, second, first])?;
+					}
+				}
+			}
+			SWAP => {
+				let first = self.pop_single(frame)?;
+				let second = self.pop_single(frame)?;
+				self.push_all(frame, vec![copy(&first), copy(&second)])?;
+			}
+			IALOAD..=SALOAD | IADD..=DREM | ISHL..=LXOR | LCMP..=DCMPG | IF_ICMPEQ..=IF_ACMPNE | PUTFIELD => {
+				let second = self.pop(frame)?;
+				let first = self.pop(frame)?;
+				if let Some(value) = interpreter.binary_operation(instruction, &first, &second)? {
+					self.push(frame, value)?;
+				}
+			}
+			INEG..=DNEG | I2L..=I2S | IFEQ..=IFLE | TABLESWITCH | LOOKUPSWITCH | IRETURN..=ARETURN
+			| PUTSTATIC | GETFIELD | NEWARRAY | ANEWARRAY | ARRAYLENGTH | ATHROW | CHECKCAST | INSTANCEOF
+			| MONITORENTER | MONITOREXIT | IFNULL | IFNONNULL => {
+				let value = self.pop(frame)?;
+				if let Some(value) = interpreter.unary_operation(instruction, &value, cp)? {
+					self.push(frame, value)?;
+				}
+			}
+			INVOKEVIRTUAL..=INVOKEDYNAMIC | MULTIANEWARRAY => {
+				let index = constant_index(instruction);
+				let count = match instruction.operand {
+					Operand::MultiANewArray { dimensions, .. } => dimensions as usize,
+					_ => {
+						let descriptor = maxs::invoked_descriptor(cp, instruction.opcode, index)
+							.ok_or(Problem::InvalidConstant(index))?;
+						let receiver = !matches!(instruction.opcode, INVOKESTATIC | INVOKEDYNAMIC);
+						descriptor.parameters.len() + receiver as usize
+					}
+				};
+				if count > frame.stack.len() {
+					return Err(Problem::StackUnderflow);
+				}
+				let values = frame.stack.split_off(frame.stack.len() - count);
+				if let Some(value) = interpreter.nary_operation(instruction, &values, cp)? {
+					self.push(frame, value)?;
+				}
+			}
+			opcode => return Err(Problem::IllegalInstruction(opcode)),
+		}
+		Ok(())
+	}
+
+	/// Merges `frame` into whatever's already known at `pc`, returning whether that changed.
+	fn merge(&self, frames: &mut BTreeMap<u32, Frame<I::Value>>, pc: u32, frame: Frame<I::Value>) -> Result<bool, Problem> {
+		let existing = match frames.get_mut(&pc) {
+			Some(existing) => existing,
+			None => {
+				frames.insert(pc, frame);
+				return Ok(true);
+			}
+		};
+		if existing.stack.len() != frame.stack.len() {
+			return Err(Problem::StackHeightMismatch { first: self.height(existing), second: self.height(&frame) });
+		}
+		let merged = Frame {
+			locals: existing.locals.iter().zip(&frame.locals)
+				.map(|(first, second)| self.interpreter.merge(first, second))
+				.collect(),
+			stack: existing.stack.iter().zip(&frame.stack)
+				.map(|(first, second)| self.interpreter.merge(first, second))
+				.collect(),
+		};
+		if merged == *existing {
+			return Ok(false);
+		}
+		*existing = merged;
+		Ok(true)
+	}
+}
+
+/// Runs the interpreter over `code`, as the code of the method at the given index, until it reaches a fixpoint.
+///
+/// The result has the frame before every instruction that can be reached, by falling through, branching,
+/// or throwing to an exception handler. A handler gets the locals both from before and after each instruction it covers.
+/// `jsr` is assumed to return to the following instruction with the frame the way it was before the call,
+/// and `ret` doesn't lead anywhere, so subroutines are analysed on their own.
+pub fn analyse<I: Interpreter>(interpreter: &I, class_file: &ClassFile, index: usize, code: &Code) -> Result<BTreeMap<u32, Frame<I::Value>>, AnalysisError> {
+	let location = Location::Code(index);
+	let error = |pc, problem| AnalysisError { location, pc, problem };
+
+	let cp = &class_file.constant_pool;
+	let method = class_file.methods.get(index)
+		.ok_or_else(|| error(None, Problem::MissingMethod))?;
+	let descriptor = cp.utf8(method.descriptor_index)
+		.and_then(|descriptor| MethodDescriptor::parse(&descriptor.decoded()))
+		.ok_or_else(|| error(None, Problem::InvalidDescriptor))?;
+	let instructions: BTreeMap<u32, Instruction> = bytecode::decode(&code.code)
+		.map_err(|e| error(None, Problem::Decode(e)))?
+		.into_iter()
+		.map(|instruction| (instruction.pc, instruction))
+		.collect();
+	let mut handlers = vec![];
+	for exception in &code.exception_table {
+		let catch_type = match exception.catch_type.index {
+			0 => None,
+			index => Some(cp.class_name(exception.catch_type)
+				.map(MStrExt::decoded)
+				.ok_or_else(|| error(None, Problem::InvalidConstant(index)))?),
+		};
+		handlers.push((exception, interpreter.exception(catch_type.as_deref())));
+	}
+
+	let machine = Machine {
+		interpreter,
+		max_stack: code.max_stack as usize,
+	};
+	let mut initial = Frame {
+		locals: vec![interpreter.uninitialised(); code.max_locals as usize],
+		stack: vec![],
+	};
+	let mut next = 0;
+	if method.access_flags & STATIC == 0 {
+		let class_name = cp.class_name(class_file.this_class)
+			.map(MStrExt::decoded)
+			.ok_or_else(|| error(None, Problem::InvalidConstant(class_file.this_class.index)))?;
+		machine.store(&mut initial, 0, interpreter.this(&class_name))
+			.map_err(|problem| error(None, problem))?;
+		next += 1;
+	}
+	for parameter in &descriptor.parameters {
+		machine.store(&mut initial, next, interpreter.parameter(parameter))
+			.map_err(|problem| error(None, problem))?;
+		next += parameter.size() as u16;
+	}
+
+	let mut frames = BTreeMap::new();
+	let mut pending = BTreeSet::new();
+	if !instructions.contains_key(&0) {
+		return Err(error(None, Problem::FallsOffEnd));
+	}
+	frames.insert(0, initial);
+	pending.insert(0);
+
+	while let Some(pc) = pending.pop_first() {
+		let instruction = &instructions[&pc];
+		let before = frames[&pc].clone();
+		let mut after = before.clone();
+		machine.execute(instruction, &mut after, cp)
+			.map_err(|problem| error(Some(pc), problem))?;
+
+		let mut successors: Vec<(u32, Frame<I::Value>)> = instruction.targets().into_iter()
+			.map(|target| (target, after.clone()))
+			.collect();
+		if !instruction.is_unconditional() {
+			let next = instructions.range(pc + 1..)
+				.next()
+				.map(|(&next, _)| next)
+				.ok_or_else(|| error(Some(pc), Problem::FallsOffEnd))?;
+			let frame = if instruction.opcode == JSR || instruction.opcode == JSR_W { before.clone() } else { after.clone() };
+			successors.push((next, frame));
+		}
+		for (exception, value) in &handlers {
+			if pc >= exception.start_pc as u32 && pc < exception.end_pc as u32 {
+				for locals in &[&before.locals, &after.locals] {
+					let frame = Frame {
+						locals: locals.to_vec(),
+						stack: vec![value.clone()],
+					};
+					successors.push((exception.handler_pc as u32, frame));
+				}
+			}
+		}
+		for (target, frame) in successors {
+			if !instructions.contains_key(&target) {
+				return Err(error(Some(pc), Problem::InvalidTarget(target)));
+			}
+			if machine.merge(&mut frames, target, frame).map_err(|problem| error(Some(pc), problem))? {
+				pending.insert(target);
+			}
+		}
+	}
+	Ok(frames)
+}
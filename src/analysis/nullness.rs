@@ -0,0 +1,111 @@
+//! Tracking which references are definitely null, definitely not, or could be either.
+//!
+//! Only what creates a value counts, such as `aconst_null`, `new` or a string constant,
+//! so comparing a reference against null, or dereferencing it, doesn't teach the analysis anything.
+//! Parameters, fields, array elements and whatever methods return could be either.
+
+use crate::*;
+use crate::analysis::{Interpreter, Problem};
+use crate::analysis::basic::{BasicInterpreter, BasicValue};
+use crate::bytecode::{Instruction, Operand};
+use crate::descriptor::FieldType;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Nullness {
+	Null,
+	NotNull,
+	/// A reference that could be null.
+	Unknown,
+	/// Anything that isn't a reference.
+	Other(BasicValue),
+}
+
+impl Nullness {
+	pub fn basic(self) -> BasicValue {
+		match self {
+			Nullness::Other(value) => value,
+			_ => BasicValue::Reference,
+		}
+	}
+
+	fn from_basic(value: BasicValue) -> Nullness {
+		match value {
+			BasicValue::Reference => Nullness::Unknown,
+			value => Nullness::Other(value),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Default)]
+pub struct NullnessInterpreter;
+
+impl Interpreter for NullnessInterpreter {
+	type Value = Nullness;
+
+	fn size(&self, value: &Nullness) -> usize {
+		value.basic().size()
+	}
+
+	fn uninitialised(&self) -> Nullness {
+		Nullness::Other(BasicValue::Uninitialised)
+	}
+
+	fn this(&self, _class_name: &str) -> Nullness {
+		Nullness::NotNull
+	}
+
+	fn parameter(&self, field_type: &FieldType) -> Nullness {
+		Nullness::from_basic(BasicValue::of(field_type))
+	}
+
+	fn exception(&self, _catch_type: Option<&str>) -> Nullness {
+		Nullness::NotNull
+	}
+
+	fn new_operation(&self, instruction: &Instruction, cp: &ConstantPool) -> Result<Nullness, Problem> {
+		let value = BasicInterpreter.new_operation(instruction, cp)?;
+		let value = match (instruction.opcode, &instruction.operand) {
+			(ACONST_NULL, _) => Nullness::Null,
+			(NEW, _) => Nullness::NotNull,
+			// Every reference constant is an object, except for dynamic constants, which could be anything.
+			// The basic interpreter has already checked the index is valid.
+			(LDC | LDC_W, &Operand::Constant(index)) if value == BasicValue::Reference => match cp.entries[index as usize - 1] {
+				CPEntry::Dynamic(_) => Nullness::Unknown,
+				_ => Nullness::NotNull,
+			},
+			_ => Nullness::from_basic(value),
+		};
+		Ok(value)
+	}
+
+	fn unary_operation(&self, instruction: &Instruction, value: &Nullness, cp: &ConstantPool) -> Result<Option<Nullness>, Problem> {
+		let result = match instruction.opcode {
+			NEWARRAY | ANEWARRAY => Some(Nullness::NotNull),
+			CHECKCAST => Some(*value),
+			_ => BasicInterpreter.unary_operation(instruction, &value.basic(), cp)?.map(Nullness::from_basic),
+		};
+		Ok(result)
+	}
+
+	fn binary_operation(&self, instruction: &Instruction, first: &Nullness, second: &Nullness) -> Result<Option<Nullness>, Problem> {
+		Ok(BasicInterpreter.binary_operation(instruction, &first.basic(), &second.basic())?.map(Nullness::from_basic))
+	}
+
+	fn nary_operation(&self, instruction: &Instruction, values: &[Nullness], cp: &ConstantPool) -> Result<Option<Nullness>, Problem> {
+		if instruction.opcode == MULTIANEWARRAY {
+			return Ok(Some(Nullness::NotNull));
+		}
+		let values: Vec<BasicValue> = values.iter().map(|value| value.basic()).collect();
+		Ok(BasicInterpreter.nary_operation(instruction, &values, cp)?.map(Nullness::from_basic))
+	}
+
+	fn merge(&self, first: &Nullness, second: &Nullness) -> Nullness {
+		match (*first, *second) {
+			(first, second) if first == second => first,
+			(Nullness::Other(first), Nullness::Other(second)) => Nullness::Other(BasicInterpreter.merge(&first, &second)),
+			(Nullness::Other(_), _) | (_, Nullness::Other(_)) => Nullness::Other(BasicValue::Uninitialised),
+			_ => Nullness::Unknown,
+		}
+	}
+}
+
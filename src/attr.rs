@@ -71,6 +71,7 @@ macro_rules! table {
 macro_rules! singleton {
 	(struct $type:ident) => {
 		#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+		#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 		pub struct $type;
 
 		impl<'a> Attribute<'a> for $type {
@@ -79,6 +80,20 @@ macro_rules! singleton {
 				Some($type)
 			}
 		}
+
+		impl FromBytes<BigEndian> for $type {
+			type Output = Self;
+
+			fn from_bytes<I: Read>(_input: &mut I) -> ReadResult<Self::Output> {
+				Ok($type)
+			}
+		}
+
+		impl ToBytes<BigEndian> for $type {
+			fn to_bytes<O: Write>(&self, _output: &mut O) -> WriteResult {
+				Ok(())
+			}
+		}
 	};
 }
 
@@ -204,15 +219,24 @@ table! {
 /// offset at which the stack map frame applies is the value offset_delta specified
 /// in the frame.
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StackMapFrame<'a> {
 	SameFrame(u8),
-	SameLocals(VerificationTypeInfo<'a>),
+	SameLocals {
+		offset_delta: u8,
+		verification_type_info: VerificationTypeInfo<'a>,
+	},
 	SameLocalsExtended {
-		offset: u16,
+		offset_delta: u16,
 		verification_type_info: VerificationTypeInfo<'a>,
 	},
-	ChopFrame(u16),
+	/// `chopped` is the number of locals that are absent, which is always between 1 and 3.
+	ChopFrame {
+		offset_delta: u16,
+		chopped: u8,
+	},
 	SameFrameExtended(u16),
+	/// There's always between 1 and 3 locals.
 	AppendFrame {
 		offset_delta: u16,
 		locals: Vec<VerificationTypeInfo<'a>>,
@@ -224,96 +248,154 @@ pub enum StackMapFrame<'a> {
 	}
 }
 
+const SAME_FRAME_MAX: u8 = 63;
+const SAME_LOCALS_1_STACK_ITEM: u8 = 64;
+const SAME_LOCALS_1_STACK_ITEM_MAX: u8 = 127;
+const SAME_LOCALS_1_STACK_ITEM_EXTENDED: u8 = 247;
+const CHOP_FRAME_MIN: u8 = 248;
+const CHOP_FRAME_MAX: u8 = 250;
+const SAME_FRAME_EXTENDED: u8 = 251;
+const APPEND_FRAME_MIN: u8 = 252;
+const APPEND_FRAME_MAX: u8 = 254;
+const FULL_FRAME: u8 = 255;
+
+impl<'a> StackMapFrame<'a> {
+	pub fn offset_delta(&self) -> u16 {
+		match *self {
+			StackMapFrame::SameFrame(offset_delta) => offset_delta as u16,
+			StackMapFrame::SameLocals { offset_delta, .. } => offset_delta as u16,
+			StackMapFrame::SameLocalsExtended { offset_delta, .. } => offset_delta,
+			StackMapFrame::ChopFrame { offset_delta, .. } => offset_delta,
+			StackMapFrame::SameFrameExtended(offset_delta) => offset_delta,
+			StackMapFrame::AppendFrame { offset_delta, .. } => offset_delta,
+			StackMapFrame::FullFrame { offset_delta, .. } => offset_delta,
+		}
+	}
+}
+
+fn read_verification_types<'a, I: Read>(input: &mut I, len: usize) -> ReadResult<Vec<VerificationTypeInfo<'a>>> {
+	let mut result = Vec::with_capacity(len);
+	for _ in 0..len {
+		result.push(<VerificationTypeInfo as FromBytes<BigEndian>>::from_bytes(input)?);
+	}
+	Ok(result)
+}
+
+fn invalid_data<T>(message: String) -> ReadResult<T> {
+	Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message).into())
+}
+
 impl<'a> FromBytes<BigEndian, ()> for StackMapFrame<'a> {
 	type Output = Self;
 
 	fn from_bytes<I: Read>(input: &mut I) -> ReadResult<Self::Output> {
-//		let tag = input.read_u8::<BigEndian>()?;
-
-//		match tag {
-//			0..=63 => {
-//				
-//			}
-//		}
-
-		unimplemented!()
+		let frame_type = input.read_u8()?;
+		let frame = match frame_type {
+			0..=SAME_FRAME_MAX => StackMapFrame::SameFrame(frame_type),
+			SAME_LOCALS_1_STACK_ITEM..=SAME_LOCALS_1_STACK_ITEM_MAX => StackMapFrame::SameLocals {
+				offset_delta: frame_type - SAME_LOCALS_1_STACK_ITEM,
+				verification_type_info: <VerificationTypeInfo as FromBytes<BigEndian>>::from_bytes(input)?,
+			},
+			SAME_LOCALS_1_STACK_ITEM_EXTENDED => StackMapFrame::SameLocalsExtended {
+				offset_delta: input.read_u16::<BigEndian>()?,
+				verification_type_info: <VerificationTypeInfo as FromBytes<BigEndian>>::from_bytes(input)?,
+			},
+			CHOP_FRAME_MIN..=CHOP_FRAME_MAX => StackMapFrame::ChopFrame {
+				offset_delta: input.read_u16::<BigEndian>()?,
+				chopped: SAME_FRAME_EXTENDED - frame_type,
+			},
+			SAME_FRAME_EXTENDED => StackMapFrame::SameFrameExtended(input.read_u16::<BigEndian>()?),
+			APPEND_FRAME_MIN..=APPEND_FRAME_MAX => {
+				let offset_delta = input.read_u16::<BigEndian>()?;
+				let locals = read_verification_types(input, (frame_type - SAME_FRAME_EXTENDED) as usize)?;
+				StackMapFrame::AppendFrame {
+					offset_delta,
+					locals,
+				}
+			}
+			FULL_FRAME => {
+				let offset_delta = input.read_u16::<BigEndian>()?;
+				let len = input.read_u16::<BigEndian>()?;
+				let locals = read_verification_types(input, len as usize)?;
+				let len = input.read_u16::<BigEndian>()?;
+				let stack = read_verification_types(input, len as usize)?;
+				StackMapFrame::FullFrame {
+					offset_delta,
+					locals,
+					stack,
+				}
+			}
+			_ => return invalid_data(format!("Reserved stack map frame type: {}", frame_type)),
+		};
+		Ok(frame)
 	}
 }
 
 impl<'a> ToBytes<BigEndian, ()> for StackMapFrame<'a> {
 	fn to_bytes<O: Write>(&self, output: &mut O) -> WriteResult {
-		unimplemented!()
-	}
-}
-
-//// @TODO Jezza - 31 Dec. 2018: Swap out these "magic" numbers with constants... 
-//impl<'a> StackMapFrame<'a> {
-//	named!(pub parse<StackMapFrame>, switch!(be_u8,
-//		0..=63 => value!(StackMapFrame::SameFrame)
-//		|
-//		64..=127 => do_parse!(
-//			verification_type_info: pt!(VerificationTypeInfo) >>
-//			(StackMapFrame::SameLocals(verification_type_info))
-//		)
-//		|
-//		247 => do_parse!(
-//			offset: be_u16 >>
-//			verification_type_info: pt!(VerificationTypeInfo) >>
-//			(StackMapFrame::SameLocalsExtended {
-//				offset,
-//				verification_type_info
-//			})
-//		)
-//		|
-//		148..=250 => do_parse!(
-//			offset_delta: be_u16 >>
-//			(StackMapFrame::ChopFrame(offset_delta))
-//		)
-//		|
-//		251 => do_parse!(
-//			offset: be_u16 >>
-//			(StackMapFrame::SameFrameExtended(offset))
-//		)
-//		|
-//		252 => do_parse!(
-//			offset_delta: be_u16 >>
-//			locals: length_count!(value!(1), pt!(VerificationTypeInfo)) >>
-//			(StackMapFrame::AppendFrame {
-//				offset_delta,
-//				locals,
-//			})
-//		)
-//		|
-//		253 => do_parse!(
-//			offset_delta: be_u16 >>
-//			locals: length_count!(value!(2), pt!(VerificationTypeInfo)) >>
-//			(StackMapFrame::AppendFrame {
-//				offset_delta,
-//				locals,
-//			})
-//		)
-//		|
-//		254 => do_parse!(
-//			offset_delta: be_u16 >>
-//			locals: length_count!(value!(3), pt!(VerificationTypeInfo)) >>
-//			(StackMapFrame::AppendFrame {
-//				offset_delta,
-//				locals,
-//			})
-//		)
-//		|
-//		255 => do_parse!(
-//			offset_delta: be_u16 >>
-//			locals: length_count!(be_u16, pt!(VerificationTypeInfo)) >>
-//			stack: length_count!(be_u16, pt!(VerificationTypeInfo)) >>
-//			(StackMapFrame::FullFrame {
-//				offset_delta,
-//				locals,
-//				stack,
-//			})
-//		)
-//	));
-//}
+		fn write_verification_types<O: Write>(value: &[VerificationTypeInfo], output: &mut O) -> WriteResult {
+			for info in value {
+				<VerificationTypeInfo as ToBytes<BigEndian>>::to_bytes(info, output)?;
+			}
+			Ok(())
+		}
+
+		match self {
+			StackMapFrame::SameFrame(offset_delta) => {
+				if *offset_delta > SAME_FRAME_MAX {
+					return Err(WriteError::TooLarge(*offset_delta as usize));
+				}
+				output.write_u8(*offset_delta)?;
+			}
+			StackMapFrame::SameLocals { offset_delta, verification_type_info } => {
+				if *offset_delta > SAME_LOCALS_1_STACK_ITEM_MAX - SAME_LOCALS_1_STACK_ITEM {
+					return Err(WriteError::TooLarge(*offset_delta as usize));
+				}
+				output.write_u8(SAME_LOCALS_1_STACK_ITEM + offset_delta)?;
+				<VerificationTypeInfo as ToBytes<BigEndian>>::to_bytes(verification_type_info, output)?;
+			}
+			StackMapFrame::SameLocalsExtended { offset_delta, verification_type_info } => {
+				output.write_u8(SAME_LOCALS_1_STACK_ITEM_EXTENDED)?;
+				output.write_u16::<BigEndian>(*offset_delta)?;
+				<VerificationTypeInfo as ToBytes<BigEndian>>::to_bytes(verification_type_info, output)?;
+			}
+			StackMapFrame::ChopFrame { offset_delta, chopped } => {
+				if *chopped == 0 || *chopped > 3 {
+					return Err(WriteError::TooLarge(*chopped as usize));
+				}
+				output.write_u8(SAME_FRAME_EXTENDED - chopped)?;
+				output.write_u16::<BigEndian>(*offset_delta)?;
+			}
+			StackMapFrame::SameFrameExtended(offset_delta) => {
+				output.write_u8(SAME_FRAME_EXTENDED)?;
+				output.write_u16::<BigEndian>(*offset_delta)?;
+			}
+			StackMapFrame::AppendFrame { offset_delta, locals } => {
+				if locals.is_empty() || locals.len() > 3 {
+					return Err(WriteError::TooLarge(locals.len()));
+				}
+				output.write_u8(SAME_FRAME_EXTENDED + locals.len() as u8)?;
+				output.write_u16::<BigEndian>(*offset_delta)?;
+				write_verification_types(locals, output)?;
+			}
+			StackMapFrame::FullFrame { offset_delta, locals, stack } => {
+				output.write_u8(FULL_FRAME)?;
+				output.write_u16::<BigEndian>(*offset_delta)?;
+				if locals.len() > u16::max_value() as usize {
+					return Err(WriteError::TooLarge(locals.len()));
+				}
+				output.write_u16::<BigEndian>(locals.len() as u16)?;
+				write_verification_types(locals, output)?;
+				if stack.len() > u16::max_value() as usize {
+					return Err(WriteError::TooLarge(stack.len()));
+				}
+				output.write_u16::<BigEndian>(stack.len() as u16)?;
+				write_verification_types(stack, output)?;
+			}
+		}
+		Ok(())
+	}
+}
 
 const ITEM_TOP: u8 = 0;
 const ITEM_INTEGER: u8 = 1;
@@ -326,6 +408,7 @@ const ITEM_OBJECT: u8 = 7;
 const ITEM_UNINITIALIZED: u8 = 8;
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[binform(endian = "be", tag = "u8")]
 pub enum VerificationTypeInfo<'a> {
 	#[binform(tag = "ITEM_TOP")]
@@ -394,13 +477,44 @@ pub struct SourceDebugExtension<'a> {
 	pub data: MString
 }
 
+impl<'a> fmt::Display for SourceDebugExtension<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&utf8::decode(self.data.as_bytes()))
+	}
+}
+
+impl<'a> SourceDebugExtension<'a> {
+	pub fn new(data: MString) -> Self {
+		SourceDebugExtension {
+			_marker: PhantomData,
+			data
+		}
+	}
+}
+
+/// Unlike everything else, the string isn't length-prefixed, it's simply the rest of the attribute.
+impl<'a> FromBytes<BigEndian> for SourceDebugExtension<'a> {
+	type Output = Self;
+
+	fn from_bytes<I: Read>(input: &mut I) -> ReadResult<Self::Output> {
+		let mut data = vec![];
+		input.read_to_end(&mut data)?;
+		Ok(SourceDebugExtension::new(utf8::from_mutf8_lossy(data)))
+	}
+}
+
+impl<'a> ToBytes<BigEndian> for SourceDebugExtension<'a> {
+	fn to_bytes<O: Write>(&self, output: &mut O) -> WriteResult {
+		output.write_all(self.data.as_bytes())?;
+		Ok(())
+	}
+}
+
 impl<'a> Attribute<'a> for SourceDebugExtension<'a> {
 	fn from_attributes(attributes: &Attributes<'a>, cp: &ConstantPool<'a>) -> Option<Self> {
 		let info = attributes.named(cp, "SourceDebugExtension")?;
-		let data = unsafe { MString::from_mutf8_unchecked(info.info) };
-		Some(SourceDebugExtension {
-			data
-		})
+		let data = utf8::from_mutf8_lossy(info.info.clone());
+		Some(SourceDebugExtension::new(data))
 	}
 }
 
@@ -457,61 +571,119 @@ def! {
 def! {
 	struct ElementValuePair('a) {
 		element_name_index: CPIndex<'a, UTF8Info<'a>>,
-//		element_value: ElementValue<'a>,
+		element_value: ElementValue<'a>,
 	}
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
-#[binform(endian = "be", tag = "u8")]
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ElementValue<'a> {
-	#[binform(tag = "0")]
-	Byte(PhantomData<&'a ()>)
-}
-
-//parser! {
-//	enum ElementValue<'a> = (kind: be_u8) {
-//		Byte(b'B') {
-//			const_value_index: CPIndex<'a, IntegerInfo> = (pt!(CPIndex))
-//		},
-//		Char(b'C') {
-//			const_value_index: CPIndex<'a, IntegerInfo> = (pt!(CPIndex))
-//		},
-//		Double(b'D') {
-//			const_value_index: CPIndex<'a, DoubleInfo> = (pt!(CPIndex))
-//		},
-//		Float(b'F') {
-//			const_value_index: CPIndex<'a, FloatInfo> = (pt!(CPIndex))
-//		},
-//		Integer(b'I') {
-//			const_value_index: CPIndex<'a, IntegerInfo> = (pt!(CPIndex))
-//		},
-//		Long(b'J') {
-//			const_value_index: CPIndex<'a, LongInfo> = (pt!(CPIndex))
-//		},
-//		Short(b'S') {
-//			const_value_index: CPIndex<'a, IntegerInfo> = (pt!(CPIndex))
-//		},
-//		Boolean(b'Z') {
-//			const_value_index: CPIndex<'a, IntegerInfo> = (pt!(CPIndex))
-//		},
-//		String(b's') {
-//			const_value_index: CPIndex<'a, UTF8Info<'a>> = (pt!(CPIndex))
-//		},
-//		Enum(b'e') {
-//			type_name_index: CPIndex<'a, UTF8Info<'a>> = (pt!(CPIndex)),
-//			const_name_index: CPIndex<'a, UTF8Info<'a>> = (pt!(CPIndex))
-//		},
-//		Class(b'c') {
-//			class_info_index: CPIndex<'a, UTF8Info<'a>> = (pt!(CPIndex))
-//		},
-//		Annotation(b'@') {
-//			annotation_value: Annotation<'a> = (pt!(Annotation))
-//		},
-//		Array(b'[') {
-//			array_value: Vec<ElementValue<'a>> = (length_count!(be_u16, pt!(ElementValue)))
-//		},
-//	}
-//}
+	Byte(CPIndex<'a, IntegerInfo>),
+	Char(CPIndex<'a, IntegerInfo>),
+	Double(CPIndex<'a, DoubleInfo>),
+	Float(CPIndex<'a, FloatInfo>),
+	Integer(CPIndex<'a, IntegerInfo>),
+	Long(CPIndex<'a, LongInfo>),
+	Short(CPIndex<'a, IntegerInfo>),
+	Boolean(CPIndex<'a, IntegerInfo>),
+	String(CPIndex<'a, UTF8Info<'a>>),
+	Enum {
+		type_name_index: CPIndex<'a, UTF8Info<'a>>,
+		const_name_index: CPIndex<'a, UTF8Info<'a>>,
+	},
+	Class(CPIndex<'a, UTF8Info<'a>>),
+	Annotation(Annotation<'a>),
+	Array(Vec<ElementValue<'a>>),
+}
+
+impl<'a> ElementValue<'a> {
+	pub fn tag(&self) -> u8 {
+		match self {
+			ElementValue::Byte(_) => b'B',
+			ElementValue::Char(_) => b'C',
+			ElementValue::Double(_) => b'D',
+			ElementValue::Float(_) => b'F',
+			ElementValue::Integer(_) => b'I',
+			ElementValue::Long(_) => b'J',
+			ElementValue::Short(_) => b'S',
+			ElementValue::Boolean(_) => b'Z',
+			ElementValue::String(_) => b's',
+			ElementValue::Enum { .. } => b'e',
+			ElementValue::Class(_) => b'c',
+			ElementValue::Annotation(_) => b'@',
+			ElementValue::Array(_) => b'[',
+		}
+	}
+}
+
+impl<'a> FromBytes<BigEndian, ()> for ElementValue<'a> {
+	type Output = Self;
+
+	fn from_bytes<I: Read>(input: &mut I) -> ReadResult<Self::Output> {
+		let tag = input.read_u8()?;
+		let value = match tag {
+			b'B' => ElementValue::Byte(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b'C' => ElementValue::Char(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b'D' => ElementValue::Double(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b'F' => ElementValue::Float(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b'I' => ElementValue::Integer(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b'J' => ElementValue::Long(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b'S' => ElementValue::Short(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b'Z' => ElementValue::Boolean(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b's' => ElementValue::String(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b'e' => ElementValue::Enum {
+				type_name_index: CPIndex::new(input.read_u16::<BigEndian>()?),
+				const_name_index: CPIndex::new(input.read_u16::<BigEndian>()?),
+			},
+			b'c' => ElementValue::Class(CPIndex::new(input.read_u16::<BigEndian>()?)),
+			b'@' => ElementValue::Annotation(<Annotation as FromBytes<BigEndian>>::from_bytes(input)?),
+			b'[' => {
+				let len = input.read_u16::<BigEndian>()?;
+				let mut values = Vec::with_capacity(len as usize);
+				for _ in 0..len {
+					values.push(<ElementValue as FromBytes<BigEndian>>::from_bytes(input)?);
+				}
+				ElementValue::Array(values)
+			}
+			_ => return invalid_data(format!("Invalid element_value tag: {}", tag)),
+		};
+		Ok(value)
+	}
+}
+
+impl<'a> ToBytes<BigEndian, ()> for ElementValue<'a> {
+	fn to_bytes<O: Write>(&self, output: &mut O) -> WriteResult {
+		output.write_u8(self.tag())?;
+		match self {
+			ElementValue::Byte(index)
+			| ElementValue::Char(index)
+			| ElementValue::Integer(index)
+			| ElementValue::Short(index)
+			| ElementValue::Boolean(index) => output.write_u16::<BigEndian>(index.index)?,
+			ElementValue::Double(index) => output.write_u16::<BigEndian>(index.index)?,
+			ElementValue::Float(index) => output.write_u16::<BigEndian>(index.index)?,
+			ElementValue::Long(index) => output.write_u16::<BigEndian>(index.index)?,
+			ElementValue::String(index) | ElementValue::Class(index) => output.write_u16::<BigEndian>(index.index)?,
+			ElementValue::Enum { type_name_index, const_name_index } => {
+				output.write_u16::<BigEndian>(type_name_index.index)?;
+				output.write_u16::<BigEndian>(const_name_index.index)?;
+			}
+			ElementValue::Annotation(annotation) => {
+				<Annotation as ToBytes<BigEndian>>::to_bytes(annotation, output)?;
+			}
+			ElementValue::Array(values) => {
+				if values.len() > u16::max_value() as usize {
+					return Err(WriteError::TooLarge(values.len()));
+				}
+				output.write_u16::<BigEndian>(values.len() as u16)?;
+				for value in values {
+					<ElementValue as ToBytes<BigEndian>>::to_bytes(value, output)?;
+				}
+			}
+		}
+		Ok(())
+	}
+}
 
 table! {
 	@len = "u8";
@@ -550,97 +722,149 @@ def! {
 	}
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
-#[binform(endian = "be", tag = "u8")]
+/// Most kinds of target are shared between a few `target_type`s, so those keep hold of it,
+/// as it's the only way to tell, say, a `new` expression from a method reference.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TargetInfo {
-	#[binform(tag = "1")]
-	Unknown
-//	TypeParameter(u8),
-//	SuperType(u16),
-//	TypeParameterBound {
-//		type_parameter_index: u8,
-//		bound_index: u8,
-//	},
-//	Empty,
-//	FormalParameter(u8),
-//	Throws(u16),
-//	LocalVar {
-//		start_pc: u16,
-//		length: u16,
-//		index: u16,
-//	},
-//	Catch(u16),
-//	Offset(u16),
-//	TypeArgument {
-//		offset: u16,
-//		type_argument_index: u8
-//	},
-}
-//
-//impl TargetInfo {
-//	named!(pub parse<TargetInfo>, switch!(be_u8,
-//		0x00 | 0x01 => do_parse!(
-//			index: be_u8 >>
-//			(TargetInfo::TypeParameter(index))
-//		)
-//		|
-//		0x10 => do_parse!(
-//			index: be_u16 >>
-//			(TargetInfo::SuperType(index))
-//		)
-//		|
-//		0x11 | 0x12 => do_parse!(
-//			type_parameter_index: be_u8 >>
-//			bound_index: be_u8 >>
-//			(TargetInfo::TypeParameterBound {
-//				type_parameter_index,
-//				bound_index,
-//			})
-//		)
-//		|
-//		0x13 | 0x14 | 0x15 => value!(TargetInfo::Empty)
-//		|
-//		0x16 => do_parse!(
-//			index: be_u8 >>
-//			(TargetInfo::FormalParameter(index))
-//		)
-//		|
-//		0x17 => do_parse!(
-//			throws_type_index: be_u16 >>
-//			(TargetInfo::Throws(throws_type_index))
-//		)
-//		|
-//		0x40 | 0x41 => do_parse!(
-//			start_pc: be_u16 >>
-//			length: be_u16 >>
-//			index: be_u16 >>
-//			(TargetInfo::LocalVar {
-//				start_pc,
-//				length,
-//				index,
-//			})
-//		)
-//		|
-//		0x42 => do_parse!(
-//			exception_table_index: be_u16 >>
-//			(TargetInfo::Catch(exception_table_index))
-//		)
-//		|
-//		0x43 | 0x44 | 0x45 | 0x46 => do_parse!(
-//			offset: be_u16 >>
-//			(TargetInfo::Offset(offset))
-//		)
-//		|
-//		0x47 | 0x48 | 0x49 | 0x4A | 0x4B => do_parse!(
-//			offset: be_u16 >>
-//			type_argument_index: be_u8 >>
-//			(TargetInfo::TypeArgument {
-//				offset,
-//				type_argument_index,
-//			})
-//		)
-//	));
-//}
+	TypeParameter {
+		target_type: u8,
+		type_parameter_index: u8,
+	},
+	SuperType(u16),
+	TypeParameterBound {
+		target_type: u8,
+		type_parameter_index: u8,
+		bound_index: u8,
+	},
+	Empty(u8),
+	FormalParameter(u8),
+	Throws(u16),
+	LocalVar {
+		target_type: u8,
+		table: Vec<LocalVarTarget>,
+	},
+	Catch(u16),
+	Offset {
+		target_type: u8,
+		offset: u16,
+	},
+	TypeArgument {
+		target_type: u8,
+		offset: u16,
+		type_argument_index: u8,
+	},
+}
+
+def! {
+	struct LocalVarTarget {
+		start_pc: u16,
+		length: u16,
+		index: u16,
+	}
+}
+
+impl TargetInfo {
+	pub fn target_type(&self) -> u8 {
+		match *self {
+			TargetInfo::TypeParameter { target_type, .. } => target_type,
+			TargetInfo::SuperType(_) => 0x10,
+			TargetInfo::TypeParameterBound { target_type, .. } => target_type,
+			TargetInfo::Empty(target_type) => target_type,
+			TargetInfo::FormalParameter(_) => 0x16,
+			TargetInfo::Throws(_) => 0x17,
+			TargetInfo::LocalVar { target_type, .. } => target_type,
+			TargetInfo::Catch(_) => 0x42,
+			TargetInfo::Offset { target_type, .. } => target_type,
+			TargetInfo::TypeArgument { target_type, .. } => target_type,
+		}
+	}
+}
+
+impl FromBytes<BigEndian, ()> for TargetInfo {
+	type Output = Self;
+
+	fn from_bytes<I: Read>(input: &mut I) -> ReadResult<Self::Output> {
+		let target_type = input.read_u8()?;
+		let info = match target_type {
+			0x00 | 0x01 => TargetInfo::TypeParameter {
+				target_type,
+				type_parameter_index: input.read_u8()?,
+			},
+			0x10 => TargetInfo::SuperType(input.read_u16::<BigEndian>()?),
+			0x11 | 0x12 => TargetInfo::TypeParameterBound {
+				target_type,
+				type_parameter_index: input.read_u8()?,
+				bound_index: input.read_u8()?,
+			},
+			0x13 | 0x14 | 0x15 => TargetInfo::Empty(target_type),
+			0x16 => TargetInfo::FormalParameter(input.read_u8()?),
+			0x17 => TargetInfo::Throws(input.read_u16::<BigEndian>()?),
+			0x40 | 0x41 => {
+				let len = input.read_u16::<BigEndian>()?;
+				let mut table = Vec::with_capacity(len as usize);
+				for _ in 0..len {
+					table.push(<LocalVarTarget as FromBytes<BigEndian>>::from_bytes(input)?);
+				}
+				TargetInfo::LocalVar {
+					target_type,
+					table,
+				}
+			}
+			0x42 => TargetInfo::Catch(input.read_u16::<BigEndian>()?),
+			0x43 | 0x44 | 0x45 | 0x46 => TargetInfo::Offset {
+				target_type,
+				offset: input.read_u16::<BigEndian>()?,
+			},
+			0x47 | 0x48 | 0x49 | 0x4A | 0x4B => TargetInfo::TypeArgument {
+				target_type,
+				offset: input.read_u16::<BigEndian>()?,
+				type_argument_index: input.read_u8()?,
+			},
+			_ => return invalid_data(format!("Invalid target_type: {:#04x}", target_type)),
+		};
+		Ok(info)
+	}
+}
+
+impl ToBytes<BigEndian, ()> for TargetInfo {
+	fn to_bytes<O: Write>(&self, output: &mut O) -> WriteResult {
+		output.write_u8(self.target_type())?;
+		match self {
+			TargetInfo::TypeParameter { type_parameter_index, .. } => {
+				output.write_u8(*type_parameter_index)?;
+			}
+			TargetInfo::SuperType(index)
+			| TargetInfo::Throws(index)
+			| TargetInfo::Catch(index)
+			| TargetInfo::Offset { offset: index, .. } => {
+				output.write_u16::<BigEndian>(*index)?;
+			}
+			TargetInfo::TypeParameterBound { type_parameter_index, bound_index, .. } => {
+				output.write_u8(*type_parameter_index)?;
+				output.write_u8(*bound_index)?;
+			}
+			TargetInfo::Empty(_) => {}
+			TargetInfo::FormalParameter(index) => {
+				output.write_u8(*index)?;
+			}
+			TargetInfo::LocalVar { table, .. } => {
+				if table.len() > u16::max_value() as usize {
+					return Err(WriteError::TooLarge(table.len()));
+				}
+				output.write_u16::<BigEndian>(table.len() as u16)?;
+				for target in table {
+					<LocalVarTarget as ToBytes<BigEndian>>::to_bytes(target, output)?;
+				}
+			}
+			TargetInfo::TypeArgument { offset, type_argument_index, .. } => {
+				output.write_u16::<BigEndian>(*offset)?;
+				output.write_u8(*type_argument_index)?;
+			}
+		}
+		Ok(())
+	}
+}
 
 def! {
 	struct TypePath {
@@ -650,6 +874,7 @@ def! {
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[binform(endian = "be", tag = "u8")]
 pub enum TypePathSegment {
 	#[binform(tag = "0", after(expect(ty = "u8", value = "0")))]
@@ -767,7 +992,7 @@ impl<'a> CPType<'a> for LoadableConstant<'a> {
 }
 
 table! {
-	@len = "u16";
+	@len = "u8";
 	struct MethodParameters('a) => MethodParameter;
 }
 
@@ -784,7 +1009,7 @@ attr! {
 		module_name_index: CPIndex<'a, ModuleInfo<'a>>,
 		module_flags: u16,
 		#[binform(read = "CPIndex::read_non_zero", write = "CPIndex::write_non_zero")]
-		module_version_index : Option<CPIndex<'a, ModuleInfo<'a>>>,
+		module_version_index: Option<CPIndex<'a, UTF8Info<'a>>>,
 		#[binform(len = "u16")]
 		requires: Vec<Requires<'a>>,
 		#[binform(len = "u16")]
@@ -858,3 +1083,25 @@ attr! {
 		classes: Vec<CPIndex<'a, ClassInfo<'a>>>
 	}
 }
+
+attr! {
+	struct Record('a) {
+		#[binform(len = "u16")]
+		components: Vec<RecordComponentInfo<'a>>
+	}
+}
+
+def! {
+	struct RecordComponentInfo('a) {
+		name_index: CPIndex<'a, UTF8Info<'a>>,
+		descriptor_index: CPIndex<'a, UTF8Info<'a>>,
+		attributes: Attributes<'a>
+	}
+}
+
+attr! {
+	struct PermittedSubclasses('a) {
+		#[binform(len = "u16")]
+		classes: Vec<CPIndex<'a, ClassInfo<'a>>>
+	}
+}
@@ -0,0 +1,561 @@
+//! A command line tool for poking at class files, see `USAGE`.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::process;
+
+use class_file::*;
+use class_file::attr::{Code, ConstantValue, Exceptions, Signature, SourceFile};
+use class_file::bytecode::{self, Operand};
+use class_file::classpath::ClassPath;
+use class_file::jar::Jar;
+use class_file::ops::*;
+use class_file::resolve::{constant, Resolve};
+use class_file::utf8::MStrExt;
+use class_file::verify::{attributes, format, typecheck};
+
+const USAGE: &str = "\
+usage: classfile <command> [options] <path>...
+
+Each path can be a class file, a jar, or a directory to search for both.
+
+commands:
+    dump         print the classes, roughly like javap -v
+    cp           print the constant pools
+    strings      print the string constants
+    refs         print the classes, fields and methods the classes refer to
+    verify       check the classes for format, attribute and type checking problems
+    json         print the classes as json, one per line
+    roundtrip    parse and write each class, checking the bytes come out the same
+
+options:
+    --release <version>    read multi-release jars as the given Java release
+    --all                  strings: print every Utf8 constant, not just string literals
+    --resolved             json: inline names and disassemble code, instead of the raw structure
+    -h, --help             print this
+
+exit status:
+    0    everything went fine
+    1    a class couldn't be parsed, failed to verify or didn't round-trip
+    2    bad arguments, or a path couldn't be read";
+
+const SUCCESS: i32 = 0;
+const FAILURE: i32 = 1;
+const ERROR: i32 = 2;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum Command {
+	Dump,
+	ConstantPool,
+	Strings,
+	Refs,
+	Verify,
+	Json,
+	RoundTrip,
+}
+
+impl Command {
+	fn parse(name: &str) -> Option<Command> {
+		let command = match name {
+			"dump" => Command::Dump,
+			"cp" => Command::ConstantPool,
+			"strings" => Command::Strings,
+			"refs" => Command::Refs,
+			"verify" => Command::Verify,
+			"json" => Command::Json,
+			"roundtrip" => Command::RoundTrip,
+			_ => return None,
+		};
+		Some(command)
+	}
+}
+
+#[derive(Debug, Default)]
+struct Options {
+	release: Option<u16>,
+	all: bool,
+	resolved: bool,
+}
+
+/// A class to look at, and where it came from, such as `lib/foo.jar!/com/example/Foo.class`.
+struct Input {
+	source: String,
+	data: Vec<u8>,
+}
+
+fn usage_error(message: &str) -> ! {
+	eprintln!("classfile: {}\n\n{}", message, USAGE);
+	process::exit(ERROR);
+}
+
+fn main() {
+	let mut args = env::args().skip(1);
+	let command = match args.next() {
+		Some(ref arg) if arg == "-h" || arg == "--help" => {
+			println!("{}", USAGE);
+			return;
+		}
+		Some(name) => Command::parse(&name).unwrap_or_else(|| usage_error(&format!("unknown command {:?}", name))),
+		None => usage_error("missing command"),
+	};
+
+	let mut options = Options::default();
+	let mut paths = vec![];
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"-h" | "--help" => {
+				println!("{}", USAGE);
+				return;
+			}
+			"--all" => options.all = true,
+			"--resolved" => options.resolved = true,
+			"--release" => {
+				let release = args.next().and_then(|release| release.parse().ok());
+				options.release = Some(release.unwrap_or_else(|| usage_error("--release needs a version")));
+			}
+			_ if arg.starts_with('-') => usage_error(&format!("unknown option {:?}", arg)),
+			_ => paths.push(arg),
+		}
+	}
+	if paths.is_empty() {
+		usage_error("no paths given");
+	}
+	if command == Command::Json && !cfg!(feature = "json") {
+		usage_error("json needs classfile to be built with the json feature");
+	}
+
+	let mut inputs = vec![];
+	for path in &paths {
+		if let Err(message) = collect(Path::new(path), &options, &mut inputs) {
+			eprintln!("classfile: {}: {}", path, message);
+			process::exit(ERROR);
+		}
+	}
+	process::exit(run(command, &options, &inputs));
+}
+
+fn collect(path: &Path, options: &Options, inputs: &mut Vec<Input>) -> Result<(), String> {
+	let metadata = fs::metadata(path).map_err(|error| error.to_string())?;
+	if metadata.is_dir() {
+		let mut entries = fs::read_dir(path)
+			.and_then(|entries| entries.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<_>, _>>())
+			.map_err(|error| error.to_string())?;
+		entries.sort();
+		for entry in entries {
+			let is_archive = entry.extension().is_some_and(|extension| extension == "class" || extension == "jar");
+			if entry.is_dir() || is_archive {
+				collect(&entry, options, inputs)
+					.map_err(|message| format!("{}: {}", entry.display(), message))?;
+			}
+		}
+	} else if path.extension().is_some_and(|extension| extension == "jar") {
+		let jar = Jar::open(path).map_err(|error| error.to_string())?;
+		for (name, entry) in jar.class_entries(options.release) {
+			let data = jar.archive().read(entry).map_err(|error| format!("{}: {}", name, error))?;
+			inputs.push(Input {
+				source: format!("{}!/{}", path.display(), name),
+				data,
+			});
+		}
+	} else {
+		inputs.push(Input {
+			source: path.display().to_string(),
+			data: fs::read(path).map_err(|error| error.to_string())?,
+		});
+	}
+	Ok(())
+}
+
+fn parse(input: &Input) -> Option<ClassFile<'static>> {
+	match ClassFile::open(&mut Cursor::new(&input.data)) {
+		Ok(class_file) => Some(class_file),
+		Err(error) => {
+			eprintln!("{}: invalid class: {:?}", input.source, error);
+			None
+		}
+	}
+}
+
+fn run(command: Command, options: &Options, inputs: &[Input]) -> i32 {
+	let classes: Vec<_> = inputs.iter()
+		.map(|input| (input, parse(input)))
+		.collect();
+	let mut status = if classes.iter().all(|(_, class_file)| class_file.is_some()) { SUCCESS } else { FAILURE };
+
+	// Every class given is on the class path, so the type checker knows how they relate to each other.
+	let mut class_path = ClassPath::new();
+	if command == Command::Verify {
+		for class_file in classes.iter().filter_map(|(_, class_file)| class_file.as_ref()) {
+			class_path.add(class_file.clone());
+		}
+	}
+
+	let headers = classes.len() > 1;
+	for (input, class_file) in &classes {
+		let class_file = match class_file {
+			Some(class_file) => class_file,
+			None => continue,
+		};
+		if headers && !matches!(command, Command::Json | Command::Verify | Command::RoundTrip) {
+			println!("==> {} <==", input.source);
+		}
+		match command {
+			Command::Dump => print!("{}", dump(class_file)),
+			Command::ConstantPool => print!("{}", constant_pool(class_file)),
+			Command::Strings => {
+				for string in strings(class_file, options.all) {
+					println!("{:?}", string);
+				}
+			}
+			Command::Refs => {
+				for reference in refs(class_file) {
+					println!("{}", reference);
+				}
+			}
+			Command::Verify => {
+				let problems = verify(class_file, &class_path);
+				for problem in &problems {
+					println!("{}: {}", input.source, problem);
+				}
+				if !problems.is_empty() {
+					status = FAILURE;
+				}
+			}
+			Command::Json => match json(&input.source, class_file, options.resolved) {
+				Ok(line) => println!("{}", line),
+				Err(message) => {
+					eprintln!("{}: {}", input.source, message);
+					status = FAILURE;
+				}
+			},
+			Command::RoundTrip => {
+				let mut output = vec![];
+				let result = match class_file.to_bytes(&mut output) {
+					Ok(_) => first_difference(&input.data, &output)
+						.map(|offset| format!("differs at byte {} ({} bytes read, {} written)", offset, input.data.len(), output.len())),
+					Err(error) => Some(format!("failed to write: {:?}", error)),
+				};
+				if let Some(message) = result {
+					println!("{}: {}", input.source, message);
+					status = FAILURE;
+				}
+			}
+		}
+	}
+	if headers && matches!(command, Command::Verify | Command::RoundTrip) && status == SUCCESS {
+		eprintln!("{} classes ok", classes.len());
+	}
+	status
+}
+
+fn first_difference(left: &[u8], right: &[u8]) -> Option<usize> {
+	left.iter().zip(right)
+		.position(|(left, right)| left != right)
+		.or_else(|| if left.len() == right.len() { None } else { Some(left.len().min(right.len())) })
+}
+
+fn flag_names(flags: u16, names: &[(u16, &str)]) -> String {
+	let names: Vec<_> = names.iter()
+		.filter(|&&(flag, _)| flags & flag != 0)
+		.map(|&(_, name)| name)
+		.collect();
+	format!("(0x{:04x}) {}", flags, names.join(", "))
+}
+
+const CLASS_FLAGS: [(u16, &str); 9] = [
+	(PUBLIC, "ACC_PUBLIC"),
+	(FINAL, "ACC_FINAL"),
+	(SUPER, "ACC_SUPER"),
+	(INTERFACE, "ACC_INTERFACE"),
+	(ABSTRACT, "ACC_ABSTRACT"),
+	(SYNTHETIC, "ACC_SYNTHETIC"),
+	(ANNOTATION, "ACC_ANNOTATION"),
+	(ENUM, "ACC_ENUM"),
+	(MODULE, "ACC_MODULE"),
+];
+
+const FIELD_FLAGS: [(u16, &str); 9] = [
+	(PUBLIC, "ACC_PUBLIC"),
+	(PRIVATE, "ACC_PRIVATE"),
+	(PROTECTED, "ACC_PROTECTED"),
+	(STATIC, "ACC_STATIC"),
+	(FINAL, "ACC_FINAL"),
+	(VOLATILE, "ACC_VOLATILE"),
+	(TRANSIENT, "ACC_TRANSIENT"),
+	(SYNTHETIC, "ACC_SYNTHETIC"),
+	(ENUM, "ACC_ENUM"),
+];
+
+const METHOD_FLAGS: [(u16, &str); 12] = [
+	(PUBLIC, "ACC_PUBLIC"),
+	(PRIVATE, "ACC_PRIVATE"),
+	(PROTECTED, "ACC_PROTECTED"),
+	(STATIC, "ACC_STATIC"),
+	(FINAL, "ACC_FINAL"),
+	(SYNCHRONIZED, "ACC_SYNCHRONIZED"),
+	(BRIDGE, "ACC_BRIDGE"),
+	(VARARGS, "ACC_VARARGS"),
+	(NATIVE, "ACC_NATIVE"),
+	(ABSTRACT, "ACC_ABSTRACT"),
+	(STRICT, "ACC_STRICT"),
+	(SYNTHETIC, "ACC_SYNTHETIC"),
+];
+
+/// The name of the class the index points at, or `?` if it doesn't point at one.
+fn class_name<'a>(cp: &'a ConstantPool<'a>, index: CPIndex<'a, ClassInfo<'a>>) -> String {
+	cp.class_name(index).map(MStrExt::decoded).unwrap_or_else(|| "?".to_string())
+}
+
+fn utf8<'a>(cp: &'a ConstantPool<'a>, index: CPIndex<'a, UTF8Info<'a>>) -> String {
+	cp.utf8(index).map(MStrExt::decoded).unwrap_or_else(|| "?".to_string())
+}
+
+fn dump(class_file: &ClassFile) -> String {
+	let cp = &class_file.constant_pool;
+	let mut output = String::new();
+	writeln!(output, "class {}", class_name(cp, class_file.this_class)).unwrap();
+	writeln!(output, "  minor version: {}", class_file.minor_version).unwrap();
+	writeln!(output, "  major version: {}", class_file.major_version).unwrap();
+	writeln!(output, "  flags: {}", flag_names(class_file.access_flags, &CLASS_FLAGS)).unwrap();
+	writeln!(output, "  this_class: #{} // {}", class_file.this_class.index, class_name(cp, class_file.this_class)).unwrap();
+	match class_file.super_class.index {
+		0 => writeln!(output, "  super_class: #0").unwrap(),
+		index => writeln!(output, "  super_class: #{} // {}", index, class_name(cp, class_file.super_class)).unwrap(),
+	}
+	for &interface in &class_file.interfaces {
+		writeln!(output, "  interface: #{} // {}", interface.index, class_name(cp, interface)).unwrap();
+	}
+	writeln!(output, "  interfaces: {}, fields: {}, methods: {}, attributes: {}",
+		class_file.interfaces.len(), class_file.fields.len(), class_file.methods.len(), class_file.attributes.len()).unwrap();
+
+	output.push_str("{\n");
+	let mut first = true;
+	for field in &class_file.fields {
+		if !first {
+			output.push('\n');
+		}
+		first = false;
+		writeln!(output, "  {}: {}", utf8(cp, field.name_index), utf8(cp, field.descriptor_index)).unwrap();
+		writeln!(output, "    flags: {}", flag_names(field.access_flags, &FIELD_FLAGS)).unwrap();
+		dump_attributes(&mut output, "    ", &field.attributes, cp);
+	}
+	for method in &class_file.methods {
+		if !first {
+			output.push('\n');
+		}
+		first = false;
+		writeln!(output, "  {}{}", utf8(cp, method.name_index), utf8(cp, method.descriptor_index)).unwrap();
+		writeln!(output, "    flags: {}", flag_names(method.access_flags, &METHOD_FLAGS)).unwrap();
+		dump_attributes(&mut output, "    ", &method.attributes, cp);
+	}
+	output.push_str("}\n");
+	dump_attributes(&mut output, "", &class_file.attributes, cp);
+	output
+}
+
+fn dump_attributes(output: &mut String, indent: &str, attributes: &Attributes, cp: &ConstantPool) {
+	for attribute in attributes.iter() {
+		let name = utf8(cp, attribute.name_index());
+		let info = attribute.info();
+		let mut input = Cursor::new(info);
+		match name.as_str() {
+			"Code" => match Code::from_bytes(&mut input) {
+				Ok(code) => dump_code(output, indent, &code, cp),
+				Err(_) => writeln!(output, "{}Code: invalid, {} bytes", indent, info.len()).unwrap(),
+			},
+			"ConstantValue" => match ConstantValue::from_bytes(&mut input) {
+				Ok(value) => {
+					let index = value.constantvalue_index.index;
+					let value = constant(cp, index).unwrap_or_else(|| "?".to_string());
+					writeln!(output, "{}ConstantValue: #{} // {}", indent, index, value).unwrap();
+				}
+				Err(_) => writeln!(output, "{}ConstantValue: invalid", indent).unwrap(),
+			},
+			"Exceptions" => match Exceptions::from_bytes(&mut input) {
+				Ok(exceptions) => {
+					let names: Vec<_> = exceptions.table.iter()
+						.map(|&index| class_name(cp, index))
+						.collect();
+					writeln!(output, "{}Exceptions: {}", indent, names.join(", ")).unwrap();
+				}
+				Err(_) => writeln!(output, "{}Exceptions: invalid", indent).unwrap(),
+			},
+			"Signature" => match Signature::from_bytes(&mut input) {
+				Ok(signature) => writeln!(output, "{}Signature: {}", indent, utf8(cp, signature.class_index)).unwrap(),
+				Err(_) => writeln!(output, "{}Signature: invalid", indent).unwrap(),
+			},
+			"SourceFile" => match SourceFile::from_bytes(&mut input) {
+				Ok(source_file) => writeln!(output, "{}SourceFile: {:?}", indent, utf8(cp, source_file.sourcefile_index)).unwrap(),
+				Err(_) => writeln!(output, "{}SourceFile: invalid", indent).unwrap(),
+			},
+			_ => writeln!(output, "{}{}: {} bytes", indent, name, info.len()).unwrap(),
+		}
+	}
+}
+
+fn dump_code(output: &mut String, indent: &str, code: &Code, cp: &ConstantPool) {
+	writeln!(output, "{}Code:", indent).unwrap();
+	writeln!(output, "{}  stack={}, locals={}", indent, code.max_stack, code.max_locals).unwrap();
+	match bytecode::decode(&code.code) {
+		Ok(instructions) => {
+			for instruction in instructions {
+				let mut line = format!("{:>6}: {}", instruction.pc, instruction);
+				let index = match instruction.operand {
+					Operand::Constant(index) | Operand::InvokeInterface { index, .. } | Operand::MultiANewArray { index, .. } => Some(index),
+					_ => None,
+				};
+				if let Some(comment) = index.and_then(|index| constant(cp, index)) {
+					write!(line, " // {}", comment).unwrap();
+				}
+				writeln!(output, "{}  {}", indent, line).unwrap();
+			}
+		}
+		Err(error) => writeln!(output, "{}  invalid code: {:?}", indent, error).unwrap(),
+	}
+	if !code.exception_table.is_empty() {
+		writeln!(output, "{}  Exception table:", indent).unwrap();
+		writeln!(output, "{}     from    to  target type", indent).unwrap();
+		for exception in &code.exception_table {
+			let catch_type = match exception.catch_type.index {
+				0 => "any".to_string(),
+				_ => class_name(cp, exception.catch_type),
+			};
+			writeln!(output, "{}    {:>5} {:>5} {:>5}   {}", indent, exception.start_pc, exception.end_pc, exception.handler_pc, catch_type).unwrap();
+		}
+	}
+	let indent = format!("{}  ", indent);
+	dump_attributes(output, &indent, &code.attributes, cp);
+}
+
+fn constant_pool(class_file: &ClassFile) -> String {
+	let cp = &class_file.constant_pool;
+	let mut output = String::new();
+	for (i, entry) in cp.entries.iter().enumerate() {
+		let index = i + 1;
+		let (kind, operands) = match entry {
+			CPEntry::Class(info) => ("Class", format!("#{}", info.name_index.index)),
+			CPEntry::FieldRef(info) => ("Fieldref", format!("#{}.#{}", info.class_index.index, info.name_and_type_index.index)),
+			CPEntry::MethodRef(info) => ("Methodref", format!("#{}.#{}", info.class_index.index, info.name_and_type_index.index)),
+			CPEntry::InterfaceMethodRef(info) => ("InterfaceMethodref", format!("#{}.#{}", info.class_index.index, info.name_and_type_index.index)),
+			CPEntry::String(info) => ("String", format!("#{}", info.string_index.index)),
+			CPEntry::Integer(_) => ("Integer", String::new()),
+			CPEntry::Float(_) => ("Float", String::new()),
+			CPEntry::Long(_) => ("Long", String::new()),
+			CPEntry::Double(_) => ("Double", String::new()),
+			CPEntry::NameAndType(info) => ("NameAndType", format!("#{}:#{}", info.name_index.index, info.descriptor_index.index)),
+			CPEntry::UTF8(info) => ("Utf8", info.to_string()),
+			CPEntry::MethodHandle(info) => ("MethodHandle", format!("{}:#{}", info.reference_kind(), info.reference_index())),
+			CPEntry::MethodType(info) => ("MethodType", format!("#{}", info.descriptor_index.index)),
+			CPEntry::Dynamic(info) => ("Dynamic", format!("#{}:#{}", info.bootstrap_method_attr_index, info.name_and_type_index.index)),
+			CPEntry::InvokeDynamic(info) => ("InvokeDynamic", format!("#{}:#{}", info.bootstrap_method_attr_index, info.name_and_type_index.index)),
+			CPEntry::Module(info) => ("Module", format!("#{}", info.name_index.index)),
+			CPEntry::Package(info) => ("Package", format!("#{}", info.name_index.index)),
+			CPEntry::Unusable(_) => continue,
+		};
+		let comment = match entry {
+			CPEntry::UTF8(_) => None,
+			CPEntry::NameAndType(info) => info.resolve(cp).map(|name_and_type| name_and_type.to_string()),
+			CPEntry::Module(info) => cp.utf8(info.name_index).map(MStrExt::decoded),
+			CPEntry::Package(info) => cp.utf8(info.name_index).map(MStrExt::decoded),
+			_ => constant(cp, index as u16),
+		};
+		let line = format!("{:>5} = {:<18} {}", format!("#{}", index), kind, operands);
+		match comment {
+			Some(comment) if !operands.is_empty() => writeln!(output, "{:<42} // {}", line, comment).unwrap(),
+			Some(comment) => writeln!(output, "{}{}", line, comment).unwrap(),
+			None => writeln!(output, "{}", line.trim_end()).unwrap(),
+		}
+	}
+	output
+}
+
+/// The string literals, or every Utf8 constant with `all`, in the order they're in the constant pool.
+fn strings(class_file: &ClassFile, all: bool) -> Vec<String> {
+	let cp = &class_file.constant_pool;
+	cp.entries.iter()
+		.filter_map(|entry| match entry {
+			CPEntry::UTF8(info) if all => Some(info.to_string()),
+			CPEntry::String(info) if !all => cp.utf8(info.string_index).map(MStrExt::decoded),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Every class, field and method the constant pool refers to, apart from the class itself, sorted and without duplicates.
+///
+/// Array classes are listed by their element class, and arrays of primitives are left out.
+fn refs(class_file: &ClassFile) -> BTreeSet<String> {
+	let cp = &class_file.constant_pool;
+	let this_class = cp.class_name(class_file.this_class).map(MStrExt::decoded);
+	let mut refs = BTreeSet::new();
+	for entry in &cp.entries {
+		let reference = match entry {
+			CPEntry::Class(info) => {
+				let name = match cp.utf8(info.name_index) {
+					Some(name) => name.decoded(),
+					None => continue,
+				};
+				let name = match name.trim_start_matches('[') {
+					element if element.len() == name.len() => name.clone(),
+					element => match element.strip_prefix('L').and_then(|element| element.strip_suffix(';')) {
+						Some(element) => element.to_string(),
+						None => continue,
+					},
+				};
+				if Some(&name) == this_class.as_ref() {
+					continue;
+				}
+				format!("class {}", name)
+			}
+			CPEntry::FieldRef(info) => match info.resolve(cp) {
+				Some(field) => format!("field {}", field),
+				None => continue,
+			},
+			CPEntry::MethodRef(info) => match info.resolve(cp) {
+				Some(method) => format!("method {}", method),
+				None => continue,
+			},
+			CPEntry::InterfaceMethodRef(info) => match info.resolve(cp) {
+				Some(method) => format!("method {}", method),
+				None => continue,
+			},
+			_ => continue,
+		};
+		refs.insert(reference);
+	}
+	refs
+}
+
+fn verify(class_file: &ClassFile, class_path: &ClassPath) -> Vec<String> {
+	let format = format::validate(class_file);
+	if !format.is_empty() {
+		// The other checks assume the basic structure is sound, so there's no point running them.
+		return format.iter().map(ToString::to_string).collect();
+	}
+	let attributes = attributes::validate(class_file).into_iter().map(|diagnostic| diagnostic.to_string());
+	let typecheck = typecheck::validate(class_file, class_path).into_iter().map(|diagnostic| diagnostic.to_string());
+	attributes.chain(typecheck).collect()
+}
+
+#[cfg(feature = "json")]
+fn json(source: &str, class_file: &ClassFile, resolved: bool) -> Result<String, String> {
+	use class_file::serialize::ResolvedClass;
+
+	let class = if resolved {
+		let class = ResolvedClass::new(class_file).ok_or("dangling constant pool index")?;
+		serde_json::to_value(class)
+	} else {
+		serde_json::to_value(class_file)
+	};
+	let class = class.map_err(|error| error.to_string())?;
+	Ok(serde_json::json!({ "source": source, "class": class }).to_string())
+}
+
+#[cfg(not(feature = "json"))]
+fn json(_source: &str, _class_file: &ClassFile, _resolved: bool) -> Result<String, String> {
+	Err("built without the json feature".to_string())
+}
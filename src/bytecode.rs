@@ -0,0 +1,654 @@
+//! Decoding the `code` of a `Code` attribute into instructions, and encoding them back.
+//!
+//! Every instruction keeps its opcode as it was read, so `iload_0` stays `iload_0`,
+//! but the operands are made explicit, meaning it'll have an `Operand::Local(0)`.
+//! Branch targets are absolute offsets into the code, rather than relative to the instruction.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::ops::*;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Operand {
+	None,
+	/// The value pushed by `bipush`.
+	Byte(i8),
+	/// The value pushed by `sipush`.
+	Short(i16),
+	/// The local variable index of loads, stores and `ret`.
+	Local(u16),
+	/// An index into the constant pool.
+	Constant(u16),
+	/// The absolute offset of the branch target.
+	Branch(u32),
+	Iinc {
+		index: u16,
+		value: i16,
+	},
+	InvokeInterface {
+		index: u16,
+		count: u8,
+	},
+	/// One of the `T_*` array types.
+	NewArray(u8),
+	MultiANewArray {
+		index: u16,
+		dimensions: u8,
+	},
+	TableSwitch {
+		default: u32,
+		low: i32,
+		/// The targets for `low`, `low + 1` and so on.
+		targets: Vec<u32>,
+	},
+	LookupSwitch {
+		default: u32,
+		pairs: Vec<(i32, u32)>,
+	},
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Instruction {
+	/// The offset of the instruction in the code.
+	pub pc: u32,
+	pub opcode: u8,
+	/// Whether the instruction was prefixed by `wide`.
+	pub wide: bool,
+	pub operand: Operand,
+}
+
+impl Instruction {
+	/// Every branch target of the instruction, including the default of a switch, but not the instruction that follows.
+	pub fn targets(&self) -> Vec<u32> {
+		match self.operand {
+			Operand::Branch(target) => vec![target],
+			Operand::TableSwitch { default, ref targets, .. } => {
+				let mut result = vec![default];
+				result.extend(targets);
+				result
+			}
+			Operand::LookupSwitch { default, ref pairs } => {
+				let mut result = vec![default];
+				result.extend(pairs.iter().map(|&(_, target)| target));
+				result
+			}
+			_ => vec![],
+		}
+	}
+
+	/// Whether execution never continues with the next instruction.
+	pub fn is_unconditional(&self) -> bool {
+		matches!(self.opcode, GOTO | GOTO_W | TABLESWITCH | LOOKUPSWITCH | ATHROW | RET | IRETURN..=RETURN)
+	}
+}
+
+impl fmt::Display for Instruction {
+	/// Roughly the way `javap -c` prints instructions.
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", mnemonic(self.opcode).unwrap_or("???"))?;
+		match self.operand {
+			Operand::None => Ok(()),
+			Operand::Byte(value) => write!(f, " {}", value),
+			Operand::Short(value) => write!(f, " {}", value),
+			// The short forms already have the index in their name.
+			Operand::Local(_) if !self.wide && mnemonic(self.opcode).is_some_and(|name| name.contains('_')) => Ok(()),
+			Operand::Local(index) => write!(f, " {}", index),
+			Operand::Constant(index) => write!(f, " #{}", index),
+			Operand::Branch(target) => write!(f, " {}", target),
+			Operand::Iinc { index, value } => write!(f, " {}, {}", index, value),
+			Operand::InvokeInterface { index, count } => write!(f, " #{}, {}", index, count),
+			Operand::NewArray(atype) => write!(f, " {}", array_type_name(atype).unwrap_or("???")),
+			Operand::MultiANewArray { index, dimensions } => write!(f, " #{}, {}", index, dimensions),
+			Operand::TableSwitch { default, low, ref targets } => {
+				f.write_str(" {")?;
+				for (i, target) in targets.iter().enumerate() {
+					write!(f, " {}: {};", low as i64 + i as i64, target)?;
+				}
+				write!(f, " default: {} }}", default)
+			}
+			Operand::LookupSwitch { default, ref pairs } => {
+				f.write_str(" {")?;
+				for (key, target) in pairs {
+					write!(f, " {}: {};", key, target)?;
+				}
+				write!(f, " default: {} }}", default)
+			}
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum DecodeError {
+	/// The instruction at the given offset runs past the end of the code.
+	Truncated(u32),
+	UnknownOpcode {
+		pc: u32,
+		opcode: u8,
+	},
+	/// `wide` followed by an instruction that can't be widened.
+	InvalidWide {
+		pc: u32,
+		opcode: u8,
+	},
+	/// The branch target lies outside of the code.
+	InvalidTarget {
+		pc: u32,
+		target: i64,
+	},
+	/// A `tableswitch` whose `high` is lower than its `low`.
+	InvalidSwitch(u32),
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DecodeError::Truncated(pc) => write!(f, "instruction at {} is truncated", pc),
+			DecodeError::UnknownOpcode { pc, opcode } => write!(f, "unknown opcode {} at {}", opcode, pc),
+			DecodeError::InvalidWide { pc, opcode } => write!(f, "{} at {} can't be wide", mnemonic(opcode).unwrap_or("???"), pc),
+			DecodeError::InvalidTarget { pc, target } => write!(f, "branch at {} targets {}, outside of the code", pc, target),
+			DecodeError::InvalidSwitch(pc) => write!(f, "tableswitch at {} has high < low", pc),
+		}
+	}
+}
+
+struct Reader<'c> {
+	code: &'c [u8],
+	position: usize,
+	/// The offset of the instruction being read.
+	pc: u32,
+}
+
+impl<'c> Reader<'c> {
+	fn bytes(&mut self, len: usize) -> Result<&'c [u8], DecodeError> {
+		let bytes = self.code.get(self.position..self.position + len)
+			.ok_or(DecodeError::Truncated(self.pc))?;
+		self.position += len;
+		Ok(bytes)
+	}
+
+	fn u8(&mut self) -> Result<u8, DecodeError> {
+		Ok(self.bytes(1)?[0])
+	}
+
+	fn u16(&mut self) -> Result<u16, DecodeError> {
+		let bytes = self.bytes(2)?;
+		Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+	}
+
+	fn i32(&mut self) -> Result<i32, DecodeError> {
+		let bytes = self.bytes(4)?;
+		Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+	}
+
+	fn target(&self, offset: i32) -> Result<u32, DecodeError> {
+		let target = self.pc as i64 + offset as i64;
+		if target < 0 || target >= self.code.len() as i64 {
+			return Err(DecodeError::InvalidTarget { pc: self.pc, target });
+		}
+		Ok(target as u32)
+	}
+
+	fn instruction(&mut self) -> Result<Instruction, DecodeError> {
+		self.pc = self.position as u32;
+		let mut opcode = self.u8()?;
+		let wide = opcode == WIDE;
+		if wide {
+			opcode = self.u8()?;
+			match opcode {
+				ILOAD..=ALOAD | ISTORE..=ASTORE | RET | IINC => {}
+				_ => return Err(DecodeError::InvalidWide { pc: self.pc, opcode }),
+			}
+		}
+
+		let operand = match opcode {
+			BIPUSH => Operand::Byte(self.u8()? as i8),
+			SIPUSH => Operand::Short(self.u16()? as i16),
+			LDC => Operand::Constant(self.u8()? as u16),
+			ILOAD..=ALOAD | ISTORE..=ASTORE | RET => {
+				let index = if wide { self.u16()? } else { self.u8()? as u16 };
+				Operand::Local(index)
+			}
+			ILOAD_0..=ALOAD_3 => Operand::Local(((opcode - ILOAD_0) % 4) as u16),
+			ISTORE_0..=ASTORE_3 => Operand::Local(((opcode - ISTORE_0) % 4) as u16),
+			IINC => {
+				let (index, value) = if wide {
+					(self.u16()?, self.u16()? as i16)
+				} else {
+					(self.u8()? as u16, self.u8()? as i8 as i16)
+				};
+				Operand::Iinc { index, value }
+			}
+			IFEQ..=JSR | IFNULL | IFNONNULL => {
+				let offset = self.u16()? as i16;
+				Operand::Branch(self.target(offset as i32)?)
+			}
+			GOTO_W | JSR_W => {
+				let offset = self.i32()?;
+				Operand::Branch(self.target(offset)?)
+			}
+			TABLESWITCH | LOOKUPSWITCH => {
+				// The operands are aligned to a multiple of four bytes from the start of the code.
+				self.bytes((4 - self.position % 4) % 4)?;
+				let default = self.i32()?;
+				let default = self.target(default)?;
+				if opcode == TABLESWITCH {
+					let low = self.i32()?;
+					let high = self.i32()?;
+					if high < low {
+						return Err(DecodeError::InvalidSwitch(self.pc));
+					}
+					let targets = (low as i64..=high as i64)
+						.map(|_| {
+							let offset = self.i32()?;
+							self.target(offset)
+						})
+						.collect::<Result<Vec<_>, _>>()?;
+					Operand::TableSwitch { default, low, targets }
+				} else {
+					let len = self.i32()?;
+					if len < 0 {
+						return Err(DecodeError::Truncated(self.pc));
+					}
+					let pairs = (0..len)
+						.map(|_| {
+							let key = self.i32()?;
+							let offset = self.i32()?;
+							Ok((key, self.target(offset)?))
+						})
+						.collect::<Result<Vec<_>, _>>()?;
+					Operand::LookupSwitch { default, pairs }
+				}
+			}
+			LDC_W | LDC2_W | GETSTATIC..=INVOKESTATIC | NEW | ANEWARRAY | CHECKCAST | INSTANCEOF => {
+				Operand::Constant(self.u16()?)
+			}
+			INVOKEINTERFACE => {
+				let index = self.u16()?;
+				let count = self.u8()?;
+				self.u8()?;
+				Operand::InvokeInterface { index, count }
+			}
+			INVOKEDYNAMIC => {
+				let index = self.u16()?;
+				self.u16()?;
+				Operand::Constant(index)
+			}
+			NEWARRAY => Operand::NewArray(self.u8()?),
+			MULTIANEWARRAY => {
+				let index = self.u16()?;
+				let dimensions = self.u8()?;
+				Operand::MultiANewArray { index, dimensions }
+			}
+			_ if mnemonic(opcode).is_some() => Operand::None,
+			_ => return Err(DecodeError::UnknownOpcode { pc: self.pc, opcode }),
+		};
+		Ok(Instruction {
+			pc: self.pc,
+			opcode,
+			wide,
+			operand,
+		})
+	}
+}
+
+/// Decodes all of the instructions, in order.
+pub fn decode(code: &[u8]) -> Result<Vec<Instruction>, DecodeError> {
+	let mut reader = Reader {
+		code,
+		position: 0,
+		pc: 0,
+	};
+	let mut instructions = vec![];
+	while reader.position < code.len() {
+		instructions.push(reader.instruction()?);
+	}
+	Ok(instructions)
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum EncodeError {
+	/// The branch at `pc` targets something that isn't the `pc` of one of the instructions.
+	InvalidTarget {
+		pc: u32,
+		target: u32,
+	},
+	/// The branch at `pc` ended up too far from its target for a 16-bit offset.
+	///
+	/// Only conditional branches, as `goto` and `jsr` get widened, see `encode`.
+	TooFar {
+		pc: u32,
+		target: u32,
+	},
+	/// The operand doesn't go with the opcode, or doesn't fit in it.
+	InvalidOperand {
+		pc: u32,
+		opcode: u8,
+	},
+}
+
+impl fmt::Display for EncodeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			EncodeError::InvalidTarget { pc, target } => write!(f, "branch at {} targets {}, which isn't the start of an instruction", pc, target),
+			EncodeError::TooFar { pc, target } => write!(f, "branch at {} is too far from its target {}", pc, target),
+			EncodeError::InvalidOperand { pc, opcode } => write!(f, "invalid operand for {} at {}", mnemonic(opcode).unwrap_or("???"), pc),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Encoded {
+	pub code: Vec<u8>,
+	/// Where each instruction ended up, by its `pc`, along with where the end of the old code is now.
+	pub offsets: BTreeMap<u32, u32>,
+}
+
+/// The local an opcode like `iload_2` implies, and the opcode that takes it as an operand instead, `iload` here.
+fn shorthand(opcode: u8) -> (u16, u8) {
+	let (first, general) = if opcode >= ISTORE_0 { (ISTORE_0, ISTORE) } else { (ILOAD_0, ILOAD) };
+	(((opcode - first) % 4) as u16, general + (opcode - first) / 4)
+}
+
+/// The opcode an instruction gets written as, and whether it needs a `wide`.
+///
+/// `far` is whether a `goto` or `jsr` has to be written as `goto_w` or `jsr_w`.
+fn form(instruction: &Instruction, far: bool) -> (u8, bool) {
+	match (instruction.opcode, &instruction.operand) {
+		(LDC, &Operand::Constant(index)) | (LDC_W, &Operand::Constant(index)) => {
+			(if index > u8::MAX as u16 { LDC_W } else { LDC }, false)
+		}
+		(ILOAD_0..=ALOAD_3, &Operand::Local(index)) | (ISTORE_0..=ASTORE_3, &Operand::Local(index)) => {
+			// A local other than the one the opcode implies, or a `wide`, needs the general form.
+			match shorthand(instruction.opcode) {
+				(local, _) if local == index && !instruction.wide => (instruction.opcode, false),
+				(_, general) => (general, instruction.wide || index > u8::MAX as u16),
+			}
+		}
+		(GOTO, &Operand::Branch(_)) if far => (GOTO_W, false),
+		(JSR, &Operand::Branch(_)) if far => (JSR_W, false),
+		(ILOAD..=ALOAD, &Operand::Local(index)) | (ISTORE..=ASTORE, &Operand::Local(index)) | (RET, &Operand::Local(index)) => {
+			(instruction.opcode, instruction.wide || index > u8::MAX as u16)
+		}
+		(IINC, &Operand::Iinc { index, value }) => {
+			(IINC, instruction.wide || index > u8::MAX as u16 || value != value as i8 as i16)
+		}
+		_ => (instruction.opcode, instruction.wide),
+	}
+}
+
+/// The number of bytes the instruction takes up at `pc`, which only matters to switches.
+fn size(opcode: u8, wide: bool, operand: &Operand, pc: usize) -> usize {
+	match opcode {
+		IINC if wide => 6,
+		_ if wide => 4,
+		BIPUSH | LDC | ILOAD..=ALOAD | ISTORE..=ASTORE | RET | NEWARRAY => 2,
+		SIPUSH | LDC_W | LDC2_W | IINC | IFEQ..=JSR | IFNULL | IFNONNULL
+		| GETSTATIC..=INVOKESTATIC | NEW | ANEWARRAY | CHECKCAST | INSTANCEOF => 3,
+		MULTIANEWARRAY => 4,
+		GOTO_W | JSR_W | INVOKEINTERFACE | INVOKEDYNAMIC => 5,
+		TABLESWITCH | LOOKUPSWITCH => {
+			let padding = (4 - (pc + 1) % 4) % 4;
+			let operands = match *operand {
+				Operand::TableSwitch { ref targets, .. } => 12 + 4 * targets.len(),
+				Operand::LookupSwitch { ref pairs, .. } => 8 + 8 * pairs.len(),
+				_ => 0,
+			};
+			1 + padding + operands
+		}
+		_ => 1,
+	}
+}
+
+/// Pads the operands of a switch to a multiple of four bytes from the start of the code.
+fn align(code: &mut Vec<u8>) {
+	let padding = (4 - code.len() % 4) % 4;
+	code.extend_from_slice(&[0; 3][..padding]);
+}
+
+fn write(code: &mut Vec<u8>, instruction: &Instruction, far: bool, offsets: &BTreeMap<u32, u32>) -> Result<(), EncodeError> {
+	let pc = instruction.pc;
+	let start = code.len() as i64;
+	let (opcode, wide) = form(instruction, far);
+	let invalid = EncodeError::InvalidOperand { pc, opcode: instruction.opcode };
+	let offset = |target: u32| -> Result<i64, EncodeError> {
+		offsets.get(&target)
+			.map(|&offset| offset as i64 - start)
+			.ok_or(EncodeError::InvalidTarget { pc, target })
+	};
+	let offset32 = |target: u32| -> Result<[u8; 4], EncodeError> {
+		let offset = offset(target)?;
+		if offset != offset as i32 as i64 {
+			return Err(EncodeError::TooFar { pc, target });
+		}
+		Ok((offset as i32).to_be_bytes())
+	};
+
+	if wide {
+		code.push(WIDE);
+	}
+	code.push(opcode);
+	match (opcode, &instruction.operand) {
+		(BIPUSH, &Operand::Byte(value)) => code.push(value as u8),
+		(SIPUSH, &Operand::Short(value)) => code.extend_from_slice(&value.to_be_bytes()),
+		(LDC, &Operand::Constant(index)) => code.push(index as u8),
+		(ILOAD..=ALOAD, &Operand::Local(index)) | (ISTORE..=ASTORE, &Operand::Local(index)) | (RET, &Operand::Local(index)) => {
+			if wide {
+				code.extend_from_slice(&index.to_be_bytes());
+			} else {
+				code.push(index as u8);
+			}
+		}
+		// `form` only keeps these when the local is the one they imply.
+		(ILOAD_0..=ALOAD_3, &Operand::Local(_)) | (ISTORE_0..=ASTORE_3, &Operand::Local(_)) => {}
+		(ILOAD_0..=ALOAD_3, _) | (ISTORE_0..=ASTORE_3, _) => return Err(invalid),
+		(IINC, &Operand::Iinc { index, value }) => {
+			if wide {
+				code.extend_from_slice(&index.to_be_bytes());
+				code.extend_from_slice(&value.to_be_bytes());
+			} else {
+				code.push(index as u8);
+				code.push(value as u8);
+			}
+		}
+		(IFEQ..=JSR, &Operand::Branch(target)) | (IFNULL, &Operand::Branch(target)) | (IFNONNULL, &Operand::Branch(target)) => {
+			let offset = offset(target)?;
+			if offset != offset as i16 as i64 {
+				return Err(EncodeError::TooFar { pc, target });
+			}
+			code.extend_from_slice(&(offset as i16).to_be_bytes());
+		}
+		(GOTO_W, &Operand::Branch(target)) | (JSR_W, &Operand::Branch(target)) => {
+			code.extend_from_slice(&offset32(target)?);
+		}
+		(TABLESWITCH, &Operand::TableSwitch { default, low, ref targets }) => {
+			let high = low as i64 + targets.len() as i64 - 1;
+			if targets.is_empty() || high != high as i32 as i64 {
+				return Err(invalid);
+			}
+			align(code);
+			code.extend_from_slice(&offset32(default)?);
+			code.extend_from_slice(&low.to_be_bytes());
+			code.extend_from_slice(&(high as i32).to_be_bytes());
+			for &target in targets {
+				code.extend_from_slice(&offset32(target)?);
+			}
+		}
+		(LOOKUPSWITCH, &Operand::LookupSwitch { default, ref pairs }) => {
+			align(code);
+			code.extend_from_slice(&offset32(default)?);
+			code.extend_from_slice(&(pairs.len() as i32).to_be_bytes());
+			for &(key, target) in pairs {
+				code.extend_from_slice(&key.to_be_bytes());
+				code.extend_from_slice(&offset32(target)?);
+			}
+		}
+		(LDC_W, &Operand::Constant(index))
+		| (LDC2_W, &Operand::Constant(index))
+		| (GETSTATIC..=INVOKESTATIC, &Operand::Constant(index))
+		| (NEW, &Operand::Constant(index))
+		| (ANEWARRAY, &Operand::Constant(index))
+		| (CHECKCAST, &Operand::Constant(index))
+		| (INSTANCEOF, &Operand::Constant(index)) => code.extend_from_slice(&index.to_be_bytes()),
+		(INVOKEINTERFACE, &Operand::InvokeInterface { index, count }) => {
+			code.extend_from_slice(&index.to_be_bytes());
+			code.push(count);
+			code.push(0);
+		}
+		(INVOKEDYNAMIC, &Operand::Constant(index)) => {
+			code.extend_from_slice(&index.to_be_bytes());
+			code.extend_from_slice(&[0, 0]);
+		}
+		(NEWARRAY, &Operand::NewArray(atype)) => code.push(atype),
+		(MULTIANEWARRAY, &Operand::MultiANewArray { index, dimensions }) => {
+			code.extend_from_slice(&index.to_be_bytes());
+			code.push(dimensions);
+		}
+		// Everything else is a single byte, with nothing following it.
+		(_, Operand::None) if opcode != WIDE && mnemonic(opcode).is_some() && size(opcode, false, &Operand::None, 0) == 1 => {}
+		_ => return Err(invalid),
+	}
+	Ok(())
+}
+
+/// Encodes the instructions one after another, the opposite of `decode`.
+///
+/// The `pc` of each instruction doesn't have to match where it ends up, but branch targets are taken to be
+/// the `pc` of the instruction they point at, so a decoded method can be edited and put back together.
+/// `ldc` and `ldc_w` are written as whichever one fits the index, a local variable index
+/// that doesn't fit in a byte gets a `wide`, and `iload_0` and the like with some other local become `iload`.
+/// A `goto` or `jsr` that can't reach its target with a 16-bit offset becomes a `goto_w` or `jsr_w`.
+/// Conditional branches have no such form, so one that ends up out of reach is `TooFar`,
+/// and has to be turned around to jump over a `goto_w` before encoding.
+/// All of that can move things around, so the result says where everything went.
+pub fn encode(instructions: &[Instruction]) -> Result<Encoded, EncodeError> {
+	// Widening one branch can push others out of reach, so keep going until none are.
+	let mut far = BTreeSet::new();
+	let (offsets, length) = loop {
+		let (offsets, length) = layout(instructions, &far);
+		let before = far.len();
+		for instruction in instructions {
+			let target = match (instruction.opcode, &instruction.operand) {
+				(GOTO, &Operand::Branch(target)) | (JSR, &Operand::Branch(target)) => target,
+				_ => continue,
+			};
+			if let (Some(&from), Some(&to)) = (offsets.get(&instruction.pc), offsets.get(&target)) {
+				let offset = to as i64 - from as i64;
+				if offset != offset as i16 as i64 {
+					far.insert(instruction.pc);
+				}
+			}
+		}
+		if far.len() == before {
+			break (offsets, length);
+		}
+	};
+
+	let mut code = Vec::with_capacity(length);
+	for instruction in instructions {
+		write(&mut code, instruction, far.contains(&instruction.pc), &offsets)?;
+	}
+	Ok(Encoded { code, offsets })
+}
+
+/// Where each instruction goes, by its `pc`, with the `goto`s and `jsr`s at the `pc`s in `far` widened,
+/// along with the length of the code.
+fn layout(instructions: &[Instruction], far: &BTreeSet<u32>) -> (BTreeMap<u32, u32>, usize) {
+	let mut offsets = BTreeMap::new();
+	let mut position = 0;
+	for instruction in instructions {
+		let (opcode, wide) = form(instruction, far.contains(&instruction.pc));
+		offsets.insert(instruction.pc, position as u32);
+		position += size(opcode, wide, &instruction.operand, position);
+	}
+	if let Some(last) = instructions.last() {
+		let end = last.pc as usize + size(last.opcode, last.wide, &last.operand, last.pc as usize);
+		offsets.entry(end as u32).or_insert(position as u32);
+	}
+	(offsets, position)
+}
+
+macro_rules! mnemonics {
+	( $( $opcode:ident => $name:literal, )* ) => {
+		/// The name of the instruction, as used by `javap`.
+		pub fn mnemonic(opcode: u8) -> Option<&'static str> {
+			let name = match opcode {
+				$( $opcode => $name, )*
+				_ => return None,
+			};
+			Some(name)
+		}
+	};
+}
+
+mnemonics! {
+	NOP => "nop", ACONST_NULL => "aconst_null",
+	ICONST_M1 => "iconst_m1", ICONST_0 => "iconst_0", ICONST_1 => "iconst_1", ICONST_2 => "iconst_2",
+	ICONST_3 => "iconst_3", ICONST_4 => "iconst_4", ICONST_5 => "iconst_5",
+	LCONST_0 => "lconst_0", LCONST_1 => "lconst_1",
+	FCONST_0 => "fconst_0", FCONST_1 => "fconst_1", FCONST_2 => "fconst_2",
+	DCONST_0 => "dconst_0", DCONST_1 => "dconst_1",
+	BIPUSH => "bipush", SIPUSH => "sipush", LDC => "ldc", LDC_W => "ldc_w", LDC2_W => "ldc2_w",
+	ILOAD => "iload", LLOAD => "lload", FLOAD => "fload", DLOAD => "dload", ALOAD => "aload",
+	ILOAD_0 => "iload_0", ILOAD_1 => "iload_1", ILOAD_2 => "iload_2", ILOAD_3 => "iload_3",
+	LLOAD_0 => "lload_0", LLOAD_1 => "lload_1", LLOAD_2 => "lload_2", LLOAD_3 => "lload_3",
+	FLOAD_0 => "fload_0", FLOAD_1 => "fload_1", FLOAD_2 => "fload_2", FLOAD_3 => "fload_3",
+	DLOAD_0 => "dload_0", DLOAD_1 => "dload_1", DLOAD_2 => "dload_2", DLOAD_3 => "dload_3",
+	ALOAD_0 => "aload_0", ALOAD_1 => "aload_1", ALOAD_2 => "aload_2", ALOAD_3 => "aload_3",
+	IALOAD => "iaload", LALOAD => "laload", FALOAD => "faload", DALOAD => "daload",
+	AALOAD => "aaload", BALOAD => "baload", CALOAD => "caload", SALOAD => "saload",
+	ISTORE => "istore", LSTORE => "lstore", FSTORE => "fstore", DSTORE => "dstore", ASTORE => "astore",
+	ISTORE_0 => "istore_0", ISTORE_1 => "istore_1", ISTORE_2 => "istore_2", ISTORE_3 => "istore_3",
+	LSTORE_0 => "lstore_0", LSTORE_1 => "lstore_1", LSTORE_2 => "lstore_2", LSTORE_3 => "lstore_3",
+	FSTORE_0 => "fstore_0", FSTORE_1 => "fstore_1", FSTORE_2 => "fstore_2", FSTORE_3 => "fstore_3",
+	DSTORE_0 => "dstore_0", DSTORE_1 => "dstore_1", DSTORE_2 => "dstore_2", DSTORE_3 => "dstore_3",
+	ASTORE_0 => "astore_0", ASTORE_1 => "astore_1", ASTORE_2 => "astore_2", ASTORE_3 => "astore_3",
+	IASTORE => "iastore", LASTORE => "lastore", FASTORE => "fastore", DASTORE => "dastore",
+	AASTORE => "aastore", BASTORE => "bastore", CASTORE => "castore", SASTORE => "sastore",
+	POP => "pop", POP2 => "pop2", DUP => "dup", DUP_X1 => "dup_x1", DUP_X2 => "dup_x2",
+	DUP2 => "dup2", DUP2_X1 => "dup2_x1", DUP2_X2 => "dup2_x2", SWAP => "swap",
+	IADD => "iadd", LADD => "ladd", FADD => "fadd", DADD => "dadd",
+	ISUB => "isub", LSUB => "lsub", FSUB => "fsub", DSUB => "dsub",
+	IMUL => "imul", LMUL => "lmul", FMUL => "fmul", DMUL => "dmul",
+	IDIV => "idiv", LDIV => "ldiv", FDIV => "fdiv", DDIV => "ddiv",
+	IREM => "irem", LREM => "lrem", FREM => "frem", DREM => "drem",
+	INEG => "ineg", LNEG => "lneg", FNEG => "fneg", DNEG => "dneg",
+	ISHL => "ishl", LSHL => "lshl", ISHR => "ishr", LSHR => "lshr", IUSHR => "iushr", LUSHR => "lushr",
+	IAND => "iand", LAND => "land", IOR => "ior", LOR => "lor", IXOR => "ixor", LXOR => "lxor",
+	IINC => "iinc",
+	I2L => "i2l", I2F => "i2f", I2D => "i2d", L2I => "l2i", L2F => "l2f", L2D => "l2d",
+	F2I => "f2i", F2L => "f2l", F2D => "f2d", D2I => "d2i", D2L => "d2l", D2F => "d2f",
+	I2B => "i2b", I2C => "i2c", I2S => "i2s",
+	LCMP => "lcmp", FCMPL => "fcmpl", FCMPG => "fcmpg", DCMPL => "dcmpl", DCMPG => "dcmpg",
+	IFEQ => "ifeq", IFNE => "ifne", IFLT => "iflt", IFGE => "ifge", IFGT => "ifgt", IFLE => "ifle",
+	IF_ICMPEQ => "if_icmpeq", IF_ICMPNE => "if_icmpne", IF_ICMPLT => "if_icmplt",
+	IF_ICMPGE => "if_icmpge", IF_ICMPGT => "if_icmpgt", IF_ICMPLE => "if_icmple",
+	IF_ACMPEQ => "if_acmpeq", IF_ACMPNE => "if_acmpne",
+	GOTO => "goto", JSR => "jsr", RET => "ret", TABLESWITCH => "tableswitch", LOOKUPSWITCH => "lookupswitch",
+	IRETURN => "ireturn", LRETURN => "lreturn", FRETURN => "freturn", DRETURN => "dreturn",
+	ARETURN => "areturn", RETURN => "return",
+	GETSTATIC => "getstatic", PUTSTATIC => "putstatic", GETFIELD => "getfield", PUTFIELD => "putfield",
+	INVOKEVIRTUAL => "invokevirtual", INVOKESPECIAL => "invokespecial", INVOKESTATIC => "invokestatic",
+	INVOKEINTERFACE => "invokeinterface", INVOKEDYNAMIC => "invokedynamic",
+	NEW => "new", NEWARRAY => "newarray", ANEWARRAY => "anewarray", ARRAYLENGTH => "arraylength",
+	ATHROW => "athrow", CHECKCAST => "checkcast", INSTANCEOF => "instanceof",
+	MONITORENTER => "monitorenter", MONITOREXIT => "monitorexit", WIDE => "wide",
+	MULTIANEWARRAY => "multianewarray", IFNULL => "ifnull", IFNONNULL => "ifnonnull",
+	GOTO_W => "goto_w", JSR_W => "jsr_w",
+}
+
+/// The name of a `newarray` type, such as `int` for `T_INT`.
+pub fn array_type_name(atype: u8) -> Option<&'static str> {
+	let name = match atype {
+		T_BOOLEAN => "boolean",
+		T_CHAR => "char",
+		T_FLOAT => "float",
+		T_DOUBLE => "double",
+		T_BYTE => "byte",
+		T_SHORT => "short",
+		T_INT => "int",
+		T_LONG => "long",
+		_ => return None,
+	};
+	Some(name)
+}
@@ -0,0 +1,443 @@
+//! Splitting the code of a method into basic blocks, and the control flow graph between them.
+//!
+//! Blocks start at the beginning of the code, at every branch, switch and `jsr` target, at every exception handler,
+//! and wherever a protected range starts or ends, so a block is either completely covered by a handler or not at all.
+//! They end after every branch, switch, `athrow`, return, `jsr` and `ret`.
+//!
+//! Blocks are numbered in the order they appear in the code, so the entry is always block 0.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::iter;
+
+use crate::*;
+use crate::attr::Code;
+use crate::bytecode::{self, DecodeError, Instruction, Operand};
+use crate::utf8::MStrExt;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum EdgeKind {
+	/// Execution carrying on with the next block, including after a conditional branch that isn't taken.
+	Fallthrough,
+	/// A `goto`, or a conditional branch that's taken.
+	Branch,
+	/// A `tableswitch` or `lookupswitch` case, `None` being the default.
+	Switch(Option<i32>),
+	/// An exception handler, with the internal name of the class it catches, `None` catching everything.
+	Exception(Option<String>),
+	/// A `jsr` calling a subroutine.
+	Jsr,
+	/// A `ret` returning from a subroutine, to the block following one of the `jsr`s that called it.
+	Ret,
+}
+
+impl fmt::Display for EdgeKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			EdgeKind::Fallthrough => f.write_str("fallthrough"),
+			EdgeKind::Branch => f.write_str("branch"),
+			EdgeKind::Switch(Some(key)) => write!(f, "case {}", key),
+			EdgeKind::Switch(None) => f.write_str("default"),
+			EdgeKind::Exception(Some(catch_type)) => write!(f, "catch {}", catch_type),
+			EdgeKind::Exception(None) => f.write_str("catch any"),
+			EdgeKind::Jsr => f.write_str("jsr"),
+			EdgeKind::Ret => f.write_str("ret"),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Edge {
+	pub from: usize,
+	pub to: usize,
+	pub kind: EdgeKind,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Block {
+	/// The offset of the first instruction.
+	pub start: u32,
+	/// The offset right after the last instruction.
+	pub end: u32,
+	pub instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum BuildError {
+	Decode(DecodeError),
+	/// The branch at `pc` doesn't target the start of an instruction.
+	InvalidTarget {
+		pc: u32,
+		target: u32,
+	},
+	/// The exception table entry at the given index doesn't line up with the instructions, or has an invalid catch type.
+	InvalidHandler(usize),
+	/// There's no method at the given index.
+	MissingMethod(usize),
+	/// The method's `Code` attribute couldn't be read.
+	MalformedCode,
+}
+
+impl fmt::Display for BuildError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			BuildError::Decode(error) => write!(f, "{}", error),
+			BuildError::InvalidTarget { pc, target } => write!(f, "branch at {} targets {}, which isn't the start of an instruction", pc, target),
+			BuildError::InvalidHandler(index) => write!(f, "invalid exception table entry #{}", index),
+			BuildError::MissingMethod(index) => write!(f, "no method #{}", index),
+			BuildError::MalformedCode => f.write_str("malformed Code attribute"),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ControlFlowGraph {
+	pub blocks: Vec<Block>,
+	pub edges: Vec<Edge>,
+}
+
+/// Builds the graph for the given code, the constant pool being needed for the names of the caught classes.
+pub fn build(code: &Code, cp: &ConstantPool) -> Result<ControlFlowGraph, BuildError> {
+	let instructions = bytecode::decode(&code.code)
+		.map_err(BuildError::Decode)?;
+	let starts: BTreeSet<u32> = instructions.iter()
+		.map(|instruction| instruction.pc)
+		.collect();
+	let length = code.code.len() as u32;
+
+	let mut leaders = BTreeSet::new();
+	leaders.insert(0);
+	for (i, instruction) in instructions.iter().enumerate() {
+		for target in instruction.targets() {
+			if !starts.contains(&target) {
+				return Err(BuildError::InvalidTarget { pc: instruction.pc, target });
+			}
+			leaders.insert(target);
+		}
+		let ends_block = instruction.is_unconditional() || !instruction.targets().is_empty();
+		if let (true, Some(next)) = (ends_block, instructions.get(i + 1)) {
+			leaders.insert(next.pc);
+		}
+	}
+	let mut handlers = vec![];
+	for (i, exception) in code.exception_table.iter().enumerate() {
+		let (start, end, handler) = (exception.start_pc as u32, exception.end_pc as u32, exception.handler_pc as u32);
+		let lines_up = |pc| starts.contains(&pc) || pc == length;
+		if start >= end || !starts.contains(&start) || !lines_up(end) || !starts.contains(&handler) {
+			return Err(BuildError::InvalidHandler(i));
+		}
+		let catch_type = match exception.catch_type.index {
+			0 => None,
+			_ => {
+				let name = cp.class_name(exception.catch_type)
+					.ok_or(BuildError::InvalidHandler(i))?;
+				Some(name.decoded())
+			}
+		};
+		leaders.extend([start, handler]);
+		if end < length {
+			leaders.insert(end);
+		}
+		handlers.push((start, end, handler, catch_type));
+	}
+
+	let mut blocks: Vec<Block> = vec![];
+	for instruction in instructions {
+		if leaders.contains(&instruction.pc) || blocks.is_empty() {
+			blocks.push(Block {
+				start: instruction.pc,
+				end: instruction.pc,
+				instructions: vec![],
+			});
+		}
+		blocks.last_mut().unwrap().instructions.push(instruction);
+	}
+	for i in 0..blocks.len() {
+		blocks[i].end = blocks.get(i + 1).map_or(length, |next| next.start);
+	}
+	let index: BTreeMap<u32, usize> = blocks.iter()
+		.enumerate()
+		.map(|(i, block)| (block.start, i))
+		.collect();
+
+	let mut edges = vec![];
+	for (i, block) in blocks.iter().enumerate() {
+		let last = block.instructions.last().unwrap();
+		match last.operand {
+			Operand::TableSwitch { default, low, ref targets } => {
+				for (key, target) in targets.iter().enumerate() {
+					edges.push(Edge { from: i, to: index[target], kind: EdgeKind::Switch(Some(low.wrapping_add(key as i32))) });
+				}
+				edges.push(Edge { from: i, to: index[&default], kind: EdgeKind::Switch(None) });
+			}
+			Operand::LookupSwitch { default, ref pairs } => {
+				for (key, target) in pairs {
+					edges.push(Edge { from: i, to: index[target], kind: EdgeKind::Switch(Some(*key)) });
+				}
+				edges.push(Edge { from: i, to: index[&default], kind: EdgeKind::Switch(None) });
+			}
+			Operand::Branch(target) => {
+				let kind = if last.opcode == JSR || last.opcode == JSR_W { EdgeKind::Jsr } else { EdgeKind::Branch };
+				edges.push(Edge { from: i, to: index[&target], kind });
+			}
+			_ => {}
+		}
+		let falls_through = !last.is_unconditional() && last.opcode != JSR && last.opcode != JSR_W;
+		if falls_through && i + 1 < blocks.len() {
+			edges.push(Edge { from: i, to: i + 1, kind: EdgeKind::Fallthrough });
+		}
+		for (start, end, handler, catch_type) in &handlers {
+			if block.start >= *start && block.start < *end {
+				edges.push(Edge { from: i, to: index[handler], kind: EdgeKind::Exception(catch_type.clone()) });
+			}
+		}
+	}
+
+	let mut graph = ControlFlowGraph {
+		blocks,
+		edges,
+	};
+	graph.add_ret_edges();
+	Ok(graph)
+}
+
+impl ControlFlowGraph {
+	/// Works out which subroutine each `ret` returns from, and links it to the blocks following the `jsr`s calling it.
+	///
+	/// A subroutine is everything reachable from its entry through normal control flow,
+	/// where a nested `jsr` is assumed to return, but exception handlers aren't followed.
+	fn add_ret_edges(&mut self) {
+		let calls: Vec<(usize, usize)> = self.edges.iter()
+			.filter(|edge| edge.kind == EdgeKind::Jsr)
+			.map(|edge| (edge.from, edge.to))
+			.collect();
+		let entries: BTreeSet<usize> = calls.iter()
+			.map(|&(_, entry)| entry)
+			.collect();
+
+		let mut ret_edges = vec![];
+		for &entry in &entries {
+			let mut seen = BTreeSet::new();
+			let mut pending = vec![entry];
+			while let Some(block) = pending.pop() {
+				if !seen.insert(block) {
+					continue;
+				}
+				for edge in self.successors(block) {
+					match edge.kind {
+						EdgeKind::Fallthrough | EdgeKind::Branch | EdgeKind::Switch(_) => pending.push(edge.to),
+						EdgeKind::Jsr if block + 1 < self.blocks.len() => pending.push(block + 1),
+						_ => {}
+					}
+				}
+			}
+			for &block in &seen {
+				if self.blocks[block].instructions.last().unwrap().opcode != RET {
+					continue;
+				}
+				for &(caller, _) in calls.iter().filter(|&&(_, called)| called == entry) {
+					if caller + 1 < self.blocks.len() {
+						ret_edges.push(Edge { from: block, to: caller + 1, kind: EdgeKind::Ret });
+					}
+				}
+			}
+		}
+		self.edges.extend(ret_edges);
+	}
+
+	/// The block holding the instruction at the given offset.
+	pub fn block_at(&self, pc: u32) -> Option<usize> {
+		let i = self.blocks.partition_point(|block| block.start <= pc);
+		if i == 0 || pc >= self.blocks[i - 1].end {
+			return None;
+		}
+		Some(i - 1)
+	}
+
+	/// The edges leaving the block.
+	pub fn successors(&self, block: usize) -> impl Iterator<Item = &Edge> {
+		self.edges.iter()
+			.filter(move |edge| edge.from == block)
+	}
+
+	/// The edges entering the block.
+	pub fn predecessors(&self, block: usize) -> impl Iterator<Item = &Edge> {
+		self.edges.iter()
+			.filter(move |edge| edge.to == block)
+	}
+
+	/// The successors of every block, without duplicates, indexed by block.
+	pub fn successor_map(&self) -> Vec<Vec<usize>> {
+		let mut result = vec![vec![]; self.blocks.len()];
+		for edge in &self.edges {
+			if !result[edge.from].contains(&edge.to) {
+				result[edge.from].push(edge.to);
+			}
+		}
+		result
+	}
+
+	/// The predecessors of every block, without duplicates, indexed by block.
+	pub fn predecessor_map(&self) -> Vec<Vec<usize>> {
+		let mut result = vec![vec![]; self.blocks.len()];
+		for edge in &self.edges {
+			if !result[edge.to].contains(&edge.from) {
+				result[edge.to].push(edge.from);
+			}
+		}
+		result
+	}
+
+	/// The blocks reachable from the entry, each one coming before its successors, back edges aside.
+	pub fn reverse_postorder(&self) -> Vec<usize> {
+		if self.blocks.is_empty() {
+			return vec![];
+		}
+		let successors = self.successor_map();
+		let mut postorder = vec![];
+		let mut seen = vec![false; self.blocks.len()];
+		// An explicit stack of blocks and how many of their successors have been visited, so deep graphs don't overflow.
+		let mut stack = vec![(0, 0)];
+		seen[0] = true;
+		while let Some(&mut (block, ref mut next)) = stack.last_mut() {
+			match successors[block].get(*next) {
+				Some(&successor) => {
+					*next += 1;
+					if !seen[successor] {
+						seen[successor] = true;
+						stack.push((successor, 0));
+					}
+				}
+				None => {
+					postorder.push(block);
+					stack.pop();
+				}
+			}
+		}
+		postorder.reverse();
+		postorder
+	}
+
+	/// The dominator tree, using the algorithm by Cooper, Harvey and Kennedy.
+	pub fn dominators(&self) -> Dominators {
+		let order = self.reverse_postorder();
+		let mut position = vec![usize::MAX; self.blocks.len()];
+		for (i, &block) in order.iter().enumerate() {
+			position[block] = i;
+		}
+		let predecessors = self.predecessor_map();
+
+		let mut immediate: Vec<Option<usize>> = vec![None; self.blocks.len()];
+		if self.blocks.is_empty() {
+			return Dominators {
+				immediate,
+			};
+		}
+		immediate[0] = Some(0);
+		let intersect = |immediate: &[Option<usize>], mut first: usize, mut second: usize| {
+			while first != second {
+				while position[first] > position[second] {
+					first = immediate[first].unwrap();
+				}
+				while position[second] > position[first] {
+					second = immediate[second].unwrap();
+				}
+			}
+			first
+		};
+		let mut changed = true;
+		while changed {
+			changed = false;
+			for &block in order.iter().skip(1) {
+				let mut processed = predecessors[block].iter()
+					.copied()
+					.filter(|&predecessor| immediate[predecessor].is_some());
+				let first = match processed.next() {
+					Some(first) => first,
+					None => continue,
+				};
+				let new = processed.fold(first, |dominator, predecessor| intersect(&immediate, predecessor, dominator));
+				if immediate[block] != Some(new) {
+					immediate[block] = Some(new);
+					changed = true;
+				}
+			}
+		}
+		immediate[0] = None;
+		Dominators {
+			immediate,
+		}
+	}
+
+	/// Every natural loop, one per header, with the blocks of loops sharing a header merged together.
+	///
+	/// Loops are sorted by header, so an outer loop comes before the loops nested in it, unless they share a header.
+	pub fn loops(&self) -> Vec<Loop> {
+		let dominators = self.dominators();
+		let predecessors = self.predecessor_map();
+		let mut loops: BTreeMap<usize, Loop> = BTreeMap::new();
+		for edge in &self.edges {
+			if !dominators.dominates(edge.to, edge.from) {
+				continue;
+			}
+			let header = edge.to;
+			let entry = loops.entry(header).or_insert_with(|| Loop {
+				header,
+				latches: BTreeSet::new(),
+				blocks: iter::once(header).collect(),
+			});
+			entry.latches.insert(edge.from);
+			let mut pending = vec![edge.from];
+			while let Some(block) = pending.pop() {
+				if dominators.dominates(header, block) && entry.blocks.insert(block) {
+					pending.extend(&predecessors[block]);
+				}
+			}
+		}
+		loops.into_values().collect()
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Dominators {
+	/// `None` for the entry, and for blocks that can't be reached.
+	immediate: Vec<Option<usize>>,
+}
+
+impl Dominators {
+	pub fn immediate_dominator(&self, block: usize) -> Option<usize> {
+		self.immediate.get(block).copied().flatten()
+	}
+
+	/// Whether every path from the entry to `block` goes through `dominator`, which includes the block itself.
+	/// Blocks that can't be reached aren't dominated by anything.
+	pub fn dominates(&self, dominator: usize, block: usize) -> bool {
+		if block != 0 && self.immediate_dominator(block).is_none() {
+			return false;
+		}
+		let mut current = Some(block);
+		while let Some(block) = current {
+			if block == dominator {
+				return true;
+			}
+			current = self.immediate_dominator(block);
+		}
+		false
+	}
+
+	/// The blocks immediately dominated by the given one, its children in the dominator tree.
+	pub fn children(&self, block: usize) -> Vec<usize> {
+		(0..self.immediate.len())
+			.filter(|&child| self.immediate[child] == Some(block))
+			.collect()
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Loop {
+	pub header: usize,
+	/// The blocks with a back edge to the header.
+	pub latches: BTreeSet<usize>,
+	/// Every block in the loop, the header included.
+	pub blocks: BTreeSet<usize>,
+}
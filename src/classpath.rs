@@ -0,0 +1,540 @@
+//! Many classes at once, indexed by their internal names, for questions no single class can answer,
+//! such as what a class extends all the way up, who implements an interface, or which method a call ends up at.
+//!
+//! Nothing is loaded on demand, so a class that hasn't been added is reported as `Unresolved`,
+//! except for `java/lang/Object`, which is assumed to exist, without any methods, if it hasn't been added.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use crate::*;
+use crate::hierarchy::{element_name, ClassHierarchy, OBJECT};
+use crate::jar::{Jar, JarError};
+use crate::jimage::{JImage, JImageError};
+use crate::jmod::{Jmod, JmodError};
+use crate::utf8::MStrExt;
+
+/// A class that's needed to answer the question, but isn't in the class path.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Unresolved(pub String);
+
+impl fmt::Display for Unresolved {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Unresolved class {}", self.0)
+	}
+}
+
+/// Why a method reference doesn't resolve, named after the errors the JVM would throw.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum ResolutionError {
+	Unresolved(String),
+	/// A method of a class was looked up on an interface, or the other way around.
+	IncompatibleClassChange(String),
+	NoSuchMethod(String),
+}
+
+impl From<Unresolved> for ResolutionError {
+	fn from(error: Unresolved) -> Self {
+		ResolutionError::Unresolved(error.0)
+	}
+}
+
+impl fmt::Display for ResolutionError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ResolutionError::Unresolved(name) => write!(f, "Unresolved class {}", name),
+			ResolutionError::IncompatibleClassChange(name) => write!(f, "Incompatible class change: {}", name),
+			ResolutionError::NoSuchMethod(method) => write!(f, "No such method {}", method),
+		}
+	}
+}
+
+/// A method, along with the class that declares it.
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedMethod<'p> {
+	pub owner: &'p str,
+	pub class_file: &'p ClassFile<'p>,
+	pub method: &'p MethodInfo<'p>,
+}
+
+impl ResolvedMethod<'_> {
+	pub fn name(&self) -> String {
+		self.class_file.constant_pool.utf8(self.method.name_index)
+			.map(MStrExt::decoded)
+			.unwrap_or_default()
+	}
+
+	pub fn descriptor(&self) -> String {
+		self.class_file.constant_pool.utf8(self.method.descriptor_index)
+			.map(MStrExt::decoded)
+			.unwrap_or_default()
+	}
+}
+
+#[derive(Debug)]
+struct Entry {
+	class_file: ClassFile<'static>,
+	super_class: Option<String>,
+	interfaces: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ClassPath {
+	classes: BTreeMap<String, Entry>,
+	/// The classes and interfaces directly extending or implementing each class or interface.
+	subtypes: BTreeMap<String, BTreeSet<String>>,
+}
+
+fn package(name: &str) -> &str {
+	name.rfind('/').map_or("", |end| &name[..end])
+}
+
+fn same_method(class_file: &ClassFile, method: &MethodInfo, name: &str, descriptor: &str) -> bool {
+	let cp = &class_file.constant_pool;
+	cp.utf8(method.name_index).is_some_and(|value| value.decoded() == name)
+		&& cp.utf8(method.descriptor_index).is_some_and(|value| value.decoded() == descriptor)
+}
+
+/// Whether the method can take part in overriding, or be picked from a superinterface.
+fn is_inheritable(method: &MethodInfo) -> bool {
+	method.access_flags & (PRIVATE | STATIC) == 0
+}
+
+/// `MethodHandle.invoke` and friends, which take whatever descriptor they're called with, see JVMS 2.9.3.
+fn is_signature_polymorphic(owner: &str, class_file: &ClassFile, method: &MethodInfo) -> bool {
+	(owner == "java/lang/invoke/MethodHandle" || owner == "java/lang/invoke/VarHandle")
+		&& method.access_flags & (VARARGS | NATIVE) == VARARGS | NATIVE
+		&& class_file.constant_pool.utf8(method.descriptor_index)
+			.is_some_and(|value| value.decoded().starts_with("([Ljava/lang/Object;)"))
+}
+
+impl ClassPath {
+	pub fn new() -> Self {
+		ClassPath::default()
+	}
+
+	/// Adds the class, unless there's already one with the same name, as the first one wins on a real class path.
+	///
+	/// Classes whose names can't be found in their constant pool are skipped.
+	pub fn add(&mut self, class_file: ClassFile<'static>) -> &mut Self {
+		let cp = &class_file.constant_pool;
+		let name = match cp.class_name(class_file.this_class) {
+			Some(name) => name.decoded(),
+			None => return self,
+		};
+		if self.classes.contains_key(&name) {
+			return self;
+		}
+		let super_class = cp.class_name(class_file.super_class).map(MStrExt::decoded);
+		let interfaces: Vec<String> = class_file.interfaces.iter()
+			.filter_map(|&index| cp.class_name(index).map(MStrExt::decoded))
+			.collect();
+
+		for parent in super_class.iter().chain(&interfaces) {
+			self.subtypes.entry(parent.clone())
+				.or_default()
+				.insert(name.clone());
+		}
+		self.classes.insert(name, Entry {
+			class_file,
+			super_class,
+			interfaces,
+		});
+		self
+	}
+
+	pub fn add_bytes(&mut self, data: &[u8]) -> ReadResult<&mut Self> {
+		let class_file = ClassFile::open(&mut Cursor::new(data))?;
+		Ok(self.add(class_file))
+	}
+
+	/// Adds every `.class` file in the directory and the ones below it, in order of their paths.
+	pub fn add_directory<P: AsRef<Path>>(&mut self, path: P) -> io::Result<&mut Self> {
+		let mut entries: Vec<_> = fs::read_dir(path)?
+			.map(|entry| entry.map(|entry| entry.path()))
+			.collect::<io::Result<_>>()?;
+		entries.sort();
+
+		for path in entries {
+			if path.is_dir() {
+				self.add_directory(&path)?;
+			} else if path.extension().is_some_and(|extension| extension == "class") {
+				let data = fs::read(&path)?;
+				self.add_bytes(&data)
+					.map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {:?}", path.display(), error)))?;
+			}
+		}
+		Ok(self)
+	}
+
+	/// Adds the classes of the jar as they are on the given release, see `Jar::class_entries`,
+	/// returning the entries that couldn't be read, along with why.
+	pub fn add_jar(&mut self, jar: &Jar, release: Option<u16>) -> Vec<(String, JarError)> {
+		let mut errors = Vec::new();
+		for (entry, class_file) in jar.classes(release) {
+			match class_file {
+				Ok(class_file) => {
+					self.add(class_file);
+				}
+				Err(error) => errors.push((entry.name.clone(), error)),
+			}
+		}
+		errors
+	}
+
+	/// Adds the classes of the jmod, returning the entries that couldn't be read, along with why.
+	pub fn add_jmod(&mut self, jmod: &Jmod) -> Vec<(String, JmodError)> {
+		let mut errors = Vec::new();
+		for (entry, class_file) in jmod.classes() {
+			match class_file {
+				Ok(class_file) => {
+					self.add(class_file);
+				}
+				Err(error) => errors.push((entry.name.clone(), error)),
+			}
+		}
+		errors
+	}
+
+	/// Adds the classes of the given module of the image, or of all of them,
+	/// returning the ones that couldn't be read, by their full names, along with why.
+	pub fn add_jimage(&mut self, image: &JImage, module: Option<&str>) -> Vec<(String, JImageError)> {
+		let mut errors = Vec::new();
+		for (location, class_file) in image.classes(module) {
+			match class_file {
+				Ok(class_file) => {
+					self.add(class_file);
+				}
+				Err(error) => errors.push((location.name(), error)),
+			}
+		}
+		errors
+	}
+
+	pub fn get(&self, name: &str) -> Option<&ClassFile<'_>> {
+		self.classes.get(name)
+			.map(|entry| &entry.class_file)
+	}
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.classes.contains_key(name)
+	}
+
+	/// The names of all the classes, in order.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.classes.keys()
+			.map(String::as_str)
+	}
+
+	pub fn len(&self) -> usize {
+		self.classes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.classes.is_empty()
+	}
+
+	/// The super classes and interfaces the classes in the class path mention, but which aren't in it.
+	pub fn missing(&self) -> BTreeSet<String> {
+		self.subtypes.keys()
+			.filter(|&name| name != OBJECT && !self.classes.contains_key(name))
+			.cloned()
+			.collect()
+	}
+
+	fn entry(&self, name: &str) -> Result<Option<&Entry>, Unresolved> {
+		match self.classes.get(name) {
+			Some(entry) => Ok(Some(entry)),
+			None if name == OBJECT => Ok(None),
+			None => Err(Unresolved(name.to_string())),
+		}
+	}
+
+	fn direct_super_class(&self, name: &str) -> Result<Option<&str>, Unresolved> {
+		Ok(self.entry(name)?.and_then(|entry| entry.super_class.as_deref()))
+	}
+
+	fn access_flags(&self, name: &str) -> Result<u16, Unresolved> {
+		Ok(self.entry(name)?.map_or(PUBLIC | SUPER, |entry| entry.class_file.access_flags))
+	}
+
+	pub fn is_interface(&self, name: &str) -> Result<bool, Unresolved> {
+		Ok(self.access_flags(name)? & INTERFACE != 0)
+	}
+
+	/// The super class, its super class and so on, ending with `java/lang/Object`.
+	///
+	/// Interfaces have `java/lang/Object` as their super class too.
+	pub fn super_classes(&self, name: &str) -> Result<Vec<String>, Unresolved> {
+		let mut super_classes = Vec::new();
+		let mut current = self.direct_super_class(name)?;
+		while let Some(name) = current {
+			if super_classes.iter().any(|super_class| super_class == name) {
+				break;
+			}
+			super_classes.push(name.to_string());
+			current = self.direct_super_class(name)?;
+		}
+		Ok(super_classes)
+	}
+
+	/// Every interface the class implements, directly, through its super classes, or by extending other interfaces.
+	pub fn interfaces(&self, name: &str) -> Result<BTreeSet<String>, Unresolved> {
+		let mut interfaces = BTreeSet::new();
+		let mut pending: Vec<String> = vec![name.to_string()];
+		pending.extend(self.super_classes(name)?);
+
+		while let Some(name) = pending.pop() {
+			if let Some(entry) = self.entry(&name)? {
+				for interface in &entry.interfaces {
+					if interfaces.insert(interface.clone()) {
+						pending.push(interface.clone());
+					}
+				}
+			}
+		}
+		Ok(interfaces)
+	}
+
+	/// The classes and interfaces in the class path that directly extend or implement the given one.
+	pub fn direct_subtypes(&self, name: &str) -> BTreeSet<String> {
+		self.subtypes.get(name)
+			.cloned()
+			.unwrap_or_default()
+	}
+
+	/// The classes and interfaces in the class path that extend or implement the given one, directly or not.
+	pub fn subtypes(&self, name: &str) -> BTreeSet<String> {
+		let mut subtypes = BTreeSet::new();
+		let mut pending = vec![name.to_string()];
+		while let Some(name) = pending.pop() {
+			for subtype in self.subtypes.get(&name).into_iter().flatten() {
+				if subtypes.insert(subtype.clone()) {
+					pending.push(subtype.clone());
+				}
+			}
+		}
+		subtypes
+	}
+
+	/// Whether a value of type `from` can be stored in a variable of type `to`, following the rules of JLS 5.2.
+	///
+	/// Unlike `ClassHierarchy::is_assignable`, interfaces are taken into account.
+	pub fn is_assignable(&self, from: &str, to: &str) -> Result<bool, Unresolved> {
+		if from == to || to == OBJECT {
+			return Ok(true);
+		}
+		if let Some(to_component) = to.strip_prefix('[') {
+			let from_component = match from.strip_prefix('[') {
+				Some(from_component) => from_component,
+				None => return Ok(false),
+			};
+			return match (element_name(from_component), element_name(to_component)) {
+				(Some(from), Some(to)) => self.is_assignable(from, to),
+				_ => Ok(from_component == to_component),
+			};
+		}
+		if from.starts_with('[') {
+			return Ok(to == "java/lang/Cloneable" || to == "java/io/Serializable");
+		}
+
+		if self.is_interface(to)? {
+			Ok(self.interfaces(from)?.contains(to))
+		} else {
+			Ok(self.super_classes(from)?.iter().any(|super_class| super_class == to))
+		}
+	}
+
+	/// The closest class both `first` and `second` extend, which is what ASM's `ClassWriter.getCommonSuperClass` gives.
+	///
+	/// Interfaces have `java/lang/Object` in common with everything else.
+	pub fn common_super_class(&self, first: &str, second: &str) -> Result<String, Unresolved> {
+		if first.starts_with('[') || second.starts_with('[') {
+			return Ok(if first == second { first.to_string() } else { OBJECT.to_string() });
+		}
+		if self.is_interface(first)? || self.is_interface(second)? {
+			return Ok(if first == second { first.to_string() } else { OBJECT.to_string() });
+		}
+
+		let mut chain = vec![first.to_string()];
+		chain.extend(self.super_classes(first)?);
+		for class in chain {
+			if self.is_assignable(second, &class)? {
+				return Ok(class);
+			}
+		}
+		Ok(OBJECT.to_string())
+	}
+
+	fn declared<'p>(&'p self, owner: &str, name: &str, descriptor: &str) -> Result<Option<ResolvedMethod<'p>>, Unresolved> {
+		let (owner, entry) = match self.classes.get_key_value(owner) {
+			Some(found) => found,
+			None => return self.entry(owner).map(|_| None),
+		};
+		let class_file = &entry.class_file;
+		let method = class_file.methods.iter()
+			.find(|method| same_method(class_file, method, name, descriptor));
+		Ok(method.map(|method| ResolvedMethod {
+			owner,
+			class_file,
+			method,
+		}))
+	}
+
+	/// Looking a method up in a class and then its super classes, see JVMS 5.4.3.3.
+	fn look_up<'p>(&'p self, owner: &str, name: &str, descriptor: &str) -> Result<Option<ResolvedMethod<'p>>, Unresolved> {
+		let mut current = Some(owner.to_string());
+		while let Some(class) = current {
+			if let Some((owner, entry)) = self.classes.get_key_value(&class) {
+				let class_file = &entry.class_file;
+				let mut named = class_file.methods.iter()
+					.filter(|method| class_file.constant_pool.utf8(method.name_index).is_some_and(|value| value.decoded() == name));
+				if let (Some(method), None) = (named.next(), named.next()) {
+					if is_signature_polymorphic(owner, class_file, method) {
+						return Ok(Some(ResolvedMethod {
+							owner,
+							class_file,
+							method,
+						}));
+					}
+				}
+			}
+			if let Some(method) = self.declared(&class, name, descriptor)? {
+				return Ok(Some(method));
+			}
+			current = self.direct_super_class(&class)?.map(str::to_string);
+		}
+		Ok(None)
+	}
+
+	/// The methods of the superinterfaces that no other superinterface overrides, see JVMS 5.4.3.3.
+	fn maximally_specific<'p>(&'p self, class: &str, name: &str, descriptor: &str) -> Result<(Vec<ResolvedMethod<'p>>, Option<ResolvedMethod<'p>>), Unresolved> {
+		let mut candidates = Vec::new();
+		for interface in self.interfaces(class)? {
+			if let Some(method) = self.declared(&interface, name, descriptor)? {
+				if is_inheritable(method.method) {
+					candidates.push(method);
+				}
+			}
+		}
+		let any = candidates.first().copied();
+
+		let mut specific = Vec::new();
+		for &candidate in &candidates {
+			let mut overridden = false;
+			for other in &candidates {
+				if other.owner != candidate.owner && self.interfaces(other.owner)?.contains(candidate.owner) {
+					overridden = true;
+					break;
+				}
+			}
+			if !overridden {
+				specific.push(candidate);
+			}
+		}
+		Ok((specific, any))
+	}
+
+	/// Picks the only non-abstract maximally specific method, or failing that any method from a superinterface.
+	fn inherited<'p>(&'p self, class: &str, name: &str, descriptor: &str) -> Result<Option<ResolvedMethod<'p>>, Unresolved> {
+		let (specific, any) = self.maximally_specific(class, name, descriptor)?;
+		if let [method] = specific[..] {
+			if method.method.access_flags & ABSTRACT == 0 {
+				return Ok(Some(method));
+			}
+		}
+		Ok(any)
+	}
+
+	/// Resolves a `Methodref`, the way JVMS 5.4.3.3 describes.
+	pub fn resolve_method(&self, owner: &str, name: &str, descriptor: &str) -> Result<ResolvedMethod<'_>, ResolutionError> {
+		if self.is_interface(owner)? {
+			return Err(ResolutionError::IncompatibleClassChange(owner.to_string()));
+		}
+		if let Some(method) = self.look_up(owner, name, descriptor)? {
+			return Ok(method);
+		}
+		self.inherited(owner, name, descriptor)?
+			.ok_or_else(|| ResolutionError::NoSuchMethod(format!("{}.{}:{}", owner, name, descriptor)))
+	}
+
+	/// Resolves an `InterfaceMethodref`, the way JVMS 5.4.3.4 describes.
+	pub fn resolve_interface_method(&self, owner: &str, name: &str, descriptor: &str) -> Result<ResolvedMethod<'_>, ResolutionError> {
+		if !self.is_interface(owner)? {
+			return Err(ResolutionError::IncompatibleClassChange(owner.to_string()));
+		}
+		if let Some(method) = self.declared(owner, name, descriptor)? {
+			return Ok(method);
+		}
+		if let Some(method) = self.declared(OBJECT, name, descriptor)? {
+			if method.method.access_flags & (PUBLIC | STATIC) == PUBLIC {
+				return Ok(method);
+			}
+		}
+		self.inherited(owner, name, descriptor)?
+			.ok_or_else(|| ResolutionError::NoSuchMethod(format!("{}.{}:{}", owner, name, descriptor)))
+	}
+
+	/// The methods of the super classes and interfaces that the method declared in `owner` overrides, nearest first.
+	///
+	/// Classes follow JVMS 5.4.5, package-private methods included, while interface methods count as overridden
+	/// by any method with the same name and descriptor, the way `@Override` sees it.
+	/// Static, private and missing methods, as well as constructors, don't override anything.
+	pub fn overridden_methods(&self, owner: &str, name: &str, descriptor: &str) -> Result<Vec<ResolvedMethod<'_>>, Unresolved> {
+		let mut overridden: Vec<ResolvedMethod> = Vec::new();
+		match self.declared(owner, name, descriptor)? {
+			Some(method) if is_inheritable(method.method) && !name.starts_with('<') => {}
+			_ => return Ok(overridden),
+		}
+
+		for class in self.super_classes(owner)? {
+			let method = match self.declared(&class, name, descriptor)? {
+				Some(method) if is_inheritable(method.method) => method,
+				_ => continue,
+			};
+			let overrides = method.method.access_flags & (PUBLIC | PROTECTED) != 0
+				|| package(owner) == package(&class)
+				|| overridden.iter().any(|between| package(between.owner) == package(&class));
+			if overrides {
+				overridden.push(method);
+			}
+		}
+		for interface in self.interfaces(owner)? {
+			if let Some(method) = self.declared(&interface, name, descriptor)? {
+				if is_inheritable(method.method) {
+					overridden.push(method);
+				}
+			}
+		}
+		Ok(overridden)
+	}
+
+	/// The methods of the subclasses and implementations that override the method declared in `owner`.
+	pub fn overriding_methods(&self, owner: &str, name: &str, descriptor: &str) -> Result<Vec<ResolvedMethod<'_>>, Unresolved> {
+		let mut overriding = Vec::new();
+		for subtype in self.subtypes(owner) {
+			let overrides = self.overridden_methods(&subtype, name, descriptor)?.iter()
+				.any(|method| method.owner == owner);
+			if overrides {
+				if let Some(method) = self.declared(&subtype, name, descriptor)? {
+					overriding.push(method);
+				}
+			}
+		}
+		Ok(overriding)
+	}
+}
+
+impl ClassHierarchy for ClassPath {
+	fn super_class(&self, name: &str) -> Option<Option<String>> {
+		self.direct_super_class(name).ok()
+			.map(|super_class| super_class.map(str::to_string))
+	}
+
+	fn is_interface(&self, name: &str) -> Option<bool> {
+		ClassPath::is_interface(self, name).ok()
+	}
+}
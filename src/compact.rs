@@ -0,0 +1,739 @@
+//! Rebuilding the constant pool out of only the entries that are used.
+//!
+//! Edits tend to leave entries behind that nothing points at any more, and the order of the pool
+//! is whatever the compiler chose. A `Compactor` walks every index the class holds, in its members,
+//! attributes, code, stack maps, annotations and bootstrap methods, keeps the entries those lead to,
+//! optionally merging duplicates and sorting them, and then rewrites every index to match.
+//!
+//! `ldc` only has a byte for its index, so loading a constant that moved past 255 turns it into an `ldc_w`,
+//! and the other way around. That changes the size of the code, so everything holding an offset into it,
+//! such as the exception table, line numbers, local variables and stack map frames, is moved along too.
+//!
+//! Attributes that aren't understood can't be looked into, so they're an error,
+//! rather than being left pointing at the wrong entries.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Cursor;
+
+use crate::*;
+use crate::attr::*;
+use crate::bytecode::{self, DecodeError, EncodeError, Operand};
+use crate::ops::{LDC, LDC_W};
+use crate::utf8::MStrExt;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum CompactError {
+	/// Something points at an entry that isn't there, or at the second slot of a `Long` or `Double`.
+	InvalidIndex(u16),
+	/// An attribute that may well hold indices, but isn't understood, so can't be rewritten.
+	UnknownAttribute(String),
+	/// An attribute that doesn't decode.
+	Malformed(String),
+	Decode(DecodeError),
+	Encode(EncodeError),
+	/// An offset that doesn't fall on the start or end of an instruction, so can't be moved along with the code.
+	InvalidOffset(u32),
+	/// The code grew past the 65535 bytes a method can have.
+	CodeTooLarge(usize),
+}
+
+impl fmt::Display for CompactError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			CompactError::InvalidIndex(index) => write!(f, "invalid constant pool index #{}", index),
+			CompactError::UnknownAttribute(name) => write!(f, "unknown attribute {}", name),
+			CompactError::Malformed(name) => write!(f, "malformed {} attribute", name),
+			CompactError::Decode(error) => write!(f, "{}", error),
+			CompactError::Encode(error) => write!(f, "{}", error),
+			CompactError::InvalidOffset(offset) => write!(f, "offset {} isn't on an instruction boundary", offset),
+			CompactError::CodeTooLarge(len) => write!(f, "code grew to {} bytes", len),
+		}
+	}
+}
+
+/// Drops the unused entries of the pool, keeping the rest in the order they were.
+pub fn compact<'a>(class_file: &ClassFile<'a>) -> Result<ClassFile<'a>, CompactError> {
+	Compactor::new().compact(class_file)
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Compactor {
+	sort: bool,
+	deduplicate: bool,
+}
+
+impl Compactor {
+	pub fn new() -> Self {
+		Compactor::default()
+	}
+
+	/// Orders the entries by kind, and then by value, other than the constants loaded by `ldc`,
+	/// which go first, so they stay within its reach.
+	pub fn sort(&mut self, sort: bool) -> &mut Self {
+		self.sort = sort;
+		self
+	}
+
+	/// Merges entries that are the same, once whatever they point at has been merged.
+	pub fn deduplicate(&mut self, deduplicate: bool) -> &mut Self {
+		self.deduplicate = deduplicate;
+		self
+	}
+
+	pub fn compact<'a>(&self, class_file: &ClassFile<'a>) -> Result<ClassFile<'a>, CompactError> {
+		let entries = &class_file.constant_pool.entries[..];
+
+		let mut used = BTreeSet::new();
+		let mut loaded = HashSet::new();
+		Walker {
+			entries,
+			visit: |index, is_loaded| {
+				used.insert(index);
+				if is_loaded {
+					loaded.insert(index);
+				}
+				Ok(index)
+			},
+		}.class_file(class_file)?;
+
+		let mut reachable = BTreeSet::new();
+		let mut pending: Vec<u16> = used.into_iter().collect();
+		while let Some(index) = pending.pop() {
+			if reachable.insert(index) {
+				pending.extend(children(entry(entries, index)?)?);
+			}
+		}
+
+		let mut merger = Merger {
+			entries,
+			deduplicate: self.deduplicate,
+			canonical: HashMap::new(),
+			visiting: HashSet::new(),
+			seen: HashMap::new(),
+		};
+		for &index in &reachable {
+			merger.canonical(index)?;
+		}
+		let canonical = merger.canonical;
+		let mut kept: Vec<u16> = reachable.iter()
+			.map(|index| canonical[index])
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.collect();
+		if self.sort {
+			let loaded: HashSet<u16> = loaded.iter().map(|index| canonical[index]).collect();
+			let mut keys = HashMap::new();
+			for &index in &kept {
+				keys.insert(index, sort_key(entries, index)?);
+			}
+			kept.sort_by_key(|index| (!loaded.contains(index), entries[*index as usize - 1].tag(), &keys[index]));
+		}
+
+		let mut new_indices = HashMap::new();
+		let mut pool = Vec::with_capacity(entries.len());
+		for &index in &kept {
+			new_indices.insert(index, pool.len() as u16 + 1);
+			let entry = &entries[index as usize - 1];
+			pool.push(entry.clone());
+			if entry.is_wide() {
+				pool.push(CPEntry::Unusable(UnusableInfo {}));
+			}
+		}
+		let new_index = |index: u16| -> Result<u16, CompactError> {
+			canonical.get(&index)
+				.and_then(|index| new_indices.get(index))
+				.copied()
+				.ok_or(CompactError::InvalidIndex(index))
+		};
+		for entry in &mut pool {
+			*entry = map_entry(entry, &mut |index| new_index(index))?;
+		}
+
+		let mut result = Walker {
+			entries,
+			visit: |index, _| new_index(index),
+		}.class_file(class_file)?;
+		result.constant_pool.entries = pool;
+		Ok(result)
+	}
+}
+
+/// The entry at `index`, as long as it's a real one.
+fn entry<'e, 'a>(entries: &'e [CPEntry<'a>], index: u16) -> Result<&'e CPEntry<'a>, CompactError> {
+	match entries.get((index as usize).wrapping_sub(1)) {
+		Some(CPEntry::Unusable(_)) | None => Err(CompactError::InvalidIndex(index)),
+		Some(entry) => Ok(entry),
+	}
+}
+
+/// A copy of the entry with every index it holds passed through `f`.
+fn map_entry<'a, F>(entry: &CPEntry<'a>, f: &mut F) -> Result<CPEntry<'a>, CompactError>
+	where F: FnMut(u16) -> Result<u16, CompactError>
+{
+	let entry = match entry {
+		CPEntry::Class(info) => CPEntry::Class(ClassInfo { name_index: mapped(f, info.name_index)? }),
+		CPEntry::FieldRef(info) => CPEntry::FieldRef(FieldRefInfo {
+			class_index: mapped(f, info.class_index)?,
+			name_and_type_index: mapped(f, info.name_and_type_index)?,
+		}),
+		CPEntry::MethodRef(info) => CPEntry::MethodRef(MethodRefInfo {
+			class_index: mapped(f, info.class_index)?,
+			name_and_type_index: mapped(f, info.name_and_type_index)?,
+		}),
+		CPEntry::InterfaceMethodRef(info) => CPEntry::InterfaceMethodRef(InterfaceMethodRefInfo {
+			class_index: mapped(f, info.class_index)?,
+			name_and_type_index: mapped(f, info.name_and_type_index)?,
+		}),
+		CPEntry::String(info) => CPEntry::String(StringInfo { string_index: mapped(f, info.string_index)? }),
+		CPEntry::NameAndType(info) => CPEntry::NameAndType(NameAndTypeInfo {
+			name_index: mapped(f, info.name_index)?,
+			descriptor_index: mapped(f, info.descriptor_index)?,
+		}),
+		CPEntry::MethodHandle(info) => CPEntry::MethodHandle(match *info {
+			MethodHandleInfo::FieldRef { reference_kind, reference_index } => MethodHandleInfo::FieldRef {
+				reference_kind,
+				reference_index: mapped(f, reference_index)?,
+			},
+			MethodHandleInfo::MethodRef { reference_kind, reference_index } => MethodHandleInfo::MethodRef {
+				reference_kind,
+				reference_index: mapped(f, reference_index)?,
+			},
+			MethodHandleInfo::InterfaceMethodRef { reference_kind, reference_index } => MethodHandleInfo::InterfaceMethodRef {
+				reference_kind,
+				reference_index: mapped(f, reference_index)?,
+			},
+		}),
+		CPEntry::MethodType(info) => CPEntry::MethodType(MethodTypeInfo { descriptor_index: mapped(f, info.descriptor_index)? }),
+		// The bootstrap method is an index into the `BootstrapMethods` attribute, which stays as it is.
+		CPEntry::Dynamic(info) => CPEntry::Dynamic(DynamicInfo {
+			bootstrap_method_attr_index: info.bootstrap_method_attr_index,
+			name_and_type_index: mapped(f, info.name_and_type_index)?,
+		}),
+		CPEntry::InvokeDynamic(info) => CPEntry::InvokeDynamic(InvokeDynamicInfo {
+			bootstrap_method_attr_index: info.bootstrap_method_attr_index,
+			name_and_type_index: mapped(f, info.name_and_type_index)?,
+		}),
+		CPEntry::Module(info) => CPEntry::Module(ModuleInfo { name_index: mapped(f, info.name_index)? }),
+		CPEntry::Package(info) => CPEntry::Package(PackageInfo { name_index: mapped(f, info.name_index)? }),
+		entry => entry.clone(),
+	};
+	Ok(entry)
+}
+
+fn mapped<'a, T, F>(f: &mut F, index: CPIndex<'a, T>) -> Result<CPIndex<'a, T>, CompactError>
+	where T: CPType<'a>, F: FnMut(u16) -> Result<u16, CompactError>
+{
+	Ok(CPIndex::new(f(index.index)?))
+}
+
+/// The indices of the other entries an entry points at.
+fn children(entry: &CPEntry) -> Result<Vec<u16>, CompactError> {
+	let mut children = vec![];
+	map_entry(entry, &mut |index| {
+		children.push(index);
+		Ok(index)
+	})?;
+	Ok(children)
+}
+
+/// What entries of the same kind are sorted by: the contents of the strings and numbers they lead to,
+/// joined by zeroes, which modified UTF-8 never has.
+fn sort_key(entries: &[CPEntry], index: u16) -> Result<Vec<u8>, CompactError> {
+	let entry = entry(entries, index)?;
+	let mut key = match entry {
+		CPEntry::UTF8(info) => return Ok(info.as_bytes().to_vec()),
+		CPEntry::Integer(IntegerInfo { value }) | CPEntry::Float(FloatInfo { value }) => return Ok(value.to_be_bytes().to_vec()),
+		CPEntry::Long(LongInfo { high_bytes, low_bytes }) | CPEntry::Double(DoubleInfo { high_bytes, low_bytes }) => {
+			return Ok([high_bytes.to_be_bytes(), low_bytes.to_be_bytes()].concat());
+		}
+		CPEntry::MethodHandle(info) => vec![info.reference_kind(), 0],
+		CPEntry::Dynamic(DynamicInfo { bootstrap_method_attr_index, .. })
+		| CPEntry::InvokeDynamic(InvokeDynamicInfo { bootstrap_method_attr_index, .. }) => {
+			[&bootstrap_method_attr_index.to_be_bytes()[..], &[0u8][..]].concat()
+		}
+		_ => vec![],
+	};
+	for (i, child) in children(entry)?.into_iter().enumerate() {
+		if i > 0 {
+			key.push(0);
+		}
+		key.extend(sort_key(entries, child)?);
+	}
+	Ok(key)
+}
+
+/// Works out which entry each one gets merged into.
+struct Merger<'e, 'a> {
+	entries: &'e [CPEntry<'a>],
+	deduplicate: bool,
+	canonical: HashMap<u16, u16>,
+	/// The entries being worked on, as a broken pool may well point in circles.
+	visiting: HashSet<u16>,
+	/// The entries seen so far, with their indices swapped for those they were merged into.
+	seen: HashMap<CPEntry<'a>, u16>,
+}
+
+impl<'e, 'a> Merger<'e, 'a> {
+	fn canonical(&mut self, index: u16) -> Result<u16, CompactError> {
+		if let Some(&canonical) = self.canonical.get(&index) {
+			return Ok(canonical);
+		}
+		let entries = self.entries;
+		let entry = entry(entries, index)?;
+		if !self.visiting.insert(index) {
+			return Err(CompactError::InvalidIndex(index));
+		}
+		let canonical = if self.deduplicate {
+			let key = map_entry(entry, &mut |child| self.canonical(child))?;
+			*self.seen.entry(key).or_insert(index)
+		} else {
+			for child in children(entry)? {
+				self.canonical(child)?;
+			}
+			index
+		};
+		self.visiting.remove(&index);
+		self.canonical.insert(index, canonical);
+		Ok(canonical)
+	}
+}
+
+/// Where an offset into the old code is in the new code, `offsets` being `None` outside of code, where nothing moves.
+fn pc(offsets: Option<&BTreeMap<u32, u32>>, pc: u32) -> Result<u16, CompactError> {
+	let moved = match offsets {
+		Some(offsets) => offsets.get(&pc).copied(),
+		None => Some(pc),
+	};
+	moved.and_then(|pc| u16::try_from(pc).ok())
+		.ok_or(CompactError::InvalidOffset(pc))
+}
+
+/// Moves a `start_pc` and `length` along, as used by local variables.
+fn range(offsets: Option<&BTreeMap<u32, u32>>, start: u16, length: u16) -> Result<(u16, u16), CompactError> {
+	let end = start as u32 + length as u32;
+	let (start, new_end) = (pc(offsets, start as u32)?, pc(offsets, end)?);
+	let length = new_end.checked_sub(start).ok_or(CompactError::InvalidOffset(end))?;
+	Ok((start, length))
+}
+
+fn decode<T: FromBytes<BigEndian, Output = T>>(name: &str, info: &[u8]) -> Result<T, CompactError> {
+	T::from_bytes(&mut Cursor::new(info))
+		.map_err(|_| CompactError::Malformed(name.to_string()))
+}
+
+fn encode<T: ToBytes<BigEndian>>(name: &str, value: &T) -> Result<Vec<u8>, CompactError> {
+	let mut output = vec![];
+	value.to_bytes(&mut output)
+		.map_err(|_| CompactError::Malformed(name.to_string()))?;
+	Ok(output)
+}
+
+/// Goes through every index in the class, replacing each with what `visit` gives back.
+///
+/// `visit` is also told whether the index is loaded by an `ldc`.
+struct Walker<'e, 'a, F> {
+	/// The pool the indices point into, for the names of attributes.
+	entries: &'e [CPEntry<'a>],
+	visit: F,
+}
+
+impl<'e, 'a, F: FnMut(u16, bool) -> Result<u16, CompactError>> Walker<'e, 'a, F> {
+	fn index<T: CPType<'a>>(&mut self, index: CPIndex<'a, T>) -> Result<CPIndex<'a, T>, CompactError> {
+		// Zero stands for nothing, such as the super class of `Object`, or a handler catching everything.
+		if index.index == 0 {
+			return Ok(index);
+		}
+		Ok(CPIndex::new((self.visit)(index.index, false)?))
+	}
+
+	fn optional<T: CPType<'a>>(&mut self, index: Option<CPIndex<'a, T>>) -> Result<Option<CPIndex<'a, T>>, CompactError> {
+		index.map(|index| self.index(index)).transpose()
+	}
+
+	fn indices<T: CPType<'a>>(&mut self, indices: &mut [CPIndex<'a, T>]) -> Result<(), CompactError> {
+		for index in indices {
+			*index = self.index(*index)?;
+		}
+		Ok(())
+	}
+
+	fn class_file(&mut self, class_file: &ClassFile<'a>) -> Result<ClassFile<'a>, CompactError> {
+		let mut result = class_file.clone();
+		result.this_class = self.index(class_file.this_class)?;
+		result.super_class = self.index(class_file.super_class)?;
+		self.indices(&mut result.interfaces)?;
+		for field in &mut result.fields {
+			field.name_index = self.index(field.name_index)?;
+			field.descriptor_index = self.index(field.descriptor_index)?;
+			field.attributes = self.attributes(&field.attributes, None)?;
+		}
+		for method in &mut result.methods {
+			method.name_index = self.index(method.name_index)?;
+			method.descriptor_index = self.index(method.descriptor_index)?;
+			method.attributes = self.attributes(&method.attributes, None)?;
+		}
+		result.attributes = self.attributes(&class_file.attributes, None)?;
+		Ok(result)
+	}
+
+	/// `offsets` says where everything in the code went, for the attributes of a `Code`.
+	fn attributes(&mut self, attributes: &Attributes<'a>, offsets: Option<&BTreeMap<u32, u32>>) -> Result<Attributes<'a>, CompactError> {
+		let mut result = vec![];
+		for attribute in attributes.iter() {
+			let name_index = attribute.name_index();
+			let name = match entry(self.entries, name_index.index)? {
+				CPEntry::UTF8(info) => info.data.decoded(),
+				_ => return Err(CompactError::InvalidIndex(name_index.index)),
+			};
+			let info = self.attribute(&name, attribute.info(), offsets)?;
+			result.push(AttributeInfo::new(self.index(name_index)?, info));
+		}
+		Ok(Attributes::new(result))
+	}
+
+	fn attribute(&mut self, name: &str, info: &[u8], offsets: Option<&BTreeMap<u32, u32>>) -> Result<Vec<u8>, CompactError> {
+		match name {
+			"Synthetic" | "Deprecated" | "SourceDebugExtension" => Ok(info.to_vec()),
+			"ConstantValue" => {
+				let mut attribute: ConstantValue = decode(name, info)?;
+				attribute.constantvalue_index = self.index(attribute.constantvalue_index)?;
+				encode(name, &attribute)
+			}
+			"Code" => self.code(info),
+			"StackMapTable" => {
+				let mut attribute: StackMapTable = decode(name, info)?;
+				// Each frame is relative to the one before it, other than the first.
+				let mut previous: Option<(u32, u16)> = None;
+				for frame in &mut attribute.table {
+					let offset = match previous {
+						Some((offset, _)) => offset + frame.offset_delta() as u32 + 1,
+						None => frame.offset_delta() as u32,
+					};
+					let moved = pc(offsets, offset)?;
+					let offset_delta = match previous {
+						Some((_, moved_previous)) => moved.checked_sub(moved_previous + 1).ok_or(CompactError::InvalidOffset(offset))?,
+						None => moved,
+					};
+					*frame = self.frame(frame, offset_delta, offsets)?;
+					previous = Some((offset, moved));
+				}
+				encode(name, &attribute)
+			}
+			"Exceptions" => {
+				let mut attribute: Exceptions = decode(name, info)?;
+				self.indices(&mut attribute.table)?;
+				encode(name, &attribute)
+			}
+			"InnerClasses" => {
+				let mut attribute: InnerClasses = decode(name, info)?;
+				for inner in &mut attribute.table {
+					inner.inner_class_info_index = self.index(inner.inner_class_info_index)?;
+					inner.outer_class_info_index = self.optional(inner.outer_class_info_index)?;
+					inner.inner_name_index = self.optional(inner.inner_name_index)?;
+				}
+				encode(name, &attribute)
+			}
+			"EnclosingMethod" => {
+				let mut attribute: EnclosingMethod = decode(name, info)?;
+				attribute.class_index = self.index(attribute.class_index)?;
+				attribute.method_index = self.index(attribute.method_index)?;
+				encode(name, &attribute)
+			}
+			"Signature" => {
+				let mut attribute: Signature = decode(name, info)?;
+				attribute.class_index = self.index(attribute.class_index)?;
+				encode(name, &attribute)
+			}
+			"SourceFile" => {
+				let mut attribute: SourceFile = decode(name, info)?;
+				attribute.sourcefile_index = self.index(attribute.sourcefile_index)?;
+				encode(name, &attribute)
+			}
+			"LineNumberTable" => {
+				let mut attribute: LineNumberTable = decode(name, info)?;
+				for line in &mut attribute.table {
+					line.start_pc = pc(offsets, line.start_pc as u32)?;
+				}
+				encode(name, &attribute)
+			}
+			"LocalVariableTable" => {
+				let mut attribute: LocalVariableTable = decode(name, info)?;
+				for variable in &mut attribute.table {
+					let (start_pc, length) = range(offsets, variable.start_pc, variable.length)?;
+					variable.start_pc = start_pc;
+					variable.length = length;
+					variable.name_index = self.index(variable.name_index)?;
+					variable.descriptor_index = self.index(variable.descriptor_index)?;
+				}
+				encode(name, &attribute)
+			}
+			"LocalVariableTypeTable" => {
+				let mut attribute: LocalVariableTypeTable = decode(name, info)?;
+				for variable in &mut attribute.table {
+					let (start_pc, length) = range(offsets, variable.start_pc, variable.length)?;
+					variable.start_pc = start_pc;
+					variable.length = length;
+					variable.name_index = self.index(variable.name_index)?;
+					variable.signature_index = self.index(variable.signature_index)?;
+				}
+				encode(name, &attribute)
+			}
+			"RuntimeVisibleAnnotations" => {
+				let mut attribute: RuntimeVisibleAnnotations = decode(name, info)?;
+				self.annotations(&mut attribute.table)?;
+				encode(name, &attribute)
+			}
+			"RuntimeInvisibleAnnotations" => {
+				let mut attribute: RuntimeInvisibleAnnotations = decode(name, info)?;
+				self.annotations(&mut attribute.table)?;
+				encode(name, &attribute)
+			}
+			"RuntimeVisibleParameterAnnotations" => {
+				let mut attribute: RuntimeVisibleParameterAnnotations = decode(name, info)?;
+				for parameter in &mut attribute.table {
+					self.annotations(&mut parameter.annotations)?;
+				}
+				encode(name, &attribute)
+			}
+			"RuntimeInvisibleParameterAnnotations" => {
+				let mut attribute: RuntimeInvisibleParameterAnnotations = decode(name, info)?;
+				for parameter in &mut attribute.table {
+					self.annotations(&mut parameter.annotations)?;
+				}
+				encode(name, &attribute)
+			}
+			"RuntimeVisibleTypeAnnotations" => {
+				let mut attribute: RuntimeVisibleTypeAnnotations = decode(name, info)?;
+				self.type_annotations(&mut attribute.table, offsets)?;
+				encode(name, &attribute)
+			}
+			"RuntimeInvisibleTypeAnnotations" => {
+				let mut attribute: RuntimeInvisibleTypeAnnotations = decode(name, info)?;
+				self.type_annotations(&mut attribute.table, offsets)?;
+				encode(name, &attribute)
+			}
+			"AnnotationDefault" => {
+				let mut attribute: AnnotationDefault = decode(name, info)?;
+				self.element_value(&mut attribute.default_value)?;
+				encode(name, &attribute)
+			}
+			"BootstrapMethods" => {
+				let mut attribute: BootstrapMethods = decode(name, info)?;
+				for method in &mut attribute.table {
+					method.bootstrap_method_ref = self.index(method.bootstrap_method_ref)?;
+					self.indices(&mut method.bootstrap_arguments)?;
+				}
+				encode(name, &attribute)
+			}
+			"MethodParameters" => {
+				let mut attribute: MethodParameters = decode(name, info)?;
+				for parameter in &mut attribute.table {
+					parameter.name_index = self.optional(parameter.name_index)?;
+				}
+				encode(name, &attribute)
+			}
+			"Module" => {
+				let mut attribute: attr::Module = decode(name, info)?;
+				attribute.module_name_index = self.index(attribute.module_name_index)?;
+				attribute.module_version_index = self.optional(attribute.module_version_index)?;
+				for requires in &mut attribute.requires {
+					requires.requires_index = self.index(requires.requires_index)?;
+					requires.requires_version_index = self.optional(requires.requires_version_index)?;
+				}
+				for exports in &mut attribute.exports {
+					exports.exports_index = self.index(exports.exports_index)?;
+					self.indices(&mut exports.exports_to)?;
+				}
+				for opens in &mut attribute.opens {
+					opens.opens_index = self.index(opens.opens_index)?;
+					self.indices(&mut opens.opens_to)?;
+				}
+				self.indices(&mut attribute.uses)?;
+				for provides in &mut attribute.provides {
+					provides.provides = self.index(provides.provides)?;
+					self.indices(&mut provides.provides_with)?;
+				}
+				encode(name, &attribute)
+			}
+			"ModulePackages" => {
+				let mut attribute: ModulePackages = decode(name, info)?;
+				self.indices(&mut attribute.packages)?;
+				encode(name, &attribute)
+			}
+			"ModuleMainClass" => {
+				let mut attribute: ModuleMainClass = decode(name, info)?;
+				attribute.main_class_index = self.index(attribute.main_class_index)?;
+				encode(name, &attribute)
+			}
+			"NestHost" => {
+				let mut attribute: NestHost = decode(name, info)?;
+				attribute.host_class_index = self.index(attribute.host_class_index)?;
+				encode(name, &attribute)
+			}
+			"NestMembers" => {
+				let mut attribute: NestMembers = decode(name, info)?;
+				self.indices(&mut attribute.classes)?;
+				encode(name, &attribute)
+			}
+			"Record" => {
+				let mut attribute: Record = decode(name, info)?;
+				for component in &mut attribute.components {
+					component.name_index = self.index(component.name_index)?;
+					component.descriptor_index = self.index(component.descriptor_index)?;
+					component.attributes = self.attributes(&component.attributes, None)?;
+				}
+				encode(name, &attribute)
+			}
+			"PermittedSubclasses" => {
+				let mut attribute: PermittedSubclasses = decode(name, info)?;
+				self.indices(&mut attribute.classes)?;
+				encode(name, &attribute)
+			}
+			_ => Err(CompactError::UnknownAttribute(name.to_string())),
+		}
+	}
+
+	fn code(&mut self, info: &[u8]) -> Result<Vec<u8>, CompactError> {
+		let mut code: Code = decode("Code", info)?;
+		let mut instructions = bytecode::decode(&code.code).map_err(CompactError::Decode)?;
+		for instruction in &mut instructions {
+			let loaded = matches!(instruction.opcode, LDC | LDC_W);
+			match &mut instruction.operand {
+				Operand::Constant(index) => *index = (self.visit)(*index, loaded)?,
+				Operand::InvokeInterface { index, .. } | Operand::MultiANewArray { index, .. } => *index = (self.visit)(*index, false)?,
+				_ => {}
+			}
+		}
+		let encoded = bytecode::encode(&instructions).map_err(CompactError::Encode)?;
+		if encoded.code.len() > u16::MAX as usize {
+			return Err(CompactError::CodeTooLarge(encoded.code.len()));
+		}
+
+		let offsets = Some(&encoded.offsets);
+		for exception in &mut code.exception_table {
+			exception.start_pc = pc(offsets, exception.start_pc as u32)?;
+			exception.end_pc = pc(offsets, exception.end_pc as u32)?;
+			exception.handler_pc = pc(offsets, exception.handler_pc as u32)?;
+			exception.catch_type = self.index(exception.catch_type)?;
+		}
+		code.attributes = self.attributes(&code.attributes, offsets)?;
+		code.code = encoded.code;
+		encode("Code", &code)
+	}
+
+	/// The frame with its new `offset_delta`, which may need a bigger kind of frame.
+	fn frame(&mut self, frame: &StackMapFrame<'a>, offset_delta: u16, offsets: Option<&BTreeMap<u32, u32>>) -> Result<StackMapFrame<'a>, CompactError> {
+		let small = offset_delta < 64;
+		let frame = match frame {
+			StackMapFrame::SameFrame(_) if small => StackMapFrame::SameFrame(offset_delta as u8),
+			StackMapFrame::SameFrame(_) | StackMapFrame::SameFrameExtended(_) => StackMapFrame::SameFrameExtended(offset_delta),
+			StackMapFrame::SameLocals { verification_type_info, .. } if small => StackMapFrame::SameLocals {
+				offset_delta: offset_delta as u8,
+				verification_type_info: self.verification_type(verification_type_info, offsets)?,
+			},
+			StackMapFrame::SameLocals { verification_type_info, .. }
+			| StackMapFrame::SameLocalsExtended { verification_type_info, .. } => StackMapFrame::SameLocalsExtended {
+				offset_delta,
+				verification_type_info: self.verification_type(verification_type_info, offsets)?,
+			},
+			StackMapFrame::ChopFrame { chopped, .. } => StackMapFrame::ChopFrame {
+				offset_delta,
+				chopped: *chopped,
+			},
+			StackMapFrame::AppendFrame { locals, .. } => StackMapFrame::AppendFrame {
+				offset_delta,
+				locals: self.verification_types(locals, offsets)?,
+			},
+			StackMapFrame::FullFrame { locals, stack, .. } => StackMapFrame::FullFrame {
+				offset_delta,
+				locals: self.verification_types(locals, offsets)?,
+				stack: self.verification_types(stack, offsets)?,
+			},
+		};
+		Ok(frame)
+	}
+
+	fn verification_types(&mut self, types: &[VerificationTypeInfo<'a>], offsets: Option<&BTreeMap<u32, u32>>) -> Result<Vec<VerificationTypeInfo<'a>>, CompactError> {
+		types.iter()
+			.map(|info| self.verification_type(info, offsets))
+			.collect()
+	}
+
+	fn verification_type(&mut self, info: &VerificationTypeInfo<'a>, offsets: Option<&BTreeMap<u32, u32>>) -> Result<VerificationTypeInfo<'a>, CompactError> {
+		let info = match *info {
+			VerificationTypeInfo::ObjectVariable(index) => VerificationTypeInfo::ObjectVariable(self.index(index)?),
+			// The offset of the `new` that made the object.
+			VerificationTypeInfo::Uninitialized(offset) => VerificationTypeInfo::Uninitialized(pc(offsets, offset as u32)?),
+			ref info => info.clone(),
+		};
+		Ok(info)
+	}
+
+	fn annotations(&mut self, annotations: &mut [Annotation<'a>]) -> Result<(), CompactError> {
+		for annotation in annotations {
+			self.annotation(annotation)?;
+		}
+		Ok(())
+	}
+
+	fn annotation(&mut self, annotation: &mut Annotation<'a>) -> Result<(), CompactError> {
+		annotation.type_index = self.index(annotation.type_index)?;
+		self.element_value_pairs(&mut annotation.element_value_pairs)
+	}
+
+	fn type_annotations(&mut self, annotations: &mut [TypeAnnotation<'a>], offsets: Option<&BTreeMap<u32, u32>>) -> Result<(), CompactError> {
+		for annotation in annotations {
+			match &mut annotation.target_info {
+				TargetInfo::Offset { offset, .. } | TargetInfo::TypeArgument { offset, .. } => {
+					*offset = pc(offsets, *offset as u32)?;
+				}
+				TargetInfo::LocalVar { table, .. } => {
+					for target in table {
+						let (start_pc, length) = range(offsets, target.start_pc, target.length)?;
+						target.start_pc = start_pc;
+						target.length = length;
+					}
+				}
+				_ => {}
+			}
+			annotation.type_index = self.index(annotation.type_index)?;
+			self.element_value_pairs(&mut annotation.element_value_pairs)?;
+		}
+		Ok(())
+	}
+
+	fn element_value_pairs(&mut self, pairs: &mut [ElementValuePair<'a>]) -> Result<(), CompactError> {
+		for pair in pairs {
+			pair.element_name_index = self.index(pair.element_name_index)?;
+			self.element_value(&mut pair.element_value)?;
+		}
+		Ok(())
+	}
+
+	fn element_value(&mut self, value: &mut ElementValue<'a>) -> Result<(), CompactError> {
+		match value {
+			ElementValue::Byte(index)
+			| ElementValue::Char(index)
+			| ElementValue::Integer(index)
+			| ElementValue::Short(index)
+			| ElementValue::Boolean(index) => *index = self.index(*index)?,
+			ElementValue::Double(index) => *index = self.index(*index)?,
+			ElementValue::Float(index) => *index = self.index(*index)?,
+			ElementValue::Long(index) => *index = self.index(*index)?,
+			ElementValue::String(index) | ElementValue::Class(index) => *index = self.index(*index)?,
+			ElementValue::Enum { type_name_index, const_name_index } => {
+				*type_name_index = self.index(*type_name_index)?;
+				*const_name_index = self.index(*const_name_index)?;
+			}
+			ElementValue::Annotation(annotation) => self.annotation(annotation)?,
+			ElementValue::Array(values) => {
+				for value in values {
+					self.element_value(value)?;
+				}
+			}
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,387 @@
+//! Checking a new version of a library against an old one for changes that break binaries compiled against the old one,
+//! following the rules in chapter 13 of the JLS.
+//!
+//! Only what other packages can see is checked: public classes, and their public and protected members,
+//! leaving out protected members of final classes, and synthetic members that aren't bridges.
+//! A member that's gone from a class is fine as long as the class still inherits one like it.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::*;
+use crate::attr::ConstantValue;
+use crate::classpath::ClassPath;
+use crate::resolve::constant;
+use crate::utf8::MStrExt;
+
+/// How bad a problem is, in increasing order.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Ord, PartialOrd)]
+pub enum Severity {
+	/// Binaries still link, but might behave differently, such as by using an old constant.
+	Warning,
+	/// Binaries can fail to link or verify, with an `IncompatibleClassChangeError` or one of its friends.
+	Error,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Problem {
+	ClassRemoved,
+	/// The class isn't public anymore.
+	ClassLessAccessible,
+	/// The class became an interface, or the other way around, with whether it used to be an interface.
+	KindChanged {
+		was_interface: bool,
+	},
+	ClassMadeAbstract,
+	ClassMadeFinal,
+	/// A class or interface the class used to extend or implement, directly or not.
+	SuperTypeRemoved(String),
+	/// An abstract method was added to a class or interface, which existing subclasses don't implement.
+	AbstractMethodAdded,
+	MethodRemoved,
+	FieldRemoved,
+	/// The member went from public to protected, or from either to package private or private.
+	MemberLessAccessible {
+		old: u16,
+		new: u16,
+	},
+	FieldTypeChanged {
+		old: String,
+		new: String,
+	},
+	ReturnTypeChanged {
+		old: String,
+		new: String,
+	},
+	/// The member became static, or stopped being static, with whether it used to be static.
+	StaticChanged {
+		was_static: bool,
+	},
+	/// A field or instance method became final.
+	MemberMadeFinal,
+	MethodMadeAbstract,
+	/// The value of a constant changed, or the field stopped or started being a constant.
+	/// Binaries compiled against the old version have the old value inlined.
+	ConstantValueChanged {
+		old: Option<String>,
+		new: Option<String>,
+	},
+}
+
+impl Problem {
+	pub fn severity(&self) -> Severity {
+		match self {
+			Problem::AbstractMethodAdded | Problem::ConstantValueChanged { .. } => Severity::Warning,
+			_ => Severity::Error,
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Diagnostic {
+	pub class: String,
+	/// The field or method, such as `name:Ljava/lang/String;` or `describe(I)Ljava/lang/String;`, if the problem is with one.
+	pub member: Option<String>,
+	pub problem: Problem,
+}
+
+impl Diagnostic {
+	pub fn severity(&self) -> Severity {
+		self.problem.severity()
+	}
+}
+
+fn access(flags: u16) -> &'static str {
+	if flags & PUBLIC != 0 {
+		"public"
+	} else if flags & PROTECTED != 0 {
+		"protected"
+	} else if flags & PRIVATE != 0 {
+		"private"
+	} else {
+		"package private"
+	}
+}
+
+fn optional(value: &Option<String>) -> &str {
+	value.as_deref().unwrap_or("none")
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.severity() {
+			Severity::Warning => f.write_str("warning: ")?,
+			Severity::Error => f.write_str("error: ")?,
+		}
+		f.write_str(&self.class)?;
+		if let Some(ref member) = self.member {
+			write!(f, ".{}", member)?;
+		}
+		f.write_str(": ")?;
+		match self.problem {
+			Problem::ClassRemoved => f.write_str("class removed"),
+			Problem::ClassLessAccessible => f.write_str("class is no longer public"),
+			Problem::KindChanged { was_interface: true } => f.write_str("interface changed to a class"),
+			Problem::KindChanged { was_interface: false } => f.write_str("class changed to an interface"),
+			Problem::ClassMadeAbstract => f.write_str("class made abstract"),
+			Problem::ClassMadeFinal => f.write_str("class made final"),
+			Problem::SuperTypeRemoved(ref name) => write!(f, "no longer a subtype of {}", name),
+			Problem::AbstractMethodAdded => f.write_str("abstract method added"),
+			Problem::MethodRemoved => f.write_str("method removed"),
+			Problem::FieldRemoved => f.write_str("field removed"),
+			Problem::MemberLessAccessible { old, new } => write!(f, "access narrowed from {} to {}", access(old), access(new)),
+			Problem::FieldTypeChanged { ref old, ref new } => write!(f, "field type changed from {} to {}", old, new),
+			Problem::ReturnTypeChanged { ref old, ref new } => write!(f, "return type changed from {} to {}", old, new),
+			Problem::StaticChanged { was_static: true } => f.write_str("no longer static"),
+			Problem::StaticChanged { was_static: false } => f.write_str("made static"),
+			Problem::MemberMadeFinal => f.write_str("made final"),
+			Problem::MethodMadeAbstract => f.write_str("method made abstract"),
+			Problem::ConstantValueChanged { ref old, ref new } => write!(f, "constant value changed from {} to {}", optional(old), optional(new)),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Kind {
+	Field,
+	Method,
+}
+
+/// A field or method, with its names resolved.
+struct Member {
+	kind: Kind,
+	name: String,
+	descriptor: String,
+	flags: u16,
+	constant_value: Option<String>,
+}
+
+impl Member {
+	fn describe(&self) -> String {
+		match self.kind {
+			Kind::Field => format!("{}:{}", self.name, self.descriptor),
+			Kind::Method => format!("{}{}", self.name, self.descriptor),
+		}
+	}
+
+	/// The descriptor without the return type, for methods.
+	fn parameters(&self) -> &str {
+		match self.descriptor.rfind(')') {
+			Some(end) if self.kind == Kind::Method => &self.descriptor[..=end],
+			_ => &self.descriptor,
+		}
+	}
+
+	fn return_type(&self) -> &str {
+		&self.descriptor[self.parameters().len()..]
+	}
+}
+
+fn members(class_file: &ClassFile) -> Vec<Member> {
+	let cp = &class_file.constant_pool;
+	let fields = class_file.fields.iter().map(|field| (Kind::Field, field.access_flags, field.name_index, field.descriptor_index, &field.attributes));
+	let methods = class_file.methods.iter().map(|method| (Kind::Method, method.access_flags, method.name_index, method.descriptor_index, &method.attributes));
+	fields.chain(methods)
+		.filter_map(|(kind, flags, name_index, descriptor_index, attributes)| {
+			let constant_value = attributes.get::<ConstantValue>(cp)
+				.and_then(|value| constant(cp, value.constantvalue_index.index));
+			Some(Member {
+				kind,
+				name: cp.utf8(name_index)?.decoded(),
+				descriptor: cp.utf8(descriptor_index)?.decoded(),
+				flags,
+				constant_value,
+			})
+		})
+		.collect()
+}
+
+fn super_types(class_file: &ClassFile) -> Vec<String> {
+	let cp = &class_file.constant_pool;
+	let super_class = match class_file.super_class.index {
+		0 => None,
+		_ => cp.class_name(class_file.super_class).map(MStrExt::decoded),
+	};
+	let interfaces = class_file.interfaces.iter()
+		.filter_map(|&index| cp.class_name(index).map(MStrExt::decoded));
+	super_class.into_iter().chain(interfaces).collect()
+}
+
+/// Every class and interface the class extends or implements, directly or not,
+/// going as far as the class path allows, so classes that aren't in it are included, but not their super types.
+fn all_super_types(class_path: &ClassPath, name: &str) -> BTreeSet<String> {
+	let mut result = BTreeSet::new();
+	let mut pending = vec![name.to_string()];
+	while let Some(name) = pending.pop() {
+		if let Some(class_file) = class_path.get(&name) {
+			for super_type in super_types(class_file) {
+				if result.insert(super_type.clone()) {
+					pending.push(super_type);
+				}
+			}
+		}
+	}
+	result
+}
+
+/// Whether the class, or one of its super types, has a member like the given one that other packages can use.
+fn find_inherited(class_path: &ClassPath, name: &str, member: &Member) -> bool {
+	let mut names = vec![name.to_string()];
+	names.extend(all_super_types(class_path, name));
+	names.iter()
+		.filter_map(|name| class_path.get(name))
+		.any(|class_file| members(class_file).iter().any(|other| {
+			other.kind == member.kind
+				&& other.name == member.name
+				&& other.descriptor == member.descriptor
+				&& other.flags & (PUBLIC | PROTECTED) != 0
+				&& other.flags & STATIC == member.flags & STATIC
+		}))
+}
+
+fn is_visible(member: &Member, class_flags: u16) -> bool {
+	// Bridges are synthetic, but binaries compiled against the old version can still call them.
+	let is_bridge = member.kind == Kind::Method && member.flags & BRIDGE != 0;
+	if member.flags & SYNTHETIC != 0 && !is_bridge {
+		return false;
+	}
+	member.flags & PUBLIC != 0 || member.flags & PROTECTED != 0 && class_flags & FINAL == 0
+}
+
+/// How much access the flags give, from private up to public.
+fn access_level(flags: u16) -> u8 {
+	if flags & PUBLIC != 0 {
+		3
+	} else if flags & PROTECTED != 0 {
+		2
+	} else if flags & PRIVATE != 0 {
+		0
+	} else {
+		1
+	}
+}
+
+struct Checker<'p> {
+	old: &'p ClassPath,
+	new: &'p ClassPath,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+	fn report(&mut self, class: &str, member: Option<&Member>, problem: Problem) {
+		self.diagnostics.push(Diagnostic {
+			class: class.to_string(),
+			member: member.map(Member::describe),
+			problem,
+		});
+	}
+
+	fn check_class(&mut self, name: &str, old: &ClassFile, new: &ClassFile) {
+		let is_interface = old.access_flags & INTERFACE != 0;
+		if new.access_flags & PUBLIC == 0 {
+			self.report(name, None, Problem::ClassLessAccessible);
+			return;
+		}
+		if is_interface != (new.access_flags & INTERFACE != 0) {
+			self.report(name, None, Problem::KindChanged { was_interface: is_interface });
+			return;
+		}
+		if !is_interface && old.access_flags & ABSTRACT == 0 && new.access_flags & ABSTRACT != 0 {
+			self.report(name, None, Problem::ClassMadeAbstract);
+		}
+		if old.access_flags & FINAL == 0 && new.access_flags & FINAL != 0 {
+			self.report(name, None, Problem::ClassMadeFinal);
+		}
+		let new_super_types = all_super_types(self.new, name);
+		for super_type in all_super_types(self.old, name).difference(&new_super_types) {
+			self.report(name, None, Problem::SuperTypeRemoved(super_type.clone()));
+		}
+
+		let old_members = members(old);
+		let new_members = members(new);
+		for member in old_members.iter().filter(|member| is_visible(member, old.access_flags)) {
+			let same = new_members.iter()
+				.find(|other| other.kind == member.kind && other.name == member.name && other.descriptor == member.descriptor);
+			match same {
+				Some(other) => self.check_member(name, member, other),
+				None => self.check_missing(name, member, &new_members),
+			}
+		}
+
+		// Subclasses compiled against the old version won't implement these, see JLS 13.4.16 and 13.5.3.
+		let can_be_extended = new.access_flags & FINAL == 0;
+		for member in new_members.iter().filter(|member| member.kind == Kind::Method && member.flags & ABSTRACT != 0) {
+			let existed = old_members.iter()
+				.any(|other| other.kind == Kind::Method && other.name == member.name && other.descriptor == member.descriptor);
+			if can_be_extended && !existed {
+				self.report(name, Some(member), Problem::AbstractMethodAdded);
+			}
+		}
+	}
+
+	fn check_member(&mut self, class: &str, old: &Member, new: &Member) {
+		if access_level(new.flags) < access_level(old.flags) {
+			self.report(class, Some(old), Problem::MemberLessAccessible { old: old.flags, new: new.flags });
+		}
+		if old.flags & STATIC != new.flags & STATIC {
+			self.report(class, Some(old), Problem::StaticChanged { was_static: old.flags & STATIC != 0 });
+		}
+		// Making a static method final only stops it from being hidden, which binaries don't care about, see JLS 13.4.17.
+		let matters = old.kind == Kind::Field || new.flags & STATIC == 0;
+		if matters && old.flags & FINAL == 0 && new.flags & FINAL != 0 {
+			self.report(class, Some(old), Problem::MemberMadeFinal);
+		}
+		if old.kind == Kind::Method && old.flags & ABSTRACT == 0 && new.flags & ABSTRACT != 0 {
+			self.report(class, Some(old), Problem::MethodMadeAbstract);
+		}
+		if old.constant_value != new.constant_value {
+			self.report(class, Some(old), Problem::ConstantValueChanged {
+				old: old.constant_value.clone(),
+				new: new.constant_value.clone(),
+			});
+		}
+	}
+
+	/// The member isn't in the new class, which is fine if it's inherited, otherwise a change of type is more helpful than saying it's gone.
+	fn check_missing(&mut self, class: &str, old: &Member, new_members: &[Member]) {
+		if find_inherited(self.new, class, old) {
+			return;
+		}
+		let problem = match old.kind {
+			Kind::Field => match new_members.iter().find(|other| other.kind == Kind::Field && other.name == old.name) {
+				Some(other) => Problem::FieldTypeChanged { old: old.descriptor.clone(), new: other.descriptor.clone() },
+				None => Problem::FieldRemoved,
+			},
+			Kind::Method => {
+				let other = new_members.iter()
+					.find(|other| other.kind == Kind::Method && other.name == old.name && other.parameters() == old.parameters());
+				match other {
+					Some(other) => Problem::ReturnTypeChanged { old: old.return_type().to_string(), new: other.return_type().to_string() },
+					None => Problem::MethodRemoved,
+				}
+			}
+		};
+		self.report(class, Some(old), problem);
+	}
+}
+
+/// Everything about the public classes in `old` that could break binaries when they're swapped for the ones in `new`,
+/// ordered by class.
+pub fn check(old: &ClassPath, new: &ClassPath) -> Vec<Diagnostic> {
+	let mut checker = Checker {
+		old,
+		new,
+		diagnostics: vec![],
+	};
+	for name in old.names() {
+		let old_class = match old.get(name) {
+			Some(class_file) if class_file.access_flags & PUBLIC != 0 => class_file,
+			_ => continue,
+		};
+		match new.get(name) {
+			Some(new_class) => checker.check_class(name, old_class, new_class),
+			None => checker.report(name, None, Problem::ClassRemoved),
+		}
+	}
+	checker.diagnostics
+}
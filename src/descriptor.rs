@@ -0,0 +1,197 @@
+//! Field and method descriptors, and the names they're built from, as described in JVMS 4.2 and 4.3.
+//!
+//! Descriptors are parsed from `&str`, so run strings from the constant pool through `utf8::decode` first.
+
+use std::fmt;
+
+/// The deepest an array type may be nested.
+pub const MAX_ARRAY_DIMENSIONS: usize = 255;
+
+/// The most slots the parameters of a method may take up, including `this`.
+pub const MAX_PARAMETER_SLOTS: usize = 255;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum FieldType {
+	Byte,
+	Char,
+	Double,
+	Float,
+	Int,
+	Long,
+	Short,
+	Boolean,
+	/// The internal name of the class, such as `java/lang/String`.
+	Object(String),
+	Array(Box<FieldType>),
+}
+
+impl FieldType {
+	/// Parses a complete field descriptor, such as `[Ljava/lang/String;`.
+	pub fn parse(descriptor: &str) -> Option<FieldType> {
+		match FieldType::parse_prefix(descriptor)? {
+			(field_type, "") => Some(field_type),
+			_ => None,
+		}
+	}
+
+	/// Parses a single type off the front of `input`, handing back the rest.
+	fn parse_prefix(input: &str) -> Option<(FieldType, &str)> {
+		let dimensions = input.bytes()
+			.take_while(|&b| b == b'[')
+			.count();
+		if dimensions > MAX_ARRAY_DIMENSIONS {
+			return None;
+		}
+		let input = &input[dimensions..];
+
+		let mut chars = input.chars();
+		let field_type = match chars.next()? {
+			'B' => FieldType::Byte,
+			'C' => FieldType::Char,
+			'D' => FieldType::Double,
+			'F' => FieldType::Float,
+			'I' => FieldType::Int,
+			'J' => FieldType::Long,
+			'S' => FieldType::Short,
+			'Z' => FieldType::Boolean,
+			'L' => {
+				let end = input.find(';')?;
+				let name = &input[1..end];
+				if !is_binary_name(name) {
+					return None;
+				}
+				let field_type = FieldType::Object(name.to_string());
+				return Some((FieldType::array_of(field_type, dimensions), &input[end + 1..]));
+			}
+			_ => return None,
+		};
+		Some((FieldType::array_of(field_type, dimensions), chars.as_str()))
+	}
+
+	fn array_of(mut field_type: FieldType, dimensions: usize) -> FieldType {
+		for _ in 0..dimensions {
+			field_type = FieldType::Array(Box::new(field_type));
+		}
+		field_type
+	}
+
+	/// The number of local variable or operand stack slots a value of this type takes up.
+	pub fn size(&self) -> usize {
+		match self {
+			FieldType::Long | FieldType::Double => 2,
+			_ => 1,
+		}
+	}
+
+	pub fn is_reference(&self) -> bool {
+		matches!(self, FieldType::Object(_) | FieldType::Array(_))
+	}
+
+	/// The number of dimensions, or 0 if this isn't an array.
+	pub fn dimensions(&self) -> usize {
+		match self {
+			FieldType::Array(component) => 1 + component.dimensions(),
+			_ => 0,
+		}
+	}
+}
+
+impl fmt::Display for FieldType {
+	/// Writes the type back out as a descriptor.
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			FieldType::Byte => f.write_str("B"),
+			FieldType::Char => f.write_str("C"),
+			FieldType::Double => f.write_str("D"),
+			FieldType::Float => f.write_str("F"),
+			FieldType::Int => f.write_str("I"),
+			FieldType::Long => f.write_str("J"),
+			FieldType::Short => f.write_str("S"),
+			FieldType::Boolean => f.write_str("Z"),
+			FieldType::Object(name) => write!(f, "L{};", name),
+			FieldType::Array(component) => write!(f, "[{}", component),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct MethodDescriptor {
+	pub parameters: Vec<FieldType>,
+	/// `None` for `void`.
+	pub return_type: Option<FieldType>,
+}
+
+impl MethodDescriptor {
+	/// Parses a complete method descriptor, such as `(I[J)Ljava/lang/Object;`.
+	///
+	/// This doesn't check the number of parameter slots, see `parameter_slots`.
+	pub fn parse(descriptor: &str) -> Option<MethodDescriptor> {
+		if !descriptor.starts_with('(') {
+			return None;
+		}
+		let mut input = &descriptor[1..];
+		let mut parameters = vec![];
+		while !input.starts_with(')') {
+			let (parameter, rest) = FieldType::parse_prefix(input)?;
+			parameters.push(parameter);
+			input = rest;
+		}
+		let return_type = match &input[1..] {
+			"V" => None,
+			rest => Some(FieldType::parse(rest)?),
+		};
+		Some(MethodDescriptor {
+			parameters,
+			return_type,
+		})
+	}
+
+	/// The number of local variable slots the parameters take up, not counting `this`.
+	pub fn parameter_slots(&self) -> usize {
+		self.parameters.iter()
+			.map(FieldType::size)
+			.sum()
+	}
+}
+
+impl fmt::Display for MethodDescriptor {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("(")?;
+		for parameter in &self.parameters {
+			write!(f, "{}", parameter)?;
+		}
+		f.write_str(")")?;
+		match self.return_type {
+			Some(ref return_type) => write!(f, "{}", return_type),
+			None => f.write_str("V"),
+		}
+	}
+}
+
+/// A field name, or any other name that's not allowed to contain `.`, `;`, `[` or `/`.
+pub fn is_unqualified_name(name: &str) -> bool {
+	!name.is_empty() && !name.contains(['.', ';', '[', '/'])
+}
+
+/// Like an unqualified name, but also without `<` or `>`, unless it's `<init>` or `<clinit>`.
+pub fn is_method_name(name: &str) -> bool {
+	if name == "<init>" || name == "<clinit>" {
+		return true;
+	}
+	is_unqualified_name(name) && !name.contains(['<', '>'])
+}
+
+/// A class or interface name in internal form, such as `java/lang/Object`.
+pub fn is_binary_name(name: &str) -> bool {
+	name.split('/')
+		.all(is_unqualified_name)
+}
+
+/// What a `ClassInfo` may name, which is either a binary name or, for arrays, a descriptor.
+pub fn is_class_name(name: &str) -> bool {
+	if name.starts_with('[') {
+		FieldType::parse(name).is_some()
+	} else {
+		is_binary_name(name)
+	}
+}
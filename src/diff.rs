@@ -0,0 +1,590 @@
+//! Comparing two classes by what they contain, rather than by their bytes.
+//!
+//! Everything is compared with its constant pool indices resolved, and code is compared instruction by instruction,
+//! with branch targets turned into labels, so two classes that only differ in how their constant pools are laid out
+//! come out the same. Debug information, such as line numbers and local variable names, is left out.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::Cursor;
+
+use crate::*;
+use crate::attr::*;
+use crate::bytecode::{self, Instruction, Operand};
+use crate::ops::{LDC, LDC_W};
+use crate::resolve::constant;
+use crate::utf8::MStrExt;
+
+/// Something that changed, on the class itself or one of its members.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Change {
+	Name {
+		old: String,
+		new: String,
+	},
+	/// The major and minor versions.
+	Version {
+		old: (u16, u16),
+		new: (u16, u16),
+	},
+	Flags {
+		old: u16,
+		new: u16,
+	},
+	SuperClass {
+		old: Option<String>,
+		new: Option<String>,
+	},
+	InterfaceAdded(String),
+	InterfaceRemoved(String),
+	Signature {
+		old: Option<String>,
+		new: Option<String>,
+	},
+	/// A field's `ConstantValue`, the way `javap` would print it.
+	ConstantValue {
+		old: Option<String>,
+		new: Option<String>,
+	},
+	/// The classes in a method's `Exceptions` attribute.
+	Exceptions {
+		old: Vec<String>,
+		new: Vec<String>,
+	},
+	AnnotationAdded(Annotation),
+	AnnotationRemoved(Annotation),
+}
+
+/// An annotation written out in full, such as `@Ljava/lang/Deprecated;(since="9")`.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Ord, PartialOrd)]
+pub struct Annotation {
+	pub text: String,
+	/// Whether it's from `RuntimeVisibleAnnotations`, rather than `RuntimeInvisibleAnnotations`.
+	pub visible: bool,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Line {
+	Same(String),
+	Added(String),
+	Removed(String),
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum MemberKind {
+	Field,
+	Method,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct MemberDiff {
+	pub kind: MemberKind,
+	pub name: String,
+	pub descriptor: String,
+	pub status: MemberStatus,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum MemberStatus {
+	/// The member is new, with its code, if it has any.
+	Added(Vec<String>),
+	/// The member is gone, with the code it had, if any.
+	Removed(Vec<String>),
+	/// The member is in both, with what changed about it.
+	///
+	/// `code` has every line of both versions of the code, and is empty if the code is the same.
+	Changed {
+		changes: Vec<Change>,
+		code: Vec<Line>,
+	},
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct ClassDiff {
+	/// The name of the new class.
+	pub name: String,
+	pub changes: Vec<Change>,
+	/// The fields and methods that changed, in the order they're in the old class, followed by the new ones.
+	pub members: Vec<MemberDiff>,
+}
+
+impl ClassDiff {
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty() && self.members.is_empty()
+	}
+}
+
+fn decode<'a, T: FromBytes<BigEndian, Output = T> + 'a>(attributes: &Attributes<'a>, cp: &ConstantPool<'a>, name: &str) -> Option<T> {
+	T::from_bytes(&mut Cursor::new(attributes.named(cp, name)?.info())).ok()
+}
+
+/// A class with everything the diff looks at pulled out of it.
+struct Class {
+	name: String,
+	version: (u16, u16),
+	flags: u16,
+	super_class: Option<String>,
+	interfaces: Vec<String>,
+	signature: Option<String>,
+	annotations: BTreeSet<Annotation>,
+	members: Vec<Member>,
+}
+
+struct Member {
+	kind: MemberKind,
+	name: String,
+	descriptor: String,
+	flags: u16,
+	signature: Option<String>,
+	constant_value: Option<String>,
+	exceptions: Vec<String>,
+	annotations: BTreeSet<Annotation>,
+	code: Vec<String>,
+}
+
+impl Class {
+	fn new(class_file: &ClassFile) -> Option<Self> {
+		let cp = &class_file.constant_pool;
+		let super_class = match class_file.super_class.index {
+			0 => None,
+			_ => Some(cp.class_name(class_file.super_class)?.decoded()),
+		};
+		let interfaces = class_file.interfaces.iter()
+			.map(|&index| cp.class_name(index).map(MStrExt::decoded))
+			.collect::<Option<_>>()?;
+		let fields = class_file.fields.iter()
+			.map(|field| Member::new(MemberKind::Field, field.access_flags, field.name_index, field.descriptor_index, &field.attributes, cp));
+		let methods = class_file.methods.iter()
+			.map(|method| Member::new(MemberKind::Method, method.access_flags, method.name_index, method.descriptor_index, &method.attributes, cp));
+		Some(Class {
+			name: cp.class_name(class_file.this_class)?.decoded(),
+			version: (class_file.major_version, class_file.minor_version),
+			flags: class_file.access_flags,
+			super_class,
+			interfaces,
+			signature: signature(&class_file.attributes, cp),
+			annotations: annotations(&class_file.attributes, cp),
+			members: fields.chain(methods).collect::<Option<_>>()?,
+		})
+	}
+}
+
+impl Member {
+	fn new<'a>(kind: MemberKind, flags: u16, name_index: CPIndex<'a, UTF8Info<'a>>, descriptor_index: CPIndex<'a, UTF8Info<'a>>, attributes: &Attributes<'a>, cp: &'a ConstantPool<'a>) -> Option<Self> {
+		let constant_value = decode::<ConstantValue>(attributes, cp, "ConstantValue")
+			.and_then(|value| constant(cp, value.constantvalue_index.index));
+		let exceptions = decode::<Exceptions>(attributes, cp, "Exceptions")
+			.map(|exceptions| exceptions.table.iter().filter_map(|&index| cp.class_name(index).map(MStrExt::decoded)).collect())
+			.unwrap_or_default();
+		let code = decode::<Code>(attributes, cp, "Code")
+			.map(|code| listing(&code, cp))
+			.unwrap_or_default();
+		Some(Member {
+			kind,
+			name: cp.utf8(name_index)?.decoded(),
+			descriptor: cp.utf8(descriptor_index)?.decoded(),
+			flags,
+			signature: signature(attributes, cp),
+			constant_value,
+			exceptions,
+			annotations: annotations(attributes, cp),
+			code,
+		})
+	}
+}
+
+fn signature<'a>(attributes: &Attributes<'a>, cp: &'a ConstantPool<'a>) -> Option<String> {
+	let signature = decode::<Signature>(attributes, cp, "Signature")?;
+	cp.utf8(signature.class_index).map(MStrExt::decoded)
+}
+
+fn annotations<'a>(attributes: &Attributes<'a>, cp: &'a ConstantPool<'a>) -> BTreeSet<Annotation> {
+	let visible = decode::<RuntimeVisibleAnnotations>(attributes, cp, "RuntimeVisibleAnnotations")
+		.map(|annotations| annotations.table)
+		.unwrap_or_default();
+	let invisible = decode::<RuntimeInvisibleAnnotations>(attributes, cp, "RuntimeInvisibleAnnotations")
+		.map(|annotations| annotations.table)
+		.unwrap_or_default();
+	let visible = visible.iter().map(|annotation| (annotation, true));
+	let invisible = invisible.iter().map(|annotation| (annotation, false));
+	visible.chain(invisible)
+		.map(|(annotation, visible)| Annotation {
+			text: annotation_text(annotation, cp),
+			visible,
+		})
+		.collect()
+}
+
+fn utf8_or_index<'a>(cp: &'a ConstantPool<'a>, index: CPIndex<'a, UTF8Info<'a>>) -> String {
+	cp.utf8(index).map(MStrExt::decoded).unwrap_or_else(|| format!("#{}", index.index))
+}
+
+fn annotation_text<'a>(annotation: &attr::Annotation<'a>, cp: &'a ConstantPool<'a>) -> String {
+	let mut text = format!("@{}", utf8_or_index(cp, annotation.type_index));
+	if !annotation.element_value_pairs.is_empty() {
+		let pairs: Vec<_> = annotation.element_value_pairs.iter()
+			.map(|pair| format!("{}={}", utf8_or_index(cp, pair.element_name_index), element_value_text(&pair.element_value, cp)))
+			.collect();
+		text.push_str(&format!("({})", pairs.join(", ")));
+	}
+	text
+}
+
+fn element_value_text<'a>(value: &ElementValue<'a>, cp: &'a ConstantPool<'a>) -> String {
+	let integer = |index: CPIndex<'a, IntegerInfo>| cp.index(index).map(|info| info.value as i32);
+	let text = match *value {
+		ElementValue::Byte(index) | ElementValue::Short(index) | ElementValue::Integer(index) => integer(index).map(|value| value.to_string()),
+		ElementValue::Char(index) => integer(index).map(|value| match std::char::from_u32(value as u32) {
+			Some(c) => format!("{:?}", c),
+			None => value.to_string(),
+		}),
+		ElementValue::Boolean(index) => integer(index).map(|value| (value != 0).to_string()),
+		ElementValue::Double(index) => constant(cp, index.index),
+		ElementValue::Float(index) => constant(cp, index.index),
+		ElementValue::Long(index) => constant(cp, index.index),
+		ElementValue::String(index) => cp.utf8(index).map(|value| format!("{:?}", value.decoded())),
+		ElementValue::Enum { type_name_index, const_name_index } => {
+			Some(format!("{}.{}", utf8_or_index(cp, type_name_index), utf8_or_index(cp, const_name_index)))
+		}
+		ElementValue::Class(index) => Some(format!("{}.class", utf8_or_index(cp, index))),
+		ElementValue::Annotation(ref annotation) => Some(annotation_text(annotation, cp)),
+		ElementValue::Array(ref values) => {
+			let values: Vec<_> = values.iter()
+				.map(|value| element_value_text(value, cp))
+				.collect();
+			Some(format!("{{{}}}", values.join(", ")))
+		}
+	};
+	text.unwrap_or_else(|| "?".to_string())
+}
+
+/// The code as lines of text that don't mention offsets or constant pool indices,
+/// with labels in place of branch targets, and the exception handlers at the end.
+fn listing<'a>(code: &Code<'a>, cp: &'a ConstantPool<'a>) -> Vec<String> {
+	let instructions = match bytecode::decode(&code.code) {
+		Ok(instructions) => instructions,
+		Err(error) => return vec![format!("invalid code: {:?}", error)],
+	};
+	let mut targets = BTreeSet::new();
+	for instruction in &instructions {
+		targets.extend(instruction.targets());
+	}
+	for exception in &code.exception_table {
+		targets.extend([exception.start_pc, exception.end_pc, exception.handler_pc].iter().map(|&pc| pc as u32));
+	}
+	let targets: Vec<_> = targets.into_iter().collect();
+	let label = |pc: u32| match targets.binary_search(&pc) {
+		Ok(index) => format!("L{}", index),
+		Err(_) => format!("{}", pc),
+	};
+
+	let mut lines = vec![];
+	let mut next = targets.iter().peekable();
+	for instruction in &instructions {
+		while let Some(&&target) = next.peek() {
+			if target > instruction.pc {
+				break;
+			}
+			lines.push(format!("{}:", label(target)));
+			next.next();
+		}
+		lines.push(instruction_text(instruction, cp, &label));
+	}
+	for &target in next {
+		lines.push(format!("{}:", label(target)));
+	}
+	for exception in &code.exception_table {
+		let catch_type = match exception.catch_type.index {
+			0 => "any".to_string(),
+			index => cp.class_name(exception.catch_type).map(MStrExt::decoded).unwrap_or_else(|| format!("#{}", index)),
+		};
+		lines.push(format!("catch {} {} {} {}", catch_type, label(exception.start_pc as u32), label(exception.end_pc as u32), label(exception.handler_pc as u32)));
+	}
+	lines
+}
+
+fn instruction_text(instruction: &Instruction, cp: &ConstantPool, label: &dyn Fn(u32) -> String) -> String {
+	// Which of the two gets used only depends on where the constant ended up in the pool.
+	let opcode = if instruction.opcode == LDC_W { LDC } else { instruction.opcode };
+	let mnemonic = bytecode::mnemonic(opcode).unwrap_or("???");
+	let resolve = |index: u16| constant(cp, index).unwrap_or_else(|| format!("#{}", index));
+	match instruction.operand {
+		Operand::Constant(index) => format!("{} {}", mnemonic, resolve(index)),
+		Operand::InvokeInterface { index, count } => format!("{} {}, {}", mnemonic, resolve(index), count),
+		Operand::MultiANewArray { index, dimensions } => format!("{} {}, {}", mnemonic, resolve(index), dimensions),
+		Operand::Branch(target) => format!("{} {}", mnemonic, label(target)),
+		Operand::TableSwitch { default, low, ref targets } => {
+			let mut text = format!("{} {{", mnemonic);
+			for (i, &target) in targets.iter().enumerate() {
+				text.push_str(&format!(" {}: {};", low as i64 + i as i64, label(target)));
+			}
+			text.push_str(&format!(" default: {} }}", label(default)));
+			text
+		}
+		Operand::LookupSwitch { default, ref pairs } => {
+			let mut text = format!("{} {{", mnemonic);
+			for &(key, target) in pairs {
+				text.push_str(&format!(" {}: {};", key, label(target)));
+			}
+			text.push_str(&format!(" default: {} }}", label(default)));
+			text
+		}
+		_ => instruction.to_string(),
+	}
+}
+
+/// Above this many lines in the changed part of the code, the lines are all treated as removed and added,
+/// rather than working out what they have in common.
+const MAX_LINES: usize = 4096;
+
+/// Lines in both, and the ones only in one or the other, with as many lines in both as possible.
+fn diff_lines(old: &[String], new: &[String]) -> Vec<Line> {
+	let prefix = old.iter().zip(new)
+		.take_while(|(old, new)| old == new)
+		.count();
+	let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev())
+		.take_while(|(old, new)| old == new)
+		.count();
+	let old_middle = &old[prefix..old.len() - suffix];
+	let new_middle = &new[prefix..new.len() - suffix];
+
+	let mut lines: Vec<_> = old[..prefix].iter().cloned().map(Line::Same).collect();
+	if old_middle.len() > MAX_LINES || new_middle.len() > MAX_LINES {
+		lines.extend(old_middle.iter().cloned().map(Line::Removed));
+		lines.extend(new_middle.iter().cloned().map(Line::Added));
+	} else {
+		// The length of the longest common subsequence of what's left of both, from each pair of positions.
+		let width = new_middle.len() + 1;
+		let mut lengths = vec![0u16; (old_middle.len() + 1) * width];
+		for i in (0..old_middle.len()).rev() {
+			for j in (0..new_middle.len()).rev() {
+				lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+					lengths[(i + 1) * width + j + 1] + 1
+				} else {
+					lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+				};
+			}
+		}
+		let (mut i, mut j) = (0, 0);
+		while i < old_middle.len() && j < new_middle.len() {
+			if old_middle[i] == new_middle[j] {
+				lines.push(Line::Same(old_middle[i].clone()));
+				i += 1;
+				j += 1;
+			} else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+				lines.push(Line::Removed(old_middle[i].clone()));
+				i += 1;
+			} else {
+				lines.push(Line::Added(new_middle[j].clone()));
+				j += 1;
+			}
+		}
+		lines.extend(old_middle[i..].iter().cloned().map(Line::Removed));
+		lines.extend(new_middle[j..].iter().cloned().map(Line::Added));
+	}
+	lines.extend(old[old.len() - suffix..].iter().cloned().map(Line::Same));
+	lines
+}
+
+fn annotation_changes(old: &BTreeSet<Annotation>, new: &BTreeSet<Annotation>, changes: &mut Vec<Change>) {
+	changes.extend(old.difference(new).cloned().map(Change::AnnotationRemoved));
+	changes.extend(new.difference(old).cloned().map(Change::AnnotationAdded));
+}
+
+fn member_changes(old: &Member, new: &Member) -> MemberStatus {
+	let mut changes = vec![];
+	if old.flags != new.flags {
+		changes.push(Change::Flags { old: old.flags, new: new.flags });
+	}
+	if old.signature != new.signature {
+		changes.push(Change::Signature { old: old.signature.clone(), new: new.signature.clone() });
+	}
+	if old.constant_value != new.constant_value {
+		changes.push(Change::ConstantValue { old: old.constant_value.clone(), new: new.constant_value.clone() });
+	}
+	if old.exceptions != new.exceptions {
+		changes.push(Change::Exceptions { old: old.exceptions.clone(), new: new.exceptions.clone() });
+	}
+	annotation_changes(&old.annotations, &new.annotations, &mut changes);
+	let code = if old.code == new.code { vec![] } else { diff_lines(&old.code, &new.code) };
+	MemberStatus::Changed { changes, code }
+}
+
+/// What changed going from `old` to `new`, `None` if the name of either class, or any of their members, can't be resolved.
+pub fn diff(old: &ClassFile, new: &ClassFile) -> Option<ClassDiff> {
+	let old = Class::new(old)?;
+	let new = Class::new(new)?;
+
+	let mut changes = vec![];
+	if old.name != new.name {
+		changes.push(Change::Name { old: old.name.clone(), new: new.name.clone() });
+	}
+	if old.version != new.version {
+		changes.push(Change::Version { old: old.version, new: new.version });
+	}
+	if old.flags != new.flags {
+		changes.push(Change::Flags { old: old.flags, new: new.flags });
+	}
+	if old.super_class != new.super_class {
+		changes.push(Change::SuperClass { old: old.super_class.clone(), new: new.super_class.clone() });
+	}
+	let old_interfaces: BTreeSet<_> = old.interfaces.iter().collect();
+	let new_interfaces: BTreeSet<_> = new.interfaces.iter().collect();
+	changes.extend(old_interfaces.difference(&new_interfaces).map(|&name| Change::InterfaceRemoved(name.clone())));
+	changes.extend(new_interfaces.difference(&old_interfaces).map(|&name| Change::InterfaceAdded(name.clone())));
+	if old.signature != new.signature {
+		changes.push(Change::Signature { old: old.signature.clone(), new: new.signature.clone() });
+	}
+	annotation_changes(&old.annotations, &new.annotations, &mut changes);
+
+	let find = |members: &'_ [Member], member: &Member| members.iter()
+		.position(|other| other.kind == member.kind && other.name == member.name && other.descriptor == member.descriptor);
+	let mut members = vec![];
+	for member in &old.members {
+		let status = match find(&new.members, member) {
+			Some(index) => match member_changes(member, &new.members[index]) {
+				MemberStatus::Changed { ref changes, ref code } if changes.is_empty() && code.is_empty() => continue,
+				status => status,
+			},
+			None => MemberStatus::Removed(member.code.clone()),
+		};
+		members.push(MemberDiff {
+			kind: member.kind,
+			name: member.name.clone(),
+			descriptor: member.descriptor.clone(),
+			status,
+		});
+	}
+	for member in &new.members {
+		if find(&old.members, member).is_none() {
+			members.push(MemberDiff {
+				kind: member.kind,
+				name: member.name.clone(),
+				descriptor: member.descriptor.clone(),
+				status: MemberStatus::Added(member.code.clone()),
+			});
+		}
+	}
+
+	Some(ClassDiff {
+		name: new.name,
+		changes,
+		members,
+	})
+}
+
+fn optional(value: &Option<String>) -> &str {
+	value.as_deref().unwrap_or("none")
+}
+
+impl fmt::Display for Change {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Change::Name { old, new } => write!(f, "-name {}\n+name {}", old, new),
+			Change::Version { old, new } => write!(f, "-version {}.{}\n+version {}.{}", old.0, old.1, new.0, new.1),
+			Change::Flags { old, new } => write!(f, "-flags 0x{:04x}\n+flags 0x{:04x}", old, new),
+			Change::SuperClass { old, new } => write!(f, "-super {}\n+super {}", optional(old), optional(new)),
+			Change::InterfaceAdded(name) => write!(f, "+implements {}", name),
+			Change::InterfaceRemoved(name) => write!(f, "-implements {}", name),
+			Change::Signature { old, new } => write!(f, "-signature {}\n+signature {}", optional(old), optional(new)),
+			Change::ConstantValue { old, new } => write!(f, "-value {}\n+value {}", optional(old), optional(new)),
+			Change::Exceptions { old, new } => write!(f, "-throws {}\n+throws {}", old.join(", "), new.join(", ")),
+			Change::AnnotationAdded(annotation) => write!(f, "+{}", annotation),
+			Change::AnnotationRemoved(annotation) => write!(f, "-{}", annotation),
+		}
+	}
+}
+
+impl fmt::Display for Annotation {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.text)?;
+		if !self.visible {
+			f.write_str(" // invisible")?;
+		}
+		Ok(())
+	}
+}
+
+/// How many unchanged lines of code to show around the changed ones.
+const CONTEXT: usize = 3;
+
+fn write_code(f: &mut fmt::Formatter, lines: &[Line]) -> fmt::Result {
+	let changed: Vec<_> = lines.iter()
+		.map(|line| !matches!(line, Line::Same(_)))
+		.collect();
+	let mut skipped = false;
+	for (i, line) in lines.iter().enumerate() {
+		let near = changed[i.saturating_sub(CONTEXT)..(i + CONTEXT + 1).min(lines.len())].contains(&true);
+		if !near {
+			if !skipped {
+				writeln!(f, " ...")?;
+				skipped = true;
+			}
+			continue;
+		}
+		skipped = false;
+		match line {
+			Line::Same(line) => writeln!(f, "   {}", line)?,
+			Line::Added(line) => writeln!(f, "+  {}", line)?,
+			Line::Removed(line) => writeln!(f, "-  {}", line)?,
+		}
+	}
+	Ok(())
+}
+
+impl fmt::Display for MemberDiff {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (kind, separator) = match self.kind {
+			MemberKind::Field => ("field", ":"),
+			MemberKind::Method => ("method", ""),
+		};
+		match &self.status {
+			MemberStatus::Added(code) => {
+				writeln!(f, "@@ +{} {}{}{} @@", kind, self.name, separator, self.descriptor)?;
+				for line in code {
+					writeln!(f, "+  {}", line)?;
+				}
+			}
+			MemberStatus::Removed(code) => {
+				writeln!(f, "@@ -{} {}{}{} @@", kind, self.name, separator, self.descriptor)?;
+				for line in code {
+					writeln!(f, "-  {}", line)?;
+				}
+			}
+			MemberStatus::Changed { changes, code } => {
+				writeln!(f, "@@ {} {}{}{} @@", kind, self.name, separator, self.descriptor)?;
+				for change in changes {
+					writeln!(f, "{}", change)?;
+				}
+				write_code(f, code)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Display for ClassDiff {
+	/// Something like a unified diff, with a section for the class and each member that changed, and nothing if nothing did.
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.is_empty() {
+			return Ok(());
+		}
+		writeln!(f, "--- {}", self.changes.iter()
+			.find_map(|change| match change {
+				Change::Name { old, .. } => Some(old),
+				_ => None,
+			})
+			.unwrap_or(&self.name))?;
+		writeln!(f, "+++ {}", self.name)?;
+		if !self.changes.is_empty() {
+			writeln!(f, "@@ class @@")?;
+			for change in &self.changes {
+				writeln!(f, "{}", change)?;
+			}
+		}
+		for member in &self.members {
+			member.fmt(f)?;
+		}
+		Ok(())
+	}
+}
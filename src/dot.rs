@@ -0,0 +1,169 @@
+//! Rendering control flow graphs and call graphs in Graphviz's DOT language,
+//! for when reading the raw bytes of `Code::code` isn't going to cut it.
+//!
+//! Feed the output to `dot -Tsvg`, or any of the other Graphviz tools.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::io::Cursor;
+
+use crate::*;
+use crate::attr::Code;
+use crate::bytecode::{self, Operand};
+use crate::cfg::{self, BuildError, ControlFlowGraph, EdgeKind};
+use crate::resolve::{constant, MethodRef, Resolve};
+use crate::utf8::MStrExt;
+
+fn escape(value: &str) -> String {
+	let mut result = String::with_capacity(value.len());
+	for c in value.chars() {
+		match c {
+			'"' => result.push_str("\\\""),
+			'\\' => result.push_str("\\\\"),
+			'\n' => result.push_str("\\n"),
+			c => result.push(c),
+		}
+	}
+	result
+}
+
+/// Renders the graph, each block listing its instructions, with the constants they refer to spelled out.
+///
+/// Edges are labelled by their kind, and exception edges are dashed.
+pub fn graph_to_dot(graph: &ControlFlowGraph, cp: &ConstantPool, title: &str) -> String {
+	let mut output = String::new();
+	writeln!(output, "digraph \"{}\" {{", escape(title)).unwrap();
+	writeln!(output, "\tnode [shape=box, fontname=\"monospace\"];").unwrap();
+	for (i, block) in graph.blocks.iter().enumerate() {
+		let mut label = format!("block {} [{}, {})\\l", i, block.start, block.end);
+		for instruction in &block.instructions {
+			let mut line = format!("{}: {}", instruction.pc, instruction);
+			let index = match instruction.operand {
+				Operand::Constant(index) | Operand::InvokeInterface { index, .. } | Operand::MultiANewArray { index, .. } => Some(index),
+				_ => None,
+			};
+			if let Some(comment) = index.and_then(|index| constant(cp, index)) {
+				write!(line, " // {}", comment).unwrap();
+			}
+			label.push_str(&escape(&line));
+			label.push_str("\\l");
+		}
+		writeln!(output, "\tb{} [label=\"{}\"];", i, label).unwrap();
+	}
+	for edge in &graph.edges {
+		let style = match edge.kind {
+			EdgeKind::Exception(_) => ", style=dashed",
+			_ => "",
+		};
+		writeln!(output, "\tb{} -> b{} [label=\"{}\"{}];", edge.from, edge.to, escape(&edge.kind.to_string()), style).unwrap();
+	}
+	output.push_str("}\n");
+	output
+}
+
+/// Builds the control flow graph of the method at the given index and renders it, see `graph_to_dot`.
+///
+/// Methods without code, such as abstract and native ones, are rendered as an empty graph,
+/// but code that can't be read is an error.
+pub fn method_to_dot(class_file: &ClassFile, index: usize) -> Result<String, BuildError> {
+	let cp = &class_file.constant_pool;
+	let method = class_file.methods.get(index)
+		.ok_or(BuildError::MissingMethod(index))?;
+	let class_name = cp.class_name(class_file.this_class).map(MStrExt::decoded).unwrap_or_default();
+	let name = cp.utf8(method.name_index).map(MStrExt::decoded).unwrap_or_default();
+	let descriptor = cp.utf8(method.descriptor_index).map(MStrExt::decoded).unwrap_or_default();
+	let title = format!("{}.{}{}", class_name, name, descriptor);
+
+	let graph = match method.attributes.named_all(cp, "Code").next() {
+		Some(info) => {
+			let code = <Code as FromBytes<BigEndian>>::from_bytes(&mut Cursor::new(info.info()))
+				.map_err(|_| BuildError::MalformedCode)?;
+			cfg::build(&code, cp)?
+		}
+		None => ControlFlowGraph {
+			blocks: vec![],
+			edges: vec![],
+		},
+	};
+	Ok(graph_to_dot(&graph, cp, &title))
+}
+
+/// The method a `Methodref` or `InterfaceMethodref` refers to.
+fn invoked<'a>(cp: &'a ConstantPool<'a>, index: u16) -> Option<MethodRef<'a>> {
+	match cp.entries.get((index as usize).checked_sub(1)?)? {
+		CPEntry::MethodRef(info) => info.resolve(cp),
+		CPEntry::InterfaceMethodRef(info) => info.resolve(cp),
+		_ => None,
+	}
+}
+
+/// Renders which methods call which, for every method in the class.
+///
+/// Methods of the class are solid nodes, and everything they call that's declared elsewhere, or not at all, is dashed.
+/// Each edge is labelled with the instruction making the call, and methods whose code can't be decoded are left without edges.
+pub fn call_graph_to_dot(class_file: &ClassFile) -> String {
+	let cp = &class_file.constant_pool;
+	let this_class = cp.class_name(class_file.this_class);
+	let class_name = this_class.map(MStrExt::decoded).unwrap_or_default();
+	let signature = |name_index, descriptor_index| {
+		format!("{}{}", cp.utf8(name_index).map(MStrExt::decoded).unwrap_or_default(), cp.utf8(descriptor_index).map(MStrExt::decoded).unwrap_or_default())
+	};
+	let methods: Vec<String> = class_file.methods.iter()
+		.map(|method| signature(method.name_index, method.descriptor_index))
+		.collect();
+
+	let mut output = String::new();
+	writeln!(output, "digraph \"{}\" {{", escape(&class_name)).unwrap();
+	writeln!(output, "\tnode [shape=box];").unwrap();
+	for (i, method) in methods.iter().enumerate() {
+		writeln!(output, "\tm{} [label=\"{}\"];", i, escape(method)).unwrap();
+	}
+
+	let mut external: Vec<String> = vec![];
+	let mut edges = BTreeSet::new();
+	for (i, method) in class_file.methods.iter().enumerate() {
+		let instructions = match method.attributes.get::<Code>(cp).map(|code| bytecode::decode(&code.code)) {
+			Some(Ok(instructions)) => instructions,
+			_ => continue,
+		};
+		for instruction in instructions {
+			let index = match (instruction.opcode, &instruction.operand) {
+				(INVOKEVIRTUAL..=INVOKEDYNAMIC, &Operand::Constant(index)) => index,
+				(_, &Operand::InvokeInterface { index, .. }) => index,
+				_ => continue,
+			};
+			let callee = match constant(cp, index) {
+				Some(callee) => callee,
+				None => continue,
+			};
+			let local = invoked(cp, index)
+				.filter(|callee| Some(callee.owner) == this_class)
+				.and_then(|callee| class_file.methods.iter().position(|method| {
+					cp.utf8(method.name_index) == Some(callee.name) && cp.utf8(method.descriptor_index) == Some(callee.descriptor)
+				}));
+			let target = match local {
+				Some(j) => format!("m{}", j),
+				None => {
+					let j = match external.iter().position(|name| *name == callee) {
+						Some(j) => j,
+						None => {
+							external.push(callee);
+							external.len() - 1
+						}
+					};
+					format!("e{}", j)
+				}
+			};
+			let mnemonic = bytecode::mnemonic(instruction.opcode).unwrap_or("???");
+			edges.insert((format!("m{}", i), target, mnemonic));
+		}
+	}
+	for (i, name) in external.iter().enumerate() {
+		writeln!(output, "\te{} [label=\"{}\", style=dashed];", i, escape(name)).unwrap();
+	}
+	for (from, to, mnemonic) in edges {
+		writeln!(output, "\t{} -> {} [label=\"{}\"];", from, to, mnemonic).unwrap();
+	}
+	output.push_str("}\n");
+	output
+}
@@ -0,0 +1,1247 @@
+//! Evaluating simple static methods without a JVM, for folding constants or undoing string obfuscation.
+//!
+//! Only the deterministic part of the JVM is covered: arithmetic, locals, arrays, strings, branches and switches,
+//! static calls to methods of the classes handed to the `Evaluator`, and calls to natives, which stand in for
+//! JDK methods such as `String.length` or `StringBuilder.append`. String concatenation through
+//! `StringConcatFactory` works too, as that's what `+` on strings compiles to since Java 9.
+//! There are no fields, monitors, subroutines or exception handlers, so anything the JVM would throw
+//! ends the evaluation with a `Problem::Exception`, and `checkcast` doesn't check anything.
+//!
+//! Every call runs on a budget of instructions, so a method that never returns comes back with
+//! `Problem::BudgetExhausted`, rather than hanging. Each element of a new array costs an instruction too,
+//! so an array that's too large to build runs out of budget rather than memory.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+use std::rc::Rc;
+
+use crate::*;
+use crate::attr::{BootstrapMethods, Code};
+use crate::bytecode::{self, DecodeError, Instruction, Operand};
+use crate::descriptor::{FieldType, MethodDescriptor};
+use crate::resolve::Reference;
+use crate::utf8::MStrExt;
+
+/// How deeply calls can be nested before giving up with `Problem::TooDeep`.
+pub const MAX_DEPTH: usize = 256;
+
+/// The number of instructions a call to `Evaluator::invoke` can run, unless set otherwise.
+pub const DEFAULT_BUDGET: u64 = 1_000_000;
+
+/// Booleans, bytes, chars and shorts are all ints, just like on the JVM.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Value {
+	Int(i32),
+	Long(i64),
+	Float(f32),
+	Double(f64),
+	Null,
+	/// An index into the `Heap`.
+	Reference(usize),
+}
+
+impl Value {
+	/// Whether it takes up two slots, as longs and doubles do.
+	pub fn is_wide(self) -> bool {
+		matches!(self, Value::Long(_) | Value::Double(_))
+	}
+
+	/// The value of a freshly created array element or field of the given type.
+	fn default(field_type: &FieldType) -> Value {
+		match field_type {
+			FieldType::Long => Value::Long(0),
+			FieldType::Float => Value::Float(0.0),
+			FieldType::Double => Value::Double(0.0),
+			FieldType::Object(_) | FieldType::Array(_) => Value::Null,
+			_ => Value::Int(0),
+		}
+	}
+
+	/// Whether it's the kind of value a parameter of the given type takes.
+	fn fits(self, field_type: &FieldType) -> bool {
+		matches!(
+			(self, field_type),
+			(Value::Int(_), FieldType::Boolean | FieldType::Byte | FieldType::Char | FieldType::Short | FieldType::Int)
+			| (Value::Long(_), FieldType::Long)
+			| (Value::Float(_), FieldType::Float)
+			| (Value::Double(_), FieldType::Double)
+			| (Value::Null | Value::Reference(_), FieldType::Object(_) | FieldType::Array(_))
+		)
+	}
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Object {
+	String(String),
+	StringBuilder(String),
+	Array {
+		/// The descriptor of the array itself, such as `[I`.
+		descriptor: String,
+		elements: Vec<Value>,
+	},
+	/// The internal name of the class of an object fresh from `new`,
+	/// waiting for a native `<init>` to turn it into something else.
+	Instance(String),
+}
+
+/// Where every object lives, so that references keep their identity, and `==` works as it should.
+#[derive(Debug, Default)]
+pub struct Heap {
+	objects: Vec<Object>,
+	interned: HashMap<String, usize>,
+}
+
+impl Heap {
+	pub fn new() -> Self {
+		Heap::default()
+	}
+
+	pub fn allocate(&mut self, object: Object) -> Value {
+		self.objects.push(object);
+		Value::Reference(self.objects.len() - 1)
+	}
+
+	pub fn get(&self, value: Value) -> Option<&Object> {
+		match value {
+			Value::Reference(index) => self.objects.get(index),
+			_ => None,
+		}
+	}
+
+	pub fn get_mut(&mut self, value: Value) -> Option<&mut Object> {
+		match value {
+			Value::Reference(index) => self.objects.get_mut(index),
+			_ => None,
+		}
+	}
+
+	/// The contents of a `String`, but not of a `StringBuilder`.
+	pub fn string(&self, value: Value) -> Option<&str> {
+		match self.get(value)? {
+			Object::String(string) => Some(string),
+			_ => None,
+		}
+	}
+
+	pub fn new_string(&mut self, value: &str) -> Value {
+		self.allocate(Object::String(value.to_string()))
+	}
+
+	/// The one `String` with the given contents that string constants refer to.
+	pub fn intern(&mut self, value: &str) -> Value {
+		if let Some(&index) = self.interned.get(value) {
+			return Value::Reference(index);
+		}
+		let reference = self.new_string(value);
+		if let Value::Reference(index) = reference {
+			self.interned.insert(value.to_string(), index);
+		}
+		reference
+	}
+
+	pub fn new_int_array(&mut self, values: &[i32]) -> Value {
+		self.allocate(Object::Array {
+			descriptor: "[I".to_string(),
+			elements: values.iter().map(|&value| Value::Int(value)).collect(),
+		})
+	}
+
+	/// The elements of an array, as ints, if that's what they are.
+	pub fn int_array(&self, value: Value) -> Option<Vec<i32>> {
+		match self.get(value)? {
+			Object::Array { elements, .. } => elements.iter()
+				.map(|element| match *element {
+					Value::Int(value) => Some(value),
+					_ => None,
+				})
+				.collect(),
+			_ => None,
+		}
+	}
+
+	/// The value the way `String.valueOf` would turn it into a string, where the static type decides
+	/// whether an int is an int, a char or a boolean.
+	///
+	/// Floats and doubles are formatted the way Rust does it, with Java's exponent notation for very large
+	/// and very small numbers, which only differs from Java in the odd last digit.
+	pub fn to_java_string(&self, value: Value, field_type: &FieldType) -> Result<String, Problem> {
+		let string = match (value, field_type) {
+			(Value::Int(value), FieldType::Boolean) => (value != 0).to_string(),
+			(Value::Int(value), FieldType::Char) => String::from_utf16_lossy(&[value as u16]),
+			(Value::Int(value), _) => value.to_string(),
+			(Value::Long(value), _) => value.to_string(),
+			(Value::Float(value), _) => java_float(value, value as f64),
+			(Value::Double(value), _) => java_float(value, value),
+			(Value::Null, _) => "null".to_string(),
+			(reference, _) => match self.get(reference) {
+				Some(Object::String(string)) | Some(Object::StringBuilder(string)) => string.clone(),
+				_ => return Err(Problem::TypeMismatch),
+			},
+		};
+		Ok(string)
+	}
+
+	fn elements(&mut self, array: Value) -> Result<&mut Vec<Value>, Problem> {
+		match array {
+			Value::Null => Err(null_pointer()),
+			array => match self.get_mut(array) {
+				Some(Object::Array { elements, .. }) => Ok(elements),
+				_ => Err(Problem::TypeMismatch),
+			},
+		}
+	}
+
+	/// Creates an array with the given descriptor, sized by the first count, with arrays sized by the rest inside it.
+	fn new_array(&mut self, descriptor: &str, counts: &[i32]) -> Result<Value, Problem> {
+		let component = descriptor.strip_prefix('[')
+			.ok_or(Problem::TypeMismatch)?;
+		if counts.iter().any(|&count| count < 0) {
+			return Err(Problem::Exception("java/lang/NegativeArraySizeException".to_string()));
+		}
+		let (&count, rest) = counts.split_first()
+			.ok_or(Problem::InvalidOperand)?;
+		let mut elements = vec![];
+		for _ in 0..count {
+			elements.push(match rest {
+				[] => Value::default(&FieldType::parse(component).ok_or(Problem::TypeMismatch)?),
+				_ => self.new_array(component, rest)?,
+			});
+		}
+		Ok(self.allocate(Object::Array {
+			descriptor: descriptor.to_string(),
+			elements,
+		}))
+	}
+}
+
+fn java_float<T: fmt::Display + fmt::LowerExp>(value: T, magnitude: f64) -> String {
+	if magnitude.is_nan() {
+		return "NaN".to_string();
+	}
+	if magnitude.is_infinite() {
+		return if magnitude > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+	}
+	let magnitude = magnitude.abs();
+	if magnitude == 0.0 || (1e-3..1e7).contains(&magnitude) {
+		let string = value.to_string();
+		return if string.contains('.') { string } else { string + ".0" };
+	}
+	let string = format!("{:e}", value);
+	let (mantissa, exponent) = string.split_at(string.find('e').unwrap_or(string.len()));
+	let exponent = exponent.trim_start_matches('e');
+	if mantissa.contains('.') {
+		format!("{}E{}", mantissa, exponent)
+	} else {
+		format!("{}.0E{}", mantissa, exponent)
+	}
+}
+
+fn null_pointer() -> Problem {
+	Problem::Exception("java/lang/NullPointerException".to_string())
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Problem {
+	Decode(DecodeError),
+	/// The opcode is outside the subset that can be evaluated.
+	Unsupported(u8),
+	/// A `multianewarray` without any dimensions.
+	InvalidOperand,
+	/// Neither a native nor a static method of one of the classes, given as `owner.name:descriptor`.
+	UnknownMethod(String),
+	/// A constant that doesn't exist, or that the evaluator can't load, such as a `MethodType`.
+	InvalidConstant(u16),
+	InvalidLocal(u16),
+	InvalidTarget(u32),
+	StackUnderflow,
+	/// A value of the wrong kind, such as a long where an int should be, or an object a native doesn't expect.
+	TypeMismatch,
+	/// The arguments don't match the method's descriptor.
+	WrongArguments,
+	/// The JVM would throw an exception of the given class.
+	Exception(String),
+	/// Calls are nested deeper than `MAX_DEPTH`.
+	TooDeep,
+	/// More instructions ran than the budget allows.
+	BudgetExhausted,
+	FallsOffEnd,
+}
+
+impl fmt::Display for Problem {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Problem::Decode(error) => write!(f, "{}", error),
+			Problem::Unsupported(opcode) => write!(f, "{} is not supported", bytecode::mnemonic(*opcode).unwrap_or("???")),
+			Problem::InvalidOperand => f.write_str("invalid operand"),
+			Problem::UnknownMethod(method) => write!(f, "no native or static method {}", method),
+			Problem::InvalidConstant(index) => write!(f, "invalid constant #{}", index),
+			Problem::InvalidLocal(index) => write!(f, "invalid local variable {}", index),
+			Problem::InvalidTarget(pc) => write!(f, "{} is not the start of an instruction", pc),
+			Problem::StackUnderflow => f.write_str("stack underflow"),
+			Problem::TypeMismatch => f.write_str("value of the wrong type"),
+			Problem::WrongArguments => f.write_str("arguments don't match the descriptor"),
+			Problem::Exception(class_name) => write!(f, "threw {}", class_name),
+			Problem::TooDeep => write!(f, "calls nested deeper than {}", MAX_DEPTH),
+			Problem::BudgetExhausted => f.write_str("ran out of instructions"),
+			Problem::FallsOffEnd => f.write_str("execution falls off the end of the code"),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct EvalError {
+	/// The method that failed, as `owner.name:descriptor`.
+	pub method: String,
+	/// The offset of the failing instruction, if it got that far.
+	pub pc: Option<u32>,
+	pub problem: Problem,
+}
+
+impl fmt::Display for EvalError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.pc {
+			Some(pc) => write!(f, "{}, pc {}: {}", self.method, pc, self.problem),
+			None => write!(f, "{}: {}", self.method, self.problem),
+		}
+	}
+}
+
+/// Stands in for a method, getting the receiver, if there is one, followed by the arguments.
+///
+/// It's only called with as many values as the descriptor says, but their types are up to the native to check.
+pub type Native = Box<dyn Fn(&mut Heap, &[Value]) -> Result<Option<Value>, Problem>>;
+
+struct Method<'c> {
+	class: &'c ClassFile<'c>,
+	key: String,
+	descriptor: MethodDescriptor,
+	max_locals: usize,
+	instructions: Vec<Instruction>,
+	positions: HashMap<u32, usize>,
+}
+
+struct Frame {
+	locals: Vec<Option<Value>>,
+	stack: Vec<Value>,
+}
+
+impl Frame {
+	fn pop(&mut self) -> Result<Value, Problem> {
+		self.stack.pop().ok_or(Problem::StackUnderflow)
+	}
+
+	fn pop_single(&mut self) -> Result<Value, Problem> {
+		match self.pop()? {
+			value if value.is_wide() => Err(Problem::TypeMismatch),
+			value => Ok(value),
+		}
+	}
+
+	fn pop_int(&mut self) -> Result<i32, Problem> {
+		match self.pop()? {
+			Value::Int(value) => Ok(value),
+			_ => Err(Problem::TypeMismatch),
+		}
+	}
+
+	fn pop_long(&mut self) -> Result<i64, Problem> {
+		match self.pop()? {
+			Value::Long(value) => Ok(value),
+			_ => Err(Problem::TypeMismatch),
+		}
+	}
+
+	fn pop_float(&mut self) -> Result<f32, Problem> {
+		match self.pop()? {
+			Value::Float(value) => Ok(value),
+			_ => Err(Problem::TypeMismatch),
+		}
+	}
+
+	fn pop_double(&mut self) -> Result<f64, Problem> {
+		match self.pop()? {
+			Value::Double(value) => Ok(value),
+			_ => Err(Problem::TypeMismatch),
+		}
+	}
+
+	fn pop_reference(&mut self) -> Result<Value, Problem> {
+		match self.pop()? {
+			value @ Value::Null | value @ Value::Reference(_) => Ok(value),
+			_ => Err(Problem::TypeMismatch),
+		}
+	}
+
+	fn push(&mut self, value: Value) {
+		self.stack.push(value);
+	}
+
+	fn push_all(&mut self, values: &[Value]) {
+		self.stack.extend_from_slice(values);
+	}
+
+	fn load(&self, index: u16) -> Result<Value, Problem> {
+		self.locals.get(index as usize)
+			.copied()
+			.flatten()
+			.ok_or(Problem::InvalidLocal(index))
+	}
+
+	fn store(&mut self, index: u16, value: Value) -> Result<(), Problem> {
+		let i = index as usize;
+		let size = if value.is_wide() { 2 } else { 1 };
+		if i + size > self.locals.len() {
+			return Err(Problem::InvalidLocal(index));
+		}
+		// Overwriting the second half of a long or double leaves the first half useless.
+		if i > 0 && self.locals[i - 1].is_some_and(Value::is_wide) {
+			self.locals[i - 1] = None;
+		}
+		self.locals[i] = Some(value);
+		if size == 2 {
+			self.locals[i + 1] = None;
+		}
+		Ok(())
+	}
+}
+
+enum Flow {
+	Next,
+	Jump(u32),
+	Return(Option<Value>),
+}
+
+/// Why an instruction stopped the evaluation, which is either its own problem, or one from a method it called.
+enum Interrupt {
+	Problem(Problem),
+	Nested(EvalError),
+}
+
+impl From<Problem> for Interrupt {
+	fn from(problem: Problem) -> Self {
+		Interrupt::Problem(problem)
+	}
+}
+
+fn local(instruction: &Instruction) -> Result<u16, Problem> {
+	match instruction.operand {
+		Operand::Local(index) | Operand::Iinc { index, .. } => Ok(index),
+		_ => Err(Problem::Unsupported(instruction.opcode)),
+	}
+}
+
+fn constant_index(instruction: &Instruction) -> u16 {
+	match instruction.operand {
+		Operand::Constant(index) | Operand::InvokeInterface { index, .. } | Operand::MultiANewArray { index, .. } => index,
+		_ => 0,
+	}
+}
+
+fn branch(instruction: &Instruction) -> Result<u32, Problem> {
+	match instruction.operand {
+		Operand::Branch(target) => Ok(target),
+		_ => Err(Problem::Unsupported(instruction.opcode)),
+	}
+}
+
+fn class_name(cp: &ConstantPool, index: u16) -> Result<String, Problem> {
+	cp.class_name(CPIndex::new(index))
+		.map(MStrExt::decoded)
+		.ok_or(Problem::InvalidConstant(index))
+}
+
+fn ldc(cp: &ConstantPool, heap: &mut Heap, index: u16) -> Result<Value, Problem> {
+	let entry = (index as usize).checked_sub(1)
+		.and_then(|i| cp.entries.get(i))
+		.ok_or(Problem::InvalidConstant(index))?;
+	let value = match entry {
+		CPEntry::Integer(info) => Value::Int(info.value as i32),
+		CPEntry::Float(info) => Value::Float(f32::from_bits(info.value)),
+		CPEntry::Long(info) => Value::Long(((info.high_bytes as u64) << 32 | info.low_bytes as u64) as i64),
+		CPEntry::Double(info) => Value::Double(f64::from_bits((info.high_bytes as u64) << 32 | info.low_bytes as u64)),
+		CPEntry::String(info) => {
+			let value = cp.utf8(info.string_index)
+				.map(MStrExt::decoded)
+				.ok_or(Problem::InvalidConstant(index))?;
+			heap.intern(&value)
+		}
+		_ => return Err(Problem::InvalidConstant(index)),
+	};
+	Ok(value)
+}
+
+fn arithmetic(opcode: u8, frame: &mut Frame) -> Result<(), Problem> {
+	let arithmetic_exception = || Problem::Exception("java/lang/ArithmeticException".to_string());
+	let value = match opcode {
+		IADD | ISUB | IMUL | IDIV | IREM | ISHL | ISHR | IUSHR | IAND | IOR | IXOR => {
+			let b = frame.pop_int()?;
+			let a = frame.pop_int()?;
+			Value::Int(match opcode {
+				IADD => a.wrapping_add(b),
+				ISUB => a.wrapping_sub(b),
+				IMUL => a.wrapping_mul(b),
+				IDIV | IREM if b == 0 => return Err(arithmetic_exception()),
+				IDIV => a.wrapping_div(b),
+				IREM => a.wrapping_rem(b),
+				ISHL => a.wrapping_shl(b as u32),
+				ISHR => a.wrapping_shr(b as u32),
+				IUSHR => (a as u32).wrapping_shr(b as u32) as i32,
+				IAND => a & b,
+				IOR => a | b,
+				_ => a ^ b,
+			})
+		}
+		LSHL | LSHR | LUSHR => {
+			let b = frame.pop_int()?;
+			let a = frame.pop_long()?;
+			Value::Long(match opcode {
+				LSHL => a.wrapping_shl(b as u32),
+				LSHR => a.wrapping_shr(b as u32),
+				_ => (a as u64).wrapping_shr(b as u32) as i64,
+			})
+		}
+		LADD | LSUB | LMUL | LDIV | LREM | LAND | LOR | LXOR => {
+			let b = frame.pop_long()?;
+			let a = frame.pop_long()?;
+			Value::Long(match opcode {
+				LADD => a.wrapping_add(b),
+				LSUB => a.wrapping_sub(b),
+				LMUL => a.wrapping_mul(b),
+				LDIV | LREM if b == 0 => return Err(arithmetic_exception()),
+				LDIV => a.wrapping_div(b),
+				LREM => a.wrapping_rem(b),
+				LAND => a & b,
+				LOR => a | b,
+				_ => a ^ b,
+			})
+		}
+		FADD | FSUB | FMUL | FDIV | FREM => {
+			let b = frame.pop_float()?;
+			let a = frame.pop_float()?;
+			Value::Float(match opcode {
+				FADD => a + b,
+				FSUB => a - b,
+				FMUL => a * b,
+				FDIV => a / b,
+				_ => a % b,
+			})
+		}
+		_ => {
+			let b = frame.pop_double()?;
+			let a = frame.pop_double()?;
+			Value::Double(match opcode {
+				DADD => a + b,
+				DSUB => a - b,
+				DMUL => a * b,
+				DDIV => a / b,
+				_ => a % b,
+			})
+		}
+	};
+	frame.push(value);
+	Ok(())
+}
+
+fn convert(opcode: u8, frame: &mut Frame) -> Result<(), Problem> {
+	let value = match opcode {
+		INEG => Value::Int(frame.pop_int()?.wrapping_neg()),
+		LNEG => Value::Long(frame.pop_long()?.wrapping_neg()),
+		FNEG => Value::Float(-frame.pop_float()?),
+		DNEG => Value::Double(-frame.pop_double()?),
+		I2L => Value::Long(frame.pop_int()? as i64),
+		I2F => Value::Float(frame.pop_int()? as f32),
+		I2D => Value::Double(frame.pop_int()? as f64),
+		L2I => Value::Int(frame.pop_long()? as i32),
+		L2F => Value::Float(frame.pop_long()? as f32),
+		L2D => Value::Double(frame.pop_long()? as f64),
+		// Casting saturates and turns NaN into 0, just like the JVM.
+		F2I => Value::Int(frame.pop_float()? as i32),
+		F2L => Value::Long(frame.pop_float()? as i64),
+		F2D => Value::Double(frame.pop_float()? as f64),
+		D2I => Value::Int(frame.pop_double()? as i32),
+		D2L => Value::Long(frame.pop_double()? as i64),
+		D2F => Value::Float(frame.pop_double()? as f32),
+		I2B => Value::Int(frame.pop_int()? as i8 as i32),
+		I2C => Value::Int(frame.pop_int()? as u16 as i32),
+		_ => Value::Int(frame.pop_int()? as i16 as i32),
+	};
+	frame.push(value);
+	Ok(())
+}
+
+/// `fcmpl` and `dcmpl` push -1 if either is NaN, `fcmpg` and `dcmpg` push 1.
+fn compare<T: PartialOrd>(a: T, b: T, nan: i32) -> i32 {
+	match a.partial_cmp(&b) {
+		Some(std::cmp::Ordering::Less) => -1,
+		Some(std::cmp::Ordering::Equal) => 0,
+		Some(std::cmp::Ordering::Greater) => 1,
+		None => nan,
+	}
+}
+
+fn dup(opcode: u8, frame: &mut Frame) -> Result<(), Problem> {
+	match opcode {
+		POP => {
+			frame.pop_single()?;
+		}
+		POP2 => {
+			if !frame.pop()?.is_wide() {
+				frame.pop_single()?;
+			}
+		}
+		DUP => {
+			let first = frame.pop_single()?;
+			frame.push_all(&[first, first]);
+		}
+		DUP_X1 => {
+			let first = frame.pop_single()?;
+			let second = frame.pop_single()?;
+			frame.push_all(&[first, second, first]);
+		}
+		DUP_X2 => {
+			let first = frame.pop_single()?;
+			let second = frame.pop()?;
+			if second.is_wide() {
+				frame.push_all(&[first, second, first]);
+			} else {
+				let third = frame.pop_single()?;
+				frame.push_all(&[first, third, second, first]);
+			}
+		}
+		DUP2 => {
+			let first = frame.pop()?;
+			if first.is_wide() {
+				frame.push_all(&[first, first]);
+			} else {
+				let second = frame.pop_single()?;
+				frame.push_all(&[second, first, second, first]);
+			}
+		}
+		DUP2_X1 => {
+			let first = frame.pop()?;
+			if first.is_wide() {
+				let second = frame.pop_single()?;
+				frame.push_all(&[first, second, first]);
+			} else {
+				let second = frame.pop_single()?;
+				let third = frame.pop_single()?;
+				frame.push_all(&[second, first, third, second, first]);
+			}
+		}
+		DUP2_X2 => {
+			let first = frame.pop()?;
+			if first.is_wide() {
+				let second = frame.pop()?;
+				if second.is_wide() {
+					frame.push_all(&[first, second, first]);
+				} else {
+					let third = frame.pop_single()?;
+					frame.push_all(&[first, third, second, first]);
+				}
+			} else {
+				let second = frame.pop_single()?;
+				let third = frame.pop()?;
+				if third.is_wide() {
+					frame.push_all(&[second, first, third, second, first]);
+				} else {
+					let fourth = frame.pop_single()?;
+					frame.push_all(&[second, first, fourth, third, second, first]);
+				}
+			}
+		}
+		_ => {
+			let first = frame.pop_single()?;
+			let second = frame.pop_single()?;
+			frame.push_all(&[first, second]);
+		}
+	}
+	Ok(())
+}
+
+/// Pops as many values as the parameters take, in the order they were pushed.
+fn pop_arguments(frame: &mut Frame, count: usize) -> Result<Vec<Value>, Problem> {
+	if count > frame.stack.len() {
+		return Err(Problem::StackUnderflow);
+	}
+	Ok(frame.stack.split_off(frame.stack.len() - count))
+}
+
+/// Evaluates static methods of the classes it's given, see the module documentation for what's supported.
+pub struct Evaluator<'c> {
+	classes: Vec<&'c ClassFile<'c>>,
+	natives: HashMap<String, Native>,
+	methods: HashMap<String, Rc<Method<'c>>>,
+	/// Every object created so far, including the results of calls.
+	pub heap: Heap,
+	budget: u64,
+	remaining: u64,
+}
+
+impl<'c> Evaluator<'c> {
+	/// An evaluator with natives for the most common methods of `String`, `StringBuilder` and `Math`.
+	pub fn new() -> Self {
+		let mut evaluator = Evaluator::empty();
+		natives::register(&mut evaluator);
+		evaluator
+	}
+
+	/// An evaluator without any natives.
+	pub fn empty() -> Self {
+		Evaluator {
+			classes: vec![],
+			natives: HashMap::new(),
+			methods: HashMap::new(),
+			heap: Heap::new(),
+			budget: DEFAULT_BUDGET,
+			remaining: 0,
+		}
+	}
+
+	/// Makes the static methods of the class available.
+	pub fn add(&mut self, class_file: &'c ClassFile<'c>) -> &mut Self {
+		self.classes.push(class_file);
+		self
+	}
+
+	/// Calls `native` instead of the given method, whether it's static or not.
+	///
+	/// This takes precedence over methods of the classes.
+	pub fn register<F>(&mut self, owner: &str, name: &str, descriptor: &str, native: F) -> &mut Self
+		where F: Fn(&mut Heap, &[Value]) -> Result<Option<Value>, Problem> + 'static {
+		self.natives.insert(format!("{}.{}:{}", owner, name, descriptor), Box::new(native));
+		self
+	}
+
+	pub fn remove(&mut self, owner: &str, name: &str, descriptor: &str) -> &mut Self {
+		self.natives.remove(&format!("{}.{}:{}", owner, name, descriptor));
+		self
+	}
+
+	/// Sets how many instructions each call to `invoke` can run, counting those of the methods it calls,
+	/// and the elements of the arrays they create.
+	pub fn set_budget(&mut self, budget: u64) -> &mut Self {
+		self.budget = budget;
+		self
+	}
+
+	/// Calls a static method, or a native, with the given arguments, returning what it returns.
+	pub fn invoke(&mut self, owner: &str, name: &str, descriptor: &str, arguments: &[Value]) -> Result<Option<Value>, EvalError> {
+		self.remaining = self.budget;
+		self.call(owner, name, descriptor, arguments, true, 0)
+	}
+
+	fn call(&mut self, owner: &str, name: &str, descriptor: &str, arguments: &[Value], is_static: bool, depth: usize) -> Result<Option<Value>, EvalError> {
+		let key = format!("{}.{}:{}", owner, name, descriptor);
+		let error = |problem| EvalError { method: key.clone(), pc: None, problem };
+		if depth > MAX_DEPTH {
+			return Err(error(Problem::TooDeep));
+		}
+		let parsed = MethodDescriptor::parse(descriptor)
+			.ok_or_else(|| error(Problem::WrongArguments))?;
+		let receiver = !is_static as usize;
+		if arguments.len() != parsed.parameters.len() + receiver
+			|| !arguments[receiver..].iter().zip(&parsed.parameters).all(|(argument, parameter)| argument.fits(parameter)) {
+			return Err(error(Problem::WrongArguments));
+		}
+
+		if let Some(native) = self.natives.get(&key) {
+			return native(&mut self.heap, arguments).map_err(error);
+		}
+		if !is_static {
+			return Err(error(Problem::UnknownMethod(key.clone())));
+		}
+		let method = self.method(owner, name, descriptor, &key).map_err(error)?;
+		self.run(&method, arguments, depth)
+	}
+
+	/// Finds and decodes the static method, or fetches it from the ones decoded already.
+	fn method(&mut self, owner: &str, name: &str, descriptor: &str, key: &str) -> Result<Rc<Method<'c>>, Problem> {
+		if let Some(method) = self.methods.get(key) {
+			return Ok(method.clone());
+		}
+		let unknown = || Problem::UnknownMethod(key.to_string());
+		let class = *self.classes.iter()
+			.find(|class| class.constant_pool.class_name(class.this_class).is_some_and(|name| name.decoded() == owner))
+			.ok_or_else(unknown)?;
+		let cp = &class.constant_pool;
+		let info = class.methods.iter()
+			.find(|method| {
+				method.access_flags & STATIC != 0
+					&& cp.utf8(method.name_index).is_some_and(|value| value.decoded() == name)
+					&& cp.utf8(method.descriptor_index).is_some_and(|value| value.decoded() == descriptor)
+			})
+			.ok_or_else(unknown)?;
+		let code: Code = info.attributes.get(cp).ok_or_else(unknown)?;
+		let instructions = bytecode::decode(&code.code).map_err(Problem::Decode)?;
+		let method = Rc::new(Method {
+			class,
+			key: key.to_string(),
+			descriptor: MethodDescriptor::parse(descriptor).ok_or(Problem::WrongArguments)?,
+			max_locals: code.max_locals as usize,
+			positions: instructions.iter().enumerate().map(|(i, instruction)| (instruction.pc, i)).collect(),
+			instructions,
+		});
+		self.methods.insert(key.to_string(), method.clone());
+		Ok(method)
+	}
+
+	fn run(&mut self, method: &Method<'c>, arguments: &[Value], depth: usize) -> Result<Option<Value>, EvalError> {
+		let error = |pc, problem| EvalError { method: method.key.clone(), pc, problem };
+		let mut frame = Frame {
+			locals: vec![None; method.max_locals],
+			stack: vec![],
+		};
+		let mut slot = 0;
+		for (&argument, parameter) in arguments.iter().zip(&method.descriptor.parameters) {
+			frame.store(slot, argument).map_err(|problem| error(None, problem))?;
+			slot += parameter.size() as u16;
+		}
+
+		let mut index = 0;
+		loop {
+			let instruction = method.instructions.get(index)
+				.ok_or_else(|| error(None, Problem::FallsOffEnd))?;
+			let pc = instruction.pc;
+			if self.remaining == 0 {
+				return Err(error(Some(pc), Problem::BudgetExhausted));
+			}
+			self.remaining -= 1;
+			match self.step(method, instruction, &mut frame, depth) {
+				Ok(Flow::Next) => index += 1,
+				Ok(Flow::Jump(target)) => {
+					index = *method.positions.get(&target)
+						.ok_or_else(|| error(Some(pc), Problem::InvalidTarget(target)))?;
+				}
+				Ok(Flow::Return(value)) => return Ok(value),
+				Err(Interrupt::Problem(problem)) => return Err(error(Some(pc), problem)),
+				Err(Interrupt::Nested(error)) => return Err(error),
+			}
+		}
+	}
+
+	/// Takes the elements of a new array, of every dimension, out of the budget.
+	fn charge(&mut self, counts: &[i32]) -> Result<(), Problem> {
+		let mut arrays: u64 = 1;
+		let mut elements: u64 = 0;
+		for &count in counts {
+			arrays = arrays.saturating_mul(count.max(0) as u64);
+			elements = elements.saturating_add(arrays);
+		}
+		if elements > self.remaining {
+			return Err(Problem::BudgetExhausted);
+		}
+		self.remaining -= elements;
+		Ok(())
+	}
+
+	fn step(&mut self, method: &Method<'c>, instruction: &Instruction, frame: &mut Frame, depth: usize) -> Result<Flow, Interrupt> {
+		let cp = &method.class.constant_pool;
+		let opcode = instruction.opcode;
+		let index = constant_index(instruction);
+		match opcode {
+			NOP | CHECKCAST => {}
+			ACONST_NULL => frame.push(Value::Null),
+			ICONST_M1..=ICONST_5 => frame.push(Value::Int(opcode as i32 - ICONST_0 as i32)),
+			LCONST_0 | LCONST_1 => frame.push(Value::Long((opcode - LCONST_0) as i64)),
+			FCONST_0..=FCONST_2 => frame.push(Value::Float((opcode - FCONST_0) as f32)),
+			DCONST_0 | DCONST_1 => frame.push(Value::Double((opcode - DCONST_0) as f64)),
+			BIPUSH | SIPUSH => match instruction.operand {
+				Operand::Byte(value) => frame.push(Value::Int(value as i32)),
+				Operand::Short(value) => frame.push(Value::Int(value as i32)),
+				_ => return Err(Problem::Unsupported(opcode).into()),
+			},
+			LDC | LDC_W | LDC2_W => frame.push(ldc(cp, &mut self.heap, index)?),
+			ILOAD..=ALOAD_3 => frame.push(frame.load(local(instruction)?)?),
+			ISTORE..=ASTORE_3 => {
+				let value = frame.pop()?;
+				frame.store(local(instruction)?, value)?;
+			}
+			IALOAD..=SALOAD => {
+				let index = frame.pop_int()?;
+				let array = frame.pop_reference()?;
+				let value = self.heap.elements(array)?
+					.get(index as usize)
+					.copied()
+					.filter(|_| index >= 0)
+					.ok_or_else(|| Problem::Exception("java/lang/ArrayIndexOutOfBoundsException".to_string()))?;
+				frame.push(value);
+			}
+			IASTORE..=SASTORE => {
+				let mut value = frame.pop()?;
+				let index = frame.pop_int()?;
+				let array = frame.pop_reference()?;
+				let boolean = matches!(self.heap.get(array), Some(Object::Array { descriptor, .. }) if descriptor == "[Z");
+				value = match (opcode, value) {
+					(BASTORE, Value::Int(value)) if boolean => Value::Int(value & 1),
+					(BASTORE, Value::Int(value)) => Value::Int(value as i8 as i32),
+					(CASTORE, Value::Int(value)) => Value::Int(value as u16 as i32),
+					(SASTORE, Value::Int(value)) => Value::Int(value as i16 as i32),
+					(_, value) => value,
+				};
+				let element = self.heap.elements(array)?
+					.get_mut(index as usize)
+					.filter(|_| index >= 0)
+					.ok_or_else(|| Problem::Exception("java/lang/ArrayIndexOutOfBoundsException".to_string()))?;
+				*element = value;
+			}
+			POP..=SWAP => dup(opcode, frame)?,
+			IADD..=DREM | ISHL..=LXOR => arithmetic(opcode, frame)?,
+			INEG..=DNEG | I2L..=I2S => convert(opcode, frame)?,
+			IINC => {
+				let index = local(instruction)?;
+				let increment = match instruction.operand {
+					Operand::Iinc { value, .. } => value as i32,
+					_ => return Err(Problem::Unsupported(opcode).into()),
+				};
+				match frame.load(index)? {
+					Value::Int(value) => frame.store(index, Value::Int(value.wrapping_add(increment)))?,
+					_ => return Err(Problem::TypeMismatch.into()),
+				}
+			}
+			LCMP => {
+				let b = frame.pop_long()?;
+				let a = frame.pop_long()?;
+				frame.push(Value::Int(compare(a, b, 0)));
+			}
+			FCMPL | FCMPG => {
+				let b = frame.pop_float()?;
+				let a = frame.pop_float()?;
+				frame.push(Value::Int(compare(a, b, if opcode == FCMPL { -1 } else { 1 })));
+			}
+			DCMPL | DCMPG => {
+				let b = frame.pop_double()?;
+				let a = frame.pop_double()?;
+				frame.push(Value::Int(compare(a, b, if opcode == DCMPL { -1 } else { 1 })));
+			}
+			IFEQ..=IFLE => {
+				let value = frame.pop_int()?;
+				let taken = match opcode {
+					IFEQ => value == 0,
+					IFNE => value != 0,
+					IFLT => value < 0,
+					IFGE => value >= 0,
+					IFGT => value > 0,
+					_ => value <= 0,
+				};
+				if taken {
+					return Ok(Flow::Jump(branch(instruction)?));
+				}
+			}
+			IF_ICMPEQ..=IF_ICMPLE => {
+				let b = frame.pop_int()?;
+				let a = frame.pop_int()?;
+				let taken = match opcode {
+					IF_ICMPEQ => a == b,
+					IF_ICMPNE => a != b,
+					IF_ICMPLT => a < b,
+					IF_ICMPGE => a >= b,
+					IF_ICMPGT => a > b,
+					_ => a <= b,
+				};
+				if taken {
+					return Ok(Flow::Jump(branch(instruction)?));
+				}
+			}
+			IF_ACMPEQ | IF_ACMPNE | IFNULL | IFNONNULL => {
+				let b = match opcode {
+					IFNULL | IFNONNULL => Value::Null,
+					_ => frame.pop_reference()?,
+				};
+				let a = frame.pop_reference()?;
+				if (a == b) == matches!(opcode, IF_ACMPEQ | IFNULL) {
+					return Ok(Flow::Jump(branch(instruction)?));
+				}
+			}
+			GOTO | GOTO_W => return Ok(Flow::Jump(branch(instruction)?)),
+			TABLESWITCH | LOOKUPSWITCH => {
+				let key = frame.pop_int()?;
+				let target = match instruction.operand {
+					Operand::TableSwitch { default, low, ref targets } => {
+						let offset = key as i64 - low as i64;
+						targets.get(offset as usize).copied().filter(|_| offset >= 0).unwrap_or(default)
+					}
+					Operand::LookupSwitch { default, ref pairs } => pairs.iter()
+						.find(|&&(value, _)| value == key)
+						.map_or(default, |&(_, target)| target),
+					_ => return Err(Problem::Unsupported(opcode).into()),
+				};
+				return Ok(Flow::Jump(target));
+			}
+			IRETURN..=ARETURN => return Ok(Flow::Return(Some(frame.pop()?))),
+			RETURN => return Ok(Flow::Return(None)),
+			INVOKEVIRTUAL..=INVOKEINTERFACE => {
+				let callee = cp.resolve(CPIndex::<MethodRefInfo>::new(index))
+					.or_else(|| cp.resolve(CPIndex::<InterfaceMethodRefInfo>::new(index)))
+					.ok_or(Problem::InvalidConstant(index))?;
+				let descriptor = callee.descriptor.decoded();
+				let parameters = MethodDescriptor::parse(&descriptor)
+					.ok_or(Problem::InvalidConstant(index))?
+					.parameters
+					.len();
+				let is_static = opcode == INVOKESTATIC;
+				let arguments = pop_arguments(frame, parameters + !is_static as usize)?;
+				let result = self.call(&callee.owner.decoded(), &callee.name.decoded(), &descriptor, &arguments, is_static, depth + 1)
+					.map_err(Interrupt::Nested)?;
+				if let Some(value) = result {
+					frame.push(value);
+				}
+			}
+			INVOKEDYNAMIC => {
+				let value = self.concat(method.class, index, frame)?;
+				frame.push(value);
+			}
+			NEW => {
+				let value = self.heap.allocate(Object::Instance(class_name(cp, index)?));
+				frame.push(value);
+			}
+			NEWARRAY => {
+				let descriptor = match instruction.operand {
+					Operand::NewArray(T_BOOLEAN) => "[Z",
+					Operand::NewArray(T_CHAR) => "[C",
+					Operand::NewArray(T_FLOAT) => "[F",
+					Operand::NewArray(T_DOUBLE) => "[D",
+					Operand::NewArray(T_BYTE) => "[B",
+					Operand::NewArray(T_SHORT) => "[S",
+					Operand::NewArray(T_INT) => "[I",
+					Operand::NewArray(T_LONG) => "[J",
+					_ => return Err(Problem::Unsupported(opcode).into()),
+				};
+				let count = frame.pop_int()?;
+				self.charge(&[count])?;
+				frame.push(self.heap.new_array(descriptor, &[count])?);
+			}
+			ANEWARRAY => {
+				let component = class_name(cp, index)?;
+				let descriptor = match component.starts_with('[') {
+					true => format!("[{}", component),
+					false => format!("[L{};", component),
+				};
+				let count = frame.pop_int()?;
+				self.charge(&[count])?;
+				frame.push(self.heap.new_array(&descriptor, &[count])?);
+			}
+			MULTIANEWARRAY => {
+				let dimensions = match instruction.operand {
+					Operand::MultiANewArray { dimensions: 0, .. } => return Err(Problem::InvalidOperand.into()),
+					Operand::MultiANewArray { dimensions, .. } => dimensions as usize,
+					_ => return Err(Problem::Unsupported(opcode).into()),
+				};
+				let counts = pop_arguments(frame, dimensions)?.into_iter()
+					.map(|count| match count {
+						Value::Int(count) => Ok(count),
+						_ => Err(Problem::TypeMismatch),
+					})
+					.collect::<Result<Vec<i32>, Problem>>()?;
+				self.charge(&counts)?;
+				frame.push(self.heap.new_array(&class_name(cp, index)?, &counts)?);
+			}
+			ARRAYLENGTH => {
+				let array = frame.pop_reference()?;
+				let length = self.heap.elements(array)?.len();
+				frame.push(Value::Int(length as i32));
+			}
+			_ => return Err(Problem::Unsupported(opcode).into()),
+		}
+		Ok(Flow::Next)
+	}
+
+	/// `invokedynamic` with one of `StringConcatFactory`'s bootstrap methods, and nothing else.
+	fn concat(&mut self, class: &ClassFile, index: u16, frame: &mut Frame) -> Result<Value, Problem> {
+		let cp = &class.constant_pool;
+		let invalid = || Problem::InvalidConstant(index);
+		let call_site = cp.resolve(CPIndex::<InvokeDynamicInfo>::new(index)).ok_or_else(invalid)?;
+		let bootstrap = class.attributes.named(cp, "BootstrapMethods")
+			.and_then(|info| <BootstrapMethods as FromBytes<BigEndian>>::from_bytes(&mut Cursor::new(info.info())).ok())
+			.and_then(|methods| methods.table.get(call_site.bootstrap_method_attr_index as usize).cloned())
+			.ok_or_else(invalid)?;
+		let handle = cp.resolve(bootstrap.bootstrap_method_ref).ok_or_else(invalid)?;
+		let bootstrap_name = match handle.reference {
+			Reference::Method(method) if method.owner.decoded() == "java/lang/invoke/StringConcatFactory" => method.name.decoded(),
+			_ => return Err(Problem::Unsupported(INVOKEDYNAMIC)),
+		};
+		let parameters = MethodDescriptor::parse(&call_site.descriptor.decoded()).ok_or_else(invalid)?.parameters;
+
+		// Constants from the recipe come from the bootstrap arguments following it.
+		let mut constants = vec![];
+		for argument in &bootstrap.bootstrap_arguments {
+			let constant = match ldc(cp, &mut self.heap, argument.index)? {
+				value @ Value::Reference(_) => self.heap.string(value).unwrap_or_default().to_string(),
+				value => self.heap.to_java_string(value, &FieldType::Int)?,
+			};
+			constants.push(constant);
+		}
+		let recipe = match &*bootstrap_name {
+			"makeConcat" => "\u{1}".repeat(parameters.len()),
+			"makeConcatWithConstants" if !constants.is_empty() => constants.remove(0),
+			_ => return Err(Problem::Unsupported(INVOKEDYNAMIC)),
+		};
+
+		let arguments = pop_arguments(frame, parameters.len())?;
+		let mut arguments = arguments.iter().zip(&parameters);
+		let mut constants = constants.into_iter();
+		let mut result = String::new();
+		for c in recipe.chars() {
+			match c {
+				'\u{1}' => {
+					let (&argument, parameter) = arguments.next().ok_or(Problem::WrongArguments)?;
+					result.push_str(&self.heap.to_java_string(argument, parameter)?);
+				}
+				'\u{2}' => result.push_str(&constants.next().ok_or_else(invalid)?),
+				c => result.push(c),
+			}
+		}
+		Ok(self.heap.new_string(&result))
+	}
+}
+
+impl<'c> Default for Evaluator<'c> {
+	fn default() -> Self {
+		Evaluator::new()
+	}
+}
+
+mod natives {
+	use super::*;
+
+	fn string_argument(heap: &Heap, value: Value) -> Result<&str, Problem> {
+		match value {
+			Value::Null => Err(null_pointer()),
+			value => heap.string(value).ok_or(Problem::TypeMismatch),
+		}
+	}
+
+	fn int_argument(value: Value) -> Result<i32, Problem> {
+		match value {
+			Value::Int(value) => Ok(value),
+			_ => Err(Problem::TypeMismatch),
+		}
+	}
+
+	fn builder(heap: &mut Heap, value: Value) -> Result<&mut String, Problem> {
+		match heap.get_mut(value) {
+			Some(Object::StringBuilder(builder)) => Ok(builder),
+			_ => Err(Problem::TypeMismatch),
+		}
+	}
+
+	/// Turns the object fresh from `new` into something else.
+	fn initialise(heap: &mut Heap, value: Value, object: Object) -> Result<Option<Value>, Problem> {
+		match heap.get_mut(value) {
+			Some(instance @ Object::Instance(_)) => {
+				*instance = object;
+				Ok(None)
+			}
+			_ => Err(Problem::TypeMismatch),
+		}
+	}
+
+	fn substring(value: &str, begin: i32, end: i32) -> Result<String, Problem> {
+		let units: Vec<u16> = value.encode_utf16().collect();
+		if begin < 0 || end < begin || end as usize > units.len() {
+			return Err(Problem::Exception("java/lang/StringIndexOutOfBoundsException".to_string()));
+		}
+		Ok(String::from_utf16_lossy(&units[begin as usize..end as usize]))
+	}
+
+	pub(super) fn register(evaluator: &mut Evaluator) {
+		const STRING: &str = "java/lang/String";
+		const BUILDER: &str = "java/lang/StringBuilder";
+
+		evaluator
+			.register(STRING, "<init>", "([C)V", |heap, arguments| {
+				let chars: Vec<u16> = match arguments[1] {
+					Value::Null => return Err(null_pointer()),
+					array => heap.int_array(array).ok_or(Problem::TypeMismatch)?.into_iter().map(|c| c as u16).collect(),
+				};
+				initialise(heap, arguments[0], Object::String(String::from_utf16_lossy(&chars)))
+			})
+			.register(STRING, "length", "()I", |heap, arguments| {
+				Ok(Some(Value::Int(string_argument(heap, arguments[0])?.encode_utf16().count() as i32)))
+			})
+			.register(STRING, "isEmpty", "()Z", |heap, arguments| {
+				Ok(Some(Value::Int(string_argument(heap, arguments[0])?.is_empty() as i32)))
+			})
+			.register(STRING, "charAt", "(I)C", |heap, arguments| {
+				let index = int_argument(arguments[1])?;
+				let c = string_argument(heap, arguments[0])?.encode_utf16()
+					.nth(index as usize)
+					.filter(|_| index >= 0)
+					.ok_or_else(|| Problem::Exception("java/lang/StringIndexOutOfBoundsException".to_string()))?;
+				Ok(Some(Value::Int(c as i32)))
+			})
+			.register(STRING, "equals", "(Ljava/lang/Object;)Z", |heap, arguments| {
+				let value = string_argument(heap, arguments[0])?;
+				Ok(Some(Value::Int((heap.string(arguments[1]) == Some(value)) as i32)))
+			})
+			.register(STRING, "hashCode", "()I", |heap, arguments| {
+				let hash = string_argument(heap, arguments[0])?.encode_utf16()
+					.fold(0i32, |hash, c| hash.wrapping_mul(31).wrapping_add(c as i32));
+				Ok(Some(Value::Int(hash)))
+			})
+			.register(STRING, "concat", "(Ljava/lang/String;)Ljava/lang/String;", |heap, arguments| {
+				let value = format!("{}{}", string_argument(heap, arguments[0])?, string_argument(heap, arguments[1])?);
+				Ok(Some(heap.new_string(&value)))
+			})
+			.register(STRING, "substring", "(I)Ljava/lang/String;", |heap, arguments| {
+				let value = string_argument(heap, arguments[0])?;
+				let end = value.encode_utf16().count() as i32;
+				let value = substring(value, int_argument(arguments[1])?, end)?;
+				Ok(Some(heap.new_string(&value)))
+			})
+			.register(STRING, "substring", "(II)Ljava/lang/String;", |heap, arguments| {
+				let value = substring(string_argument(heap, arguments[0])?, int_argument(arguments[1])?, int_argument(arguments[2])?)?;
+				Ok(Some(heap.new_string(&value)))
+			})
+			.register(STRING, "intern", "()Ljava/lang/String;", |heap, arguments| {
+				let value = string_argument(heap, arguments[0])?.to_string();
+				Ok(Some(heap.intern(&value)))
+			})
+			.register(STRING, "toString", "()Ljava/lang/String;", |heap, arguments| {
+				string_argument(heap, arguments[0])?;
+				Ok(Some(arguments[0]))
+			})
+			.register(BUILDER, "<init>", "()V", |heap, arguments| {
+				initialise(heap, arguments[0], Object::StringBuilder(String::new()))
+			})
+			.register(BUILDER, "<init>", "(Ljava/lang/String;)V", |heap, arguments| {
+				let value = string_argument(heap, arguments[1])?.to_string();
+				initialise(heap, arguments[0], Object::StringBuilder(value))
+			})
+			.register(BUILDER, "length", "()I", |heap, arguments| {
+				Ok(Some(Value::Int(builder(heap, arguments[0])?.encode_utf16().count() as i32)))
+			})
+			.register(BUILDER, "toString", "()Ljava/lang/String;", |heap, arguments| {
+				let value = builder(heap, arguments[0])?.clone();
+				Ok(Some(heap.new_string(&value)))
+			});
+		for parameter in &["Ljava/lang/String;", "Ljava/lang/Object;", "Ljava/lang/CharSequence;", "I", "J", "F", "D", "C", "Z"] {
+			let field_type = FieldType::parse(parameter).unwrap();
+			let descriptor = format!("({})Ljava/lang/StringBuilder;", parameter);
+			evaluator.register(BUILDER, "append", &descriptor, move |heap, arguments| {
+				let value = heap.to_java_string(arguments[1], &field_type)?;
+				builder(heap, arguments[0])?.push_str(&value);
+				Ok(Some(arguments[0]))
+			});
+		}
+		for parameter in &["I", "J", "F", "D", "C", "Z", "Ljava/lang/Object;"] {
+			let field_type = FieldType::parse(parameter).unwrap();
+			let descriptor = format!("({})Ljava/lang/String;", parameter);
+			evaluator.register(STRING, "valueOf", &descriptor, move |heap, arguments| {
+				let value = heap.to_java_string(arguments[0], &field_type)?;
+				Ok(Some(heap.new_string(&value)))
+			});
+		}
+
+		evaluator
+			.register("java/lang/Math", "abs", "(I)I", |_, arguments| {
+				Ok(Some(Value::Int(int_argument(arguments[0])?.wrapping_abs())))
+			})
+			.register("java/lang/Math", "max", "(II)I", |_, arguments| {
+				Ok(Some(Value::Int(int_argument(arguments[0])?.max(int_argument(arguments[1])?))))
+			})
+			.register("java/lang/Math", "min", "(II)I", |_, arguments| {
+				Ok(Some(Value::Int(int_argument(arguments[0])?.min(int_argument(arguments[1])?))))
+			});
+	}
+}
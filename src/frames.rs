@@ -0,0 +1,384 @@
+//! Computing the `StackMapTable` of a method from scratch, for when its code has been generated or changed.
+//!
+//! The frames are inferred by running the type checker's rules over the instructions until nothing changes,
+//! merging the types wherever two paths meet, exception handlers included.
+//! Two classes merge to their closest common super class, which is left to a `ClassHierarchy`,
+//! so nothing has to be loaded, but the frames are only as precise as what the hierarchy knows.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::*;
+use crate::attr::{Code, StackMapFrame, StackMapTable, VerificationTypeInfo};
+use crate::hierarchy::{element_name, ClassHierarchy};
+use crate::utf8::MStrExt;
+use crate::verify::Location;
+use crate::verify::typecheck::{self, Checker, Diagnostic, Frame, Slot, Type};
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Problem {
+	/// The code breaks one of the type checker's rules.
+	Check(typecheck::Problem),
+	/// Two paths meet with stack values that can't be merged.
+	Unmergeable {
+		slot: Slot,
+		first: Type,
+		second: Type,
+	},
+	/// There's no frame that dead code would be guaranteed to pass with.
+	Unreachable,
+}
+
+impl From<typecheck::Problem> for Problem {
+	fn from(problem: typecheck::Problem) -> Self {
+		Problem::Check(problem)
+	}
+}
+
+impl fmt::Display for Problem {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Problem::Check(problem) => write!(f, "{}", problem),
+			Problem::Unmergeable { slot, first, second } => {
+				write!(f, "{} is {} on one path, but {} on another", slot, first, second)
+			}
+			Problem::Unreachable => f.write_str("unreachable code can't be given a stack map frame"),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct FrameError {
+	pub location: Location,
+	/// The offset of the failing instruction, if the problem is with one.
+	pub pc: Option<u32>,
+	pub problem: Problem,
+}
+
+impl From<Diagnostic> for FrameError {
+	fn from(diagnostic: Diagnostic) -> Self {
+		FrameError {
+			location: diagnostic.location,
+			pc: diagnostic.pc,
+			problem: Problem::Check(diagnostic.problem),
+		}
+	}
+}
+
+impl fmt::Display for FrameError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.pc {
+			Some(pc) => write!(f, "{}, pc {}: {}", self.location, pc, self.problem),
+			None => write!(f, "{}: {}", self.location, self.problem),
+		}
+	}
+}
+
+struct Inference<'c, 'a> {
+	checker: Checker<'c, 'a>,
+	frames: BTreeMap<u32, Frame>,
+	pending: BTreeSet<u32>,
+}
+
+impl<'c, 'a> Inference<'c, 'a> {
+	/// Merges `frame` into the one at `pc`, queueing the instruction up again if that changed anything.
+	fn merge(&mut self, pc: u32, frame: Frame) -> Result<(), Problem> {
+		if !self.checker.instructions.contains_key(&pc) {
+			return Err(typecheck::Problem::InvalidTarget(pc).into());
+		}
+		let merged = match self.frames.get(&pc) {
+			Some(existing) => {
+				let merged = self.merge_frames(existing, &frame)?;
+				if merged == *existing {
+					return Ok(());
+				}
+				merged
+			}
+			None => frame,
+		};
+		self.frames.insert(pc, merged);
+		self.pending.insert(pc);
+		Ok(())
+	}
+
+	fn merge_frames(&self, existing: &Frame, frame: &Frame) -> Result<Frame, Problem> {
+		if existing.stack.len() != frame.stack.len() {
+			return Err(typecheck::Problem::StackSizeMismatch { expected: existing.stack.len(), actual: frame.stack.len() }.into());
+		}
+		let locals = existing.locals.iter()
+			.zip(&frame.locals)
+			.map(|(first, second)| self.merge_types(first, second))
+			.collect();
+		let mut stack = vec![];
+		for (i, (first, second)) in existing.stack.iter().zip(&frame.stack).enumerate() {
+			match self.merge_types(first, second) {
+				Type::Top => return Err(Problem::Unmergeable { slot: Slot::Stack(i), first: first.clone(), second: second.clone() }),
+				merged => stack.push(merged),
+			}
+		}
+		Ok(Frame {
+			locals,
+			stack,
+		})
+	}
+
+	fn merge_types(&self, first: &Type, second: &Type) -> Type {
+		match (first, second) {
+			_ if first == second => first.clone(),
+			(Type::Null, Type::Object(_)) => second.clone(),
+			(Type::Object(_), Type::Null) => first.clone(),
+			(Type::Object(first), Type::Object(second)) => Type::Object(self.common_super_class(first, second)),
+			_ => Type::Top,
+		}
+	}
+
+	/// Arrays of references merge by their components, which isn't something the hierarchy has to know about.
+	fn common_super_class(&self, first: &str, second: &str) -> String {
+		if first == second {
+			return first.to_string();
+		}
+		let components = (first.strip_prefix('[').and_then(element_name), second.strip_prefix('[').and_then(element_name));
+		if let (Some(first), Some(second)) = components {
+			let merged = self.common_super_class(first, second);
+			return if merged.starts_with('[') { format!("[{}", merged) } else { format!("[L{};", merged) };
+		}
+		self.checker.hierarchy.common_super_class(first, second)
+	}
+
+	fn merge_handlers(&mut self, pc: u32, locals: &[Type]) -> Result<(), Problem> {
+		let handlers: Vec<(u32, Type)> = self.checker.handlers.iter()
+			.filter(|handler| pc >= handler.start && pc < handler.end)
+			.map(|handler| (handler.handler, handler.catch_type.clone()))
+			.collect();
+		for (handler, catch_type) in handlers {
+			let frame = Frame {
+				locals: locals.to_vec(),
+				stack: vec![catch_type],
+			};
+			self.merge(handler, frame)?;
+		}
+		Ok(())
+	}
+
+	fn step(&mut self, pc: u32) -> Result<(), Problem> {
+		let instruction = self.checker.instructions[&pc].clone();
+		let mut frame = self.frames[&pc].clone();
+		self.merge_handlers(pc, &frame.locals)?;
+		self.checker.execute(&instruction, &mut frame)?;
+		self.merge_handlers(pc, &frame.locals)?;
+
+		for target in instruction.targets() {
+			self.merge(target, frame.clone())?;
+		}
+		if !instruction.is_unconditional() {
+			let next = self.checker.instructions.range(pc + 1..)
+				.next()
+				.map(|(&next, _)| next)
+				.ok_or(typecheck::Problem::FallsOffEnd)?;
+			self.merge(next, frame)?;
+		}
+		Ok(())
+	}
+}
+
+/// Drops the `Top`s padding the locals, but not the second half of a long or double.
+fn trim(mut frame: Frame) -> Frame {
+	while frame.locals.last() == Some(&Type::Top) {
+		let len = frame.locals.len();
+		if len >= 2 && frame.locals[len - 2].size() == 2 {
+			break;
+		}
+		frame.locals.pop();
+	}
+	frame
+}
+
+/// Infers the frame at every offset of the method's code that needs one,
+/// which is every branch target, exception handler, and instruction following an unconditional one.
+///
+/// `code` is what's going to end up in the method at the given index, whatever its `Code` attribute holds now.
+/// Its `max_locals` has to be right, see `maxs::update_maxs`, but `max_stack` isn't looked at.
+/// The locals of the frames aren't padded to `max_locals`, just like `Frame::initial`.
+pub fn compute_frames(class_file: &ClassFile, index: usize, code: &Code, hierarchy: &dyn ClassHierarchy) -> Result<Vec<(u32, Frame)>, FrameError> {
+	let location = Location::Code(index);
+	let error = |pc, problem| FrameError { location, pc, problem };
+
+	let (mut checker, initial) = typecheck::checker(class_file, index, code, hierarchy)?;
+	checker.max_stack = u16::MAX;
+	let initial = checker.pad(initial)
+		.map_err(|problem| error(None, problem.into()))?;
+
+	let mut inference = Inference {
+		checker,
+		frames: BTreeMap::new(),
+		pending: BTreeSet::new(),
+	};
+	inference.merge(0, initial)
+		.map_err(|problem| error(Some(0), problem))?;
+	while let Some(pc) = inference.pending.pop_first() {
+		inference.step(pc)
+			.map_err(|problem| error(Some(pc), problem))?;
+	}
+
+	let Inference { checker, frames, .. } = inference;
+	if let Some(&pc) = checker.instructions.keys().find(|pc| !frames.contains_key(pc)) {
+		return Err(error(Some(pc), Problem::Unreachable));
+	}
+
+	let mut needed: BTreeSet<u32> = checker.handlers.iter()
+		.map(|handler| handler.handler)
+		.collect();
+	let mut follows_unconditional = false;
+	for (&pc, instruction) in &checker.instructions {
+		if follows_unconditional {
+			needed.insert(pc);
+		}
+		needed.extend(instruction.targets());
+		follows_unconditional = instruction.is_unconditional();
+	}
+	needed.into_iter()
+		.map(|pc| {
+			frames.get(&pc)
+				.map(|frame| (pc, trim(frame.clone())))
+				.ok_or_else(|| error(Some(pc), typecheck::Problem::InvalidTarget(pc).into()))
+		})
+		.collect()
+}
+
+/// The locals as the `StackMapTable` sees them, where a long or double is a single entry, without the trailing `Top`s.
+fn verification_types(locals: &[Type]) -> Vec<&Type> {
+	let mut result = vec![];
+	let mut i = 0;
+	while i < locals.len() {
+		result.push(&locals[i]);
+		i += locals[i].size();
+	}
+	while result.last() == Some(&&Type::Top) {
+		result.pop();
+	}
+	result
+}
+
+fn verification_type<'a>(value: &Type, cp: &mut ConstantPool<'a>) -> Option<VerificationTypeInfo<'a>> {
+	let result = match value {
+		Type::Top => VerificationTypeInfo::Top,
+		Type::Integer => VerificationTypeInfo::Integer,
+		Type::Float => VerificationTypeInfo::Float,
+		Type::Long => VerificationTypeInfo::Long,
+		Type::Double => VerificationTypeInfo::Double,
+		Type::Null => VerificationTypeInfo::Null,
+		Type::UninitializedThis => VerificationTypeInfo::UninitializedThis,
+		Type::Uninitialized(offset) => VerificationTypeInfo::Uninitialized(*offset),
+		Type::Object(name) => VerificationTypeInfo::ObjectVariable(cp.add_class(name)?),
+	};
+	Some(result)
+}
+
+/// Encodes the frames as a `StackMapTable`, picking the smallest kind of frame that describes each of them
+/// relative to the one before.
+///
+/// `initial` is the frame on entry to the method, see `Frame::initial`, and the frames have to be sorted by offset.
+/// Classes are added to the constant pool as needed, and `None` means it ran out of room.
+pub fn compress<'a>(initial: &Frame, frames: &[(u32, Frame)], cp: &mut ConstantPool<'a>) -> Option<StackMapTable<'a>> {
+	let mut table = vec![];
+	let mut previous = verification_types(&initial.locals);
+	let mut previous_pc = None;
+	for (pc, frame) in frames {
+		let offset_delta = match previous_pc {
+			Some(previous_pc) => pc - previous_pc - 1,
+			None => *pc,
+		} as u16;
+		let locals = verification_types(&frame.locals);
+		let same = locals == previous;
+
+		let entry = match frame.stack.len() {
+			0 if same && offset_delta < 64 => StackMapFrame::SameFrame(offset_delta as u8),
+			0 if same => StackMapFrame::SameFrameExtended(offset_delta),
+			1 if same && offset_delta < 64 => StackMapFrame::SameLocals {
+				offset_delta: offset_delta as u8,
+				verification_type_info: verification_type(&frame.stack[0], cp)?,
+			},
+			1 if same => StackMapFrame::SameLocalsExtended {
+				offset_delta,
+				verification_type_info: verification_type(&frame.stack[0], cp)?,
+			},
+			0 if locals.len() > previous.len() && locals.len() - previous.len() <= 3 && locals.starts_with(&previous) => {
+				StackMapFrame::AppendFrame {
+					offset_delta,
+					locals: locals[previous.len()..].iter()
+						.map(|value| verification_type(value, cp))
+						.collect::<Option<_>>()?,
+				}
+			}
+			0 if previous.len() > locals.len() && previous.len() - locals.len() <= 3 && previous.starts_with(&locals) => {
+				StackMapFrame::ChopFrame {
+					offset_delta,
+					chopped: (previous.len() - locals.len()) as u8,
+				}
+			}
+			_ => StackMapFrame::FullFrame {
+				offset_delta,
+				locals: locals.iter()
+					.map(|value| verification_type(value, cp))
+					.collect::<Option<_>>()?,
+				stack: frame.stack.iter()
+					.map(|value| verification_type(value, cp))
+					.collect::<Option<_>>()?,
+			},
+		};
+		table.push(entry);
+		previous = locals;
+		previous_pc = Some(*pc);
+	}
+	Some(StackMapTable {
+		table,
+	})
+}
+
+/// Recomputes the `StackMapTable` of the method at the given index, and stores it in the method's `Code` attribute,
+/// or removes it if the method doesn't need one.
+///
+/// Abstract and native methods are left alone, and so are classes older than 50.0, which don't use stack maps.
+pub fn update_frames(class_file: &mut ClassFile, index: usize, hierarchy: &dyn ClassHierarchy) -> Result<(), FrameError> {
+	let location = Location::Code(index);
+	let error = |problem: typecheck::Problem| FrameError { location, pc: None, problem: problem.into() };
+
+	if class_file.major_version < 50 {
+		return Ok(());
+	}
+	let mut code = match typecheck::method_code(class_file, index)? {
+		Some(code) => code,
+		None => return Ok(()),
+	};
+	let frames = compute_frames(class_file, index, &code, hierarchy)?;
+
+	let cp = &class_file.constant_pool;
+	let method = &class_file.methods[index];
+	let string = |index| cp.utf8(index).map(MStrExt::decoded).unwrap_or_default();
+	let class_name = cp.class_name(class_file.this_class).map(MStrExt::decoded).unwrap_or_default();
+	let initial = Frame::initial(&class_name, method.access_flags, &string(method.name_index), &string(method.descriptor_index))
+		.ok_or_else(|| error(typecheck::Problem::InvalidDescriptor))?;
+
+	let full = || error(typecheck::Problem::InvalidStackMap("the constant pool is full".to_string()));
+	let cp = &mut class_file.constant_pool;
+	let table = compress(&initial, &frames, cp)
+		.ok_or_else(full)?;
+	if table.table.is_empty() {
+		code.attributes.remove(cp, "StackMapTable");
+	} else {
+		let name_index = cp.add_utf8("StackMapTable")
+			.ok_or_else(full)?;
+		let mut info = vec![];
+		<StackMapTable as ToBytes<BigEndian>>::to_bytes(&table, &mut info)
+			.map_err(|_| error(typecheck::Problem::MalformedCode))?;
+		code.attributes.set(cp, AttributeInfo::new(name_index, info));
+	}
+
+	let name_index = cp.add_utf8("Code")
+		.ok_or_else(full)?;
+	let mut info = vec![];
+	<Code as ToBytes<BigEndian>>::to_bytes(&code, &mut info)
+		.map_err(|_| error(typecheck::Problem::MalformedCode))?;
+	class_file.methods[index].attributes.set(cp, AttributeInfo::new(name_index, info));
+	Ok(())
+}
@@ -0,0 +1,159 @@
+//! What the verifier and frame computation need to know about classes other than the one being looked at.
+//!
+//! All names are internal names, such as `java/lang/Object`, or descriptors for arrays, such as `[I`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::*;
+use crate::ops::INTERFACE;
+use crate::utf8::MStrExt;
+
+pub const OBJECT: &str = "java/lang/Object";
+
+pub trait ClassHierarchy {
+	/// The super class of the given class, `None` for `java/lang/Object`.
+	///
+	/// The outer `None` means the class is unknown.
+	fn super_class(&self, name: &str) -> Option<Option<String>>;
+
+	/// `None` if the class is unknown.
+	fn is_interface(&self, name: &str) -> Option<bool>;
+
+	/// Whether a value of type `from` can be stored in a variable of type `to`, the way the JVMS type checker sees it.
+	///
+	/// Interfaces are treated like `java/lang/Object`, and so is anything the hierarchy doesn't know about,
+	/// meaning this only returns `false` if it can prove the assignment is wrong.
+	/// A class whose super classes go round in circles can never be loaded, so nothing is assignable from it.
+	fn is_assignable(&self, from: &str, to: &str) -> bool {
+		if from == to || to == OBJECT {
+			return true;
+		}
+		if let Some(to_component) = to.strip_prefix('[') {
+			return match from.strip_prefix('[') {
+				Some(from_component) => is_component_assignable(self, from_component, to_component),
+				None => false,
+			};
+		}
+		if from.starts_with('[') {
+			return to == "java/lang/Cloneable" || to == "java/io/Serializable";
+		}
+		if self.is_interface(to) != Some(false) {
+			return true;
+		}
+
+		let mut visited = HashSet::new();
+		let mut current = from.to_string();
+		loop {
+			match self.super_class(&current) {
+				None => return true,
+				Some(None) => return false,
+				Some(Some(super_class)) if super_class == to => return true,
+				Some(Some(super_class)) => {
+					if !visited.insert(current) {
+						return false;
+					}
+					current = super_class;
+				}
+			}
+		}
+	}
+
+	/// The closest class both `first` and `second` extend, which is what the two types merge to.
+	///
+	/// Interfaces and unknown classes merge to `java/lang/Object`, and so do classes with cyclic super classes.
+	fn common_super_class(&self, first: &str, second: &str) -> String {
+		if self.is_assignable(first, second) && self.is_interface(second) == Some(false) {
+			return second.to_string();
+		}
+		if self.is_assignable(second, first) && self.is_interface(first) == Some(false) {
+			return first.to_string();
+		}
+		if first.starts_with('[') || second.starts_with('[') {
+			return OBJECT.to_string();
+		}
+		if self.is_interface(first) != Some(false) || self.is_interface(second) != Some(false) {
+			return OBJECT.to_string();
+		}
+
+		let mut visited = HashSet::new();
+		let mut current = first.to_string();
+		while let Some(Some(super_class)) = self.super_class(&current) {
+			if !visited.insert(current) {
+				break;
+			}
+			if self.is_assignable(second, &super_class) {
+				return super_class;
+			}
+			current = super_class;
+		}
+		OBJECT.to_string()
+	}
+}
+
+fn is_component_assignable<H: ClassHierarchy + ?Sized>(hierarchy: &H, from: &str, to: &str) -> bool {
+	match (element_name(from), element_name(to)) {
+		(Some(from), Some(to)) => hierarchy.is_assignable(from, to),
+		_ => from == to,
+	}
+}
+
+/// The name of the class or array that an array component descriptor refers to,
+/// `None` for primitives and anything malformed.
+pub(crate) fn element_name(component: &str) -> Option<&str> {
+	if component.starts_with('[') {
+		Some(component)
+	} else {
+		component.strip_prefix('L')?.strip_suffix(';')
+	}
+}
+
+/// A hierarchy built from the classes you hand it, which always knows about `java/lang/Object`.
+#[derive(Debug, Clone)]
+pub struct SimpleHierarchy {
+	classes: HashMap<String, (Option<String>, bool)>,
+}
+
+impl SimpleHierarchy {
+	pub fn new() -> Self {
+		let mut classes = HashMap::new();
+		classes.insert(OBJECT.to_string(), (None, false));
+		SimpleHierarchy {
+			classes,
+		}
+	}
+
+	pub fn insert(&mut self, name: &str, super_class: Option<&str>, is_interface: bool) -> &mut Self {
+		self.classes.insert(name.to_string(), (super_class.map(str::to_string), is_interface));
+		self
+	}
+
+	/// Adds the class, skipping it if its names can't be found in the constant pool.
+	pub fn add(&mut self, class_file: &ClassFile) -> &mut Self {
+		let cp = &class_file.constant_pool;
+		let name = match cp.class_name(class_file.this_class) {
+			Some(name) => name.decoded(),
+			None => return self,
+		};
+		let super_class = cp.class_name(class_file.super_class)
+			.map(MStrExt::decoded);
+		self.insert(&name, super_class.as_deref(), class_file.access_flags & INTERFACE != 0)
+	}
+}
+
+impl Default for SimpleHierarchy {
+	fn default() -> Self {
+		SimpleHierarchy::new()
+	}
+}
+
+impl ClassHierarchy for SimpleHierarchy {
+	fn super_class(&self, name: &str) -> Option<Option<String>> {
+		self.classes.get(name)
+			.map(|(super_class, _)| super_class.clone())
+	}
+
+	fn is_interface(&self, name: &str) -> Option<bool> {
+		self.classes.get(name)
+			.map(|&(_, is_interface)| is_interface)
+	}
+}
@@ -0,0 +1,486 @@
+//! Reading the classes and resources of a jar, including multi-release jars, where `META-INF/versions/N/`
+//! holds the classes to use instead of the base ones when running on release `N` or later,
+//! and writing jars back out with some of their entries changed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use crate::*;
+use crate::utf8::MStrExt;
+use crate::zip::{self, ZipArchive, ZipEntry, ZipError, ZipWriter};
+
+pub const MANIFEST: &str = "META-INF/MANIFEST.MF";
+
+const VERSIONS: &str = "META-INF/versions/";
+
+/// The first release that looks at `META-INF/versions/`.
+const FIRST_VERSION: u16 = 9;
+
+#[derive(Debug)]
+pub enum JarError {
+	Io(io::Error),
+	Zip(ZipError),
+	/// The entry is named like a class, but isn't one, with what was wrong with it.
+	Class(String),
+}
+
+impl From<io::Error> for JarError {
+	fn from(error: io::Error) -> Self {
+		JarError::Io(error)
+	}
+}
+
+impl From<ZipError> for JarError {
+	fn from(error: ZipError) -> Self {
+		JarError::Zip(error)
+	}
+}
+
+impl fmt::Display for JarError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			JarError::Io(error) => error.fmt(f),
+			JarError::Zip(error) => error.fmt(f),
+			JarError::Class(message) => write!(f, "invalid class: {}", message),
+		}
+	}
+}
+
+/// The attributes of a manifest, keeping their order and the case of their names.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Default)]
+pub struct Manifest {
+	/// The attributes of the main section.
+	pub main: Vec<(String, String)>,
+	/// The sections for individual entries, by the entry's name.
+	pub entries: Vec<(String, Vec<(String, String)>)>,
+}
+
+fn find<'m>(attributes: &'m [(String, String)], name: &str) -> Option<&'m str> {
+	attributes.iter()
+		.find(|(key, _)| key.eq_ignore_ascii_case(name))
+		.map(|(_, value)| value.as_str())
+}
+
+impl Manifest {
+	/// Reads the sections of a manifest, joining continuation lines and skipping lines that aren't attributes.
+	pub fn parse(data: &[u8]) -> Manifest {
+		let text = String::from_utf8_lossy(data);
+		let mut sections: Vec<Vec<(String, String)>> = vec![Vec::new()];
+		let mut lines: Vec<String> = Vec::new();
+		for line in text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)) {
+			match line.strip_prefix(' ') {
+				Some(rest) => if let Some(last) = lines.last_mut() {
+					last.push_str(rest);
+				},
+				None => lines.push(line.to_string()),
+			}
+		}
+		for line in lines {
+			if line.is_empty() {
+				if !sections.last().is_some_and(Vec::is_empty) {
+					sections.push(Vec::new());
+				}
+				continue;
+			}
+			if let Some((name, value)) = line.split_once(':') {
+				let value = value.strip_prefix(' ').unwrap_or(value);
+				sections.last_mut().unwrap().push((name.to_string(), value.to_string()));
+			}
+		}
+
+		let mut sections = sections.into_iter();
+		let main = sections.next().unwrap_or_default();
+		let entries = sections
+			.filter_map(|attributes| {
+				let name = find(&attributes, "Name")?.to_string();
+				Some((name, attributes.into_iter().filter(|(key, _)| !key.eq_ignore_ascii_case("Name")).collect()))
+			})
+			.collect();
+		Manifest {
+			main,
+			entries,
+		}
+	}
+
+	/// An attribute of the main section, ignoring the case of its name like Java does.
+	pub fn get(&self, name: &str) -> Option<&str> {
+		find(&self.main, name)
+	}
+
+	/// The attributes for the entry with the given name.
+	pub fn section(&self, entry: &str) -> Option<&[(String, String)]> {
+		self.entries.iter()
+			.find(|(name, _)| name == entry)
+			.map(|(_, attributes)| attributes.as_slice())
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Jar {
+	archive: ZipArchive,
+	multi_release: bool,
+}
+
+/// The release and the path the entry has in the base of the jar, if it's in `META-INF/versions/`.
+fn versioned(name: &str) -> Option<(u16, &str)> {
+	let rest = name.strip_prefix(VERSIONS)?;
+	let (version, path) = rest.split_once('/')?;
+	Some((version.parse().ok()?, path))
+}
+
+fn parse(data: &[u8]) -> Result<ClassFile<'static>, JarError> {
+	ClassFile::open(&mut Cursor::new(data))
+		.map_err(|error| JarError::Class(format!("{:?}", error)))
+}
+
+impl Jar {
+	pub fn new(data: Vec<u8>) -> Result<Self, ZipError> {
+		let archive = ZipArchive::new(data)?;
+		let multi_release = match archive.read_named(MANIFEST) {
+			Some(Ok(data)) => Manifest::parse(&data).get("Multi-Release").is_some_and(|value| value.eq_ignore_ascii_case("true")),
+			_ => false,
+		};
+		Ok(Jar {
+			archive,
+			multi_release,
+		})
+	}
+
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, JarError> {
+		Ok(Jar::new(fs::read(path)?)?)
+	}
+
+	pub fn archive(&self) -> &ZipArchive {
+		&self.archive
+	}
+
+	/// The names of all the entries, directories included, in the order they're in the archive.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.archive.entries().iter()
+			.map(|entry| entry.name.as_str())
+	}
+
+	pub fn read(&self, name: &str) -> Option<Result<Vec<u8>, ZipError>> {
+		self.archive.read_named(name)
+	}
+
+	pub fn manifest(&self) -> Option<Result<Manifest, ZipError>> {
+		self.read(MANIFEST)
+			.map(|data| data.map(|data| Manifest::parse(&data)))
+	}
+
+	/// Whether the manifest says `Multi-Release: true`, as `META-INF/versions/` is ignored otherwise.
+	pub fn is_multi_release(&self) -> bool {
+		self.multi_release
+	}
+
+	fn release(&self, release: Option<u16>) -> u16 {
+		if self.multi_release { release.unwrap_or(0) } else { 0 }
+	}
+
+	/// Everything but directories and classes, including the manifest.
+	pub fn resources(&self) -> impl Iterator<Item = &ZipEntry> {
+		self.archive.entries().iter()
+			.filter(|entry| !entry.is_directory() && !entry.name.ends_with(".class"))
+	}
+
+	/// The entries of the classes to use on the given release, by their path in the base of the jar, in order.
+	///
+	/// `None` only looks at the base of the jar, as does any release when the jar isn't a multi-release one.
+	pub fn class_entries(&self, release: Option<u16>) -> BTreeMap<&str, &ZipEntry> {
+		let release = self.release(release);
+		let mut chosen: BTreeMap<&str, (u16, &ZipEntry)> = BTreeMap::new();
+		for entry in self.archive.entries() {
+			if !entry.name.ends_with(".class") || entry.is_directory() {
+				continue;
+			}
+			let (version, path) = match versioned(&entry.name) {
+				Some((version, path)) if (FIRST_VERSION..=release).contains(&version) => (version, path),
+				Some(_) => continue,
+				None if entry.name.starts_with(VERSIONS) => continue,
+				None => (0, entry.name.as_str()),
+			};
+			match chosen.get(path) {
+				Some(&(existing, _)) if existing >= version => {}
+				_ => {
+					chosen.insert(path, (version, entry));
+				}
+			}
+		}
+		chosen.into_iter()
+			.map(|(path, (_, entry))| (path, entry))
+			.collect()
+	}
+
+	/// Parses every class to use on the given release, see `class_entries`,
+	/// with an error for each entry that can't be read or isn't a valid class.
+	pub fn classes(&self, release: Option<u16>) -> impl Iterator<Item = (&ZipEntry, Result<ClassFile<'static>, JarError>)> {
+		self.class_entries(release).into_values()
+			.map(move |entry| (entry, self.archive.read(entry).map_err(JarError::from).and_then(|data| parse(&data))))
+	}
+
+	/// The class with the given internal name, as it is on the given release.
+	pub fn class(&self, name: &str, release: Option<u16>) -> Option<Result<ClassFile<'static>, JarError>> {
+		let path = format!("{}.class", name);
+		let entry = (FIRST_VERSION..=self.release(release)).rev()
+			.find_map(|version| self.archive.entry(&format!("{}{}/{}", VERSIONS, version, path)))
+			.or_else(|| self.archive.entry(&path))?;
+		Some(self.archive.read(entry).map_err(JarError::from).and_then(|data| parse(&data)))
+	}
+}
+
+/// Writes the attributes of a manifest section, wrapping lines at 72 bytes like Java does.
+fn write_section(f: &mut fmt::Formatter, attributes: &[(String, String)]) -> fmt::Result {
+	for (name, value) in attributes {
+		let line = format!("{}: {}", name, value);
+		let mut rest = line.as_str();
+		let mut limit = 72;
+		while rest.len() > limit {
+			let mut end = limit;
+			while !rest.is_char_boundary(end) {
+				end -= 1;
+			}
+			f.write_str(&rest[..end])?;
+			f.write_str("\r\n ")?;
+			rest = &rest[end..];
+			// Continuation lines start with a space, which counts towards their length.
+			limit = 71;
+		}
+		f.write_str(rest)?;
+		f.write_str("\r\n")?;
+	}
+	f.write_str("\r\n")
+}
+
+impl fmt::Display for Manifest {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write_section(f, &self.main)?;
+		for (name, attributes) in &self.entries {
+			let mut section = vec![("Name".to_string(), name.clone())];
+			section.extend(attributes.iter().cloned());
+			write_section(f, &section)?;
+		}
+		Ok(())
+	}
+}
+
+/// What to do with the signature of a signed jar, which no longer holds once its entries change.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Signatures {
+	/// Copy the signature files and the digests in the manifest as they are.
+	Keep,
+	/// Drop the signature files, and the digests from the manifest.
+	Strip,
+	/// Drop the signature files, which can't be redone without the signer's key, and put a fresh `SHA-256-Digest`
+	/// for every entry in the manifest, which is what `jarsigner` would start from.
+	Regenerate,
+}
+
+/// `.SF`, `.RSA`, `.DSA` and `.EC` files, and `SIG-*` ones, directly under `META-INF/`.
+pub fn is_signature_file(name: &str) -> bool {
+	let name = match name.strip_prefix("META-INF/") {
+		Some(name) if !name.contains('/') => name.to_ascii_uppercase(),
+		_ => return false,
+	};
+	name.starts_with("SIG-") || [".SF", ".RSA", ".DSA", ".EC"].iter().any(|extension| name.ends_with(extension))
+}
+
+fn is_digest(name: &str) -> bool {
+	name.to_ascii_uppercase().ends_with("-DIGEST")
+}
+
+/// Whether the entry goes before the others in a reproducible jar, as `JarInputStream` expects the manifest
+/// to be the first or second entry.
+fn is_manifest(name: &str) -> bool {
+	name == "META-INF/" || name == MANIFEST
+}
+
+/// Writes a jar, copying the entries of another one unless they're replaced or removed.
+///
+/// Entries that are copied keep their compressed data byte for byte.
+#[derive(Debug, Clone)]
+pub struct JarWriter<'j> {
+	jar: Option<&'j Jar>,
+	/// The entries that are added or replaced, in the order they were put.
+	entries: Vec<(String, Vec<u8>)>,
+	removed: Vec<String>,
+	deterministic: bool,
+	signatures: Signatures,
+}
+
+impl<'j> JarWriter<'j> {
+	/// A writer for a jar with nothing in it.
+	pub fn new() -> Self {
+		JarWriter {
+			jar: None,
+			entries: Vec::new(),
+			removed: Vec::new(),
+			deterministic: false,
+			signatures: Signatures::Keep,
+		}
+	}
+
+	/// A writer starting out with the entries of the given jar.
+	pub fn from_jar(jar: &'j Jar) -> Self {
+		JarWriter {
+			jar: Some(jar),
+			..JarWriter::new()
+		}
+	}
+
+	/// Adds an entry, or replaces the one with the same name.
+	pub fn put(&mut self, name: &str, data: Vec<u8>) -> &mut Self {
+		self.removed.retain(|removed| removed != name);
+		match self.entries.iter_mut().find(|(existing, _)| existing == name) {
+			Some(entry) => entry.1 = data,
+			None => self.entries.push((name.to_string(), data)),
+		}
+		self
+	}
+
+	/// Adds the class, or replaces it, under the path its name gives.
+	pub fn put_class(&mut self, class_file: &ClassFile) -> Result<&mut Self, JarError> {
+		let cp = &class_file.constant_pool;
+		let name = cp.class_name(class_file.this_class)
+			.map(MStrExt::decoded)
+			.ok_or_else(|| JarError::Class("the class has no name".to_string()))?;
+		let mut data = Vec::new();
+		class_file.to_bytes(&mut data)
+			.map_err(|error| JarError::Class(format!("{:?}", error)))?;
+		Ok(self.put(&format!("{}.class", name), data))
+	}
+
+	pub fn remove(&mut self, name: &str) -> &mut Self {
+		self.entries.retain(|(existing, _)| existing != name);
+		self.removed.push(name.to_string());
+		self
+	}
+
+	/// Whether to write the same bytes for the same entries every time, for reproducible builds,
+	/// by giving every entry the same time, dropping extra fields, and sorting entries by name, manifest first.
+	pub fn deterministic(&mut self, deterministic: bool) -> &mut Self {
+		self.deterministic = deterministic;
+		self
+	}
+
+	pub fn signatures(&mut self, signatures: Signatures) -> &mut Self {
+		self.signatures = signatures;
+		self
+	}
+
+	fn is_removed(&self, name: &str) -> bool {
+		self.removed.iter().any(|removed| removed == name)
+			|| self.signatures != Signatures::Keep && is_signature_file(name)
+	}
+
+	/// The data of an entry, as it'll be written.
+	fn data(&self, name: &str) -> Option<Result<Vec<u8>, ZipError>> {
+		match self.entries.iter().find(|(existing, _)| existing == name) {
+			Some((_, data)) => Some(Ok(data.clone())),
+			None => self.jar?.read(name),
+		}
+	}
+
+	/// The manifest with the digests dropped or recomputed, if that's what's wanted.
+	fn manifest(&self, names: &[&str]) -> Result<Option<Vec<u8>>, ZipError> {
+		if self.signatures == Signatures::Keep {
+			return Ok(None);
+		}
+		let mut manifest = match self.data(MANIFEST) {
+			Some(data) => Manifest::parse(&data?),
+			None if self.signatures == Signatures::Strip => return Ok(None),
+			None => Manifest {
+				main: vec![("Manifest-Version".to_string(), "1.0".to_string())],
+				entries: Vec::new(),
+			},
+		};
+		for (_, attributes) in &mut manifest.entries {
+			attributes.retain(|(name, _)| !is_digest(name));
+		}
+		manifest.entries.retain(|(name, attributes)| !attributes.is_empty() && names.contains(&name.as_str()));
+
+		if self.signatures == Signatures::Regenerate {
+			for &name in names {
+				if name.ends_with('/') || is_manifest(name) {
+					continue;
+				}
+				let digest = ("SHA-256-Digest".to_string(), STANDARD.encode(Sha256::digest(self.data(name).unwrap()?)));
+				match manifest.entries.iter_mut().find(|(existing, _)| existing == name) {
+					Some((_, attributes)) => attributes.push(digest),
+					None => manifest.entries.push((name.to_string(), vec![digest])),
+				}
+			}
+		}
+		Ok(Some(manifest.to_string().into_bytes()))
+	}
+
+	pub fn write<W: Write>(&self, output: W) -> Result<W, JarError> {
+		let mut names: Vec<&str> = Vec::new();
+		if let Some(jar) = self.jar {
+			names.extend(jar.names().filter(|name| !self.is_removed(name)));
+		}
+		for (name, _) in &self.entries {
+			if !names.contains(&name.as_str()) {
+				names.push(name);
+			}
+		}
+		if self.signatures == Signatures::Regenerate && !names.contains(&MANIFEST) {
+			names.insert(0, MANIFEST);
+		}
+		if self.deterministic {
+			names.sort_by_key(|&name| (!is_manifest(name), name != "META-INF/", name));
+		}
+
+		let manifest = self.manifest(&names)?;
+		let time = if self.deterministic {
+			zip::EPOCH
+		} else {
+			let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+			zip::dos_time(now)
+		};
+
+		let mut writer = ZipWriter::new(output);
+		for name in names {
+			let original = self.jar.and_then(|jar| jar.archive.entry(name));
+			let replaced = self.entries.iter().find(|(existing, _)| existing == name);
+			match (original, replaced) {
+				(Some(entry), None) if !(name == MANIFEST && manifest.is_some()) => {
+					let raw = self.jar.unwrap().archive.raw(entry)?;
+					let mut entry = entry.clone();
+					if self.deterministic {
+						entry.time = time.0;
+						entry.date = time.1;
+						entry.extra.clear();
+					}
+					writer.write_raw(&entry, raw)?;
+				}
+				_ => {
+					let data = match &manifest {
+						Some(manifest) if name == MANIFEST => manifest.clone(),
+						_ => self.data(name).unwrap()?,
+					};
+					writer.write(name, &data, time)?;
+				}
+			}
+		}
+		Ok(writer.finish()?)
+	}
+
+	pub fn to_vec(&self) -> Result<Vec<u8>, JarError> {
+		self.write(Vec::new())
+	}
+}
+
+impl Default for JarWriter<'_> {
+	fn default() -> Self {
+		JarWriter::new()
+	}
+}
@@ -0,0 +1,494 @@
+//! Reading the jimage format JDKs since 9 keep their classes in, as `lib/modules`.
+//!
+//! An image starts with a header, followed by the index: a redirect table and a table of location offsets,
+//! which together make a perfect hash from resource names to locations, then the locations' attributes,
+//! and a string table the attributes point into. The resources themselves follow the index,
+//! possibly compressed by `jlink --compress`, with zip, string sharing (`compact-cp`), or both.
+//!
+//! Everything is in the byte order of the machine that made the image, which the magic gives away.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use crate::*;
+
+pub const MAGIC: u32 = 0xCAFE_DADA;
+pub const MAJOR_VERSION: u16 = 1;
+pub const MINOR_VERSION: u16 = 0;
+
+/// What a compressed resource starts with, once for every time it was compressed.
+pub const COMPRESSED_MAGIC: u32 = 0xCAFE_FAFA;
+
+const HEADER_SIZE: usize = 28;
+const COMPRESSED_HEADER_SIZE: usize = 29;
+const HASH_MULTIPLIER: i32 = 0x0100_0193;
+
+const ATTRIBUTE_END: u8 = 0;
+const ATTRIBUTE_MODULE: usize = 1;
+const ATTRIBUTE_PARENT: usize = 2;
+const ATTRIBUTE_BASE: usize = 3;
+const ATTRIBUTE_EXTENSION: usize = 4;
+const ATTRIBUTE_OFFSET: usize = 5;
+const ATTRIBUTE_COMPRESSED: usize = 6;
+const ATTRIBUTE_UNCOMPRESSED: usize = 7;
+const ATTRIBUTE_COUNT: usize = 8;
+
+/// The constant pool tags string sharing replaces `Utf8` entries with.
+const EXTERNALIZED_STRING: u8 = 23;
+const EXTERNALIZED_STRING_DESCRIPTOR: u8 = 25;
+
+#[derive(Debug)]
+pub enum JImageError {
+	Io(io::Error),
+	BadMagic(u32),
+	UnsupportedVersion {
+		major: u16,
+		minor: u16,
+	},
+	/// Something in the image points past its end.
+	Truncated,
+	/// The attributes at the given offset can't be decoded.
+	BadLocation(u32),
+	UnknownDecompressor(String),
+	/// The resource with the given name couldn't be decompressed.
+	Decompression(String),
+	/// The resource is named like a class, but isn't one, with what was wrong with it.
+	Class(String),
+}
+
+impl From<io::Error> for JImageError {
+	fn from(error: io::Error) -> Self {
+		JImageError::Io(error)
+	}
+}
+
+impl fmt::Display for JImageError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			JImageError::Io(error) => error.fmt(f),
+			JImageError::BadMagic(magic) => write!(f, "bad magic {:#010x}", magic),
+			JImageError::UnsupportedVersion { major, minor } => write!(f, "unsupported version {}.{}", major, minor),
+			JImageError::Truncated => f.write_str("image is truncated"),
+			JImageError::BadLocation(offset) => write!(f, "bad location attributes at {}", offset),
+			JImageError::UnknownDecompressor(name) => write!(f, "unknown decompressor {}", name),
+			JImageError::Decompression(name) => write!(f, "{} can't be decompressed", name),
+			JImageError::Class(message) => write!(f, "invalid class: {}", message),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct Header {
+	pub major_version: u16,
+	pub minor_version: u16,
+	pub flags: u32,
+	pub resource_count: u32,
+	/// The length of the redirect table, which is also the length of the table of location offsets.
+	pub table_length: u32,
+	pub locations_size: u32,
+	pub strings_size: u32,
+}
+
+/// Where a resource is, and what it's called, split the way the image stores the name.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Location {
+	pub module: String,
+	pub parent: String,
+	pub base: String,
+	pub extension: String,
+	/// Where the content is, relative to the end of the index.
+	pub offset: u64,
+	/// 0 if the content isn't compressed.
+	pub compressed_size: u64,
+	pub uncompressed_size: u64,
+}
+
+impl Location {
+	/// The full name, such as `/java.base/java/lang/Object.class`.
+	pub fn name(&self) -> String {
+		let mut name = String::new();
+		if !self.module.is_empty() {
+			name.push('/');
+			name.push_str(&self.module);
+			name.push('/');
+		}
+		if !self.parent.is_empty() {
+			name.push_str(&self.parent);
+			name.push('/');
+		}
+		name.push_str(&self.base);
+		if !self.extension.is_empty() {
+			name.push('.');
+			name.push_str(&self.extension);
+		}
+		name
+	}
+}
+
+/// The hash the image's perfect hash table is built on, which is FNV-1 with the sign bit cleared.
+fn hash(name: &str, seed: i32) -> i32 {
+	let hash = name.bytes()
+		.fold(seed, |hash, byte| hash.wrapping_mul(HASH_MULTIPLIER) ^ byte as i32);
+	hash & 0x7FFF_FFFF
+}
+
+/// The variable length ints string sharing uses, with the length in the top bits of the first byte, if its top bit is set.
+fn compressed_int(data: &[u8], position: &mut usize) -> Option<u32> {
+	let header = *data.get(*position)?;
+	let (length, mut value) = if header & 0x80 != 0 {
+		((header >> 5 & 0x3) as usize, (header & 0x1F) as u32)
+	} else {
+		(4, header as u32)
+	};
+	for i in 1..length {
+		value = value << 8 | *data.get(*position + i)? as u32;
+	}
+	*position += length.max(1);
+	Some(value)
+}
+
+fn parse_class(data: Vec<u8>) -> Result<ClassFile<'static>, JImageError> {
+	ClassFile::open(&mut Cursor::new(data))
+		.map_err(|error| JImageError::Class(format!("{:?}", error)))
+}
+
+#[derive(Debug, Clone)]
+pub struct JImage {
+	data: Vec<u8>,
+	big_endian: bool,
+	header: Header,
+}
+
+impl JImage {
+	pub fn new(data: Vec<u8>) -> Result<Self, JImageError> {
+		if data.len() < HEADER_SIZE {
+			return Err(JImageError::Truncated);
+		}
+		let magic = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+		let big_endian = match magic {
+			MAGIC => false,
+			_ if magic.swap_bytes() == MAGIC => true,
+			_ => return Err(JImageError::BadMagic(magic)),
+		};
+		let mut image = JImage {
+			data,
+			big_endian,
+			header: Header {
+				major_version: 0,
+				minor_version: 0,
+				flags: 0,
+				resource_count: 0,
+				table_length: 0,
+				locations_size: 0,
+				strings_size: 0,
+			},
+		};
+		let version = image.u32(4)?;
+		image.header = Header {
+			major_version: (version >> 16) as u16,
+			minor_version: version as u16,
+			flags: image.u32(8)?,
+			resource_count: image.u32(12)?,
+			table_length: image.u32(16)?,
+			locations_size: image.u32(20)?,
+			strings_size: image.u32(24)?,
+		};
+		let header = image.header;
+		if header.major_version != MAJOR_VERSION || header.minor_version > MINOR_VERSION {
+			return Err(JImageError::UnsupportedVersion {
+				major: header.major_version,
+				minor: header.minor_version,
+			});
+		}
+		if image.index_size() > image.data.len() as u64 {
+			return Err(JImageError::Truncated);
+		}
+		Ok(image)
+	}
+
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, JImageError> {
+		JImage::new(fs::read(path)?)
+	}
+
+	pub fn header(&self) -> &Header {
+		&self.header
+	}
+
+	pub fn is_big_endian(&self) -> bool {
+		self.big_endian
+	}
+
+	fn u32(&self, position: usize) -> Result<u32, JImageError> {
+		let bytes = self.data.get(position..position + 4).ok_or(JImageError::Truncated)?;
+		let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+		Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+	}
+
+	fn redirect_start(&self) -> usize {
+		HEADER_SIZE
+	}
+
+	fn offsets_start(&self) -> usize {
+		self.redirect_start() + self.header.table_length as usize * 4
+	}
+
+	fn locations_start(&self) -> usize {
+		self.offsets_start() + self.header.table_length as usize * 4
+	}
+
+	fn strings_start(&self) -> usize {
+		self.locations_start() + self.header.locations_size as usize
+	}
+
+	/// The size of the header and the index, after which the resources start.
+	pub fn index_size(&self) -> u64 {
+		self.strings_start() as u64 + self.header.strings_size as u64
+	}
+
+	/// The string at the given offset into the string table, as modified UTF-8.
+	pub fn string_bytes(&self, offset: u32) -> Result<&[u8], JImageError> {
+		let start = self.strings_start() + offset as usize;
+		let end = self.index_size() as usize;
+		let strings = self.data.get(start..end).ok_or(JImageError::Truncated)?;
+		let length = strings.iter().position(|&byte| byte == 0).ok_or(JImageError::Truncated)?;
+		Ok(&strings[..length])
+	}
+
+	pub fn string(&self, offset: u32) -> Result<String, JImageError> {
+		Ok(utf8::decode(self.string_bytes(offset)?).into_owned())
+	}
+
+	/// Decodes the location whose attributes are at the given offset into the locations.
+	pub fn location_at(&self, offset: u32) -> Result<Location, JImageError> {
+		let bad = || JImageError::BadLocation(offset);
+		let locations = &self.data[self.locations_start()..self.strings_start()];
+		let mut attributes = [0u64; ATTRIBUTE_COUNT];
+		let mut position = offset as usize;
+		loop {
+			let byte = *locations.get(position).ok_or_else(bad)?;
+			let kind = byte >> 3;
+			if kind == ATTRIBUTE_END {
+				break;
+			}
+			let kind = kind as usize;
+			let length = (byte & 0x7) as usize + 1;
+			let value = locations.get(position + 1..position + 1 + length).ok_or_else(bad)?;
+			if kind >= ATTRIBUTE_COUNT {
+				return Err(bad());
+			}
+			attributes[kind] = value.iter().fold(0, |value, &byte| value << 8 | byte as u64);
+			position += 1 + length;
+		}
+
+		let string = |kind: usize| -> Result<String, JImageError> {
+			self.string(u32::try_from(attributes[kind]).map_err(|_| bad())?)
+		};
+		Ok(Location {
+			module: string(ATTRIBUTE_MODULE)?,
+			parent: string(ATTRIBUTE_PARENT)?,
+			base: string(ATTRIBUTE_BASE)?,
+			extension: string(ATTRIBUTE_EXTENSION)?,
+			offset: attributes[ATTRIBUTE_OFFSET],
+			compressed_size: attributes[ATTRIBUTE_COMPRESSED],
+			uncompressed_size: attributes[ATTRIBUTE_UNCOMPRESSED],
+		})
+	}
+
+	/// Every location in the image, in the order of the table of offsets, which is effectively random.
+	pub fn locations(&self) -> impl Iterator<Item = Result<Location, JImageError>> + '_ {
+		(0..self.header.table_length as usize)
+			.map(move |i| self.location_at(self.u32(self.offsets_start() + i * 4)?))
+	}
+
+	/// Looks up the location with the given full name, such as `/java.base/java/lang/Object.class`.
+	pub fn find(&self, name: &str) -> Option<Location> {
+		let length = self.header.table_length as i32;
+		if length == 0 {
+			return None;
+		}
+		let redirect = self.u32(self.redirect_start() + (hash(name, HASH_MULTIPLIER) % length) as usize * 4).ok()? as i32;
+		let index = match redirect {
+			0 => return None,
+			// `!redirect` is `-redirect - 1`, without overflowing on `i32::MIN`.
+			redirect if redirect < 0 => !redirect,
+			seed => hash(name, seed) % length,
+		};
+		let offset = self.u32(self.offsets_start() + index as usize * 4).ok()?;
+		self.location_at(offset).ok()
+			.filter(|location| location.name() == name)
+	}
+
+	/// The content of the resource, decompressed.
+	pub fn read(&self, location: &Location) -> Result<Vec<u8>, JImageError> {
+		let start = self.index_size().checked_add(location.offset).ok_or(JImageError::Truncated)?;
+		let compressed = location.compressed_size != 0;
+		let size = if compressed { location.compressed_size } else { location.uncompressed_size };
+		let end = start.checked_add(size)
+			.filter(|&end| end <= self.data.len() as u64)
+			.ok_or(JImageError::Truncated)?;
+		let content = self.data[start as usize..end as usize].to_vec();
+		if !compressed {
+			return Ok(content);
+		}
+
+		let name = location.name();
+		let failed = || JImageError::Decompression(name.clone());
+		let mut content = content;
+		while content.len() >= COMPRESSED_HEADER_SIZE && self.u32_in(&content, 0) == COMPRESSED_MAGIC {
+			let uncompressed_size = self.u64_in(&content, 12);
+			let decompressor = self.string(self.u32_in(&content, 20))?;
+			let data = &content[COMPRESSED_HEADER_SIZE..];
+			content = match decompressor.as_str() {
+				// The header says how big it'll be, so don't inflate any further than that.
+				"zip" => {
+					let limit = usize::try_from(uncompressed_size).unwrap_or(usize::MAX);
+					miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, limit).map_err(|_| failed())?
+				}
+				"compact-cp" => self.expand_strings(data).ok_or_else(failed)?,
+				_ => return Err(JImageError::UnknownDecompressor(decompressor)),
+			};
+			if content.len() as u64 != uncompressed_size {
+				return Err(failed());
+			}
+		}
+		Ok(content)
+	}
+
+	fn u32_in(&self, data: &[u8], position: usize) -> u32 {
+		let bytes = [data[position], data[position + 1], data[position + 2], data[position + 3]];
+		if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+	}
+
+	fn u64_in(&self, data: &[u8], position: usize) -> u64 {
+		let (first, second) = (self.u32_in(data, position) as u64, self.u32_in(data, position + 4) as u64);
+		if self.big_endian { first << 32 | second } else { second << 32 | first }
+	}
+
+	/// Puts back the strings string sharing moved out of the constant pool and into the string table.
+	fn expand_strings(&self, data: &[u8]) -> Option<Vec<u8>> {
+		fn push_utf8(output: &mut Vec<u8>, value: &[u8]) -> Option<()> {
+			output.push(CONSTANT_UTF8_TAG);
+			output.extend_from_slice(&u16::try_from(value.len()).ok()?.to_be_bytes());
+			output.extend_from_slice(value);
+			Some(())
+		}
+
+		// The magic and version, then the constant pool count.
+		let mut output = data.get(..10)?.to_vec();
+		let count = u16::from_be_bytes([data[8], data[9]]);
+		let mut position = 10;
+		let mut i = 1;
+		while i < count {
+			let tag = *data.get(position)?;
+			position += 1;
+			match tag {
+				CONSTANT_UTF8_TAG => {
+					let length = u16::from_be_bytes([*data.get(position)?, *data.get(position + 1)?]) as usize;
+					output.push(tag);
+					output.extend_from_slice(data.get(position..position + 2 + length)?);
+					position += 2 + length;
+				}
+				EXTERNALIZED_STRING => {
+					let index = compressed_int(data, &mut position)?;
+					push_utf8(&mut output, self.string_bytes(index).ok()?)?;
+				}
+				EXTERNALIZED_STRING_DESCRIPTOR => {
+					// A descriptor with its classes taken out, each of which is a package and a simple name.
+					let descriptor = self.string_bytes(compressed_int(data, &mut position)?).ok()?;
+					let length = compressed_int(data, &mut position)? as usize;
+					let indices = data.get(position..position + length)?;
+					position += length;
+
+					let mut indices_position = 0;
+					let mut next = || compressed_int(indices, &mut indices_position);
+					let mut value = Vec::new();
+					for &byte in descriptor {
+						value.push(byte);
+						if byte == b'L' {
+							let package = self.string_bytes(next()?).ok()?;
+							if !package.is_empty() {
+								value.extend_from_slice(package);
+								value.push(b'/');
+							}
+							value.extend_from_slice(self.string_bytes(next()?).ok()?);
+						}
+					}
+					push_utf8(&mut output, &value)?;
+				}
+				_ => {
+					let size = match tag {
+						CONSTANT_CLASS_TAG | CONSTANT_STRING_TAG | CONSTANT_METHOD_TYPE_TAG | CONSTANT_MODULE_TAG | CONSTANT_PACKAGE_TAG => 2,
+						CONSTANT_METHOD_HANDLE_TAG => 3,
+						CONSTANT_INTEGER_TAG | CONSTANT_FLOAT_TAG | CONSTANT_FIELDREF_TAG | CONSTANT_METHODREF_TAG | CONSTANT_INTERFACE_METHODREF_TAG
+						| CONSTANT_NAME_AND_TYPE_TAG | CONSTANT_DYNAMIC_TAG | CONSTANT_INVOKE_DYNAMIC_TAG => 4,
+						CONSTANT_LONG_TAG | CONSTANT_DOUBLE_TAG => {
+							i += 1;
+							8
+						}
+						_ => return None,
+					};
+					output.push(tag);
+					output.extend_from_slice(data.get(position..position + size)?);
+					position += size;
+				}
+			}
+			i += 1;
+		}
+		output.extend_from_slice(&data[position..]);
+		Some(output)
+	}
+
+	/// Reads the resource with the given full name, `None` if there isn't one.
+	pub fn read_named(&self, name: &str) -> Option<Result<Vec<u8>, JImageError>> {
+		self.find(name)
+			.map(|location| self.read(&location))
+	}
+
+	/// The module the package is in, going by the image's `/packages/` entries.
+	pub fn package_module(&self, package: &str) -> Option<String> {
+		let package = package.replace('/', ".");
+		let content = self.read_named(&format!("/packages/{}", package))?.ok()?;
+		// Pairs of whether the package is empty in that module, and the module's name.
+		content.chunks_exact(8)
+			.find(|pair| self.u32_in(pair, 0) == 0)
+			.and_then(|pair| self.string(self.u32_in(pair, 4)).ok())
+	}
+
+	/// The names of the modules with resources in the image.
+	pub fn modules(&self) -> Vec<String> {
+		let mut modules: Vec<String> = self.locations()
+			.filter_map(Result::ok)
+			.map(|location| location.module)
+			.filter(|module| !module.is_empty() && module != "modules" && module != "packages")
+			.collect();
+		modules.sort();
+		modules.dedup();
+		modules
+	}
+
+	/// The classes in the given module, or in every module, leaving out `module-info`,
+	/// along with their locations.
+	pub fn classes<'i>(&'i self, module: Option<&'i str>)
+		-> impl Iterator<Item = (Location, Result<ClassFile<'static>, JImageError>)> + 'i {
+		self.locations()
+			.filter_map(Result::ok)
+			.filter(move |location| {
+				location.extension == "class" && location.base != MODULE_INFO
+					&& location.module != "modules" && location.module != "packages"
+					&& (module.is_none() || module == Some(location.module.as_str()))
+			})
+			.map(move |location| {
+				let class_file = self.read(&location).and_then(parse_class);
+				(location, class_file)
+			})
+	}
+
+	/// The class with the given internal name from the given module.
+	pub fn class(&self, module: &str, name: &str) -> Option<Result<ClassFile<'static>, JImageError>> {
+		let data = self.read_named(&format!("/{}/{}.class", module, name))?;
+		Some(data.and_then(parse_class))
+	}
+}
@@ -0,0 +1,208 @@
+//! Reading the jmods JDKs ship their modules as, for jlink to put together into runtime images.
+//!
+//! A jmod is a zip archive behind a four byte header, with its entries split into sections by their first directory,
+//! such as `classes/` for the module's classes and its `module-info.class`, `lib/` for native libraries and `conf/` for configuration.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use crate::*;
+use crate::attr::{Module, ModuleMainClass, ModulePackages};
+use crate::module::ModuleDescriptor;
+use crate::utf8::MStrExt;
+use crate::zip::{ZipArchive, ZipEntry, ZipError};
+
+/// `JM` followed by the major and minor version, 1.0 being the only one there's been.
+pub const MAGIC: [u8; 4] = [b'J', b'M', 1, 0];
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Ord, PartialOrd)]
+pub enum Section {
+	Classes,
+	Config,
+	HeaderFiles,
+	LegalNotices,
+	ManPages,
+	NativeLibraries,
+	NativeCommands,
+}
+
+impl Section {
+	pub const ALL: [Section; 7] = [
+		Section::Classes,
+		Section::Config,
+		Section::HeaderFiles,
+		Section::LegalNotices,
+		Section::ManPages,
+		Section::NativeLibraries,
+		Section::NativeCommands,
+	];
+
+	/// The directory the section's entries are in, slash included.
+	pub fn prefix(self) -> &'static str {
+		match self {
+			Section::Classes => "classes/",
+			Section::Config => "conf/",
+			Section::HeaderFiles => "include/",
+			Section::LegalNotices => "legal/",
+			Section::ManPages => "man/",
+			Section::NativeLibraries => "lib/",
+			Section::NativeCommands => "bin/",
+		}
+	}
+
+	/// The section an entry is in, and its name within it.
+	pub fn of(name: &str) -> Option<(Section, &str)> {
+		Section::ALL.iter()
+			.find_map(|&section| Some((section, name.strip_prefix(section.prefix())?)))
+	}
+}
+
+#[derive(Debug)]
+pub enum JmodError {
+	Io(io::Error),
+	/// The data doesn't start with `MAGIC`.
+	NotAJmod,
+	Zip(ZipError),
+	/// The entry is named like a class, but isn't one, with what was wrong with it.
+	Class(String),
+	/// There's no `module-info.class`, or it doesn't have a `Module` attribute.
+	NoModule,
+}
+
+impl From<io::Error> for JmodError {
+	fn from(error: io::Error) -> Self {
+		JmodError::Io(error)
+	}
+}
+
+impl From<ZipError> for JmodError {
+	fn from(error: ZipError) -> Self {
+		JmodError::Zip(error)
+	}
+}
+
+impl fmt::Display for JmodError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			JmodError::Io(error) => error.fmt(f),
+			JmodError::NotAJmod => f.write_str("not a jmod"),
+			JmodError::Zip(error) => error.fmt(f),
+			JmodError::Class(message) => write!(f, "invalid class: {}", message),
+			JmodError::NoModule => f.write_str("no module descriptor"),
+		}
+	}
+}
+
+fn parse(data: &[u8]) -> Result<ClassFile<'static>, JmodError> {
+	ClassFile::open(&mut Cursor::new(data))
+		.map_err(|error| JmodError::Class(format!("{:?}", error)))
+}
+
+/// A `module-info.class`, along with the attributes that describe the module.
+#[derive(Debug, Clone)]
+pub struct ModuleInfo {
+	pub class_file: ClassFile<'static>,
+	pub module: Module<'static>,
+	pub packages: Option<ModulePackages<'static>>,
+	pub main_class: Option<ModuleMainClass<'static>>,
+}
+
+impl ModuleInfo {
+	/// `None` if the class doesn't have a `Module` attribute.
+	pub fn new(class_file: ClassFile<'static>) -> Option<Self> {
+		let cp = &class_file.constant_pool;
+		let module = class_file.attributes.get::<Module>(cp)?;
+		let packages = class_file.attributes.get::<ModulePackages>(cp);
+		let main_class = class_file.attributes.get::<ModuleMainClass>(cp);
+		Some(ModuleInfo {
+			class_file,
+			module,
+			packages,
+			main_class,
+		})
+	}
+
+	pub fn name(&self) -> Option<String> {
+		let cp = &self.class_file.constant_pool;
+		let info = cp.index(self.module.module_name_index)?;
+		cp.utf8(info.name_index).map(MStrExt::decoded)
+	}
+
+	/// The packages in the module, with slashes, as the `ModulePackages` attribute lists them.
+	pub fn package_names(&self) -> Vec<String> {
+		let cp = &self.class_file.constant_pool;
+		self.packages.iter()
+			.flat_map(|packages| packages.packages.iter())
+			.filter_map(|&index| {
+				let info = cp.index(index)?;
+				cp.utf8(info.name_index).map(MStrExt::decoded)
+			})
+			.collect()
+	}
+
+	/// The same information, with names in place of indices, see `ModuleDescriptor::from_class_file`.
+	pub fn descriptor(&self) -> Option<ModuleDescriptor> {
+		ModuleDescriptor::from_class_file(&self.class_file)
+	}
+
+	/// The internal name of the main class, if there's a `ModuleMainClass` attribute.
+	pub fn main_class_name(&self) -> Option<String> {
+		let cp = &self.class_file.constant_pool;
+		cp.class_name(self.main_class.as_ref()?.main_class_index).map(MStrExt::decoded)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Jmod {
+	archive: ZipArchive,
+}
+
+impl Jmod {
+	pub fn new(data: Vec<u8>) -> Result<Self, JmodError> {
+		if !data.starts_with(&MAGIC) {
+			return Err(JmodError::NotAJmod);
+		}
+		Ok(Jmod {
+			archive: ZipArchive::new(data)?,
+		})
+	}
+
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, JmodError> {
+		Jmod::new(fs::read(path)?)
+	}
+
+	pub fn archive(&self) -> &ZipArchive {
+		&self.archive
+	}
+
+	/// The files in the section, by their names within it, in the order they're in the archive.
+	pub fn entries(&self, section: Section) -> impl Iterator<Item = (&str, &ZipEntry)> {
+		self.archive.entries().iter()
+			.filter(|entry| !entry.is_directory())
+			.filter_map(move |entry| Some((entry.name.strip_prefix(section.prefix())?, entry)))
+	}
+
+	pub fn read(&self, section: Section, name: &str) -> Option<Result<Vec<u8>, ZipError>> {
+		self.archive.read_named(&format!("{}{}", section.prefix(), name))
+	}
+
+	/// The module's classes, leaving out `module-info.class`.
+	pub fn classes(&self) -> impl Iterator<Item = (&ZipEntry, Result<ClassFile<'static>, JmodError>)> {
+		self.entries(Section::Classes)
+			.filter(|(name, _)| matches!(name.strip_suffix(".class"), Some(class) if class != MODULE_INFO))
+			.map(move |(_, entry)| (entry, self.archive.read(entry).map_err(JmodError::from).and_then(|data| parse(&data))))
+	}
+
+	/// The class with the given internal name.
+	pub fn class(&self, name: &str) -> Option<Result<ClassFile<'static>, JmodError>> {
+		let data = self.read(Section::Classes, &format!("{}.class", name))?;
+		Some(data.map_err(JmodError::from).and_then(|data| parse(&data)))
+	}
+
+	pub fn module_info(&self) -> Result<ModuleInfo, JmodError> {
+		let data = self.read(Section::Classes, &format!("{}.class", MODULE_INFO)).ok_or(JmodError::NoModule)??;
+		ModuleInfo::new(parse(&data)?).ok_or(JmodError::NoModule)
+	}
+}
@@ -2,6 +2,7 @@ extern crate binform;
 extern crate mutf8;
 
 use std::convert::TryInto;
+use std::fmt;
 use std::marker::PhantomData;
 
 use binform::*;
@@ -15,12 +16,43 @@ use crate::attr::Attribute;
 use crate::ops::*;
 
 pub mod ops;
+pub mod analysis;
 pub mod attr;
+pub mod bytecode;
+pub mod cfg;
+pub mod classpath;
+pub mod compact;
+pub mod compat;
 pub mod macros;
+pub mod descriptor;
+pub mod diff;
+pub mod dot;
+pub mod eval;
+pub mod frames;
+pub mod hierarchy;
+pub mod jar;
+pub mod jimage;
+pub mod jmod;
+pub mod mapping;
+pub mod maxs;
+pub mod module;
+pub mod registry;
+pub mod remap;
+pub mod resolve;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod utf8;
+pub mod verify;
+pub mod zip;
 
 const MAGIC: u32 = 0xCAFE_BABE;
 
+/// The name of the class a module is described by, kept in `module-info.class`.
+pub const MODULE_INFO: &str = "module-info";
+
 def! {
+	/// Reading a class with `FromBytes::from_bytes` is lenient, the same as `ClassFile::open_lenient`,
+	/// so use `ClassFile::open` to have malformed strings rejected.
 	struct ClassFile('a) {
 		#[binform(before(expect(ty = "u32", value = "MAGIC")))]
 		minor_version: u16,
@@ -40,7 +72,26 @@ def! {
 }
 
 impl<'a> ClassFile<'a> {
-	pub fn open<I: Read>(input: &mut I) -> ReadResult<ClassFile> {
+	/// Parses the class, rejecting it if any of its strings aren't valid modified UTF-8.
+	pub fn open<I: Read>(input: &mut I) -> ReadResult<ClassFile<'a>> {
+		let class_file = ClassFile::from_bytes(input)?;
+		for (i, entry) in class_file.constant_pool.entries.iter().enumerate() {
+			if let CPEntry::UTF8(info) = entry {
+				if let Some(raw) = info.raw() {
+					let error = utf8::validate(raw).unwrap_err();
+					let message = format!("Constant pool entry #{}: {}", i + 1, error);
+					return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message).into());
+				}
+			}
+		}
+		Ok(class_file)
+	}
+
+	/// Parses the class, accepting malformed strings.
+	///
+	/// Those are repaired for the sake of `UTF8Info::data`, but the original bytes are kept,
+	/// and written back out unchanged, see `UTF8Info::raw`.
+	pub fn open_lenient<I: Read>(input: &mut I) -> ReadResult<ClassFile<'a>> {
 		ClassFile::from_bytes(input)
 	}
 }
@@ -52,11 +103,27 @@ def! {
 	}
 }
 
+/// `Long` and `Double` entries are followed by a `CPEntry::Unusable`,
+/// so the position of an entry always lines up with its index.
 fn read_constant_pool<'a, I: Read, BO: ByteOrder, L>(input: &mut I) -> ReadResult<Vec<CPEntry<'a>>> {
-	let len = input.read_u16::<BO>()? - 1;
-	let mut result = Vec::with_capacity(len as usize);
-	for _ in 0..len {
-		result.push(CPEntry::from_bytes(input)?);
+	let len = input.read_u16::<BO>()?.saturating_sub(1) as usize;
+	let mut result = Vec::with_capacity(len);
+	while result.len() < len {
+		let entry = CPEntry::from_bytes(input)?;
+		let message = match entry {
+			CPEntry::Unusable(_) => Some("invalid tag 0"),
+			ref entry if entry.is_wide() && result.len() + 1 == len => Some("the last entry can't take up two slots"),
+			_ => None,
+		};
+		if let Some(message) = message {
+			let message = format!("Constant pool entry #{}: {}", result.len() + 1, message);
+			return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message).into());
+		}
+		let wide = entry.is_wide();
+		result.push(entry);
+		if wide {
+			result.push(CPEntry::Unusable(UnusableInfo {}));
+		}
 	}
 	Ok(result)
 }
@@ -68,6 +135,9 @@ fn write_constant_pool<'a, O: Write, BO: ByteOrder, L>(value: &Vec<CPEntry<'a>>,
 	}
 	output.write_u16::<BO>(len as u16)?;
 	for e in value {
+		if let CPEntry::Unusable(_) = e {
+			continue;
+		}
 		e.to_bytes(output)?;
 	}
 	Ok(())
@@ -75,9 +145,70 @@ fn write_constant_pool<'a, O: Write, BO: ByteOrder, L>(value: &Vec<CPEntry<'a>>,
 
 impl<'a> ConstantPool<'a> {
 	pub fn index<T: 'a + CPType<'a>>(&'a self, index: CPIndex<'a, T>) -> Option<T::Output> {
-		let entry = &self.entries[(index.index - 1) as usize];
+		let entry = self.entries.get((index.index as usize).checked_sub(1)?)?;
 		T::fetch(entry)
 	}
+
+	/// Shorthand for the common case of fetching the string data behind a `UTF8Info` index.
+	pub fn utf8(&'a self, index: CPIndex<'a, UTF8Info<'a>>) -> Option<&'a mstr> {
+		self.index(index)
+			.map(|info| &*info.data)
+	}
+
+	/// Fetches the name of the class that the given `ClassInfo` index points at.
+	pub fn class_name(&'a self, index: CPIndex<'a, ClassInfo<'a>>) -> Option<&'a mstr> {
+		let info = self.index(index)?;
+		self.utf8(info.name_index)
+	}
+
+	/// Finds the `UTF8Info` holding the given string, adding one to the end if there isn't one.
+	///
+	/// `None` if the pool is full.
+	pub fn add_utf8(&mut self, value: &str) -> Option<CPIndex<'a, UTF8Info<'a>>> {
+		let position = self.entries.iter()
+			.position(|entry| matches!(entry, CPEntry::UTF8(info) if *info == *value));
+		let position = match position {
+			Some(position) => position,
+			None => self.push(CPEntry::UTF8(UTF8Info::new(value)))?,
+		};
+		Some(CPIndex::new(position as u16 + 1))
+	}
+
+	/// Finds the `ClassInfo` for the given internal name, adding it, and its name, if there isn't one.
+	pub fn add_class(&mut self, name: &str) -> Option<CPIndex<'a, ClassInfo<'a>>> {
+		let name_index = self.add_utf8(name)?;
+		self.add(CPEntry::Class(ClassInfo { name_index }))
+	}
+
+	/// Finds the `ModuleInfo` for the given module name, adding it, and its name, if there isn't one.
+	pub fn add_module(&mut self, name: &str) -> Option<CPIndex<'a, ModuleInfo<'a>>> {
+		let name_index = self.add_utf8(name)?;
+		self.add(CPEntry::Module(ModuleInfo { name_index }))
+	}
+
+	/// Finds the `PackageInfo` for the given package, with slashes, adding it, and its name, if there isn't one.
+	pub fn add_package(&mut self, name: &str) -> Option<CPIndex<'a, PackageInfo<'a>>> {
+		let name_index = self.add_utf8(name)?;
+		self.add(CPEntry::Package(PackageInfo { name_index }))
+	}
+
+	/// Finds an entry equal to the given one, adding it to the end if there isn't one.
+	fn add<T: CPType<'a>>(&mut self, entry: CPEntry<'a>) -> Option<CPIndex<'a, T>> {
+		let position = match self.entries.iter().position(|existing| *existing == entry) {
+			Some(position) => position,
+			None => self.push(entry)?,
+		};
+		Some(CPIndex::new(position as u16 + 1))
+	}
+
+	fn push(&mut self, entry: CPEntry<'a>) -> Option<usize> {
+		// The count written out is one more than the number of entries, and has to fit in a u16.
+		if self.entries.len() + 2 > u16::MAX as usize {
+			return None;
+		}
+		self.entries.push(entry);
+		Some(self.entries.len() - 1)
+	}
 }
 
 impl<'a> IntoIterator for ConstantPool<'a> {
@@ -208,7 +339,12 @@ def_enum_of_structs! {
 		@[binform(endian = "be")]
 		Package(PackageInfo('a) {
 			pub name_index: CPIndex<'a, UTF8Info<'a>>
-		})
+		}),
+		/// The slot following a `Long` or `Double`, which the JVMS considers unusable.
+		/// It's never written out, and if you add a `Long` or `Double` yourself, you'll need to push one of these after it.
+		#[binform(tag = "0")]
+		@[binform(endian = "be")]
+		Unusable(UnusableInfo {})
 	}
 }
 
@@ -232,8 +368,14 @@ impl CPEntry<'_> {
 			CPEntry::InvokeDynamic(_) => CONSTANT_INVOKE_DYNAMIC_TAG,
 			CPEntry::Module(_) => CONSTANT_MODULE_TAG,
 			CPEntry::Package(_) => CONSTANT_PACKAGE_TAG,
+			CPEntry::Unusable(_) => 0,
 		}
 	}
+
+	/// `Long` and `Double` take up two slots in the constant pool.
+	pub fn is_wide(&self) -> bool {
+		matches!(self, CPEntry::Long(_) | CPEntry::Double(_))
+	}
 }
 
 /// So, technically, I don't need the lifetime here, as we copy the string data,
@@ -247,13 +389,94 @@ impl CPEntry<'_> {
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub struct UTF8Info<'a> {
 	_marker: PhantomData<&'a ()>,
-	pub data: MString
+	pub data: MString,
+	/// The original bytes, if they weren't valid modified UTF-8.
+	/// These are written back out as-is, so a corrupt class survives a round-trip untouched.
+	raw: Option<Vec<u8>>,
 }
 def_fetch!(UTF8Info('a) => UTF8);
 
+impl<'a> UTF8Info<'a> {
+	/// Encodes the string, taking care of the null character and supplementary characters.
+	pub fn new(data: &str) -> Self {
+		UTF8Info {
+			_marker: PhantomData,
+			data: utf8::from_str(data),
+			raw: None,
+		}
+	}
+
+	/// Rejects the bytes if they aren't valid modified UTF-8.
+	pub fn from_mutf8(data: Vec<u8>) -> Result<Self, utf8::Mutf8Error> {
+		Ok(UTF8Info {
+			_marker: PhantomData,
+			data: utf8::from_mutf8(data)?,
+			raw: None,
+		})
+	}
+
+	/// If the bytes aren't valid modified UTF-8, `data` will hold a repaired copy,
+	/// and the original bytes are kept around, see `raw`.
+	pub fn from_mutf8_lossy(data: Vec<u8>) -> Self {
+		match utf8::validate(&data) {
+			Ok(_) => UTF8Info {
+				_marker: PhantomData,
+				data: unsafe { MString::from_mutf8_unchecked(data) },
+				raw: None,
+			},
+			Err(_) => UTF8Info {
+				_marker: PhantomData,
+				data: unsafe { MString::from_mutf8_unchecked(utf8::repair(&data)) },
+				raw: Some(data),
+			},
+		}
+	}
+
+	pub fn is_valid(&self) -> bool {
+		self.raw.is_none()
+	}
+
+	/// The bytes as they were read, only present if they failed validation.
+	pub fn raw(&self) -> Option<&[u8]> {
+		self.raw.as_deref()
+	}
+
+	/// The bytes that will be written out.
+	pub fn as_bytes(&self) -> &[u8] {
+		match self.raw {
+			Some(ref raw) => raw,
+			None => self.data.as_bytes(),
+		}
+	}
+}
+
+impl<'a> From<&str> for UTF8Info<'a> {
+	fn from(data: &str) -> Self {
+		UTF8Info::new(data)
+	}
+}
+
+impl<'a> PartialEq<str> for UTF8Info<'a> {
+	fn eq(&self, other: &str) -> bool {
+		self.is_valid() && utf8::eq_str(self.data.as_bytes(), other)
+	}
+}
+
+impl<'a, 'b> PartialEq<&'b str> for UTF8Info<'a> {
+	fn eq(&self, other: &&'b str) -> bool {
+		*self == **other
+	}
+}
+
+impl<'a> fmt::Display for UTF8Info<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&utf8::decode(self.data.as_bytes()))
+	}
+}
+
 impl<'a> ToBytes<BigEndian> for UTF8Info<'a> {
 	fn to_bytes<O: Write>(&self, output: &mut O) -> WriteResult {
-		let data = self.data.as_bytes();
+		let data = self.as_bytes();
 		let len = data.len();
 		let len = match len.try_into() {
 			Result::Err(_e) => return Err(WriteError::TooLarge(len)),
@@ -272,61 +495,108 @@ impl<'a> FromBytes<BigEndian> for UTF8Info<'a> {
 		let len = input.read_u16::<BigEndian>()?;
 		let mut data = Vec::with_capacity(len as usize);
 		input.take(len as u64).read_to_end(&mut data)?;
-		let data = unsafe { MString::from_mutf8_unchecked(data) };
-		Ok(UTF8Info {
-			_marker: PhantomData,
-			data
-		})
+		Ok(UTF8Info::from_mutf8_lossy(data))
 	}
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
-#[binform(endian = "be", tag = "u8")]
+/// The `reference_kind` decides what kind of entry `reference_index` points at.
+///
+/// Kinds 6 (`REF_invokeStatic`) and 7 (`REF_invokeSpecial`) may point at either a `MethodRefInfo` or,
+/// as of version 52.0, an `InterfaceMethodRefInfo`.
+/// That can't be known without looking at the constant pool, so those are stored as a `MethodRef`,
+/// and it's up to the resolver to check what's actually there.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MethodHandleInfo<'a> {
-//	#[binform(tag)]
-//	FieldRef {
-//	}
-	#[binform(tag = "55")]
-	Value(PhantomData<&'a ()>)
+	FieldRef {
+		reference_kind: u8,
+		reference_index: CPIndex<'a, FieldRefInfo<'a>>,
+	},
+	MethodRef {
+		reference_kind: u8,
+		reference_index: CPIndex<'a, MethodRefInfo<'a>>,
+	},
+	InterfaceMethodRef {
+		reference_kind: u8,
+		reference_index: CPIndex<'a, InterfaceMethodRefInfo<'a>>,
+	},
 }
 def_fetch!(MethodHandleInfo('a) => MethodHandle);
 
-//cp_entry! {
-//	enum MethodHandleInfo<'a> = (reference_kind: be_u8) {
-//		FieldRef (1 | 2 | 3 | 4) {
-//			reference_kind: u8 = (value!(reference_kind)),
-//			reference_index: CPIndex<'a, FieldRefInfo<'a>> = (pt!(CPIndex)),
-//		},
-//		MethodRef (5 | 8 | 6 | 7) {
-//			reference_kind: u8 = (value!(reference_kind)),
-//			reference_index: CPIndex<'a, MethodRefInfo<'a>> = (pt!(CPIndex)),
-//		},
-//		InterfaceMethodRef (9) {
-//			reference_kind: u8 = (value!(reference_kind)),
-//			reference_index: CPIndex<'a, InterfaceMethodRefInfo<'a>> = (pt!(CPIndex)),
-//		},
-//	} => MethodHandle
-//}
+impl<'a> MethodHandleInfo<'a> {
+	pub fn reference_kind(&self) -> u8 {
+		match *self {
+			MethodHandleInfo::FieldRef { reference_kind, .. } => reference_kind,
+			MethodHandleInfo::MethodRef { reference_kind, .. } => reference_kind,
+			MethodHandleInfo::InterfaceMethodRef { reference_kind, .. } => reference_kind,
+		}
+	}
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
-#[binform(endian = "be")]
-pub struct FieldInfo<'a> {
-	access_flags: u16,
-	name_index: CPIndex<'a, UTF8Info<'a>>,
-	descriptor_index: CPIndex<'a, UTF8Info<'a>>,
-	attributes: Attributes<'a>,
+	pub fn reference_index(&self) -> u16 {
+		match *self {
+			MethodHandleInfo::FieldRef { reference_index, .. } => reference_index.index,
+			MethodHandleInfo::MethodRef { reference_index, .. } => reference_index.index,
+			MethodHandleInfo::InterfaceMethodRef { reference_index, .. } => reference_index.index,
+		}
+	}
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
-#[binform(endian = "be")]
-pub struct MethodInfo<'a> {
-	access_flags: u16,
-	name_index: CPIndex<'a, UTF8Info<'a>>,
-	descriptor_index: CPIndex<'a, UTF8Info<'a>>,
-	attributes: Attributes<'a>,
+impl<'a> ToBytes<BigEndian> for MethodHandleInfo<'a> {
+	fn to_bytes<O: Write>(&self, output: &mut O) -> WriteResult {
+		output.write_u8(self.reference_kind())?;
+		output.write_u16::<BigEndian>(self.reference_index())?;
+		Ok(())
+	}
+}
+
+impl<'a> FromBytes<BigEndian> for MethodHandleInfo<'a> {
+	type Output = Self;
+
+	fn from_bytes<I: Read>(input: &mut I) -> ReadResult<Self::Output> {
+		let reference_kind = input.read_u8()?;
+		let index = input.read_u16::<BigEndian>()?;
+		let info = match reference_kind {
+			H_GETFIELD | H_GETSTATIC | H_PUTFIELD | H_PUTSTATIC => MethodHandleInfo::FieldRef {
+				reference_kind,
+				reference_index: CPIndex::new(index),
+			},
+			H_INVOKEVIRTUAL | H_INVOKESTATIC | H_INVOKESPECIAL | H_NEWINVOKESPECIAL => MethodHandleInfo::MethodRef {
+				reference_kind,
+				reference_index: CPIndex::new(index),
+			},
+			H_INVOKEINTERFACE => MethodHandleInfo::InterfaceMethodRef {
+				reference_kind,
+				reference_index: CPIndex::new(index),
+			},
+			_ => {
+				let message = format!("Invalid reference_kind in CONSTANT_MethodHandle_info: {}", reference_kind);
+				return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message).into());
+			}
+		};
+		Ok(info)
+	}
+}
+
+def! {
+	struct FieldInfo('a) {
+		access_flags: u16,
+		name_index: CPIndex<'a, UTF8Info<'a>>,
+		descriptor_index: CPIndex<'a, UTF8Info<'a>>,
+		attributes: Attributes<'a>,
+	}
+}
+
+def! {
+	struct MethodInfo('a) {
+		access_flags: u16,
+		name_index: CPIndex<'a, UTF8Info<'a>>,
+		descriptor_index: CPIndex<'a, UTF8Info<'a>>,
+		attributes: Attributes<'a>,
+	}
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[binform(endian = "be")]
 pub struct Attributes<'a> {
 	#[binform(len = "u16")]
@@ -334,14 +604,48 @@ pub struct Attributes<'a> {
 }
 
 impl<'a> Attributes<'a> {
+	pub fn new(attributes: Vec<AttributeInfo<'a>>) -> Self {
+		Attributes {
+			attributes,
+		}
+	}
+
 	pub fn iter(&self) -> impl Iterator<Item = &AttributeInfo<'a>> {
 		self.attributes.iter()
 	}
 
+	pub fn push(&mut self, attribute: AttributeInfo<'a>) {
+		self.attributes.push(attribute);
+	}
+
+	pub fn len(&self) -> usize {
+		self.attributes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.attributes.is_empty()
+	}
+
+	/// Every attribute with the given name, for the attributes that may legally appear more than once,
+	/// or when you need to know whether one that shouldn't has been duplicated.
+	pub fn named_all<'b>(&'b self, cp: &'b ConstantPool<'a>, name: &'b str) -> impl Iterator<Item = &'b AttributeInfo<'a>> + 'b {
+		self.attributes.iter()
+			.filter(move |attr| {
+				match cp.index(attr.attribute_name_index) {
+					Some(info) => *info == *name,
+					None => false,
+				}
+			})
+	}
+
+	/// The first attribute with the given name.
+	///
+	/// If the attribute was duplicated, the rest are silently ignored,
+	/// see `named_all` and `verify::attributes` for catching that.
 	pub fn named(&self, cp: &ConstantPool<'a>, name: &str) -> Option<&AttributeInfo<'a>> {
 		for attr in &self.attributes {
 			let info = cp.index(attr.attribute_name_index).expect("Unable to locate attribute_name_index in constant pool");
-			if info.data.to_utf8() == name {
+			if *info == *name {
 				return Some(attr);
 			}
 		}
@@ -351,6 +655,22 @@ impl<'a> Attributes<'a> {
 	pub fn get<T: Attribute<'a>>(&self, cp: &ConstantPool<'a>) -> Option<T> {
 		T::from_attributes(self, cp)
 	}
+
+	/// Replaces the first attribute with the same name, or adds it to the end if there isn't one.
+	pub fn set(&mut self, cp: &ConstantPool<'a>, attribute: AttributeInfo<'a>) {
+		let name = cp.index(attribute.attribute_name_index);
+		let position = self.attributes.iter()
+			.position(|attr| name.is_some() && cp.index(attr.attribute_name_index) == name);
+		match position {
+			Some(position) => self.attributes[position] = attribute,
+			None => self.attributes.push(attribute),
+		}
+	}
+
+	/// Removes every attribute with the given name.
+	pub fn remove(&mut self, cp: &ConstantPool<'a>, name: &str) {
+		self.attributes.retain(|attr| !matches!(cp.index(attr.attribute_name_index), Some(info) if *info == *name));
+	}
 }
 
 impl<'a> IntoIterator for Attributes<'a> {
@@ -363,6 +683,7 @@ impl<'a> IntoIterator for Attributes<'a> {
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[binform(endian = "be")]
 pub struct AttributeInfo<'a> {
 	attribute_name_index: CPIndex<'a, UTF8Info<'a>>,
@@ -370,6 +691,23 @@ pub struct AttributeInfo<'a> {
 	info: Vec<u8>,
 }
 
+impl<'a> AttributeInfo<'a> {
+	pub fn new(attribute_name_index: CPIndex<'a, UTF8Info<'a>>, info: Vec<u8>) -> Self {
+		AttributeInfo {
+			attribute_name_index,
+			info,
+		}
+	}
+
+	pub fn name_index(&self) -> CPIndex<'a, UTF8Info<'a>> {
+		self.attribute_name_index
+	}
+
+	pub fn info(&self) -> &[u8] {
+		&self.info
+	}
+}
+
 fn read_attr_info<I: Read, BO: ByteOrder, L>(input: &mut I) -> ReadResult<Vec<u8>> {
 	let len = input.read_u32::<BO>()?;
 	let mut result = Vec::with_capacity(len as usize);
@@ -429,6 +767,13 @@ impl<'a, T: 'a + CPType<'a>, BO: ByteOrder, L> FromBytes<BO, L> for CPIndex<'a,
 }
 
 impl<'a, T: 'a + CPType<'a>> CPIndex<'a, T> {
+	pub fn new(index: u16) -> Self {
+		CPIndex {
+			index,
+			_marker: PhantomData,
+		}
+	}
+
 	pub fn read_non_zero<I: Read, BO: ByteOrder, L>(input: &mut I) -> ReadResult<Option<Self>> {
 		let value = input.read_u16::<BO>()?;
 		if value == 0 {
@@ -11,6 +11,7 @@ macro_rules! def {
 		} 
     ) => {
     	#[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
+    	#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 		#[binform(endian = "be")]
 		$(#[$struct_attr])*
 		pub struct $type $( < $($generics),* > )? {
@@ -51,6 +52,7 @@ macro_rules! def_enum_of_structs {
     	}
     ) => {
     	#[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
+    	#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
    		$(#[$enum_attr])*
 		pub enum $name $( < $($generics)* > )? {
 			$(
@@ -92,6 +94,7 @@ macro_rules! def_enum_of_structs {
 		)?
 	) => {
 		#[derive(Debug, Eq, PartialEq, Hash, Clone, ToBytes, FromBytes)]
+		#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 		$(#[$attr])*
 		pub struct $struct $( < $($struct_generics)* > )? {
 			$($($body)*)?
@@ -0,0 +1,522 @@
+//! Mapping files, as ProGuard and R8, Tiny and SRG write them, to rename classes and their members with `remap`.
+//!
+//! Mappings go from one set of names to another, such as from the original names to the obfuscated ones
+//! in a ProGuard `mapping.txt`. Member descriptors are always in terms of the names being mapped from.
+//! Use `reversed` to go the other way.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+use crate::classpath::ClassPath;
+use crate::remap::{self, Mapper};
+use crate::utf8::{self, MStrExt};
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct ParseError {
+	/// Counting from one.
+	pub line: usize,
+	pub message: String,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, ParseError> {
+	Err(ParseError {
+		line: line + 1,
+		message: message.into(),
+	})
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct Mappings {
+	classes: BTreeMap<String, String>,
+	packages: BTreeMap<String, String>,
+	/// By owner, name and descriptor, if the format has one.
+	fields: BTreeMap<(String, String, Option<String>), String>,
+	methods: BTreeMap<(String, String, String), String>,
+}
+
+impl Mappings {
+	pub fn new() -> Self {
+		Mappings::default()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.classes.is_empty() && self.packages.is_empty() && self.fields.is_empty() && self.methods.is_empty()
+	}
+
+	/// Renames a class, given internal names.
+	pub fn class(&mut self, from: &str, to: &str) -> &mut Self {
+		self.classes.insert(from.to_string(), to.to_string());
+		self
+	}
+
+	/// Moves the classes of a package that aren't renamed themselves, with an empty name for the default package.
+	pub fn package(&mut self, from: &str, to: &str) -> &mut Self {
+		self.packages.insert(from.to_string(), to.to_string());
+		self
+	}
+
+	/// Renames a field, going by its descriptor too if there is one.
+	pub fn field(&mut self, owner: &str, name: &str, descriptor: Option<&str>, to: &str) -> &mut Self {
+		self.fields.insert((owner.to_string(), name.to_string(), descriptor.map(str::to_string)), to.to_string());
+		self
+	}
+
+	pub fn method(&mut self, owner: &str, name: &str, descriptor: &str, to: &str) -> &mut Self {
+		self.methods.insert((owner.to_string(), name.to_string(), descriptor.to_string()), to.to_string());
+		self
+	}
+
+	/// The same mappings, going the other way.
+	pub fn reversed(&self) -> Mappings {
+		let mut reversed = Mappings::new();
+		for (from, to) in &self.classes {
+			reversed.class(to, from);
+		}
+		for (from, to) in &self.packages {
+			reversed.package(to, from);
+		}
+		for ((owner, name, descriptor), to) in &self.fields {
+			let descriptor = descriptor.as_ref().map(|descriptor| remap::descriptor(self, descriptor));
+			reversed.field(&remap::class_name(self, owner), to, descriptor.as_deref(), name);
+		}
+		for ((owner, name, descriptor), to) in &self.methods {
+			reversed.method(&remap::class_name(self, owner), to, &remap::descriptor(self, descriptor), name);
+		}
+		reversed
+	}
+
+	/// Parses a ProGuard or R8 `mapping.txt`, which goes from the original names to the obfuscated ones.
+	///
+	/// Methods inlined into others are left out, as are members pulled in from other classes.
+	pub fn parse_proguard(text: &str) -> Result<Mappings, ParseError> {
+		let mut mappings = Mappings::new();
+		let mut owner = None;
+		// Inlined methods are listed before the method they're inlined into, all sharing its line range.
+		let mut pending: Option<(String, (String, String, String), String)> = None;
+		for (number, line) in text.lines().enumerate() {
+			let trimmed = line.trim();
+			if trimmed.is_empty() || trimmed.starts_with('#') {
+				continue;
+			}
+			let (from, to) = match trimmed.split_once(" -> ") {
+				Some(split) => split,
+				None => return error(number, "expected ->"),
+			};
+			if !line.starts_with(char::is_whitespace) {
+				let to = match to.strip_suffix(':') {
+					Some(to) => to,
+					None => return error(number, "expected : after the class"),
+				};
+				let (from, to) = (from.replace('.', "/"), to.replace('.', "/"));
+				mappings.class(&from, &to);
+				owner = Some(from);
+				continue;
+			}
+			let owner = match &owner {
+				Some(owner) => owner,
+				None => return error(number, "member outside of a class"),
+			};
+
+			let (member_type, rest) = match from.split_once(' ') {
+				Some(split) => split,
+				None => return error(number, "expected a type and a name"),
+			};
+			let start = match rest.find('(') {
+				Some(start) => start,
+				None => {
+					if !rest.contains('.') {
+						match proguard_type(member_type) {
+							Some(descriptor) => mappings.field(owner, rest, Some(&descriptor), to),
+							None => return error(number, format!("invalid type {}", member_type)),
+						};
+					}
+					continue;
+				}
+			};
+			// Methods may start with the range of lines they were given in the obfuscated class, `1:4:void run()`.
+			let (range, member_type) = match member_type.rfind(':') {
+				Some(end) => (&member_type[..end], &member_type[end + 1..]),
+				None => ("", member_type),
+			};
+			let name = &rest[..start];
+			let end = match rest[start..].find(')') {
+				Some(end) => start + end,
+				None => return error(number, "expected )"),
+			};
+			let mut descriptor = String::from("(");
+			for parameter in rest[start + 1..end].split(',').filter(|parameter| !parameter.is_empty()) {
+				match proguard_type(parameter) {
+					Some(parameter) => descriptor.push_str(&parameter),
+					None => return error(number, format!("invalid type {}", parameter)),
+				}
+			}
+			descriptor.push(')');
+			match proguard_type(member_type) {
+				Some(return_type) => descriptor.push_str(&return_type),
+				None => return error(number, format!("invalid type {}", member_type)),
+			}
+
+			let key = (owner.clone(), name.to_string(), descriptor);
+			let current = (range.to_string(), key, to.to_string());
+			if let Some((range, key, to)) = pending.take() {
+				let inlined = !range.is_empty() && range == current.0 && to == current.2;
+				if !inlined && !key.1.contains('.') {
+					mappings.methods.insert(key, to);
+				}
+			}
+			pending = Some(current);
+		}
+		if let Some((_, key, to)) = pending {
+			if !key.1.contains('.') {
+				mappings.methods.insert(key, to);
+			}
+		}
+		Ok(mappings)
+	}
+
+	/// Parses a Tiny file, version 1 or 2, mapping between the two given namespaces, such as `official` and `named`.
+	pub fn parse_tiny(text: &str, from: &str, to: &str) -> Result<Mappings, ParseError> {
+		let mut lines = text.lines().enumerate();
+		let header: Vec<_> = match lines.next() {
+			Some((_, header)) => header.split('\t').collect(),
+			None => return error(0, "missing header"),
+		};
+		let (version, namespaces) = match header[..] {
+			["v1", ref namespaces @ ..] => (1, namespaces),
+			["tiny", "2", _, ref namespaces @ ..] => (2, namespaces),
+			_ => return error(0, "not a Tiny v1 or v2 header"),
+		};
+		let column = |namespace: &str| namespaces.iter().position(|name| *name == namespace);
+		let (from_column, to_column) = match (column(from), column(to)) {
+			(Some(from), Some(to)) => (from, to),
+			(None, _) => return error(0, format!("no namespace {}", from)),
+			(_, None) => return error(0, format!("no namespace {}", to)),
+		};
+		let mut escaped = false;
+
+		// Descriptors are in terms of the first namespace, so classes are gathered before anything else.
+		let mut classes = vec![];
+		let mut members = vec![];
+		let mut owner: Option<Vec<String>> = None;
+		for (number, line) in lines {
+			let mut columns: Vec<String> = line.split('\t').map(str::to_string).collect();
+			if escaped {
+				for column in &mut columns {
+					*column = unescape(column);
+				}
+			}
+			let columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+			let names = |names: &[&str]| -> Result<Vec<String>, ParseError> {
+				if names.len() < namespaces.len() {
+					return error(number, "missing names");
+				}
+				Ok(names.iter().map(|name| name.to_string()).collect())
+			};
+			match (version, &columns[..]) {
+				(1, ["CLASS", rest @ ..]) => classes.push(names(rest)?),
+				(1, [kind @ "FIELD", owner, descriptor, rest @ ..])
+				| (1, [kind @ "METHOD", owner, descriptor, rest @ ..]) => {
+					members.push((*kind == "METHOD", owner.to_string(), descriptor.to_string(), names(rest)?));
+				}
+				(2, ["", "escaped-names"]) if owner.is_none() && classes.is_empty() => escaped = true,
+				(2, ["c", rest @ ..]) => {
+					let names = names(rest)?;
+					classes.push(names.clone());
+					owner = Some(names);
+				}
+				(2, ["", kind @ "f", descriptor, rest @ ..])
+				| (2, ["", kind @ "m", descriptor, rest @ ..]) => {
+					let owner = match &owner {
+						Some(owner) => owner[0].clone(),
+						None => return error(number, "member outside of a class"),
+					};
+					members.push((*kind == "m", owner, descriptor.to_string(), names(rest)?));
+				}
+				// Comments, parameters, local variables and properties.
+				(2, ["", ..]) => {}
+				(_, [""]) => {}
+				_ => return error(number, format!("unexpected line {:?}", line)),
+			}
+		}
+
+		// Tiny v2 leaves a name empty when it's the same as in the first namespace.
+		let name = |names: &[String], column: usize| -> String {
+			if names[column].is_empty() {
+				names[0].clone()
+			} else {
+				names[column].clone()
+			}
+		};
+		let mut first = Mappings::new();
+		let mut mappings = Mappings::new();
+		for names in &classes {
+			first.class(&names[0], &name(names, from_column));
+			mappings.class(&name(names, from_column), &name(names, to_column));
+		}
+		for (method, owner, descriptor, names) in &members {
+			let owner = remap::class_name(&first, owner);
+			let descriptor = remap::descriptor(&first, descriptor);
+			let (from, to) = (name(names, from_column), name(names, to_column));
+			if *method {
+				mappings.method(&owner, &from, &descriptor, &to);
+			} else {
+				mappings.field(&owner, &from, Some(&descriptor), &to);
+			}
+		}
+		mappings.classes.retain(|from, to| from != to);
+		Ok(mappings)
+	}
+
+	/// Parses an SRG file, with its `PK:`, `CL:`, `FD:` and `MD:` lines.
+	///
+	/// Fields don't come with descriptors, so they're renamed whatever their type.
+	pub fn parse_srg(text: &str) -> Result<Mappings, ParseError> {
+		let mut mappings = Mappings::new();
+		for (number, line) in text.lines().enumerate() {
+			let columns: Vec<_> = line.split_whitespace().collect();
+			// The owner comes with each member, as in `a/b/c`, for field `c` of class `a/b`.
+			let member = |name: &str| -> Result<(String, String), ParseError> {
+				match name.rsplit_once('/') {
+					Some((owner, name)) => Ok((owner.to_string(), name.to_string())),
+					None => error(number, format!("expected an owner for {}", name)),
+				}
+			};
+			match columns[..] {
+				[] => {}
+				[comment, ..] if comment.starts_with('#') => {}
+				["PK:", from, to] => {
+					let package = |name: &str| if name == "." { String::new() } else { name.trim_end_matches('/').to_string() };
+					mappings.package(&package(from), &package(to));
+				}
+				["CL:", from, to] => {
+					mappings.class(from, to);
+				}
+				["FD:", from, to] | ["FD:", from, _, to, _] => {
+					let (owner, from) = member(from)?;
+					let (_, to) = member(to)?;
+					mappings.field(&owner, &from, None, &to);
+				}
+				["MD:", from, descriptor, to, _] => {
+					let (owner, from) = member(from)?;
+					let (_, to) = member(to)?;
+					mappings.method(&owner, &from, descriptor, &to);
+				}
+				_ => return error(number, format!("unexpected line {:?}", line)),
+			}
+		}
+		Ok(mappings)
+	}
+}
+
+/// A type as ProGuard writes it, such as `int` or `java.lang.String[]`, as a descriptor.
+fn proguard_type(name: &str) -> Option<String> {
+	let mut descriptor = String::new();
+	let mut name = name;
+	while let Some(component) = name.strip_suffix("[]") {
+		descriptor.push('[');
+		name = component;
+	}
+	let base = match name {
+		"boolean" => "Z",
+		"byte" => "B",
+		"char" => "C",
+		"short" => "S",
+		"int" => "I",
+		"long" => "J",
+		"float" => "F",
+		"double" => "D",
+		"void" => "V",
+		"" => return None,
+		name => {
+			descriptor.push('L');
+			descriptor.push_str(&name.replace('.', "/"));
+			descriptor.push(';');
+			return Some(descriptor);
+		}
+	};
+	descriptor.push_str(base);
+	Some(descriptor)
+}
+
+fn unescape(value: &str) -> String {
+	let mut result = String::with_capacity(value.len());
+	let mut chars = value.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			result.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('n') => result.push('\n'),
+			Some('r') => result.push('\r'),
+			Some('t') => result.push('\t'),
+			Some('0') => result.push('\0'),
+			Some(c) => result.push(c),
+			None => result.push('\\'),
+		}
+	}
+	result
+}
+
+impl Mapper for Mappings {
+	fn map_class(&self, name: &str) -> Option<String> {
+		if let Some(to) = self.classes.get(name) {
+			return Some(to.clone());
+		}
+		let (package, simple) = match name.rfind('/') {
+			Some(end) => (&name[..end], &name[end + 1..]),
+			None => ("", name),
+		};
+		match self.packages.get(package)?.as_str() {
+			"" => Some(simple.to_string()),
+			to => Some(format!("{}/{}", to, simple)),
+		}
+	}
+
+	fn map_package(&self, name: &str) -> Option<String> {
+		self.packages.get(name).cloned()
+	}
+
+	fn map_field(&self, owner: &str, name: &str, descriptor: &str) -> Option<String> {
+		let key = (owner.to_string(), name.to_string(), Some(descriptor.to_string()));
+		if let Some(to) = self.fields.get(&key) {
+			return Some(to.clone());
+		}
+		self.fields.get(&(key.0, key.1, None)).cloned()
+	}
+
+	fn map_method(&self, owner: &str, name: &str, descriptor: &str) -> Option<String> {
+		self.methods.get(&(owner.to_string(), name.to_string(), descriptor.to_string())).cloned()
+	}
+}
+
+/// Applies mappings with the classes being mapped at hand, so renames don't need spelling out for every class.
+///
+/// A method that isn't mapped itself takes the name of any method it overrides, or that overrides it,
+/// and a reference to an inherited member finds the mapping of the class that declares it.
+///
+/// ```no_run
+/// # use class_file::classpath::ClassPath;
+/// # use class_file::jar::Jar;
+/// # use class_file::mapping::*;
+/// # use class_file::remap::remap;
+/// let mappings = Mappings::parse_proguard(&std::fs::read_to_string("mapping.txt").unwrap()).unwrap();
+/// let mut class_path = ClassPath::new();
+/// class_path.add_jar(&Jar::open("app.jar").unwrap(), None);
+/// let mapper = HierarchyMapper::new(&mappings, &class_path);
+/// let obfuscated: Vec<_> = class_path.names()
+///     .map(|name| remap(class_path.get(name).unwrap(), &mapper))
+///     .collect();
+/// ```
+pub struct HierarchyMapper<'m> {
+	mappings: &'m Mappings,
+	class_path: &'m ClassPath,
+}
+
+impl<'m> HierarchyMapper<'m> {
+	/// The class path should hold the classes under the names being mapped from.
+	pub fn new(mappings: &'m Mappings, class_path: &'m ClassPath) -> Self {
+		HierarchyMapper {
+			mappings,
+			class_path,
+		}
+	}
+
+	/// The class the method the reference ends up at is declared in, if that's known.
+	fn declaring_class(&self, owner: &str, name: &str, descriptor: &str) -> Option<String> {
+		let resolved = match self.class_path.is_interface(owner).ok()? {
+			true => self.class_path.resolve_interface_method(owner, name, descriptor),
+			false => self.class_path.resolve_method(owner, name, descriptor),
+		};
+		resolved.ok().map(|method| method.owner.to_string())
+	}
+
+	/// The class declaring the field a reference through `owner` ends up at, following JVMS 5.4.3.2:
+	/// the class itself, then each of its direct interfaces along with theirs, then its super class the same way.
+	fn field_owner(&self, owner: &str, name: &str, descriptor: &str, seen: &mut BTreeSet<String>) -> Option<String> {
+		// An interface reachable more than once has already turned up nothing the first time.
+		if !seen.insert(owner.to_string()) {
+			return None;
+		}
+		if self.declares_field(owner, name, descriptor) {
+			return Some(owner.to_string());
+		}
+		let class_file = self.class_path.get(owner)?;
+		let cp = &class_file.constant_pool;
+		for &interface in &class_file.interfaces {
+			let interface = cp.class_name(interface)?.decoded();
+			if let Some(declaring) = self.field_owner(&interface, name, descriptor, seen) {
+				return Some(declaring);
+			}
+		}
+		match class_file.super_class.index {
+			0 => None,
+			_ => self.field_owner(&cp.class_name(class_file.super_class)?.decoded(), name, descriptor, seen),
+		}
+	}
+
+	fn declares_field(&self, owner: &str, name: &str, descriptor: &str) -> bool {
+		let class_file = match self.class_path.get(owner) {
+			Some(class_file) => class_file,
+			None => return false,
+		};
+		let cp = &class_file.constant_pool;
+		class_file.fields.iter()
+			.any(|field| cp.utf8(field.name_index).is_some_and(|value| utf8::decode(value.as_bytes()) == name)
+				&& cp.utf8(field.descriptor_index).is_some_and(|value| utf8::decode(value.as_bytes()) == descriptor))
+	}
+}
+
+impl Mapper for HierarchyMapper<'_> {
+	fn map_class(&self, name: &str) -> Option<String> {
+		self.mappings.map_class(name)
+	}
+
+	fn map_package(&self, name: &str) -> Option<String> {
+		self.mappings.map_package(name)
+	}
+
+	/// Fields are looked up the way the JVM resolves them, see `field_owner`.
+	fn map_field(&self, owner: &str, name: &str, descriptor: &str) -> Option<String> {
+		if let Some(to) = self.mappings.map_field(owner, name, descriptor) {
+			return Some(to);
+		}
+		let declaring = self.field_owner(owner, name, descriptor, &mut BTreeSet::new())?;
+		if declaring == owner {
+			return None;
+		}
+		self.mappings.map_field(&declaring, name, descriptor)
+	}
+
+	fn map_method(&self, owner: &str, name: &str, descriptor: &str) -> Option<String> {
+		if let Some(to) = self.mappings.map_method(owner, name, descriptor) {
+			return Some(to);
+		}
+		let start = self.declaring_class(owner, name, descriptor)?;
+
+		// Every method linked by overriding has to end up with the same name.
+		let mut seen = BTreeSet::new();
+		let mut pending = VecDeque::new();
+		seen.insert(start.clone());
+		pending.push_back(start);
+		while let Some(class) = pending.pop_front() {
+			if let Some(to) = self.mappings.map_method(&class, name, descriptor) {
+				return Some(to);
+			}
+			let overridden = self.class_path.overridden_methods(&class, name, descriptor).unwrap_or_default();
+			let overriding = self.class_path.overriding_methods(&class, name, descriptor).unwrap_or_default();
+			for method in overridden.into_iter().chain(overriding) {
+				if seen.insert(method.owner.to_string()) {
+					pending.push_back(method.owner.to_string());
+				}
+			}
+		}
+		None
+	}
+}
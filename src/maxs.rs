@@ -0,0 +1,275 @@
+//! Computing `max_stack` and `max_locals`, the way ASM's `COMPUTE_MAXS` does.
+//!
+//! Unlike computing frames, this only needs to know how many slots each instruction pops and pushes,
+//! not what's in them, so there's no hierarchy involved, and it works for any version of class,
+//! `jsr` and `ret` included.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::*;
+use crate::attr::Code;
+use crate::bytecode::{self, DecodeError, Instruction, Operand};
+use crate::descriptor::{FieldType, MethodDescriptor};
+use crate::verify::Location;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Problem {
+	Decode(DecodeError),
+	/// There's no method at the given index.
+	MissingMethod,
+	/// The method's descriptor couldn't be parsed.
+	InvalidDescriptor,
+	/// A branch or handler that doesn't point at the start of an instruction.
+	InvalidTarget(u32),
+	/// The instruction refers to a constant that doesn't fit, or it's not an instruction at all.
+	InvalidConstant(u16),
+	StackUnderflow,
+	/// Two paths meet with a different number of slots on the stack.
+	StackHeightMismatch {
+		first: usize,
+		second: usize,
+	},
+	FallsOffEnd,
+	/// The stack or the locals need more slots than fit in a `u16`.
+	TooLarge,
+}
+
+impl fmt::Display for Problem {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Problem::Decode(error) => write!(f, "{}", error),
+			Problem::MissingMethod => f.write_str("no such method"),
+			Problem::InvalidDescriptor => f.write_str("invalid method descriptor"),
+			Problem::InvalidTarget(pc) => write!(f, "{} is not the start of an instruction", pc),
+			Problem::InvalidConstant(index) => write!(f, "invalid constant #{}", index),
+			Problem::StackUnderflow => f.write_str("stack underflow"),
+			Problem::StackHeightMismatch { first, second } => {
+				write!(f, "stack is {} slots high on one path, but {} on another", first, second)
+			}
+			Problem::FallsOffEnd => f.write_str("execution falls off the end of the code"),
+			Problem::TooLarge => f.write_str("more than 65535 slots needed"),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct MaxsError {
+	pub location: Location,
+	/// The offset of the failing instruction, if the problem is with one.
+	pub pc: Option<u32>,
+	pub problem: Problem,
+}
+
+impl fmt::Display for MaxsError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.pc {
+			Some(pc) => write!(f, "{}, pc {}: {}", self.location, pc, self.problem),
+			None => write!(f, "{}: {}", self.location, self.problem),
+		}
+	}
+}
+
+fn field_size(cp: &ConstantPool, index: u16) -> Option<usize> {
+	let field = cp.resolve(CPIndex::<FieldRefInfo>::new(index))?;
+	FieldType::parse(&utf8::decode(field.descriptor.as_bytes()))
+		.map(|field_type| field_type.size())
+}
+
+/// The descriptor of the method an invoke instruction calls.
+pub(crate) fn invoked_descriptor(cp: &ConstantPool, opcode: u8, index: u16) -> Option<MethodDescriptor> {
+	let descriptor = match opcode {
+		INVOKEDYNAMIC => cp.resolve(CPIndex::<InvokeDynamicInfo>::new(index))?.descriptor,
+		_ => match cp.resolve(CPIndex::<MethodRefInfo>::new(index)) {
+			Some(method) => method.descriptor,
+			None => cp.resolve(CPIndex::<InterfaceMethodRefInfo>::new(index))?.descriptor,
+		},
+	};
+	MethodDescriptor::parse(&utf8::decode(descriptor.as_bytes()))
+}
+
+/// The slots taken up by the arguments and the return value of the method an invoke instruction calls.
+fn method_sizes(cp: &ConstantPool, opcode: u8, index: u16) -> Option<(usize, usize)> {
+	let descriptor = invoked_descriptor(cp, opcode, index)?;
+	let receiver = if opcode == INVOKESTATIC || opcode == INVOKEDYNAMIC { 0 } else { 1 };
+	let returned = descriptor.return_type.as_ref().map_or(0, FieldType::size);
+	Some((descriptor.parameter_slots() + receiver, returned))
+}
+
+fn constant_index(instruction: &Instruction) -> u16 {
+	match instruction.operand {
+		Operand::Constant(index) | Operand::InvokeInterface { index, .. } | Operand::MultiANewArray { index, .. } => index,
+		_ => 0,
+	}
+}
+
+/// How many stack slots the instruction pops, and how many it pushes afterwards.
+///
+/// `None` if it refers to a constant that doesn't fit, or it's not an instruction at all.
+pub fn stack_effect(instruction: &Instruction, cp: &ConstantPool) -> Option<(usize, usize)> {
+	let index = constant_index(instruction);
+	let effect = match instruction.opcode {
+		NOP | IINC | GOTO | GOTO_W | RET | RETURN => (0, 0),
+		ACONST_NULL | ICONST_M1..=ICONST_5 | FCONST_0..=FCONST_2 | BIPUSH | SIPUSH | LDC | LDC_W => (0, 1),
+		LCONST_0 | LCONST_1 | DCONST_0 | DCONST_1 | LDC2_W => (0, 2),
+		ILOAD | FLOAD | ALOAD | ILOAD_0..=ILOAD_3 | FLOAD_0..=FLOAD_3 | ALOAD_0..=ALOAD_3 => (0, 1),
+		LLOAD | DLOAD | LLOAD_0..=LLOAD_3 | DLOAD_0..=DLOAD_3 => (0, 2),
+		IALOAD | FALOAD | AALOAD | BALOAD | CALOAD | SALOAD => (2, 1),
+		LALOAD | DALOAD => (2, 2),
+		ISTORE | FSTORE | ASTORE | ISTORE_0..=ISTORE_3 | FSTORE_0..=FSTORE_3 | ASTORE_0..=ASTORE_3 => (1, 0),
+		LSTORE | DSTORE | LSTORE_0..=LSTORE_3 | DSTORE_0..=DSTORE_3 => (2, 0),
+		IASTORE | FASTORE | AASTORE | BASTORE | CASTORE | SASTORE => (3, 0),
+		LASTORE | DASTORE => (4, 0),
+		POP => (1, 0),
+		POP2 => (2, 0),
+		DUP => (1, 2),
+		DUP_X1 => (2, 3),
+		DUP_X2 => (3, 4),
+		DUP2 => (2, 4),
+		DUP2_X1 => (3, 5),
+		DUP2_X2 => (4, 6),
+		SWAP => (2, 2),
+		IADD | ISUB | IMUL | IDIV | IREM | ISHL | ISHR | IUSHR | IAND | IOR | IXOR => (2, 1),
+		FADD | FSUB | FMUL | FDIV | FREM => (2, 1),
+		LADD | LSUB | LMUL | LDIV | LREM | LAND | LOR | LXOR => (4, 2),
+		DADD | DSUB | DMUL | DDIV | DREM => (4, 2),
+		LSHL | LSHR | LUSHR => (3, 2),
+		INEG | FNEG | I2F | F2I | I2B | I2C | I2S => (1, 1),
+		LNEG | DNEG | L2D | D2L => (2, 2),
+		I2L | I2D | F2L | F2D => (1, 2),
+		L2I | L2F | D2I | D2F => (2, 1),
+		LCMP | DCMPL | DCMPG => (4, 1),
+		FCMPL | FCMPG => (2, 1),
+		IFEQ..=IFLE | IFNULL | IFNONNULL | TABLESWITCH | LOOKUPSWITCH => (1, 0),
+		IF_ICMPEQ..=IF_ICMPLE | IF_ACMPEQ | IF_ACMPNE => (2, 0),
+		// The return address, which the subroutine is expected to store away.
+		JSR | JSR_W => (0, 1),
+		IRETURN | FRETURN | ARETURN | ATHROW | MONITORENTER | MONITOREXIT => (1, 0),
+		LRETURN | DRETURN => (2, 0),
+		GETSTATIC => (0, field_size(cp, index)?),
+		PUTSTATIC => (field_size(cp, index)?, 0),
+		GETFIELD => (1, field_size(cp, index)?),
+		PUTFIELD => (1 + field_size(cp, index)?, 0),
+		INVOKEVIRTUAL..=INVOKEDYNAMIC => method_sizes(cp, instruction.opcode, index)?,
+		NEW => (0, 1),
+		NEWARRAY | ANEWARRAY | ARRAYLENGTH | CHECKCAST | INSTANCEOF => (1, 1),
+		MULTIANEWARRAY => match instruction.operand {
+			Operand::MultiANewArray { dimensions, .. } => (dimensions as usize, 1),
+			_ => return None,
+		},
+		_ => return None,
+	};
+	Some(effect)
+}
+
+/// The local variable the instruction reads or writes, if any, along with the number of slots it takes up.
+fn local(instruction: &Instruction) -> Option<(u16, usize)> {
+	let index = match instruction.operand {
+		Operand::Local(index) | Operand::Iinc { index, .. } => index,
+		_ => return None,
+	};
+	let size = match instruction.opcode {
+		LLOAD | DLOAD | LSTORE | DSTORE => 2,
+		LLOAD_0..=LLOAD_3 | DLOAD_0..=DLOAD_3 | LSTORE_0..=LSTORE_3 | DSTORE_0..=DSTORE_3 => 2,
+		_ => 1,
+	};
+	Some((index, size))
+}
+
+/// Computes `max_stack` and `max_locals`, in that order, for `code` as the code of the method at the given index.
+///
+/// The locals are sized to fit `this`, the parameters, and every local any instruction touches.
+/// The stack is sized by following every path through the code, exception handlers included,
+/// which have to agree on the stack's height wherever they meet, and code that's never reached doesn't count.
+/// `jsr` is assumed to return to the following instruction with the stack the way it was.
+pub fn compute_maxs(class_file: &ClassFile, index: usize, code: &Code) -> Result<(u16, u16), MaxsError> {
+	let location = Location::Code(index);
+	let error = |pc, problem| MaxsError { location, pc, problem };
+
+	let cp = &class_file.constant_pool;
+	let method = class_file.methods.get(index)
+		.ok_or_else(|| error(None, Problem::MissingMethod))?;
+	let descriptor = cp.utf8(method.descriptor_index)
+		.and_then(|descriptor| MethodDescriptor::parse(&utf8::decode(descriptor.as_bytes())))
+		.ok_or_else(|| error(None, Problem::InvalidDescriptor))?;
+	let instructions: BTreeMap<u32, Instruction> = bytecode::decode(&code.code)
+		.map_err(|e| error(None, Problem::Decode(e)))?
+		.into_iter()
+		.map(|instruction| (instruction.pc, instruction))
+		.collect();
+
+	let mut max_locals = descriptor.parameter_slots() + if method.access_flags & STATIC == 0 { 1 } else { 0 };
+	for (index, size) in instructions.values().filter_map(local) {
+		max_locals = max_locals.max(index as usize + size);
+	}
+
+	let mut heights: BTreeMap<u32, usize> = BTreeMap::new();
+	let mut pending = BTreeSet::new();
+	let mut max_stack = 0;
+	let reach = |pc: u32, height: usize, heights: &mut BTreeMap<u32, usize>, pending: &mut BTreeSet<u32>| {
+		if !instructions.contains_key(&pc) {
+			return Err(Problem::InvalidTarget(pc));
+		}
+		match heights.get(&pc) {
+			Some(&first) if first != height => Err(Problem::StackHeightMismatch { first, second: height }),
+			Some(_) => Ok(()),
+			None => {
+				heights.insert(pc, height);
+				pending.insert(pc);
+				Ok(())
+			}
+		}
+	};
+	reach(0, 0, &mut heights, &mut pending)
+		.map_err(|problem| error(Some(0), problem))?;
+
+	while let Some(pc) = pending.pop_first() {
+		let instruction = &instructions[&pc];
+		let height = heights[&pc];
+		let (popped, pushed) = stack_effect(instruction, cp)
+			.ok_or_else(|| error(Some(pc), Problem::InvalidConstant(constant_index(instruction))))?;
+		if popped > height {
+			return Err(error(Some(pc), Problem::StackUnderflow));
+		}
+		let after = height - popped + pushed;
+		// The height on entry counts too, for exception handlers that start with the exception on the stack.
+		max_stack = max_stack.max(height).max(after);
+
+		let mut successors: Vec<(u32, usize)> = instruction.targets().into_iter()
+			.map(|target| (target, after))
+			.collect();
+		for exception in &code.exception_table {
+			if pc >= exception.start_pc as u32 && pc < exception.end_pc as u32 {
+				successors.push((exception.handler_pc as u32, 1));
+			}
+		}
+		if !instruction.is_unconditional() {
+			let next = instructions.range(pc + 1..)
+				.next()
+				.map(|(&next, _)| next)
+				.ok_or_else(|| error(Some(pc), Problem::FallsOffEnd))?;
+			let height = if instruction.opcode == JSR || instruction.opcode == JSR_W { height } else { after };
+			successors.push((next, height));
+		}
+		for (target, height) in successors {
+			reach(target, height, &mut heights, &mut pending)
+				.map_err(|problem| error(Some(pc), problem))?;
+		}
+	}
+
+	if max_stack > u16::MAX as usize {
+		return Err(error(None, Problem::TooLarge));
+	}
+	if max_locals > u16::MAX as usize {
+		return Err(error(None, Problem::TooLarge));
+	}
+	Ok((max_stack as u16, max_locals as u16))
+}
+
+/// Computes `max_stack` and `max_locals`, and stores them in `code`.
+pub fn update_maxs(class_file: &ClassFile, index: usize, code: &mut Code) -> Result<(), MaxsError> {
+	let (max_stack, max_locals) = compute_maxs(class_file, index, code)?;
+	code.max_stack = max_stack;
+	code.max_locals = max_locals;
+	Ok(())
+}
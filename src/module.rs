@@ -0,0 +1,358 @@
+//! A module descriptor, as what `module-info.class` says rather than how it says it,
+//! with names in place of constant pool indices and flags as fields.
+//!
+//! Module names have dots, as in `java.base`, while packages and classes have slashes, as in `java/lang` and `java/sql/Driver`,
+//! the same as in the class file.
+
+use std::collections::BTreeSet;
+
+use crate::*;
+use crate::attr;
+use crate::ops::{MANDATED, MODULE, OPEN, STATIC_PHASE, SYNTHETIC, TRANSITIVE, V9};
+use crate::utf8::MStrExt;
+
+/// The module every other module depends on, whether it says so or not.
+pub const JAVA_BASE: &str = "java.base";
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Requires {
+	pub module: String,
+	pub transitive: bool,
+	/// Only needed at compile time, `requires static`.
+	pub static_phase: bool,
+	pub synthetic: bool,
+	pub mandated: bool,
+	/// The version that was compiled against.
+	pub version: Option<String>,
+}
+
+impl Requires {
+	pub fn new(module: &str) -> Self {
+		Requires {
+			module: module.to_string(),
+			transitive: false,
+			static_phase: false,
+			synthetic: false,
+			mandated: false,
+			version: None,
+		}
+	}
+
+	fn from_flags(module: String, flags: u16, version: Option<String>) -> Self {
+		Requires {
+			module,
+			transitive: flags & TRANSITIVE != 0,
+			static_phase: flags & STATIC_PHASE != 0,
+			synthetic: flags & SYNTHETIC != 0,
+			mandated: flags & MANDATED != 0,
+			version,
+		}
+	}
+
+	pub fn flags(&self) -> u16 {
+		flag(self.transitive, TRANSITIVE) | flag(self.static_phase, STATIC_PHASE) | flag(self.synthetic, SYNTHETIC) | flag(self.mandated, MANDATED)
+	}
+}
+
+/// A package that's exported or opened, to everyone if there aren't any modules it's restricted to.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Exports {
+	pub package: String,
+	pub synthetic: bool,
+	pub mandated: bool,
+	pub to: Vec<String>,
+}
+
+/// Opening a package looks just like exporting it, but is about reflection rather than compiling against it.
+pub type Opens = Exports;
+
+impl Exports {
+	pub fn new(package: &str, to: &[&str]) -> Self {
+		Exports {
+			package: package.to_string(),
+			synthetic: false,
+			mandated: false,
+			to: to.iter().map(|module| module.to_string()).collect(),
+		}
+	}
+
+	pub fn flags(&self) -> u16 {
+		flag(self.synthetic, SYNTHETIC) | flag(self.mandated, MANDATED)
+	}
+
+	pub fn is_qualified(&self) -> bool {
+		!self.to.is_empty()
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Provides {
+	pub service: String,
+	/// The implementations, in the order the service loader should try them.
+	pub with: Vec<String>,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct ModuleDescriptor {
+	pub name: String,
+	pub open: bool,
+	pub synthetic: bool,
+	pub mandated: bool,
+	pub version: Option<String>,
+	pub requires: Vec<Requires>,
+	pub exports: Vec<Exports>,
+	pub opens: Vec<Opens>,
+	pub uses: Vec<String>,
+	pub provides: Vec<Provides>,
+	/// What the `ModulePackages` attribute lists, empty if there isn't one.
+	pub packages: Vec<String>,
+	pub main_class: Option<String>,
+}
+
+fn flag(set: bool, flag: u16) -> u16 {
+	if set { flag } else { 0 }
+}
+
+/// The package a class is in, with slashes, empty for the unnamed package.
+fn package_of(class: &str) -> &str {
+	class.rfind('/').map_or("", |slash| &class[..slash])
+}
+
+impl ModuleDescriptor {
+	/// A module that only requires `java.base`, as every module does, unless it's `java.base` itself.
+	pub fn new(name: &str) -> Self {
+		let mut requires = Vec::new();
+		if name != JAVA_BASE {
+			let mut java_base = Requires::new(JAVA_BASE);
+			java_base.mandated = true;
+			requires.push(java_base);
+		}
+		ModuleDescriptor {
+			name: name.to_string(),
+			open: false,
+			synthetic: false,
+			mandated: false,
+			version: None,
+			requires,
+			exports: Vec::new(),
+			opens: Vec::new(),
+			uses: Vec::new(),
+			provides: Vec::new(),
+			packages: Vec::new(),
+			main_class: None,
+		}
+	}
+
+	pub fn set_open(&mut self, open: bool) -> &mut Self {
+		self.open = open;
+		self
+	}
+
+	pub fn set_version(&mut self, version: &str) -> &mut Self {
+		self.version = Some(version.to_string());
+		self
+	}
+
+	pub fn set_main_class(&mut self, class: &str) -> &mut Self {
+		self.main_class = Some(class.to_string());
+		self
+	}
+
+	/// Replaces any existing requirement on the same module.
+	pub fn add_requires(&mut self, requires: Requires) -> &mut Self {
+		self.requires.retain(|existing| existing.module != requires.module);
+		self.requires.push(requires);
+		self
+	}
+
+	pub fn add_exports(&mut self, package: &str, to: &[&str]) -> &mut Self {
+		self.exports.push(Exports::new(package, to));
+		self
+	}
+
+	pub fn add_opens(&mut self, package: &str, to: &[&str]) -> &mut Self {
+		self.opens.push(Exports::new(package, to));
+		self
+	}
+
+	pub fn add_uses(&mut self, service: &str) -> &mut Self {
+		self.uses.push(service.to_string());
+		self
+	}
+
+	pub fn add_provides(&mut self, service: &str, with: &[&str]) -> &mut Self {
+		self.provides.push(Provides {
+			service: service.to_string(),
+			with: with.iter().map(|class| class.to_string()).collect(),
+		});
+		self
+	}
+
+	pub fn add_package(&mut self, package: &str) -> &mut Self {
+		if !self.packages.iter().any(|existing| existing == package) {
+			self.packages.push(package.to_string());
+		}
+		self
+	}
+
+	/// The module's flags, as the `Module` attribute has them.
+	pub fn flags(&self) -> u16 {
+		flag(self.open, OPEN) | flag(self.synthetic, SYNTHETIC) | flag(self.mandated, MANDATED)
+	}
+
+	/// Reads the descriptor out of a `module-info` class, `None` if it doesn't have a `Module` attribute,
+	/// or any of the attribute's indices don't point where they should.
+	pub fn from_class_file(class_file: &ClassFile) -> Option<Self> {
+		let cp = &class_file.constant_pool;
+		let module = class_file.attributes.get::<attr::Module>(cp)?;
+		let module_name = |index| cp.index(index).and_then(|info: &ModuleInfo| cp.utf8(info.name_index)).map(MStrExt::decoded);
+		let package_name = |index| cp.index(index).and_then(|info: &PackageInfo| cp.utf8(info.name_index)).map(MStrExt::decoded);
+		let class_name = |index| cp.class_name(index).map(MStrExt::decoded);
+		let version = |index: Option<_>| match index {
+			Some(index) => cp.utf8(index).map(|value| Some(value.decoded())),
+			None => Some(None),
+		};
+		let exports = |package, flags: u16, to: &[_]| -> Option<Exports> {
+			Some(Exports {
+				package: package_name(package)?,
+				synthetic: flags & SYNTHETIC != 0,
+				mandated: flags & MANDATED != 0,
+				to: to.iter().map(|&index| module_name(index)).collect::<Option<_>>()?,
+			})
+		};
+
+		let packages = match class_file.attributes.get::<attr::ModulePackages>(cp) {
+			Some(packages) => packages.packages.iter().map(|&index| package_name(index)).collect::<Option<_>>()?,
+			None => Vec::new(),
+		};
+		let main_class = match class_file.attributes.get::<attr::ModuleMainClass>(cp) {
+			Some(main_class) => Some(class_name(main_class.main_class_index)?),
+			None => None,
+		};
+		Some(ModuleDescriptor {
+			name: module_name(module.module_name_index)?,
+			open: module.module_flags & OPEN != 0,
+			synthetic: module.module_flags & SYNTHETIC != 0,
+			mandated: module.module_flags & MANDATED != 0,
+			version: version(module.module_version_index)?,
+			requires: module.requires.iter()
+				.map(|requires| Some(Requires::from_flags(
+					module_name(requires.requires_index)?,
+					requires.requires_flags,
+					version(requires.requires_version_index)?,
+				)))
+				.collect::<Option<_>>()?,
+			exports: module.exports.iter()
+				.map(|info| exports(info.exports_index, info.exports_flags, &info.exports_to))
+				.collect::<Option<_>>()?,
+			opens: module.opens.iter()
+				.map(|info| exports(info.opens_index, info.opens_flags, &info.opens_to))
+				.collect::<Option<_>>()?,
+			uses: module.uses.iter().map(|&index| class_name(index)).collect::<Option<_>>()?,
+			provides: module.provides.iter()
+				.map(|provides| Some(Provides {
+					service: class_name(provides.provides)?,
+					with: provides.provides_with.iter().map(|&index| class_name(index)).collect::<Option<_>>()?,
+				}))
+				.collect::<Option<_>>()?,
+			packages,
+			main_class,
+		})
+	}
+
+	/// Every package the `ModulePackages` attribute should list: the ones listed already,
+	/// along with those that are exported or opened, and the ones the main class and the service implementations are in.
+	pub fn all_packages(&self) -> BTreeSet<String> {
+		let classes = self.main_class.iter()
+			.chain(self.provides.iter().flat_map(|provides| provides.with.iter()))
+			.map(|class| package_of(class));
+		self.packages.iter()
+			.chain(self.exports.iter().chain(self.opens.iter()).map(|exports| &exports.package))
+			.map(String::as_str)
+			.chain(classes)
+			.filter(|package| !package.is_empty())
+			.map(str::to_string)
+			.collect()
+	}
+
+	/// Builds a complete `module-info` class, with a `ModulePackages` attribute listing `all_packages`,
+	/// and a `ModuleMainClass` attribute if there's a main class.
+	///
+	/// `None` if the constant pool overflows, or an attribute can't be encoded.
+	pub fn to_class_file(&self) -> Option<ClassFile<'static>> {
+		let mut cp = ConstantPool { entries: Vec::new() };
+		let this_class = cp.add_class(MODULE_INFO)?;
+
+		let version = |cp: &mut ConstantPool<'static>, version: &Option<String>| match version {
+			Some(version) => cp.add_utf8(version).map(Some),
+			None => Some(None),
+		};
+		let exports = |cp: &mut ConstantPool<'static>, exports: &Exports| -> Option<_> {
+			let to = exports.to.iter().map(|module| cp.add_module(module)).collect::<Option<Vec<_>>>()?;
+			Some((cp.add_package(&exports.package)?, exports.flags(), to))
+		};
+		let module = attr::Module {
+			module_name_index: cp.add_module(&self.name)?,
+			module_flags: self.flags(),
+			module_version_index: version(&mut cp, &self.version)?,
+			requires: self.requires.iter()
+				.map(|requires| Some(attr::Requires {
+					requires_index: cp.add_module(&requires.module)?,
+					requires_flags: requires.flags(),
+					requires_version_index: version(&mut cp, &requires.version)?,
+				}))
+				.collect::<Option<_>>()?,
+			exports: self.exports.iter()
+				.map(|info| exports(&mut cp, info).map(|(exports_index, exports_flags, exports_to)| attr::Exports { exports_index, exports_flags, exports_to }))
+				.collect::<Option<_>>()?,
+			opens: self.opens.iter()
+				.map(|info| exports(&mut cp, info).map(|(opens_index, opens_flags, opens_to)| attr::Opens { opens_index, opens_flags, opens_to }))
+				.collect::<Option<_>>()?,
+			uses: self.uses.iter().map(|service| cp.add_class(service)).collect::<Option<_>>()?,
+			provides: self.provides.iter()
+				.map(|provides| Some(attr::Provides {
+					provides: cp.add_class(&provides.service)?,
+					provides_with: provides.with.iter().map(|class| cp.add_class(class)).collect::<Option<_>>()?,
+				}))
+				.collect::<Option<_>>()?,
+		};
+
+		let mut attributes = Vec::new();
+		let name_index = cp.add_utf8("Module")?;
+		attributes.push(AttributeInfo::new(name_index, encode(&module)?));
+		let packages = self.all_packages();
+		if !packages.is_empty() {
+			let packages = attr::ModulePackages {
+				packages: packages.iter().map(|package| cp.add_package(package)).collect::<Option<_>>()?,
+			};
+			let name_index = cp.add_utf8("ModulePackages")?;
+			attributes.push(AttributeInfo::new(name_index, encode(&packages)?));
+		}
+		if let Some(main_class) = &self.main_class {
+			let main_class = attr::ModuleMainClass {
+				main_class_index: cp.add_class(main_class)?,
+			};
+			let name_index = cp.add_utf8("ModuleMainClass")?;
+			attributes.push(AttributeInfo::new(name_index, encode(&main_class)?));
+		}
+
+		Some(ClassFile {
+			minor_version: (V9 >> 16) as u16,
+			major_version: V9 as u16,
+			constant_pool: cp,
+			access_flags: MODULE,
+			this_class,
+			super_class: CPIndex::new(0),
+			interfaces: Vec::new(),
+			fields: Vec::new(),
+			methods: Vec::new(),
+			attributes: Attributes::new(attributes),
+		})
+	}
+}
+
+fn encode<T: ToBytes<BigEndian>>(value: &T) -> Option<Vec<u8>> {
+	let mut output = vec![];
+	value.to_bytes(&mut output).ok()?;
+	Some(output)
+}
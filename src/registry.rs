@@ -0,0 +1,269 @@
+//! Decoding every attribute of an element in one go.
+//!
+//! The `Attribute` trait is great when you know what you're looking for,
+//! but sometimes you want everything an element carries, including the attributes
+//! that aren't part of the JVMS, such as Scala's `ScalaSig`.
+//!
+//! An `AttributeRegistry` maps attribute names to decoders.
+//! Out of the box it knows every standard attribute, and vendor attributes can be added
+//! by implementing `CustomAttribute` and registering them under their name.
+//! Anything it doesn't know, or fails to decode, comes back as `KnownAttribute::Unknown`,
+//! which holds the original `AttributeInfo`, so it's written back out byte for byte.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use crate::*;
+use crate::attr::*;
+
+/// An attribute that isn't defined by the JVMS.
+///
+/// Decoded values end up in a `KnownAttribute::Custom`, so they have to be `'static`.
+pub trait CustomAttribute: Any + Sized {
+	fn decode(data: &[u8], cp: &ConstantPool) -> Option<Self>;
+
+	fn encode(&self) -> Vec<u8>;
+}
+
+#[derive(Debug)]
+pub enum EncodeError {
+	Write(WriteError),
+	/// A `KnownAttribute::Custom` whose name has no registered `CustomAttribute`,
+	/// or whose registered type doesn't match the value.
+	Unregistered(String),
+	/// The `attribute_name_index` doesn't point at a `UTF8Info`.
+	InvalidName(u16),
+}
+
+impl From<WriteError> for EncodeError {
+	fn from(error: WriteError) -> Self {
+		EncodeError::Write(error)
+	}
+}
+
+macro_rules! known_attributes {
+	(
+		$( $name:ident $( ( $lifetime:lifetime ) )? ),* $(,)?
+	) => {
+		#[derive(Debug)]
+		pub enum KnownAttribute<'a> {
+			$( $name($name $( < $lifetime > )?), )*
+			Custom(Box<dyn Any>),
+			Unknown(AttributeInfo<'a>),
+		}
+
+		/// The names of every attribute defined by the JVMS.
+		pub const STANDARD_ATTRIBUTES: &[&str] = &[
+			$( stringify!($name), )*
+		];
+
+		impl<'a> KnownAttribute<'a> {
+			/// The JVMS name of the attribute, if it's a standard one.
+			pub fn standard_name(&self) -> Option<&'static str> {
+				match self {
+					$( KnownAttribute::$name(_) => Some(stringify!($name)), )*
+					KnownAttribute::Custom(_) | KnownAttribute::Unknown(_) => None,
+				}
+			}
+		}
+
+		fn decode_standard<'a>(name: &str, data: &[u8]) -> Option<KnownAttribute<'a>> {
+			let attribute = match name {
+				$( stringify!($name) => KnownAttribute::$name(decode::<$name>(data)?), )*
+				_ => return None,
+			};
+			Some(attribute)
+		}
+
+		fn encode_standard(attribute: &KnownAttribute) -> Option<Result<Vec<u8>, WriteError>> {
+			let result = match attribute {
+				$( KnownAttribute::$name(value) => encode(value), )*
+				KnownAttribute::Custom(_) | KnownAttribute::Unknown(_) => return None,
+			};
+			Some(result)
+		}
+	};
+}
+
+known_attributes! {
+	ConstantValue('a),
+	Code('a),
+	StackMapTable('a),
+	Exceptions('a),
+	InnerClasses('a),
+	EnclosingMethod('a),
+	Synthetic,
+	Signature('a),
+	SourceFile('a),
+	SourceDebugExtension('a),
+	LineNumberTable,
+	LocalVariableTable('a),
+	LocalVariableTypeTable('a),
+	Deprecated,
+	RuntimeVisibleAnnotations('a),
+	RuntimeInvisibleAnnotations('a),
+	RuntimeVisibleParameterAnnotations('a),
+	RuntimeInvisibleParameterAnnotations('a),
+	RuntimeVisibleTypeAnnotations('a),
+	RuntimeInvisibleTypeAnnotations('a),
+	AnnotationDefault('a),
+	BootstrapMethods('a),
+	MethodParameters('a),
+	Module('a),
+	ModulePackages('a),
+	ModuleMainClass('a),
+	NestHost('a),
+	NestMembers('a),
+	Record('a),
+	PermittedSubclasses('a),
+}
+
+/// Only succeeds if the value covers the entire attribute, otherwise something's clearly off,
+/// and it's safer to leave the attribute as it is.
+fn decode<T: FromBytes<BigEndian, Output = T>>(data: &[u8]) -> Option<T> {
+	let mut input = Cursor::new(data);
+	let value = T::from_bytes(&mut input).ok()?;
+	if input.position() as usize != data.len() {
+		return None;
+	}
+	Some(value)
+}
+
+fn encode<T: ToBytes<BigEndian>>(value: &T) -> Result<Vec<u8>, WriteError> {
+	let mut output = vec![];
+	value.to_bytes(&mut output)?;
+	Ok(output)
+}
+
+fn decode_custom<T: CustomAttribute>(data: &[u8], cp: &ConstantPool) -> Option<Box<dyn Any>> {
+	let value = T::decode(data, cp)?;
+	Some(Box::new(value))
+}
+
+fn encode_custom<T: CustomAttribute>(value: &dyn Any) -> Option<Vec<u8>> {
+	value.downcast_ref::<T>()
+		.map(T::encode)
+}
+
+type DecodeFn = fn(&[u8], &ConstantPool) -> Option<Box<dyn Any>>;
+type EncodeFn = fn(&dyn Any) -> Option<Vec<u8>>;
+
+enum Decoder {
+	Standard,
+	Custom {
+		decode: DecodeFn,
+		encode: EncodeFn,
+	},
+}
+
+#[derive(Debug)]
+pub struct DecodedAttribute<'a> {
+	pub name_index: CPIndex<'a, UTF8Info<'a>>,
+	pub attribute: KnownAttribute<'a>,
+}
+
+pub struct AttributeRegistry {
+	decoders: HashMap<String, Decoder>,
+}
+
+impl AttributeRegistry {
+	/// A registry that knows every standard attribute.
+	pub fn new() -> Self {
+		let mut registry = AttributeRegistry::empty();
+		for name in STANDARD_ATTRIBUTES {
+			registry.decoders.insert(name.to_string(), Decoder::Standard);
+		}
+		registry
+	}
+
+	/// A registry that knows nothing, so every attribute comes back as `KnownAttribute::Unknown`.
+	pub fn empty() -> Self {
+		AttributeRegistry {
+			decoders: HashMap::new(),
+		}
+	}
+
+	/// Decodes attributes with the given name using `T`.
+	///
+	/// This takes precedence over a standard attribute of the same name.
+	pub fn register<T: CustomAttribute>(&mut self, name: &str) -> &mut Self {
+		let decoder = Decoder::Custom {
+			decode: decode_custom::<T>,
+			encode: encode_custom::<T>,
+		};
+		self.decoders.insert(name.to_string(), decoder);
+		self
+	}
+
+	/// Stops decoding attributes with the given name, standard or not.
+	pub fn remove(&mut self, name: &str) -> &mut Self {
+		self.decoders.remove(name);
+		self
+	}
+
+	pub fn is_registered(&self, name: &str) -> bool {
+		self.decoders.contains_key(name)
+	}
+
+	pub fn decode<'a>(&self, info: &AttributeInfo<'a>, cp: &ConstantPool<'a>) -> DecodedAttribute<'a> {
+		let attribute = self.decode_known(info, cp)
+			.unwrap_or_else(|| KnownAttribute::Unknown(info.clone()));
+		DecodedAttribute {
+			name_index: info.attribute_name_index,
+			attribute,
+		}
+	}
+
+	fn decode_known<'a>(&self, info: &AttributeInfo<'a>, cp: &ConstantPool<'a>) -> Option<KnownAttribute<'a>> {
+		let name = cp.utf8(info.attribute_name_index)?;
+		let name = utf8::decode(name.as_bytes());
+		match self.decoders.get(&*name)? {
+			Decoder::Standard => decode_standard(&name, &info.info),
+			Decoder::Custom { decode, .. } => decode(&info.info, cp).map(KnownAttribute::Custom),
+		}
+	}
+
+	pub fn decode_all<'a>(&self, attributes: &Attributes<'a>, cp: &ConstantPool<'a>) -> Vec<DecodedAttribute<'a>> {
+		attributes.iter()
+			.map(|info| self.decode(info, cp))
+			.collect()
+	}
+
+	pub fn encode<'a>(&self, decoded: &DecodedAttribute<'a>, cp: &ConstantPool<'a>) -> Result<AttributeInfo<'a>, EncodeError> {
+		let info = match decoded.attribute {
+			KnownAttribute::Unknown(ref info) => return Ok(info.clone()),
+			KnownAttribute::Custom(ref value) => {
+				let name = cp.utf8(decoded.name_index)
+					.ok_or(EncodeError::InvalidName(decoded.name_index.index))?;
+				let name = utf8::decode(name.as_bytes());
+				match self.decoders.get(&*name) {
+					Some(Decoder::Custom { encode, .. }) => {
+						encode(&**value).ok_or_else(|| EncodeError::Unregistered(name.to_string()))?
+					}
+					_ => return Err(EncodeError::Unregistered(name.to_string())),
+				}
+			}
+			ref attribute => match encode_standard(attribute) {
+				Some(result) => result?,
+				None => unreachable!(),
+			},
+		};
+		Ok(AttributeInfo::new(decoded.name_index, info))
+	}
+
+	pub fn encode_all<'a>(&self, decoded: &[DecodedAttribute<'a>], cp: &ConstantPool<'a>) -> Result<Attributes<'a>, EncodeError> {
+		let attributes = decoded.iter()
+			.map(|decoded| self.encode(decoded, cp))
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(Attributes {
+			attributes,
+		})
+	}
+}
+
+impl Default for AttributeRegistry {
+	fn default() -> Self {
+		AttributeRegistry::new()
+	}
+}
@@ -0,0 +1,802 @@
+//! Renaming classes, packages and members everywhere they show up in a class,
+//! for shading dependencies into a jar, or applying a set of mappings.
+//!
+//! Every constant keeps its index, so the code never needs touching.
+//! A string that's shared between uses wanting different values is split: the first use keeps
+//! the original entry, and the others get a new one on the end of the pool. Attributes that aren't
+//! understood are copied as they are, so they see whatever the strings they point at became.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+
+use crate::*;
+use crate::attr::*;
+use crate::utf8::MStrExt;
+
+/// Decides what everything gets renamed to, going by the old names.
+///
+/// Only `map_class` has to be provided, everything else is left alone by default.
+pub trait Mapper {
+	/// The new internal name of the class, or `None` to leave it be.
+	fn map_class(&self, name: &str) -> Option<String>;
+
+	/// The new name of a package, with slashes, as used by module attributes.
+	fn map_package(&self, _name: &str) -> Option<String> {
+		None
+	}
+
+	/// The new name of a field, given the class that declares or references it, its name, and descriptor.
+	fn map_field(&self, _owner: &str, _name: &str, _descriptor: &str) -> Option<String> {
+		None
+	}
+
+	/// The new name of a method, like `map_field`. Never asked about `<init>` or `<clinit>`.
+	fn map_method(&self, _owner: &str, _name: &str, _descriptor: &str) -> Option<String> {
+		None
+	}
+
+	/// The new value of a string constant.
+	fn map_string(&self, _value: &str) -> Option<String> {
+		None
+	}
+}
+
+/// What a `ClassInfo` names after remapping, which may be an array descriptor.
+pub fn class_name<M: Mapper + ?Sized>(mapper: &M, name: &str) -> String {
+	if name.starts_with('[') {
+		descriptor(mapper, name)
+	} else {
+		mapper.map_class(name).unwrap_or_else(|| name.to_string())
+	}
+}
+
+/// Remaps the classes in a field or method descriptor.
+pub fn descriptor<M: Mapper + ?Sized>(mapper: &M, descriptor: &str) -> String {
+	let mut output = String::with_capacity(descriptor.len());
+	let mut rest = descriptor;
+	while let Some(start) = rest.find('L') {
+		let end = match rest[start..].find(';') {
+			Some(end) => start + end,
+			None => break,
+		};
+		output.push_str(&rest[..=start]);
+		output.push_str(&class_name(mapper, &rest[start + 1..end]));
+		rest = &rest[end..];
+	}
+	output.push_str(rest);
+	output
+}
+
+/// Remaps the classes in a generic class, method or field signature, including inner classes
+/// like `Lcom/example/Outer<TT;>.Inner;`.
+///
+/// A signature that doesn't parse is handed back unchanged.
+pub fn signature<M: Mapper + ?Sized>(mapper: &M, signature: &str) -> String {
+	let mut parser = SignatureMapper {
+		mapper,
+		input: signature,
+		position: 0,
+		output: String::with_capacity(signature.len()),
+	};
+	match parser.signature() {
+		Some(()) if parser.position == signature.len() => parser.output,
+		_ => signature.to_string(),
+	}
+}
+
+struct SignatureMapper<'s, M: ?Sized> {
+	mapper: &'s M,
+	input: &'s str,
+	position: usize,
+	output: String,
+}
+
+impl<'s, M: Mapper + ?Sized> SignatureMapper<'s, M> {
+	fn peek(&self) -> Option<char> {
+		self.input[self.position..].chars().next()
+	}
+
+	fn bump(&mut self) -> Option<char> {
+		let c = self.peek()?;
+		self.position += c.len_utf8();
+		self.output.push(c);
+		Some(c)
+	}
+
+	fn expect(&mut self, expected: char) -> Option<()> {
+		if self.bump()? == expected {
+			Some(())
+		} else {
+			None
+		}
+	}
+
+	/// Everything up to, but not including, any of the given characters, which has to be there.
+	fn identifier(&mut self, terminators: &[char]) -> Option<&'s str> {
+		let input = self.input;
+		let length = input[self.position..].find(terminators)?;
+		if length == 0 {
+			return None;
+		}
+		let identifier = &input[self.position..self.position + length];
+		self.position += length;
+		Some(identifier)
+	}
+
+	fn signature(&mut self) -> Option<()> {
+		if self.peek() == Some('<') {
+			self.type_parameters()?;
+		}
+		if self.peek() == Some('(') {
+			self.bump();
+			while self.peek()? != ')' {
+				self.type_signature()?;
+			}
+			self.bump();
+			if self.peek() == Some('V') {
+				self.bump();
+			} else {
+				self.type_signature()?;
+			}
+			while self.peek() == Some('^') {
+				self.bump();
+				self.type_signature()?;
+			}
+		} else {
+			// The super class and interfaces, or the type of a field.
+			while self.peek().is_some() {
+				self.type_signature()?;
+			}
+		}
+		Some(())
+	}
+
+	fn type_parameters(&mut self) -> Option<()> {
+		self.expect('<')?;
+		while self.peek()? != '>' {
+			let name = self.identifier(&[':'])?;
+			self.output.push_str(name);
+			// The class bound may be left out, but the colon stays.
+			while self.peek() == Some(':') {
+				self.bump();
+				if let Some('L') | Some('T') | Some('[') = self.peek() {
+					self.type_signature()?;
+				}
+			}
+		}
+		self.bump();
+		Some(())
+	}
+
+	fn type_signature(&mut self) -> Option<()> {
+		match self.peek()? {
+			'L' => self.class_type(),
+			'T' => {
+				self.bump();
+				let name = self.identifier(&[';'])?;
+				self.output.push_str(name);
+				self.expect(';')
+			}
+			'[' => {
+				self.bump();
+				self.type_signature()
+			}
+			'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' => {
+				self.bump();
+				Some(())
+			}
+			_ => None,
+		}
+	}
+
+	fn class_type(&mut self) -> Option<()> {
+		self.expect('L')?;
+		let mut old = self.identifier(&['<', '.', ';'])?.to_string();
+		let mut new = class_name(self.mapper, &old);
+		self.output.push_str(&new);
+		loop {
+			if self.peek()? == '<' {
+				self.type_arguments()?;
+			}
+			match self.bump()? {
+				';' => return Some(()),
+				'.' => {
+					let inner = self.identifier(&['<', '.', ';'])?;
+					let old_inner = format!("{}${}", old, inner);
+					let new_inner = class_name(self.mapper, &old_inner);
+					let simple = if new_inner == old_inner {
+						inner
+					} else {
+						inner_name(&new_inner, Some(&new)).unwrap_or(inner)
+					};
+					self.output.push_str(simple);
+					old = old_inner;
+					new = new_inner;
+				}
+				_ => return None,
+			}
+		}
+	}
+
+	fn type_arguments(&mut self) -> Option<()> {
+		self.expect('<')?;
+		while self.peek()? != '>' {
+			match self.peek()? {
+				'*' => {
+					self.bump();
+				}
+				'+' | '-' => {
+					self.bump();
+					self.type_signature()?;
+				}
+				_ => self.type_signature()?,
+			}
+		}
+		self.bump();
+		Some(())
+	}
+}
+
+/// The simple name of an inner class, going by its new name, and that of its outer class if it has one.
+///
+/// Without an outer class, as with local classes, it's whatever follows the last `$`, minus the numbering javac adds.
+fn inner_name<'n>(name: &'n str, outer: Option<&str>) -> Option<&'n str> {
+	if let Some(outer) = outer {
+		if let Some(simple) = name.strip_prefix(outer).and_then(|rest| rest.strip_prefix('$')) {
+			return Some(simple);
+		}
+	}
+	let simple = name.rsplit(['$', '/']).next()?
+		.trim_start_matches(|c: char| c.is_ascii_digit());
+	if simple.is_empty() {
+		None
+	} else {
+		Some(simple)
+	}
+}
+
+/// Moves packages and classes somewhere else, the way shading does.
+///
+/// Nested classes follow the class they're nested in.
+///
+/// ```
+/// # use class_file::remap::*;
+/// let mut relocator = Relocator::new();
+/// relocator.package("com.google.common", "shaded.guava").class("org/example/Util", "shaded/Util");
+/// assert_eq!(relocator.map_class("com/google/common/base/Strings"), Some("shaded/guava/base/Strings".to_string()));
+/// assert_eq!(relocator.map_class("org/example/Util$Cache"), Some("shaded/Util$Cache".to_string()));
+/// assert_eq!(relocator.map_class("com/google/commonality/Other"), None);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Relocator {
+	classes: BTreeMap<String, String>,
+	packages: Vec<(String, String)>,
+	strings: bool,
+}
+
+impl Relocator {
+	pub fn new() -> Self {
+		Relocator::default()
+	}
+
+	/// Moves a package, and every package under it. Either dots or slashes will do.
+	pub fn package(&mut self, from: &str, to: &str) -> &mut Self {
+		self.packages.push((from.replace('.', "/"), to.replace('.', "/")));
+		// The most specific package wins.
+		self.packages.sort_by_key(|(from, _)| Reverse(from.len()));
+		self
+	}
+
+	/// Moves a single class, and whatever's nested in it, given internal names.
+	pub fn class(&mut self, from: &str, to: &str) -> &mut Self {
+		self.classes.insert(from.to_string(), to.to_string());
+		self
+	}
+
+	/// Whether string constants naming a relocated class or package, or a resource under one,
+	/// are rewritten too, with either dots or slashes. They're not by default,
+	/// as there's no telling whether a string is meant as a name.
+	pub fn strings(&mut self, strings: bool) -> &mut Self {
+		self.strings = strings;
+		self
+	}
+}
+
+impl Mapper for Relocator {
+	fn map_class(&self, name: &str) -> Option<String> {
+		for (from, to) in &self.classes {
+			if let Some(rest) = name.strip_prefix(from.as_str()) {
+				if rest.is_empty() || rest.starts_with('$') {
+					return Some(format!("{}{}", to, rest));
+				}
+			}
+		}
+		self.packages.iter()
+			.find_map(|(from, to)| {
+				let rest = name.strip_prefix(from.as_str())?.strip_prefix('/')?;
+				Some(format!("{}/{}", to, rest))
+			})
+	}
+
+	fn map_package(&self, name: &str) -> Option<String> {
+		self.packages.iter()
+			.find_map(|(from, to)| {
+				let rest = name.strip_prefix(from.as_str())?;
+				if rest.is_empty() || rest.starts_with('/') {
+					Some(format!("{}{}", to, rest))
+				} else {
+					None
+				}
+			})
+	}
+
+	fn map_string(&self, value: &str) -> Option<String> {
+		if !self.strings {
+			return None;
+		}
+		if value.contains('/') {
+			return self.map_class(value)
+				.or_else(|| self.map_package(value));
+		}
+		let name = value.replace('.', "/");
+		self.map_class(&name)
+			.or_else(|| self.map_package(&name))
+			.map(|name| name.replace('/', "."))
+	}
+}
+
+/// Renames everything in the class the mapper asks for, keeping the constant pool consistent.
+///
+/// `None` if the class is broken, or there's no room left in the constant pool for the new strings.
+pub fn remap<'a, M: Mapper + ?Sized>(class_file: &ClassFile<'a>, mapper: &M) -> Option<ClassFile<'a>> {
+	let mut remapper = Remapper {
+		mapper,
+		old: class_file.constant_pool.entries.clone(),
+		entries: class_file.constant_pool.entries.clone(),
+		utf8_claims: HashMap::new(),
+		utf8s: HashMap::new(),
+		name_and_type_claims: HashMap::new(),
+		name_and_types: HashMap::new(),
+	};
+	remapper.constant_pool()?;
+
+	let owner = remapper.class_name(class_file.this_class.index)?;
+	let mut result = class_file.clone();
+	for field in &mut result.fields {
+		let (name_index, descriptor_index) = remapper.member(&owner, field.name_index.index, field.descriptor_index.index, false)?;
+		field.name_index = CPIndex::new(name_index);
+		field.descriptor_index = CPIndex::new(descriptor_index);
+		field.attributes = remapper.attributes(&field.attributes)?;
+	}
+	for method in &mut result.methods {
+		let (name_index, descriptor_index) = remapper.member(&owner, method.name_index.index, method.descriptor_index.index, true)?;
+		method.name_index = CPIndex::new(name_index);
+		method.descriptor_index = CPIndex::new(descriptor_index);
+		method.attributes = remapper.attributes(&method.attributes)?;
+	}
+	result.attributes = remapper.attributes(&class_file.attributes)?;
+	result.constant_pool.entries = remapper.entries;
+	Some(result)
+}
+
+struct Remapper<'a, 'm, M: ?Sized> {
+	mapper: &'m M,
+	/// The pool as it was, which is what every name is looked up in.
+	old: Vec<CPEntry<'a>>,
+	entries: Vec<CPEntry<'a>>,
+	/// The value each string entry has been settled on, and the other way around, for sharing new entries.
+	utf8_claims: HashMap<u16, String>,
+	utf8s: HashMap<String, u16>,
+	name_and_type_claims: HashMap<u16, (String, String)>,
+	name_and_types: HashMap<(String, String), u16>,
+}
+
+impl<'a, 'm, M: Mapper + ?Sized> Remapper<'a, 'm, M> {
+	fn old_entry(&self, index: u16) -> Option<&CPEntry<'a>> {
+		self.old.get((index as usize).checked_sub(1)?)
+	}
+
+	fn old_utf8(&self, index: u16) -> Option<String> {
+		match self.old_entry(index)? {
+			CPEntry::UTF8(info) => Some(info.data.decoded()),
+			_ => None,
+		}
+	}
+
+	fn class_name(&self, index: u16) -> Option<String> {
+		match self.old_entry(index)? {
+			CPEntry::Class(info) => self.old_utf8(info.name_index.index),
+			_ => None,
+		}
+	}
+
+	fn old_name_and_type(&self, index: u16) -> Option<(u16, u16)> {
+		match self.old_entry(index)? {
+			CPEntry::NameAndType(info) => Some((info.name_index.index, info.descriptor_index.index)),
+			_ => None,
+		}
+	}
+
+	fn push(&mut self, entry: CPEntry<'a>) -> Option<u16> {
+		// The count written out is one more than the number of entries, and has to fit in a u16.
+		if self.entries.len() + 2 > u16::MAX as usize {
+			return None;
+		}
+		self.entries.push(entry);
+		Some(self.entries.len() as u16)
+	}
+
+	/// The index a use of the string at `index` should point at for it to read `value`.
+	///
+	/// The first use of an entry decides what it holds, later uses that disagree get another entry.
+	fn utf8(&mut self, index: u16, value: &str) -> Option<u16> {
+		match self.utf8_claims.get(&index) {
+			Some(claimed) if claimed == value => return Some(index),
+			Some(_) => {}
+			None => {
+				let unchanged = match self.old_entry(index)? {
+					CPEntry::UTF8(info) => *info == *value,
+					_ => return None,
+				};
+				// Leaving it alone keeps any malformed bytes as they were.
+				if !unchanged {
+					self.entries[index as usize - 1] = CPEntry::UTF8(UTF8Info::new(value));
+				}
+				self.utf8_claims.insert(index, value.to_string());
+				self.utf8s.entry(value.to_string()).or_insert(index);
+				return Some(index);
+			}
+		}
+		if let Some(&index) = self.utf8s.get(value) {
+			return Some(index);
+		}
+		let index = self.push(CPEntry::UTF8(UTF8Info::new(value)))?;
+		self.utf8_claims.insert(index, value.to_string());
+		self.utf8s.insert(value.to_string(), index);
+		Some(index)
+	}
+
+	/// Like `utf8`, but for a string that stays as it is, so nothing else can change it from under its user.
+	fn keep(&mut self, index: u16) -> Option<u16> {
+		let value = self.old_utf8(index)?;
+		self.utf8(index, &value)
+	}
+
+	fn name_and_type(&mut self, index: u16, name: &str, descriptor: &str) -> Option<u16> {
+		let (name_index, descriptor_index) = self.old_name_and_type(index)?;
+		let value = (name.to_string(), descriptor.to_string());
+		let claimed = match self.name_and_type_claims.get(&index) {
+			Some(claimed) if *claimed == value => return Some(index),
+			Some(_) => true,
+			None => false,
+		};
+		if claimed {
+			if let Some(&index) = self.name_and_types.get(&value) {
+				return Some(index);
+			}
+		}
+		let info = NameAndTypeInfo {
+			name_index: CPIndex::new(self.utf8(name_index, name)?),
+			descriptor_index: CPIndex::new(self.utf8(descriptor_index, descriptor)?),
+		};
+		let index = if claimed {
+			self.push(CPEntry::NameAndType(info))?
+		} else {
+			self.entries[index as usize - 1] = CPEntry::NameAndType(info);
+			index
+		};
+		self.name_and_type_claims.insert(index, value.clone());
+		self.name_and_types.entry(value).or_insert(index);
+		Some(index)
+	}
+
+	fn map_member(&self, owner: &str, name: &str, descriptor: &str, method: bool) -> String {
+		let mapped = if !method {
+			self.mapper.map_field(owner, name, descriptor)
+		} else if name.starts_with('<') {
+			None
+		} else {
+			self.mapper.map_method(owner, name, descriptor)
+		};
+		mapped.unwrap_or_else(|| name.to_string())
+	}
+
+	/// The new name and descriptor indices of a member of `owner`.
+	fn member(&mut self, owner: &str, name_index: u16, descriptor_index: u16, method: bool) -> Option<(u16, u16)> {
+		let name = self.old_utf8(name_index)?;
+		let old_descriptor = self.old_utf8(descriptor_index)?;
+		let name = self.map_member(owner, &name, &old_descriptor, method);
+		let descriptor = descriptor(self.mapper, &old_descriptor);
+		Some((self.utf8(name_index, &name)?, self.utf8(descriptor_index, &descriptor)?))
+	}
+
+	/// The new index of the `NameAndTypeInfo` of a reference to a member of `owner`.
+	fn member_ref(&mut self, owner: Option<&str>, index: u16, method: bool) -> Option<u16> {
+		let (name_index, descriptor_index) = self.old_name_and_type(index)?;
+		let name = self.old_utf8(name_index)?;
+		let old_descriptor = self.old_utf8(descriptor_index)?;
+		let name = match owner {
+			Some(owner) => self.map_member(owner, &name, &old_descriptor, method),
+			None => name,
+		};
+		let descriptor = descriptor(self.mapper, &old_descriptor);
+		self.name_and_type(index, &name, &descriptor)
+	}
+
+	fn constant_pool(&mut self) -> Option<()> {
+		for i in 0..self.old.len() {
+			let index = i as u16 + 1;
+			let entry = match self.old[i].clone() {
+				CPEntry::Class(ClassInfo { name_index }) => {
+					let name = class_name(self.mapper, &self.old_utf8(name_index.index)?);
+					CPEntry::Class(ClassInfo { name_index: CPIndex::new(self.utf8(name_index.index, &name)?) })
+				}
+				CPEntry::String(StringInfo { string_index }) => {
+					let value = self.old_utf8(string_index.index)?;
+					let value = self.mapper.map_string(&value).unwrap_or(value);
+					CPEntry::String(StringInfo { string_index: CPIndex::new(self.utf8(string_index.index, &value)?) })
+				}
+				CPEntry::MethodType(MethodTypeInfo { descriptor_index }) => {
+					let descriptor = descriptor(self.mapper, &self.old_utf8(descriptor_index.index)?);
+					CPEntry::MethodType(MethodTypeInfo { descriptor_index: CPIndex::new(self.utf8(descriptor_index.index, &descriptor)?) })
+				}
+				CPEntry::Package(PackageInfo { name_index }) => {
+					let name = self.old_utf8(name_index.index)?;
+					let name = self.mapper.map_package(&name).unwrap_or(name);
+					CPEntry::Package(PackageInfo { name_index: CPIndex::new(self.utf8(name_index.index, &name)?) })
+				}
+				CPEntry::Module(ModuleInfo { name_index }) => {
+					CPEntry::Module(ModuleInfo { name_index: CPIndex::new(self.keep(name_index.index)?) })
+				}
+				CPEntry::FieldRef(FieldRefInfo { class_index, name_and_type_index }) => {
+					let owner = self.class_name(class_index.index)?;
+					let name_and_type_index = CPIndex::new(self.member_ref(Some(&owner), name_and_type_index.index, false)?);
+					CPEntry::FieldRef(FieldRefInfo { class_index, name_and_type_index })
+				}
+				CPEntry::MethodRef(MethodRefInfo { class_index, name_and_type_index }) => {
+					let owner = self.class_name(class_index.index)?;
+					let name_and_type_index = CPIndex::new(self.member_ref(Some(&owner), name_and_type_index.index, true)?);
+					CPEntry::MethodRef(MethodRefInfo { class_index, name_and_type_index })
+				}
+				CPEntry::InterfaceMethodRef(InterfaceMethodRefInfo { class_index, name_and_type_index }) => {
+					let owner = self.class_name(class_index.index)?;
+					let name_and_type_index = CPIndex::new(self.member_ref(Some(&owner), name_and_type_index.index, true)?);
+					CPEntry::InterfaceMethodRef(InterfaceMethodRefInfo { class_index, name_and_type_index })
+				}
+				// The names of call sites and dynamic constants are up to their bootstrap methods, so only the types change.
+				CPEntry::Dynamic(DynamicInfo { bootstrap_method_attr_index, name_and_type_index }) => {
+					let name_and_type_index = CPIndex::new(self.member_ref(None, name_and_type_index.index, false)?);
+					CPEntry::Dynamic(DynamicInfo { bootstrap_method_attr_index, name_and_type_index })
+				}
+				CPEntry::InvokeDynamic(InvokeDynamicInfo { bootstrap_method_attr_index, name_and_type_index }) => {
+					let name_and_type_index = CPIndex::new(self.member_ref(None, name_and_type_index.index, true)?);
+					CPEntry::InvokeDynamic(InvokeDynamicInfo { bootstrap_method_attr_index, name_and_type_index })
+				}
+				_ => continue,
+			};
+			self.entries[index as usize - 1] = entry;
+		}
+		Some(())
+	}
+
+	fn attributes(&mut self, attributes: &Attributes<'a>) -> Option<Attributes<'a>> {
+		let mut result = vec![];
+		for attribute in attributes.iter() {
+			let name_index = attribute.name_index().index;
+			let name = self.old_utf8(name_index)?;
+			let name_index = CPIndex::new(self.keep(name_index)?);
+			let info = match self.attribute(&name, attribute.info()) {
+				Some(info) => info,
+				// Whatever can't be decoded is copied as it is.
+				None => attribute.info().to_vec(),
+			};
+			result.push(AttributeInfo::new(name_index, info));
+		}
+		Some(Attributes::new(result))
+	}
+
+	fn attribute(&mut self, name: &str, info: &[u8]) -> Option<Vec<u8>> {
+		match name {
+			"Code" => {
+				let mut code: Code = decode(info)?;
+				code.attributes = self.attributes(&code.attributes)?;
+				encode(&code)
+			}
+			"Signature" => {
+				let mut attribute: Signature = decode(info)?;
+				let value = signature(self.mapper, &self.old_utf8(attribute.class_index.index)?);
+				attribute.class_index = CPIndex::new(self.utf8(attribute.class_index.index, &value)?);
+				encode(&attribute)
+			}
+			"SourceFile" => {
+				let mut attribute: SourceFile = decode(info)?;
+				attribute.sourcefile_index = CPIndex::new(self.keep(attribute.sourcefile_index.index)?);
+				encode(&attribute)
+			}
+			"InnerClasses" => {
+				let mut attribute: InnerClasses = decode(info)?;
+				for inner in &mut attribute.table {
+					if let Some(index) = inner.inner_name_index {
+						let old = self.old_utf8(index.index)?;
+						let old_name = self.class_name(inner.inner_class_info_index.index)?;
+						let name = class_name(self.mapper, &old_name);
+						let outer = match inner.outer_class_info_index {
+							Some(outer) => Some(class_name(self.mapper, &self.class_name(outer.index)?)),
+							None => None,
+						};
+						let simple = if name == old_name {
+							old.clone()
+						} else {
+							inner_name(&name, outer.as_deref()).unwrap_or(&old).to_string()
+						};
+						inner.inner_name_index = Some(CPIndex::new(self.utf8(index.index, &simple)?));
+					}
+				}
+				encode(&attribute)
+			}
+			"EnclosingMethod" => {
+				let mut attribute: EnclosingMethod = decode(info)?;
+				// Zero when the class isn't directly inside a method.
+				if attribute.method_index.index != 0 {
+					let owner = self.class_name(attribute.class_index.index)?;
+					attribute.method_index = CPIndex::new(self.member_ref(Some(&owner), attribute.method_index.index, true)?);
+				}
+				encode(&attribute)
+			}
+			"LocalVariableTable" => {
+				let mut attribute: LocalVariableTable = decode(info)?;
+				for variable in &mut attribute.table {
+					variable.name_index = CPIndex::new(self.keep(variable.name_index.index)?);
+					let value = descriptor(self.mapper, &self.old_utf8(variable.descriptor_index.index)?);
+					variable.descriptor_index = CPIndex::new(self.utf8(variable.descriptor_index.index, &value)?);
+				}
+				encode(&attribute)
+			}
+			"LocalVariableTypeTable" => {
+				let mut attribute: LocalVariableTypeTable = decode(info)?;
+				for variable in &mut attribute.table {
+					variable.name_index = CPIndex::new(self.keep(variable.name_index.index)?);
+					let value = signature(self.mapper, &self.old_utf8(variable.signature_index.index)?);
+					variable.signature_index = CPIndex::new(self.utf8(variable.signature_index.index, &value)?);
+				}
+				encode(&attribute)
+			}
+			"MethodParameters" => {
+				let mut attribute: MethodParameters = decode(info)?;
+				for parameter in &mut attribute.table {
+					if let Some(index) = parameter.name_index {
+						parameter.name_index = Some(CPIndex::new(self.keep(index.index)?));
+					}
+				}
+				encode(&attribute)
+			}
+			"RuntimeVisibleAnnotations" => {
+				let mut attribute: RuntimeVisibleAnnotations = decode(info)?;
+				self.annotations(&mut attribute.table)?;
+				encode(&attribute)
+			}
+			"RuntimeInvisibleAnnotations" => {
+				let mut attribute: RuntimeInvisibleAnnotations = decode(info)?;
+				self.annotations(&mut attribute.table)?;
+				encode(&attribute)
+			}
+			"RuntimeVisibleParameterAnnotations" => {
+				let mut attribute: RuntimeVisibleParameterAnnotations = decode(info)?;
+				for parameter in &mut attribute.table {
+					self.annotations(&mut parameter.annotations)?;
+				}
+				encode(&attribute)
+			}
+			"RuntimeInvisibleParameterAnnotations" => {
+				let mut attribute: RuntimeInvisibleParameterAnnotations = decode(info)?;
+				for parameter in &mut attribute.table {
+					self.annotations(&mut parameter.annotations)?;
+				}
+				encode(&attribute)
+			}
+			"RuntimeVisibleTypeAnnotations" => {
+				let mut attribute: RuntimeVisibleTypeAnnotations = decode(info)?;
+				self.type_annotations(&mut attribute.table)?;
+				encode(&attribute)
+			}
+			"RuntimeInvisibleTypeAnnotations" => {
+				let mut attribute: RuntimeInvisibleTypeAnnotations = decode(info)?;
+				self.type_annotations(&mut attribute.table)?;
+				encode(&attribute)
+			}
+			"AnnotationDefault" => {
+				let mut attribute: AnnotationDefault = decode(info)?;
+				self.element_value(&mut attribute.default_value)?;
+				encode(&attribute)
+			}
+			"Module" => {
+				let mut attribute: attr::Module = decode(info)?;
+				if let Some(index) = attribute.module_version_index {
+					attribute.module_version_index = Some(CPIndex::new(self.keep(index.index)?));
+				}
+				for requires in &mut attribute.requires {
+					if let Some(index) = requires.requires_version_index {
+						requires.requires_version_index = Some(CPIndex::new(self.keep(index.index)?));
+					}
+				}
+				encode(&attribute)
+			}
+			_ => None,
+		}
+	}
+
+	fn annotations(&mut self, annotations: &mut [Annotation<'a>]) -> Option<()> {
+		for annotation in annotations {
+			self.annotation(annotation)?;
+		}
+		Some(())
+	}
+
+	fn type_annotations(&mut self, annotations: &mut [TypeAnnotation<'a>]) -> Option<()> {
+		for annotation in annotations {
+			let value = descriptor(self.mapper, &self.old_utf8(annotation.type_index.index)?);
+			annotation.type_index = CPIndex::new(self.utf8(annotation.type_index.index, &value)?);
+			self.element_value_pairs(&mut annotation.element_value_pairs)?;
+		}
+		Some(())
+	}
+
+	fn annotation(&mut self, annotation: &mut Annotation<'a>) -> Option<()> {
+		let value = descriptor(self.mapper, &self.old_utf8(annotation.type_index.index)?);
+		annotation.type_index = CPIndex::new(self.utf8(annotation.type_index.index, &value)?);
+		self.element_value_pairs(&mut annotation.element_value_pairs)
+	}
+
+	/// The element names are left alone, as their descriptors can't be told from the values.
+	fn element_value_pairs(&mut self, pairs: &mut [ElementValuePair<'a>]) -> Option<()> {
+		for pair in pairs {
+			pair.element_name_index = CPIndex::new(self.keep(pair.element_name_index.index)?);
+			self.element_value(&mut pair.element_value)?;
+		}
+		Some(())
+	}
+
+	fn element_value(&mut self, value: &mut ElementValue<'a>) -> Option<()> {
+		match value {
+			ElementValue::String(index) => {
+				let old = self.old_utf8(index.index)?;
+				let new = self.mapper.map_string(&old).unwrap_or(old);
+				*index = CPIndex::new(self.utf8(index.index, &new)?);
+			}
+			ElementValue::Class(index) => {
+				let new = descriptor(self.mapper, &self.old_utf8(index.index)?);
+				*index = CPIndex::new(self.utf8(index.index, &new)?);
+			}
+			ElementValue::Enum { type_name_index, const_name_index } => {
+				let old = self.old_utf8(type_name_index.index)?;
+				let name = self.old_utf8(const_name_index.index)?;
+				let name = match old.strip_prefix('L').and_then(|owner| owner.strip_suffix(';')) {
+					Some(owner) => self.map_member(owner, &name, &old, false),
+					None => name,
+				};
+				let new = descriptor(self.mapper, &old);
+				*type_name_index = CPIndex::new(self.utf8(type_name_index.index, &new)?);
+				*const_name_index = CPIndex::new(self.utf8(const_name_index.index, &name)?);
+			}
+			ElementValue::Annotation(annotation) => self.annotation(annotation)?,
+			ElementValue::Array(values) => {
+				for value in values {
+					self.element_value(value)?;
+				}
+			}
+			_ => {}
+		}
+		Some(())
+	}
+}
+
+fn decode<T: FromBytes<BigEndian, Output = T>>(info: &[u8]) -> Option<T> {
+	T::from_bytes(&mut Cursor::new(info)).ok()
+}
+
+fn encode<T: ToBytes<BigEndian>>(value: &T) -> Option<Vec<u8>> {
+	let mut output = vec![];
+	value.to_bytes(&mut output).ok()?;
+	Some(output)
+}
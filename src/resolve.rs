@@ -0,0 +1,283 @@
+//! Symbolic views of the constant pool's member references.
+//!
+//! Going from a `MethodRefInfo` to something readable normally means chasing
+//! `class_index`, `name_and_type_index`, `name_index` and `descriptor_index` by hand.
+//! The `Resolve` trait does all of that in one go, and hands back small `Copy` structs
+//! that borrow their strings straight out of the constant pool.
+//!
+//! All of them implement `Eq` and `Hash`, so they're usable as map keys,
+//! and `Display`, which uses the same format as `javap`.
+
+use std::fmt;
+
+use crate::*;
+use crate::utf8::MStrExt;
+
+pub trait Resolve<'a> {
+	type Resolved;
+
+	fn resolve(&self, cp: &'a ConstantPool<'a>) -> Option<Self::Resolved>;
+}
+
+impl<'a> ConstantPool<'a> {
+	/// Fetches the entry the index points at, and resolves it in one go.
+	pub fn resolve<T>(&'a self, index: CPIndex<'a, T>) -> Option<T::Resolved>
+		where T: 'a + CPType<'a, Output = &'a T> + Resolve<'a> {
+		self.index(index)?.resolve(self)
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct NameAndType<'a> {
+	pub name: &'a mstr,
+	pub descriptor: &'a mstr,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct FieldRef<'a> {
+	pub owner: &'a mstr,
+	pub name: &'a mstr,
+	pub descriptor: &'a mstr,
+}
+
+/// Both `MethodRefInfo` and `InterfaceMethodRefInfo` resolve to this,
+/// with `interface` recording which of the two it came from.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct MethodRef<'a> {
+	pub owner: &'a mstr,
+	pub name: &'a mstr,
+	pub descriptor: &'a mstr,
+	pub interface: bool,
+}
+
+/// The bootstrap method itself lives in the class's `BootstrapMethods` attribute,
+/// so only its index is carried here.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct InvokeDynamic<'a> {
+	pub bootstrap_method_attr_index: u16,
+	pub name: &'a mstr,
+	pub descriptor: &'a mstr,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct Dynamic<'a> {
+	pub bootstrap_method_attr_index: u16,
+	pub name: &'a mstr,
+	pub descriptor: &'a mstr,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Reference<'a> {
+	Field(FieldRef<'a>),
+	Method(MethodRef<'a>),
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct MethodHandle<'a> {
+	pub reference_kind: u8,
+	pub reference: Reference<'a>,
+}
+
+impl<'a> Resolve<'a> for ClassInfo<'a> {
+	type Resolved = &'a mstr;
+
+	fn resolve(&self, cp: &'a ConstantPool<'a>) -> Option<Self::Resolved> {
+		cp.utf8(self.name_index)
+	}
+}
+
+impl<'a> Resolve<'a> for NameAndTypeInfo<'a> {
+	type Resolved = NameAndType<'a>;
+
+	fn resolve(&self, cp: &'a ConstantPool<'a>) -> Option<Self::Resolved> {
+		Some(NameAndType {
+			name: cp.utf8(self.name_index)?,
+			descriptor: cp.utf8(self.descriptor_index)?,
+		})
+	}
+}
+
+impl<'a> Resolve<'a> for FieldRefInfo<'a> {
+	type Resolved = FieldRef<'a>;
+
+	fn resolve(&self, cp: &'a ConstantPool<'a>) -> Option<Self::Resolved> {
+		let owner = cp.class_name(self.class_index)?;
+		let nat = cp.resolve(self.name_and_type_index)?;
+		Some(FieldRef {
+			owner,
+			name: nat.name,
+			descriptor: nat.descriptor,
+		})
+	}
+}
+
+impl<'a> Resolve<'a> for MethodRefInfo<'a> {
+	type Resolved = MethodRef<'a>;
+
+	fn resolve(&self, cp: &'a ConstantPool<'a>) -> Option<Self::Resolved> {
+		let owner = cp.class_name(self.class_index)?;
+		let nat = cp.resolve(self.name_and_type_index)?;
+		Some(MethodRef {
+			owner,
+			name: nat.name,
+			descriptor: nat.descriptor,
+			interface: false,
+		})
+	}
+}
+
+impl<'a> Resolve<'a> for InterfaceMethodRefInfo<'a> {
+	type Resolved = MethodRef<'a>;
+
+	fn resolve(&self, cp: &'a ConstantPool<'a>) -> Option<Self::Resolved> {
+		let owner = cp.class_name(self.class_index)?;
+		let nat = cp.resolve(self.name_and_type_index)?;
+		Some(MethodRef {
+			owner,
+			name: nat.name,
+			descriptor: nat.descriptor,
+			interface: true,
+		})
+	}
+}
+
+impl<'a> Resolve<'a> for InvokeDynamicInfo<'a> {
+	type Resolved = InvokeDynamic<'a>;
+
+	fn resolve(&self, cp: &'a ConstantPool<'a>) -> Option<Self::Resolved> {
+		let nat = cp.resolve(self.name_and_type_index)?;
+		Some(InvokeDynamic {
+			bootstrap_method_attr_index: self.bootstrap_method_attr_index,
+			name: nat.name,
+			descriptor: nat.descriptor,
+		})
+	}
+}
+
+impl<'a> Resolve<'a> for DynamicInfo<'a> {
+	type Resolved = Dynamic<'a>;
+
+	fn resolve(&self, cp: &'a ConstantPool<'a>) -> Option<Self::Resolved> {
+		let nat = cp.resolve(self.name_and_type_index)?;
+		Some(Dynamic {
+			bootstrap_method_attr_index: self.bootstrap_method_attr_index,
+			name: nat.name,
+			descriptor: nat.descriptor,
+		})
+	}
+}
+
+impl<'a> Resolve<'a> for MethodHandleInfo<'a> {
+	type Resolved = MethodHandle<'a>;
+
+	fn resolve(&self, cp: &'a ConstantPool<'a>) -> Option<Self::Resolved> {
+		let reference = match *self {
+			MethodHandleInfo::FieldRef { reference_index, .. } => {
+				Reference::Field(cp.resolve(reference_index)?)
+			}
+			MethodHandleInfo::MethodRef { reference_index, .. } => {
+				// REF_invokeStatic and REF_invokeSpecial are allowed to point at an interface method,
+				// so fall back to that if it's not a plain method reference.
+				let method = match cp.resolve(reference_index) {
+					Some(method) => method,
+					None => cp.resolve(CPIndex::<InterfaceMethodRefInfo>::new(reference_index.index))?,
+				};
+				Reference::Method(method)
+			}
+			MethodHandleInfo::InterfaceMethodRef { reference_index, .. } => {
+				Reference::Method(cp.resolve(reference_index)?)
+			}
+		};
+		Some(MethodHandle {
+			reference_kind: self.reference_kind(),
+			reference,
+		})
+	}
+}
+
+/// The name `javap` and `java.lang.invoke.MethodHandleInfo` use for a `reference_kind`.
+pub fn reference_kind_name(reference_kind: u8) -> Option<&'static str> {
+	let name = match reference_kind {
+		H_GETFIELD => "REF_getField",
+		H_GETSTATIC => "REF_getStatic",
+		H_PUTFIELD => "REF_putField",
+		H_PUTSTATIC => "REF_putStatic",
+		H_INVOKEVIRTUAL => "REF_invokeVirtual",
+		H_INVOKESTATIC => "REF_invokeStatic",
+		H_INVOKESPECIAL => "REF_invokeSpecial",
+		H_NEWINVOKESPECIAL => "REF_newInvokeSpecial",
+		H_INVOKEINTERFACE => "REF_invokeInterface",
+		_ => return None,
+	};
+	Some(name)
+}
+
+/// What `javap` would print as the comment for a constant, or `None` for ones it doesn't comment on.
+pub fn constant(cp: &ConstantPool, index: u16) -> Option<String> {
+	let entry = cp.entries.get((index as usize).checked_sub(1)?)?;
+	let result = match entry {
+		CPEntry::Class(info) => cp.utf8(info.name_index)?.decoded(),
+		CPEntry::FieldRef(info) => info.resolve(cp)?.to_string(),
+		CPEntry::MethodRef(info) => info.resolve(cp)?.to_string(),
+		CPEntry::InterfaceMethodRef(info) => info.resolve(cp)?.to_string(),
+		CPEntry::String(info) => format!("{:?}", cp.utf8(info.string_index)?.decoded()),
+		CPEntry::Integer(info) => (info.value as i32).to_string(),
+		CPEntry::Float(info) => format!("{}f", f32::from_bits(info.value)),
+		CPEntry::Long(info) => format!("{}l", ((info.high_bytes as u64) << 32 | info.low_bytes as u64) as i64),
+		CPEntry::Double(info) => format!("{}d", f64::from_bits((info.high_bytes as u64) << 32 | info.low_bytes as u64)),
+		CPEntry::MethodType(info) => cp.utf8(info.descriptor_index)?.decoded(),
+		CPEntry::MethodHandle(info) => info.resolve(cp)?.to_string(),
+		CPEntry::Dynamic(info) => info.resolve(cp)?.to_string(),
+		CPEntry::InvokeDynamic(info) => info.resolve(cp)?.to_string(),
+		_ => return None,
+	};
+	Some(result)
+}
+
+impl fmt::Display for NameAndType<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}", self.name.to_utf8(), self.descriptor.to_utf8())
+	}
+}
+
+impl fmt::Display for FieldRef<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}.{}:{}", self.owner.to_utf8(), self.name.to_utf8(), self.descriptor.to_utf8())
+	}
+}
+
+impl fmt::Display for MethodRef<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}.{}:{}", self.owner.to_utf8(), self.name.to_utf8(), self.descriptor.to_utf8())
+	}
+}
+
+impl fmt::Display for InvokeDynamic<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "#{}:{}:{}", self.bootstrap_method_attr_index, self.name.to_utf8(), self.descriptor.to_utf8())
+	}
+}
+
+impl fmt::Display for Dynamic<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "#{}:{}:{}", self.bootstrap_method_attr_index, self.name.to_utf8(), self.descriptor.to_utf8())
+	}
+}
+
+impl fmt::Display for Reference<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Reference::Field(field) => field.fmt(f),
+			Reference::Method(method) => method.fmt(f),
+		}
+	}
+}
+
+impl fmt::Display for MethodHandle<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match reference_kind_name(self.reference_kind) {
+			Some(name) => write!(f, "{} {}", name, self.reference),
+			None => write!(f, "{} {}", self.reference_kind, self.reference),
+		}
+	}
+}
@@ -0,0 +1,272 @@
+//! Serde support, behind the `serde` feature, in two flavours.
+//!
+//! The raw one is just the `ClassFile` itself, along with the constant pool, its entries and every attribute type,
+//! with indices as plain numbers and attributes as their bytes, so writing a deserialized class gives back the exact same bytes.
+//! Strings are written as strings when they survive decoding, and as their bytes when they don't.
+//!
+//! The resolved one is `ResolvedClass`, which has the names, descriptors and constants inlined and the code disassembled,
+//! for reading rather than writing back out.
+
+use std::fmt;
+use std::io::Cursor;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, SeqAccess, Visitor};
+
+use crate::*;
+use crate::attr::{Code, ConstantValue, Exceptions, Signature, SourceDebugExtension, SourceFile};
+use crate::bytecode::{self, Operand};
+use crate::resolve::constant;
+use crate::utf8::MStrExt;
+
+impl<'a, T: 'a + CPType<'a>> Serialize for CPIndex<'a, T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_u16(self.index)
+	}
+}
+
+impl<'de, 'a, T: 'a + CPType<'a>> Deserialize<'de> for CPIndex<'a, T> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		u16::deserialize(deserializer).map(CPIndex::new)
+	}
+}
+
+fn serialize_mutf8<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+	let decoded = utf8::decode(bytes);
+	if utf8::validate(bytes).is_ok() && utf8::encode(&decoded) == bytes {
+		serializer.serialize_str(&decoded)
+	} else {
+		serializer.serialize_bytes(bytes)
+	}
+}
+
+/// Takes either a string, which is encoded, or the bytes as they are.
+struct Mutf8Visitor;
+
+impl<'de> Visitor<'de> for Mutf8Visitor {
+	type Value = Vec<u8>;
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("a string, or its modified UTF-8 bytes")
+	}
+
+	fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+		Ok(utf8::encode(value))
+	}
+
+	fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+		Ok(value.to_vec())
+	}
+
+	fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+		Ok(value)
+	}
+
+	fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+		let mut bytes = Vec::new();
+		while let Some(byte) = seq.next_element()? {
+			bytes.push(byte);
+		}
+		Ok(bytes)
+	}
+}
+
+fn deserialize_mutf8<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+	deserializer.deserialize_any(Mutf8Visitor)
+}
+
+impl<'a> Serialize for UTF8Info<'a> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serialize_mutf8(self.as_bytes(), serializer)
+	}
+}
+
+impl<'de, 'a> Deserialize<'de> for UTF8Info<'a> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserialize_mutf8(deserializer).map(UTF8Info::from_mutf8_lossy)
+	}
+}
+
+impl<'a> Serialize for SourceDebugExtension<'a> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serialize_mutf8(self.data.as_bytes(), serializer)
+	}
+}
+
+impl<'de, 'a> Deserialize<'de> for SourceDebugExtension<'a> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserialize_mutf8(deserializer).map(|data| SourceDebugExtension::new(utf8::from_mutf8_lossy(data)))
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
+pub struct ResolvedClass {
+	pub minor_version: u16,
+	pub major_version: u16,
+	pub access_flags: u16,
+	pub name: String,
+	/// `None` for `java/lang/Object` and `module-info`.
+	pub super_class: Option<String>,
+	pub interfaces: Vec<String>,
+	pub fields: Vec<ResolvedMember>,
+	pub methods: Vec<ResolvedMember>,
+	pub attributes: Vec<ResolvedAttribute>,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
+pub struct ResolvedMember {
+	pub access_flags: u16,
+	pub name: String,
+	pub descriptor: String,
+	pub attributes: Vec<ResolvedAttribute>,
+}
+
+/// The attributes worth spelling out, with the rest left as their bytes.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
+pub enum ResolvedAttribute {
+	Code {
+		max_stack: u16,
+		max_locals: u16,
+		instructions: Vec<ResolvedInstruction>,
+		exception_table: Vec<ResolvedHandler>,
+		attributes: Vec<ResolvedAttribute>,
+	},
+	ConstantValue(String),
+	Exceptions(Vec<String>),
+	Signature(String),
+	SourceFile(String),
+	Other {
+		name: String,
+		info: Vec<u8>,
+	},
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
+pub struct ResolvedInstruction {
+	pub pc: u32,
+	/// The instruction the way `javap -c` prints it, such as `invokevirtual #7`.
+	pub text: String,
+	/// What the constant the instruction refers to is, such as `java/io/PrintStream.println:(Ljava/lang/String;)V`.
+	pub constant: Option<String>,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
+pub struct ResolvedHandler {
+	pub start_pc: u16,
+	pub end_pc: u16,
+	pub handler_pc: u16,
+	/// `None` for `finally` handlers, which catch everything.
+	pub catch_type: Option<String>,
+}
+
+fn decode<T: FromBytes<BigEndian, Output = T>>(data: &[u8]) -> Option<T> {
+	let mut input = Cursor::new(data);
+	let value = T::from_bytes(&mut input).ok()?;
+	if input.position() as usize != data.len() {
+		return None;
+	}
+	Some(value)
+}
+
+/// Class names, except for 0, which some places use to mean there isn't one.
+fn class_name(cp: &ConstantPool, index: u16) -> Option<Option<String>> {
+	if index == 0 {
+		return Some(None);
+	}
+	cp.class_name(CPIndex::new(index)).map(|name| Some(name.decoded()))
+}
+
+fn resolve_attributes(attributes: &Attributes, cp: &ConstantPool) -> Option<Vec<ResolvedAttribute>> {
+	attributes.iter()
+		.map(|attribute| {
+			let name = cp.utf8(attribute.name_index())?.decoded();
+			let info = attribute.info();
+			Some(resolve_attribute(&name, info, cp).unwrap_or_else(|| ResolvedAttribute::Other {
+				name,
+				info: info.to_vec(),
+			}))
+		})
+		.collect()
+}
+
+/// `None` if the attribute isn't one that's spelled out, or it can't be decoded.
+fn resolve_attribute(name: &str, info: &[u8], cp: &ConstantPool) -> Option<ResolvedAttribute> {
+	let attribute = match name {
+		"Code" => {
+			let code = decode::<Code>(info)?;
+			let instructions = bytecode::decode(&code.code).ok()?
+				.into_iter()
+				.map(|instruction| {
+					let index = match instruction.operand {
+						Operand::Constant(index) | Operand::InvokeInterface { index, .. } | Operand::MultiANewArray { index, .. } => Some(index),
+						_ => None,
+					};
+					ResolvedInstruction {
+						pc: instruction.pc,
+						text: instruction.to_string(),
+						constant: index.and_then(|index| constant(cp, index)),
+					}
+				})
+				.collect();
+			let exception_table = code.exception_table.iter()
+				.map(|exception| Some(ResolvedHandler {
+					start_pc: exception.start_pc,
+					end_pc: exception.end_pc,
+					handler_pc: exception.handler_pc,
+					catch_type: class_name(cp, exception.catch_type.index)?,
+				}))
+				.collect::<Option<_>>()?;
+			ResolvedAttribute::Code {
+				max_stack: code.max_stack,
+				max_locals: code.max_locals,
+				instructions,
+				exception_table,
+				attributes: resolve_attributes(&code.attributes, cp)?,
+			}
+		}
+		"ConstantValue" => ResolvedAttribute::ConstantValue(constant(cp, decode::<ConstantValue>(info)?.constantvalue_index.index)?),
+		"Exceptions" => ResolvedAttribute::Exceptions(decode::<Exceptions>(info)?.table.iter()
+			.map(|&index| cp.class_name(index).map(MStrExt::decoded))
+			.collect::<Option<_>>()?),
+		"Signature" => ResolvedAttribute::Signature(cp.utf8(decode::<Signature>(info)?.class_index)?.decoded()),
+		"SourceFile" => ResolvedAttribute::SourceFile(cp.utf8(decode::<SourceFile>(info)?.sourcefile_index)?.decoded()),
+		_ => return None,
+	};
+	Some(attribute)
+}
+
+fn resolve_members<'a>(members: impl Iterator<Item = (u16, CPIndex<'a, UTF8Info<'a>>, CPIndex<'a, UTF8Info<'a>>, &'a Attributes<'a>)>, cp: &'a ConstantPool<'a>) -> Option<Vec<ResolvedMember>> {
+	members
+		.map(|(access_flags, name_index, descriptor_index, attributes)| Some(ResolvedMember {
+			access_flags,
+			name: cp.utf8(name_index)?.decoded(),
+			descriptor: cp.utf8(descriptor_index)?.decoded(),
+			attributes: resolve_attributes(attributes, cp)?,
+		}))
+		.collect()
+}
+
+impl ResolvedClass {
+	/// `None` if any of the names or descriptors can't be found in the constant pool.
+	/// Attributes that can't be decoded are kept as their bytes.
+	pub fn new(class_file: &ClassFile) -> Option<Self> {
+		let cp = &class_file.constant_pool;
+		let fields = class_file.fields.iter()
+			.map(|field| (field.access_flags, field.name_index, field.descriptor_index, &field.attributes));
+		let methods = class_file.methods.iter()
+			.map(|method| (method.access_flags, method.name_index, method.descriptor_index, &method.attributes));
+		Some(ResolvedClass {
+			minor_version: class_file.minor_version,
+			major_version: class_file.major_version,
+			access_flags: class_file.access_flags,
+			name: cp.class_name(class_file.this_class)?.decoded(),
+			super_class: class_name(cp, class_file.super_class.index)?,
+			interfaces: class_file.interfaces.iter()
+				.map(|&index| cp.class_name(index).map(MStrExt::decoded))
+				.collect::<Option<_>>()?,
+			fields: resolve_members(fields, cp)?,
+			methods: resolve_members(methods, cp)?,
+			attributes: resolve_attributes(&class_file.attributes, cp)?,
+		})
+	}
+}
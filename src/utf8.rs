@@ -0,0 +1,267 @@
+//! Modified UTF-8 support for the strings stored inside of a class file.
+//!
+//! The JVM's flavour of UTF-8 differs from the standard one in two ways:
+//! the null character is encoded using two bytes (`0xC0 0x80`), so a string never contains a zero byte,
+//! and supplementary characters are encoded as a surrogate pair, with each surrogate taking three bytes.
+//!
+//! This module validates, encodes and compares those bytes without going through `String` first.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+use crate::{mstr, MString};
+
+/// The replacement character (U+FFFD), as it's encoded in modified UTF-8.
+const REPLACEMENT: [u8; 3] = [0xEF, 0xBF, 0xBD];
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct Mutf8Error {
+	valid_up_to: usize,
+}
+
+impl Mutf8Error {
+	/// The index in the given bytes up to which valid modified UTF-8 was verified.
+	pub fn valid_up_to(&self) -> usize {
+		self.valid_up_to
+	}
+}
+
+impl fmt::Display for Mutf8Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "invalid modified utf-8 sequence at index {}", self.valid_up_to)
+	}
+}
+
+impl Error for Mutf8Error {}
+
+/// Returns the length of the valid sequence at the start of the input, if there is one.
+fn sequence_len(input: &[u8]) -> Option<usize> {
+	#[inline]
+	fn continuation(byte: Option<&u8>) -> bool {
+		match byte {
+			Some(byte) => byte & 0xC0 == 0x80,
+			None => false,
+		}
+	}
+
+	let first = *input.first()?;
+	match first {
+		// The null character is never encoded with a single byte.
+		0x00 => None,
+		0x01..=0x7F => Some(1),
+		// Only the null character is allowed to use an overlong encoding.
+		0xC0 => if input.get(1) == Some(&0x80) { Some(2) } else { None },
+		0xC2..=0xDF => if continuation(input.get(1)) { Some(2) } else { None },
+		0xE0..=0xEF => {
+			if !continuation(input.get(1)) || !continuation(input.get(2)) {
+				return None;
+			}
+			// Anything below U+0800 should have been encoded with fewer bytes.
+			if first == 0xE0 && input[1] < 0xA0 {
+				return None;
+			}
+			Some(3)
+		}
+		// 0x80..=0xBF are continuation bytes, 0xC1 is always overlong, and modified UTF-8 has no four byte form.
+		_ => None,
+	}
+}
+
+/// Checks that the given bytes are well-formed modified UTF-8.
+///
+/// Unpaired surrogates are accepted, as a `java.lang.String` is allowed to contain them.
+pub fn validate(input: &[u8]) -> Result<(), Mutf8Error> {
+	let mut index = 0;
+	while index < input.len() {
+		match sequence_len(&input[index..]) {
+			Some(len) => index += len,
+			None => return Err(Mutf8Error {
+				valid_up_to: index,
+			}),
+		}
+	}
+	Ok(())
+}
+
+/// Encodes a single character into the given buffer, and returns the part of the buffer that was used.
+pub fn encode_char(c: char, buf: &mut [u8; 6]) -> &[u8] {
+	#[inline]
+	fn encode_unit(unit: u32, buf: &mut [u8]) -> usize {
+		if unit != 0 && unit < 0x80 {
+			buf[0] = unit as u8;
+			1
+		} else if unit < 0x800 {
+			buf[0] = (0xC0 | (unit >> 6)) as u8;
+			buf[1] = (0x80 | (unit & 0x3F)) as u8;
+			2
+		} else {
+			buf[0] = (0xE0 | (unit >> 12)) as u8;
+			buf[1] = (0x80 | ((unit >> 6) & 0x3F)) as u8;
+			buf[2] = (0x80 | (unit & 0x3F)) as u8;
+			3
+		}
+	}
+
+	let value = c as u32;
+	let len = if value < 0x10000 {
+		encode_unit(value, &mut buf[..])
+	} else {
+		let value = value - 0x10000;
+		let high = 0xD800 | (value >> 10);
+		let low = 0xDC00 | (value & 0x3FF);
+		let len = encode_unit(high, &mut buf[..]);
+		len + encode_unit(low, &mut buf[len..])
+	};
+	&buf[..len]
+}
+
+/// Encodes the string into modified UTF-8.
+pub fn encode(input: &str) -> Vec<u8> {
+	let mut buf = [0u8; 6];
+	let mut output = Vec::with_capacity(input.len());
+	for c in input.chars() {
+		output.extend_from_slice(encode_char(c, &mut buf));
+	}
+	output
+}
+
+/// Compares the modified UTF-8 bytes against the string, without allocating.
+pub fn eq_str(input: &[u8], other: &str) -> bool {
+	let mut rest = input;
+	let mut buf = [0u8; 6];
+	for c in other.chars() {
+		let encoded = encode_char(c, &mut buf);
+		if !rest.starts_with(encoded) {
+			return false;
+		}
+		rest = &rest[encoded.len()..];
+	}
+	rest.is_empty()
+}
+
+/// Decodes the modified UTF-8 bytes into a standard string.
+///
+/// Surrogate pairs are combined, and anything that has no standard UTF-8 equivalent,
+/// such as an unpaired surrogate or an invalid sequence, comes out as the replacement character (U+FFFD).
+/// If the input happens to already be standard UTF-8, it's borrowed as is.
+pub fn decode(input: &[u8]) -> Cow<'_, str> {
+	// Without an encoded null character or any surrogates, valid modified UTF-8 is standard UTF-8,
+	// but standard UTF-8 isn't always valid modified UTF-8, as it has four byte sequences.
+	if !input.iter().any(|&b| b == 0xC0 || b == 0xED) && validate(input).is_ok() {
+		if let Ok(value) = std::str::from_utf8(input) {
+			return Cow::Borrowed(value);
+		}
+	}
+
+	#[inline]
+	fn unit(input: &[u8], len: usize) -> u32 {
+		match len {
+			1 => input[0] as u32,
+			2 => ((input[0] as u32 & 0x1F) << 6) | (input[1] as u32 & 0x3F),
+			_ => ((input[0] as u32 & 0x0F) << 12) | ((input[1] as u32 & 0x3F) << 6) | (input[2] as u32 & 0x3F),
+		}
+	}
+
+	let mut output = String::with_capacity(input.len());
+	let mut index = 0;
+	while index < input.len() {
+		let len = match sequence_len(&input[index..]) {
+			Some(len) => len,
+			None => {
+				output.push('\u{FFFD}');
+				index += 1;
+				continue;
+			}
+		};
+		let value = unit(&input[index..], len);
+		index += len;
+		if (0xD800..0xDC00).contains(&value) {
+			let rest = &input[index..];
+			if sequence_len(rest) == Some(3) {
+				let low = unit(rest, 3);
+				if (0xDC00..0xE000).contains(&low) {
+					let value = 0x10000 + ((value - 0xD800) << 10) + (low - 0xDC00);
+					output.push(std::char::from_u32(value).unwrap_or('\u{FFFD}'));
+					index += 3;
+					continue;
+				}
+			}
+		}
+		output.push(std::char::from_u32(value).unwrap_or('\u{FFFD}'));
+	}
+	Cow::Owned(output)
+}
+
+/// Copies the input, replacing every invalid sequence with the replacement character (U+FFFD).
+pub fn repair(input: &[u8]) -> Vec<u8> {
+	let mut output = Vec::with_capacity(input.len());
+	let mut index = 0;
+	while index < input.len() {
+		match sequence_len(&input[index..]) {
+			Some(len) => {
+				output.extend_from_slice(&input[index..index + len]);
+				index += len;
+			}
+			None => {
+				output.extend_from_slice(&REPLACEMENT);
+				index += 1;
+			}
+		}
+	}
+	output
+}
+
+/// Validates the bytes before handing them over to `MString`.
+pub fn from_mutf8(input: Vec<u8>) -> Result<MString, Mutf8Error> {
+	validate(&input)?;
+	Ok(unsafe { MString::from_mutf8_unchecked(input) })
+}
+
+/// Like `from_mutf8`, but invalid sequences are replaced, instead of rejecting the entire string.
+pub fn from_mutf8_lossy(input: Vec<u8>) -> MString {
+	let data = if validate(&input).is_ok() {
+		input
+	} else {
+		repair(&input)
+	};
+	unsafe { MString::from_mutf8_unchecked(data) }
+}
+
+pub fn from_str(input: &str) -> MString {
+	unsafe { MString::from_mutf8_unchecked(encode(input)) }
+}
+
+/// `mstr` lives in another crate, so it can't implement `Display` here.
+/// This wraps one so it can be used with `format!` and friends.
+#[derive(Debug, Clone, Copy)]
+pub struct Display<'a>(pub &'a mstr);
+
+impl fmt::Display for Display<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&decode(self.0.as_bytes()))
+	}
+}
+
+pub trait MStrExt {
+	fn display(&self) -> Display<'_>;
+
+	fn eq_str(&self, other: &str) -> bool;
+
+	/// The value as a `String`, decoded from modified UTF-8.
+	fn decoded(&self) -> String;
+}
+
+impl MStrExt for mstr {
+	fn display(&self) -> Display<'_> {
+		Display(self)
+	}
+
+	fn eq_str(&self, other: &str) -> bool {
+		eq_str(self.as_bytes(), other)
+	}
+
+	fn decoded(&self) -> String {
+		decode(self.as_bytes()).into_owned()
+	}
+}
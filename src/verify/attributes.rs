@@ -0,0 +1,252 @@
+//! Which attributes may appear where, following JVMS table 4.7-C.
+//!
+//! Covers the context each attribute is defined for, the first class file version that recognises it,
+//! and the attributes that may appear at most once in a given attribute table.
+//! Attributes the JVMS doesn't define are none of its business, so they're skipped.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+
+use crate::*;
+use crate::attr::{Code, Record};
+use crate::verify::Location;
+
+/// The structures that carry an attribute table.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Context {
+	ClassFile,
+	FieldInfo,
+	MethodInfo,
+	Code,
+	RecordComponent,
+}
+
+impl fmt::Display for Context {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match self {
+			Context::ClassFile => "ClassFile",
+			Context::FieldInfo => "field_info",
+			Context::MethodInfo => "method_info",
+			Context::Code => "Code",
+			Context::RecordComponent => "record_component_info",
+		};
+		f.write_str(name)
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct AttributeSpec {
+	pub name: &'static str,
+	pub contexts: &'static [Context],
+	/// The first class file version, as `(major, minor)`, that recognises the attribute.
+	pub since: (u16, u16),
+	pub at_most_one: bool,
+}
+
+const CLASS: &[Context] = &[Context::ClassFile];
+const FIELD: &[Context] = &[Context::FieldInfo];
+const METHOD: &[Context] = &[Context::MethodInfo];
+const CODE: &[Context] = &[Context::Code];
+const MEMBER: &[Context] = &[Context::ClassFile, Context::FieldInfo, Context::MethodInfo];
+const COMPONENT: &[Context] = &[Context::ClassFile, Context::FieldInfo, Context::MethodInfo, Context::RecordComponent];
+const TYPE_ANNOTATION: &[Context] = &[
+	Context::ClassFile,
+	Context::FieldInfo,
+	Context::MethodInfo,
+	Context::Code,
+	Context::RecordComponent,
+];
+
+macro_rules! specs {
+	(
+		$( $name:ident: $contexts:ident, ($major:literal, $minor:literal), $at_most_one:literal; )*
+	) => {
+		/// Every attribute defined by the JVMS, in the order of table 4.7-C.
+		pub const ATTRIBUTES: &[AttributeSpec] = &[
+			$(
+				AttributeSpec {
+					name: stringify!($name),
+					contexts: $contexts,
+					since: ($major, $minor),
+					at_most_one: $at_most_one,
+				},
+			)*
+		];
+	};
+}
+
+specs! {
+	ConstantValue: FIELD, (45, 3), true;
+	Code: METHOD, (45, 3), true;
+	StackMapTable: CODE, (50, 0), true;
+	Exceptions: METHOD, (45, 3), true;
+	InnerClasses: CLASS, (45, 3), true;
+	EnclosingMethod: CLASS, (49, 0), true;
+	Synthetic: MEMBER, (45, 3), false;
+	Signature: COMPONENT, (49, 0), true;
+	SourceFile: CLASS, (45, 3), true;
+	SourceDebugExtension: CLASS, (49, 0), true;
+	LineNumberTable: CODE, (45, 3), false;
+	LocalVariableTable: CODE, (45, 3), false;
+	LocalVariableTypeTable: CODE, (49, 0), false;
+	Deprecated: MEMBER, (45, 3), false;
+	RuntimeVisibleAnnotations: COMPONENT, (49, 0), true;
+	RuntimeInvisibleAnnotations: COMPONENT, (49, 0), true;
+	RuntimeVisibleParameterAnnotations: METHOD, (49, 0), true;
+	RuntimeInvisibleParameterAnnotations: METHOD, (49, 0), true;
+	RuntimeVisibleTypeAnnotations: TYPE_ANNOTATION, (52, 0), true;
+	RuntimeInvisibleTypeAnnotations: TYPE_ANNOTATION, (52, 0), true;
+	AnnotationDefault: METHOD, (49, 0), true;
+	BootstrapMethods: CLASS, (51, 0), true;
+	MethodParameters: METHOD, (52, 0), true;
+	Module: CLASS, (53, 0), true;
+	ModulePackages: CLASS, (53, 0), true;
+	ModuleMainClass: CLASS, (53, 0), true;
+	NestHost: CLASS, (55, 0), true;
+	NestMembers: CLASS, (55, 0), true;
+	Record: CLASS, (60, 0), true;
+	PermittedSubclasses: CLASS, (61, 0), true;
+}
+
+/// Pairs of attributes that can't share an attribute table.
+const CONFLICTS: &[(&str, &str)] = &[
+	("NestHost", "NestMembers"),
+];
+
+pub fn spec(name: &str) -> Option<&'static AttributeSpec> {
+	ATTRIBUTES.iter()
+		.find(|spec| spec.name == name)
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Problem {
+	/// The `attribute_name_index` doesn't point at a `UTF8Info`.
+	InvalidName(u16),
+	/// The attribute isn't defined for this context.
+	Misplaced(Context),
+	/// The attribute may appear at most once, but appeared `count` times.
+	Duplicated(usize),
+	/// The class file version, as `(major, minor)`, predates the attribute.
+	Unsupported((u16, u16)),
+	/// The attribute can't appear alongside the named one.
+	Conflict(&'static str),
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Diagnostic {
+	pub location: Location,
+	pub attribute: String,
+	pub problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.problem {
+			Problem::InvalidName(index) => {
+				write!(f, "{}: attribute name #{} is not a Utf8 constant", self.location, index)
+			}
+			Problem::Misplaced(context) => {
+				write!(f, "{}: {} is not allowed in {}", self.location, self.attribute, context)
+			}
+			Problem::Duplicated(count) => {
+				write!(f, "{}: {} may appear at most once, found {}", self.location, self.attribute, count)
+			}
+			Problem::Unsupported((major, minor)) => {
+				write!(f, "{}: {} requires class file version {}.{}", self.location, self.attribute, major, minor)
+			}
+			Problem::Conflict(other) => {
+				write!(f, "{}: {} can't appear alongside {}", self.location, self.attribute, other)
+			}
+		}
+	}
+}
+
+struct Validator<'c, 'a> {
+	cp: &'c ConstantPool<'a>,
+	version: (u16, u16),
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'c, 'a> Validator<'c, 'a> {
+	fn check(&mut self, attributes: &Attributes<'a>, context: Context, location: Location) {
+		let mut counts: HashMap<&'static str, usize> = HashMap::new();
+		for info in attributes.iter() {
+			let name = match self.cp.utf8(info.name_index()) {
+				Some(name) => utf8::decode(name.as_bytes()),
+				None => {
+					self.report(location, String::new(), Problem::InvalidName(info.name_index().index));
+					continue;
+				}
+			};
+			let spec = match spec(&name) {
+				Some(spec) => spec,
+				None => continue,
+			};
+			if !spec.contexts.contains(&context) {
+				self.report(location, spec.name.to_string(), Problem::Misplaced(context));
+				continue;
+			}
+			if self.version < spec.since {
+				self.report(location, spec.name.to_string(), Problem::Unsupported(spec.since));
+			}
+			*counts.entry(spec.name).or_insert(0) += 1;
+		}
+
+		// Walking the table, rather than the map, keeps the order stable.
+		for spec in ATTRIBUTES {
+			match counts.get(spec.name) {
+				Some(&count) if count > 1 && spec.at_most_one => {
+					self.report(location, spec.name.to_string(), Problem::Duplicated(count));
+				}
+				_ => {}
+			}
+		}
+		for &(first, second) in CONFLICTS {
+			if counts.contains_key(first) && counts.contains_key(second) {
+				self.report(location, second.to_string(), Problem::Conflict(first));
+			}
+		}
+	}
+
+	fn report(&mut self, location: Location, attribute: String, problem: Problem) {
+		self.diagnostics.push(Diagnostic {
+			location,
+			attribute,
+			problem,
+		});
+	}
+}
+
+/// Checks every attribute table in the class, including the ones nested inside of `Code` and `Record` attributes.
+pub fn validate(class_file: &ClassFile) -> Vec<Diagnostic> {
+	let cp = &class_file.constant_pool;
+	let mut validator = Validator {
+		cp,
+		version: (class_file.major_version, class_file.minor_version),
+		diagnostics: vec![],
+	};
+
+	validator.check(&class_file.attributes, Context::ClassFile, Location::Class);
+	for info in class_file.attributes.named_all(cp, "Record") {
+		// A malformed `Record` has no components to check.
+		if let Ok(record) = <Record as FromBytes<BigEndian>>::from_bytes(&mut Cursor::new(info.info())) {
+			for (index, component) in record.components.iter().enumerate() {
+				validator.check(&component.attributes, Context::RecordComponent, Location::RecordComponent(index));
+			}
+		}
+	}
+	for (index, field) in class_file.fields.iter().enumerate() {
+		validator.check(&field.attributes, Context::FieldInfo, Location::Field(index));
+	}
+	for (index, method) in class_file.methods.iter().enumerate() {
+		validator.check(&method.attributes, Context::MethodInfo, Location::Method(index));
+		for info in method.attributes.named_all(cp, "Code") {
+			let mut input = Cursor::new(info.info());
+			if let Ok(code) = <Code as FromBytes<BigEndian>>::from_bytes(&mut input) {
+				validator.check(&code.attributes, Context::Code, Location::Code(index));
+			}
+		}
+	}
+	validator.diagnostics
+}
@@ -0,0 +1,511 @@
+//! The static format checks from JVMS 4.8, the ones a JVM performs before it even looks at the bytecode.
+//!
+//! That covers the kind of constant every index points at, names and descriptors,
+//! flag combinations, duplicate members, the super class, code lengths,
+//! and constants that the class file version doesn't support yet.
+//! Attribute placement is left to `verify::attributes`.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Cursor;
+
+use crate::*;
+use crate::attr::Code;
+use crate::descriptor::*;
+use crate::utf8::MStrExt;
+use crate::verify::Location;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Problem {
+	/// The index doesn't point at a constant of the expected kind.
+	InvalidIndex {
+		index: u16,
+		expected: &'static str,
+	},
+	InvalidClassName(String),
+	/// A field, method or other unqualified name.
+	InvalidName(String),
+	InvalidDescriptor(String),
+	/// The parameters of a method take up more than 255 slots, including `this`.
+	TooManyParameters(usize),
+	InvalidFlags {
+		flags: u16,
+		reason: &'static str,
+	},
+	/// A second field or method with the same name and descriptor.
+	DuplicateMember {
+		name: String,
+		descriptor: String,
+	},
+	MissingSuperClass,
+	/// `java/lang/Object` and module-info can't have a super class.
+	UnexpectedSuperClass,
+	/// The super class of an interface has to be `java/lang/Object`, and it can't be an array.
+	InvalidSuperClass(String),
+	MissingCode,
+	/// Abstract and native methods can't have a `Code` attribute.
+	UnexpectedCode,
+	MalformedCode,
+	/// The code has to be between 1 and 65535 bytes long.
+	InvalidCodeLength(usize),
+	/// The constant with the given tag isn't supported by the class file version, or outside of module-info.
+	UnsupportedConstant(u8),
+	InvalidModule(&'static str),
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Diagnostic {
+	pub location: Location,
+	pub problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}: ", self.location)?;
+		match self.problem {
+			Problem::InvalidIndex { index, expected } => write!(f, "#{} is not a {} constant", index, expected),
+			Problem::InvalidClassName(ref name) => write!(f, "invalid class name {:?}", name),
+			Problem::InvalidName(ref name) => write!(f, "invalid name {:?}", name),
+			Problem::InvalidDescriptor(ref descriptor) => write!(f, "invalid descriptor {:?}", descriptor),
+			Problem::TooManyParameters(slots) => write!(f, "parameters take up {} slots, at most 255 are allowed", slots),
+			Problem::InvalidFlags { flags, reason } => write!(f, "invalid flags 0x{:04x}, {}", flags, reason),
+			Problem::DuplicateMember { ref name, ref descriptor } => write!(f, "duplicate member {}:{}", name, descriptor),
+			Problem::MissingSuperClass => f.write_str("missing super class"),
+			Problem::UnexpectedSuperClass => f.write_str("unexpected super class"),
+			Problem::InvalidSuperClass(ref name) => write!(f, "invalid super class {}", name),
+			Problem::MissingCode => f.write_str("missing Code attribute"),
+			Problem::UnexpectedCode => f.write_str("abstract and native methods can't have a Code attribute"),
+			Problem::MalformedCode => f.write_str("malformed Code attribute"),
+			Problem::InvalidCodeLength(len) => write!(f, "code length {} is not between 1 and 65535", len),
+			Problem::UnsupportedConstant(tag) => write!(f, "constant with tag {} is not allowed here", tag),
+			Problem::InvalidModule(reason) => write!(f, "invalid module-info, {}", reason),
+		}
+	}
+}
+
+const ACCESS: u16 = PUBLIC | PRIVATE | PROTECTED;
+
+fn has_one_access(flags: u16) -> bool {
+	(flags & ACCESS).count_ones() <= 1
+}
+
+/// The first class file version, as `(major, minor)`, that supports the constant with the given tag.
+fn constant_since(tag: u8) -> (u16, u16) {
+	match tag {
+		CONSTANT_METHOD_HANDLE_TAG | CONSTANT_METHOD_TYPE_TAG | CONSTANT_INVOKE_DYNAMIC_TAG => (51, 0),
+		CONSTANT_MODULE_TAG | CONSTANT_PACKAGE_TAG => (53, 0),
+		CONSTANT_DYNAMIC_TAG => (55, 0),
+		_ => (45, 3),
+	}
+}
+
+struct Checker<'a> {
+	cp: &'a ConstantPool<'a>,
+	version: (u16, u16),
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+	fn report(&mut self, location: Location, problem: Problem) {
+		self.diagnostics.push(Diagnostic {
+			location,
+			problem,
+		});
+	}
+
+	fn index<T: 'a + CPType<'a>>(&mut self, location: Location, index: CPIndex<'a, T>, expected: &'static str) -> Option<T::Output> {
+		let value = self.cp.index(index);
+		if value.is_none() {
+			self.report(location, Problem::InvalidIndex { index: index.index, expected });
+		}
+		value
+	}
+
+	fn string(&mut self, location: Location, index: CPIndex<'a, UTF8Info<'a>>) -> Option<String> {
+		self.index(location, index, "Utf8")
+			.map(|info| info.data.decoded())
+	}
+
+	fn class_name(&mut self, location: Location, index: CPIndex<'a, ClassInfo<'a>>) -> Option<String> {
+		let info = self.index(location, index, "Class")?;
+		// The name itself is checked along with the rest of the constant pool.
+		self.cp.utf8(info.name_index)
+			.map(MStrExt::decoded)
+	}
+
+	fn name_and_type(&mut self, location: Location, index: CPIndex<'a, NameAndTypeInfo<'a>>) -> Option<(String, String)> {
+		let info = self.index(location, index, "NameAndType")?;
+		let name = self.string(location, info.name_index);
+		let descriptor = self.string(location, info.descriptor_index);
+		Some((name?, descriptor?))
+	}
+
+	fn field_descriptor(&mut self, location: Location, descriptor: &str) {
+		if FieldType::parse(descriptor).is_none() {
+			self.report(location, Problem::InvalidDescriptor(descriptor.to_string()));
+		}
+	}
+
+	/// Checks the descriptor, counting `this` towards the parameter limit unless the method is static.
+	fn method_descriptor(&mut self, location: Location, descriptor: &str, is_static: bool) -> Option<MethodDescriptor> {
+		let parsed = match MethodDescriptor::parse(descriptor) {
+			Some(parsed) => parsed,
+			None => {
+				self.report(location, Problem::InvalidDescriptor(descriptor.to_string()));
+				return None;
+			}
+		};
+		let slots = parsed.parameter_slots() + if is_static { 0 } else { 1 };
+		if slots > MAX_PARAMETER_SLOTS {
+			self.report(location, Problem::TooManyParameters(slots));
+		}
+		Some(parsed)
+	}
+
+	/// The name of the member a field or method reference points at.
+	fn member_name(&self, index: u16) -> Option<String> {
+		let name_and_type_index = match self.cp.entries.get((index as usize).checked_sub(1)?)? {
+			CPEntry::FieldRef(info) => info.name_and_type_index,
+			CPEntry::MethodRef(info) => info.name_and_type_index,
+			CPEntry::InterfaceMethodRef(info) => info.name_and_type_index,
+			_ => return None,
+		};
+		let info = self.cp.index(name_and_type_index)?;
+		self.cp.utf8(info.name_index)
+			.map(MStrExt::decoded)
+	}
+
+	fn check_constant_pool(&mut self, is_module: bool) {
+		let cp = self.cp;
+		for (i, entry) in cp.entries.iter().enumerate() {
+			let location = Location::Constant(i as u16 + 1);
+			let tag = entry.tag();
+			let module_only = tag == CONSTANT_MODULE_TAG || tag == CONSTANT_PACKAGE_TAG;
+			if self.version < constant_since(tag) || (module_only && !is_module) {
+				self.report(location, Problem::UnsupportedConstant(tag));
+			}
+
+			match entry {
+				CPEntry::Class(info) => {
+					if let Some(name) = self.string(location, info.name_index) {
+						if !is_class_name(&name) {
+							self.report(location, Problem::InvalidClassName(name));
+						}
+					}
+				}
+				CPEntry::FieldRef(info) => {
+					self.index(location, info.class_index, "Class");
+					if let Some((name, descriptor)) = self.name_and_type(location, info.name_and_type_index) {
+						if !is_unqualified_name(&name) {
+							self.report(location, Problem::InvalidName(name));
+						}
+						self.field_descriptor(location, &descriptor);
+					}
+				}
+				CPEntry::MethodRef(MethodRefInfo { class_index, name_and_type_index, .. })
+				| CPEntry::InterfaceMethodRef(InterfaceMethodRefInfo { class_index, name_and_type_index, .. }) => {
+					self.index(location, *class_index, "Class");
+					if let Some((name, descriptor)) = self.name_and_type(location, *name_and_type_index) {
+						let interface = tag == CONSTANT_INTERFACE_METHODREF_TAG;
+						if !is_method_name(&name) || name == "<clinit>" || (interface && name == "<init>") {
+							self.report(location, Problem::InvalidName(name.clone()));
+						}
+						// The parameter limit is checked on the method itself.
+						if let Some(parsed) = self.method_descriptor(location, &descriptor, true) {
+							if name == "<init>" && parsed.return_type.is_some() {
+								self.report(location, Problem::InvalidDescriptor(descriptor));
+							}
+						}
+					}
+				}
+				CPEntry::String(info) => {
+					self.string(location, info.string_index);
+				}
+				CPEntry::NameAndType(info) => {
+					// The name and descriptor are checked by whatever refers to this.
+					self.string(location, info.name_index);
+					self.string(location, info.descriptor_index);
+				}
+				CPEntry::MethodHandle(info) => self.check_method_handle(location, info),
+				CPEntry::MethodType(info) => {
+					if let Some(descriptor) = self.string(location, info.descriptor_index) {
+						self.method_descriptor(location, &descriptor, true);
+					}
+				}
+				CPEntry::Dynamic(info) => {
+					if let Some((name, descriptor)) = self.name_and_type(location, info.name_and_type_index) {
+						if !is_unqualified_name(&name) {
+							self.report(location, Problem::InvalidName(name));
+						}
+						self.field_descriptor(location, &descriptor);
+					}
+				}
+				CPEntry::InvokeDynamic(info) => {
+					if let Some((name, descriptor)) = self.name_and_type(location, info.name_and_type_index) {
+						if !is_method_name(&name) || name.starts_with('<') {
+							self.report(location, Problem::InvalidName(name));
+						}
+						self.method_descriptor(location, &descriptor, true);
+					}
+				}
+				CPEntry::Module(info) => {
+					if let Some(name) = self.string(location, info.name_index) {
+						if name.is_empty() {
+							self.report(location, Problem::InvalidName(name));
+						}
+					}
+				}
+				CPEntry::Package(info) => {
+					if let Some(name) = self.string(location, info.name_index) {
+						if !is_binary_name(&name) {
+							self.report(location, Problem::InvalidName(name));
+						}
+					}
+				}
+				CPEntry::Integer(_) | CPEntry::Float(_) | CPEntry::Long(_) | CPEntry::Double(_)
+				| CPEntry::UTF8(_) | CPEntry::Unusable(_) => {}
+			}
+		}
+	}
+
+	fn check_method_handle(&mut self, location: Location, info: &'a MethodHandleInfo<'a>) {
+		let kind = info.reference_kind();
+		match *info {
+			MethodHandleInfo::FieldRef { reference_index, .. } => {
+				self.index(location, reference_index, "Fieldref");
+			}
+			MethodHandleInfo::MethodRef { reference_index, .. } => {
+				// As of 52.0, invokeStatic and invokeSpecial may refer to interface methods as well.
+				let interface = CPIndex::<InterfaceMethodRefInfo>::new(reference_index.index);
+				let allows_interface = self.version >= (52, 0) && (kind == H_INVOKESTATIC || kind == H_INVOKESPECIAL);
+				if self.cp.index(reference_index).is_none() && !(allows_interface && self.cp.index(interface).is_some()) {
+					let expected = if allows_interface { "Methodref or InterfaceMethodref" } else { "Methodref" };
+					self.report(location, Problem::InvalidIndex { index: reference_index.index, expected });
+				}
+			}
+			MethodHandleInfo::InterfaceMethodRef { reference_index, .. } => {
+				self.index(location, reference_index, "InterfaceMethodref");
+			}
+		}
+
+		if let Some(name) = self.member_name(info.reference_index()) {
+			let valid = match kind {
+				H_NEWINVOKESPECIAL => name == "<init>",
+				H_INVOKEVIRTUAL | H_INVOKESTATIC | H_INVOKESPECIAL | H_INVOKEINTERFACE => name != "<init>" && name != "<clinit>",
+				_ => true,
+			};
+			if !valid {
+				self.report(location, Problem::InvalidName(name));
+			}
+		}
+	}
+
+	fn check_class(&mut self, class_file: &'a ClassFile<'a>, is_module: bool) -> bool {
+		let location = Location::Class;
+		let flags = class_file.access_flags;
+		let is_interface = flags & INTERFACE != 0;
+
+		let reason = if is_module {
+			if flags != MODULE {
+				Some("module-info can't have any other flags")
+			} else {
+				None
+			}
+		} else if is_interface {
+			if flags & ABSTRACT == 0 {
+				Some("interfaces must be abstract")
+			} else if flags & (FINAL | SUPER | ENUM | MODULE) != 0 {
+				Some("interfaces can't be final, super, enum or module")
+			} else {
+				None
+			}
+		} else if flags & ANNOTATION != 0 {
+			Some("only interfaces can be annotations")
+		} else if flags & FINAL != 0 && flags & ABSTRACT != 0 {
+			Some("classes can't be both final and abstract")
+		} else {
+			None
+		};
+		if let Some(reason) = reason {
+			self.report(location, Problem::InvalidFlags { flags, reason });
+		}
+
+		let this_class = self.class_name(location, class_file.this_class);
+		if let Some(ref name) = this_class {
+			if name.starts_with('[') {
+				self.report(location, Problem::InvalidClassName(name.clone()));
+			}
+		}
+
+		let is_object = this_class.as_deref() == Some("java/lang/Object");
+		if is_object || is_module {
+			if class_file.super_class.index != 0 {
+				self.report(location, Problem::UnexpectedSuperClass);
+			}
+		} else if class_file.super_class.index == 0 {
+			self.report(location, Problem::MissingSuperClass);
+		} else if let Some(name) = self.class_name(location, class_file.super_class) {
+			if name.starts_with('[') || (is_interface && name != "java/lang/Object") {
+				self.report(location, Problem::InvalidSuperClass(name));
+			}
+		}
+
+		for &interface in &class_file.interfaces {
+			self.index(location, interface, "Class");
+		}
+
+		if is_module {
+			let reason = if this_class.as_deref().is_some_and(|name| name != MODULE_INFO) {
+				Some("the class has to be named module-info")
+			} else if !class_file.interfaces.is_empty() || !class_file.fields.is_empty() || !class_file.methods.is_empty() {
+				Some("it can't have any interfaces, fields or methods")
+			} else if class_file.attributes.named_all(self.cp, "Module").next().is_none() {
+				Some("it needs a Module attribute")
+			} else {
+				None
+			};
+			if let Some(reason) = reason {
+				self.report(location, Problem::InvalidModule(reason));
+			}
+		}
+		is_interface
+	}
+
+	fn check_field(&mut self, location: Location, field: &'a FieldInfo<'a>, is_interface: bool) -> Option<(String, String)> {
+		let flags = field.access_flags;
+		let reason = if !has_one_access(flags) {
+			Some("at most one of public, private and protected may be set")
+		} else if flags & FINAL != 0 && flags & VOLATILE != 0 {
+			Some("fields can't be both final and volatile")
+		} else if is_interface && (flags & !SYNTHETIC) != (PUBLIC | STATIC | FINAL) {
+			Some("interface fields must be public, static and final")
+		} else {
+			None
+		};
+		if let Some(reason) = reason {
+			self.report(location, Problem::InvalidFlags { flags, reason });
+		}
+
+		let name = self.string(location, field.name_index);
+		let descriptor = self.string(location, field.descriptor_index);
+		if let Some(ref name) = name {
+			if !is_unqualified_name(name) {
+				self.report(location, Problem::InvalidName(name.clone()));
+			}
+		}
+		if let Some(ref descriptor) = descriptor {
+			self.field_descriptor(location, descriptor);
+		}
+		Some((name?, descriptor?))
+	}
+
+	fn check_method(&mut self, index: usize, method: &'a MethodInfo<'a>, is_interface: bool) -> Option<(String, String)> {
+		let location = Location::Method(index);
+		let flags = method.access_flags;
+		let name = self.string(location, method.name_index);
+		let descriptor = self.string(location, method.descriptor_index);
+		let is_init = name.as_deref() == Some("<init>");
+		// Before 51.0, <clinit> is only special if it's void and takes no arguments, and its flags are ignored.
+		let is_clinit = name.as_deref() == Some("<clinit>")
+			&& (self.version >= (51, 0) || descriptor.as_deref() == Some("()V"));
+
+		let reason = if is_clinit {
+			if self.version >= (51, 0) && flags & STATIC == 0 {
+				Some("class initialisers must be static")
+			} else {
+				None
+			}
+		} else if !has_one_access(flags) {
+			Some("at most one of public, private and protected may be set")
+		} else if is_interface && self.version < (52, 0) && (flags & !(BRIDGE | VARARGS | SYNTHETIC)) != (PUBLIC | ABSTRACT) {
+			Some("interface methods must be public and abstract before version 52.0")
+		} else if is_interface && flags & (PROTECTED | FINAL | SYNCHRONIZED | NATIVE) != 0 {
+			Some("interface methods can't be protected, final, synchronized or native")
+		} else if is_interface && flags & (PUBLIC | PRIVATE) == 0 {
+			Some("interface methods must be either public or private")
+		} else if flags & ABSTRACT != 0 && flags & (PRIVATE | STATIC | FINAL | SYNCHRONIZED | NATIVE) != 0 {
+			Some("abstract methods can't be private, static, final, synchronized or native")
+		} else if flags & ABSTRACT != 0 && flags & STRICT != 0 && self.version >= (46, 0) && self.version < (61, 0) {
+			Some("abstract methods can't be strict")
+		} else if is_init && flags & !(ACCESS | VARARGS | STRICT | SYNTHETIC) != 0 {
+			Some("instance initialisers may only be public, private, protected, varargs, strict or synthetic")
+		} else {
+			None
+		};
+		if let Some(reason) = reason {
+			self.report(location, Problem::InvalidFlags { flags, reason });
+		}
+
+		if let Some(ref name) = name {
+			if !is_method_name(name) || (is_init && is_interface) {
+				self.report(location, Problem::InvalidName(name.clone()));
+			}
+		}
+		if let Some(ref descriptor) = descriptor {
+			if let Some(parsed) = self.method_descriptor(location, descriptor, flags & STATIC != 0) {
+				let must_be_void = is_init || (is_clinit && self.version >= (51, 0));
+				if must_be_void && parsed.return_type.is_some() {
+					self.report(location, Problem::InvalidDescriptor(descriptor.clone()));
+				}
+			}
+		}
+
+		self.check_code(index, method);
+		Some((name?, descriptor?))
+	}
+
+	fn check_code(&mut self, index: usize, method: &'a MethodInfo<'a>) {
+		let location = Location::Method(index);
+		let needs_code = method.access_flags & (ABSTRACT | NATIVE) == 0;
+		let info = match (method.attributes.named_all(self.cp, "Code").next(), needs_code) {
+			(Some(info), true) => info,
+			(None, false) => return,
+			(Some(_), false) => return self.report(location, Problem::UnexpectedCode),
+			(None, true) => return self.report(location, Problem::MissingCode),
+		};
+
+		let location = Location::Code(index);
+		let mut input = Cursor::new(info.info());
+		match <Code as FromBytes<BigEndian>>::from_bytes(&mut input) {
+			Ok(ref code) if code.code.is_empty() || code.code.len() > u16::MAX as usize => {
+				self.report(location, Problem::InvalidCodeLength(code.code.len()));
+			}
+			Ok(_) => {}
+			Err(_) => self.report(location, Problem::MalformedCode),
+		}
+	}
+}
+
+/// Runs every check, collecting all of the problems rather than stopping at the first one.
+pub fn validate(class_file: &ClassFile) -> Vec<Diagnostic> {
+	let mut checker = Checker {
+		cp: &class_file.constant_pool,
+		version: (class_file.major_version, class_file.minor_version),
+		diagnostics: vec![],
+	};
+	let is_module = class_file.access_flags & MODULE != 0 && checker.version >= (53, 0);
+
+	checker.check_constant_pool(is_module);
+	let is_interface = checker.check_class(class_file, is_module);
+
+	let mut members = HashSet::new();
+	for (index, field) in class_file.fields.iter().enumerate() {
+		let location = Location::Field(index);
+		if let Some((name, descriptor)) = checker.check_field(location, field, is_interface) {
+			if !members.insert((name.clone(), descriptor.clone())) {
+				checker.report(location, Problem::DuplicateMember { name, descriptor });
+			}
+		}
+	}
+
+	// Fields and methods don't clash with each other.
+	let mut members = HashSet::new();
+	for (index, method) in class_file.methods.iter().enumerate() {
+		if let Some((name, descriptor)) = checker.check_method(index, method, is_interface) {
+			if !members.insert((name.clone(), descriptor.clone())) {
+				checker.report(Location::Method(index), Problem::DuplicateMember { name, descriptor });
+			}
+		}
+	}
+	checker.diagnostics
+}
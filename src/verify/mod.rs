@@ -0,0 +1,43 @@
+//! Checks that go beyond what's needed to simply parse a class file.
+//!
+//! The parser is happy to accept anything that's structurally sound,
+//! which is exactly what you want when reading, but not when generating classes.
+//! These passes catch the kind of mistakes that would otherwise only show up
+//! as a `ClassFormatError` or `VerifyError` once the class reaches a JVM.
+//!
+//! None of them stop at the first problem, they all hand back everything they found.
+
+use std::fmt;
+
+pub mod attributes;
+pub mod format;
+pub mod typecheck;
+
+/// Where in the class file a problem was found.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Location {
+	Class,
+	/// The constant pool entry with the given index.
+	Constant(u16),
+	/// The index into `ClassFile::fields`.
+	Field(usize),
+	/// The index into `ClassFile::methods`.
+	Method(usize),
+	/// The `Code` attribute of the method at the given index into `ClassFile::methods`.
+	Code(usize),
+	/// The component of the class's `Record` attribute with the given index.
+	RecordComponent(usize),
+}
+
+impl fmt::Display for Location {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Location::Class => f.write_str("class"),
+			Location::Constant(index) => write!(f, "constant #{}", index),
+			Location::Field(index) => write!(f, "field #{}", index),
+			Location::Method(index) => write!(f, "method #{}", index),
+			Location::Code(index) => write!(f, "code of method #{}", index),
+			Location::RecordComponent(index) => write!(f, "record component #{}", index),
+		}
+	}
+}
@@ -0,0 +1,1119 @@
+//! A type checking verifier in the style of JVMS 4.10.1.
+//!
+//! Every instruction is checked against the types the `StackMapTable` promises,
+//! rather than inferring them, so a single pass over the code is enough.
+//! Like the JVM, it gives up on a method at the first problem, but carries on with the next method.
+//!
+//! Classes older than 50.0 have no stack maps, and are verified by type inference instead, so they're skipped.
+//! Anything that needs other classes, such as assignability, is answered by a `ClassHierarchy`,
+//! which gives the benefit of the doubt for classes it doesn't know.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Cursor;
+use std::iter;
+
+use crate::*;
+use crate::attr::{Code, StackMapFrame, StackMapTable, VerificationTypeInfo};
+use crate::bytecode::{self, DecodeError, Instruction, Operand};
+use crate::descriptor::{FieldType, MethodDescriptor};
+use crate::hierarchy::{ClassHierarchy, OBJECT};
+use crate::resolve::{MethodRef, Resolve};
+use crate::utf8::MStrExt;
+use crate::verify::Location;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Type {
+	Top,
+	Integer,
+	Float,
+	Long,
+	Double,
+	Null,
+	/// `this` in a constructor, before the super or another constructor has been called.
+	UninitializedThis,
+	/// The result of the `new` instruction at the given offset, before its constructor has been called.
+	Uninitialized(u16),
+	/// A class or interface by its internal name, or an array by its descriptor.
+	Object(String),
+}
+
+impl Type {
+	pub fn object(name: &str) -> Type {
+		Type::Object(name.to_string())
+	}
+
+	/// `boolean`, `byte`, `char` and `short` all become `Integer`.
+	pub fn from_field_type(field_type: &FieldType) -> Type {
+		match field_type {
+			FieldType::Byte | FieldType::Char | FieldType::Short | FieldType::Boolean | FieldType::Int => Type::Integer,
+			FieldType::Float => Type::Float,
+			FieldType::Long => Type::Long,
+			FieldType::Double => Type::Double,
+			FieldType::Object(name) => Type::Object(name.clone()),
+			FieldType::Array(_) => Type::Object(field_type.to_string()),
+		}
+	}
+
+	pub fn from_verification_type(info: &VerificationTypeInfo, cp: &ConstantPool) -> Option<Type> {
+		let result = match *info {
+			VerificationTypeInfo::Top => Type::Top,
+			VerificationTypeInfo::Integer => Type::Integer,
+			VerificationTypeInfo::Float => Type::Float,
+			VerificationTypeInfo::Double => Type::Double,
+			VerificationTypeInfo::Long => Type::Long,
+			VerificationTypeInfo::Null => Type::Null,
+			VerificationTypeInfo::UninitializedThis => Type::UninitializedThis,
+			VerificationTypeInfo::ObjectVariable(index) => Type::Object(cp.class_name(index)?.decoded()),
+			VerificationTypeInfo::Uninitialized(offset) => Type::Uninitialized(offset),
+		};
+		Some(result)
+	}
+
+	/// The number of slots the type takes up.
+	pub fn size(&self) -> usize {
+		match self {
+			Type::Long | Type::Double => 2,
+			_ => 1,
+		}
+	}
+
+	pub fn is_reference(&self) -> bool {
+		matches!(self, Type::Null | Type::UninitializedThis | Type::Uninitialized(_) | Type::Object(_))
+	}
+}
+
+impl fmt::Display for Type {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Type::Top => f.write_str("top"),
+			Type::Integer => f.write_str("int"),
+			Type::Float => f.write_str("float"),
+			Type::Long => f.write_str("long"),
+			Type::Double => f.write_str("double"),
+			Type::Null => f.write_str("null"),
+			Type::UninitializedThis => f.write_str("uninitializedThis"),
+			Type::Uninitialized(offset) => write!(f, "uninitialized({})", offset),
+			Type::Object(name) => f.write_str(name),
+		}
+	}
+}
+
+/// The types of the local variables and the operand stack at some point in a method.
+///
+/// Locals are stored per slot, so a `Long` or `Double` is followed by a `Top`.
+/// The stack holds one entry per value, whatever its size.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Default)]
+pub struct Frame {
+	pub locals: Vec<Type>,
+	pub stack: Vec<Type>,
+}
+
+impl Frame {
+	/// The frame on entry to the method, with the locals holding `this` and the parameters.
+	///
+	/// The locals aren't padded to `max_locals`, and `None` means the descriptor is invalid.
+	pub fn initial(class_name: &str, access_flags: u16, name: &str, descriptor: &str) -> Option<Frame> {
+		let descriptor = MethodDescriptor::parse(descriptor)?;
+		let mut locals = vec![];
+		if access_flags & STATIC == 0 {
+			if name == "<init>" && class_name != OBJECT {
+				locals.push(Type::UninitializedThis);
+			} else {
+				locals.push(Type::object(class_name));
+			}
+		}
+		for parameter in &descriptor.parameters {
+			push_local(&mut locals, Type::from_field_type(parameter));
+		}
+		Some(Frame {
+			locals,
+			stack: vec![],
+		})
+	}
+
+	/// The number of slots the operand stack takes up.
+	pub fn stack_size(&self) -> usize {
+		self.stack.iter()
+			.map(Type::size)
+			.sum()
+	}
+}
+
+fn push_local(locals: &mut Vec<Type>, value: Type) {
+	let size = value.size();
+	locals.push(value);
+	if size == 2 {
+		locals.push(Type::Top);
+	}
+}
+
+/// Applies the frames of a `StackMapTable` to the initial frame, handing back every frame along with its offset.
+///
+/// The locals of the returned frames aren't padded either.
+pub fn expand_frames(initial: &Frame, table: &StackMapTable, cp: &ConstantPool) -> Result<Vec<(u32, Frame)>, String> {
+	let convert = |infos: &[VerificationTypeInfo]| -> Result<Vec<Type>, String> {
+		infos.iter()
+			.map(|info| Type::from_verification_type(info, cp).ok_or_else(|| format!("invalid verification type {:?}", info)))
+			.collect()
+	};
+	let convert_locals = |infos: &[VerificationTypeInfo], locals: &mut Vec<Type>| -> Result<(), String> {
+		for value in convert(infos)? {
+			push_local(locals, value);
+		}
+		Ok(())
+	};
+
+	let mut result: Vec<(u32, Frame)> = vec![];
+	let mut previous = initial.clone();
+	for (i, frame) in table.table.iter().enumerate() {
+		let offset = match result.last() {
+			Some(&(offset, _)) => offset + frame.offset_delta() as u32 + 1,
+			None => frame.offset_delta() as u32,
+		};
+		let mut locals = previous.locals.clone();
+		let stack = match frame {
+			StackMapFrame::SameFrame(_) | StackMapFrame::SameFrameExtended(_) => vec![],
+			StackMapFrame::SameLocals { verification_type_info, .. }
+			| StackMapFrame::SameLocalsExtended { verification_type_info, .. } => {
+				convert(std::slice::from_ref(verification_type_info))?
+			}
+			StackMapFrame::ChopFrame { chopped, .. } => {
+				for _ in 0..*chopped {
+					let value = locals.pop()
+						.ok_or_else(|| format!("frame #{} chops more locals than there are", i))?;
+					if value == Type::Top {
+						if let Some(Type::Long) | Some(Type::Double) = locals.last() {
+							locals.pop();
+						}
+					}
+				}
+				vec![]
+			}
+			StackMapFrame::AppendFrame { locals: appended, .. } => {
+				convert_locals(appended, &mut locals)?;
+				vec![]
+			}
+			StackMapFrame::FullFrame { locals: full, stack, .. } => {
+				locals.clear();
+				convert_locals(full, &mut locals)?;
+				convert(stack)?
+			}
+		};
+		previous = Frame {
+			locals,
+			stack,
+		};
+		result.push((offset, previous.clone()));
+	}
+	Ok(result)
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Slot {
+	Local(u16),
+	Stack(usize),
+}
+
+impl fmt::Display for Slot {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Slot::Local(index) => write!(f, "local {}", index),
+			Slot::Stack(index) => write!(f, "stack entry {}", index),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Problem {
+	Decode(DecodeError),
+	MissingCode,
+	MalformedCode,
+	/// The `StackMapTable` is malformed, or doesn't fit the method.
+	InvalidStackMap(String),
+	/// The method's descriptor couldn't be parsed.
+	InvalidDescriptor,
+	/// An instruction found a value of the wrong type.
+	Mismatch {
+		expected: Type,
+		actual: Type,
+	},
+	/// An instruction that needs a value of the given category, 1 or 2, found something else.
+	Category {
+		expected: u8,
+		actual: Type,
+	},
+	/// The current frame isn't assignable to the one recorded in the `StackMapTable`.
+	FrameMismatch {
+		slot: Slot,
+		expected: Type,
+		actual: Type,
+	},
+	StackSizeMismatch {
+		expected: usize,
+		actual: usize,
+	},
+	StackUnderflow,
+	/// The stack grew beyond `max_stack`.
+	StackOverflow(u16),
+	/// The local variable doesn't exist, or the second slot of a long or double does.
+	InvalidLocal(u16),
+	/// There's no frame at the given offset, which is a branch target, an exception handler,
+	/// or follows an unconditional branch.
+	MissingFrame(u32),
+	/// A branch or handler that doesn't point at the start of an instruction.
+	InvalidTarget(u32),
+	InvalidConstant(u16),
+	/// `jsr` and `ret` aren't allowed once classes have stack maps.
+	IllegalInstruction(u8),
+	/// The `newarray` type or the `multianewarray` dimensions are invalid.
+	InvalidOperand,
+	/// The return instruction doesn't match the method's return type.
+	WrongReturn(u8),
+	/// A constructor returned before calling another constructor on `this`.
+	UninitializedThis,
+	FallsOffEnd,
+}
+
+impl fmt::Display for Problem {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Problem::Decode(error) => write!(f, "{}", error),
+			Problem::MissingCode => f.write_str("missing Code attribute"),
+			Problem::MalformedCode => f.write_str("malformed Code attribute"),
+			Problem::InvalidStackMap(message) => write!(f, "invalid StackMapTable, {}", message),
+			Problem::InvalidDescriptor => f.write_str("invalid method descriptor"),
+			Problem::Mismatch { expected, actual } => write!(f, "expected {}, found {}", expected, actual),
+			Problem::Category { expected, actual } => write!(f, "expected a category {} value, found {}", expected, actual),
+			Problem::FrameMismatch { slot, expected, actual } => {
+				write!(f, "{} is {}, but the stack map frame expects {}", slot, actual, expected)
+			}
+			Problem::StackSizeMismatch { expected, actual } => {
+				write!(f, "stack holds {} values, but the stack map frame expects {}", actual, expected)
+			}
+			Problem::StackUnderflow => f.write_str("stack underflow"),
+			Problem::StackOverflow(max_stack) => write!(f, "stack exceeds max_stack of {}", max_stack),
+			Problem::InvalidLocal(index) => write!(f, "invalid local variable {}", index),
+			Problem::MissingFrame(pc) => write!(f, "missing stack map frame at {}", pc),
+			Problem::InvalidTarget(pc) => write!(f, "{} is not the start of an instruction", pc),
+			Problem::InvalidConstant(index) => write!(f, "invalid constant #{}", index),
+			Problem::IllegalInstruction(opcode) => {
+				write!(f, "{} is not allowed in classes with stack maps", bytecode::mnemonic(*opcode).unwrap_or("???"))
+			}
+			Problem::InvalidOperand => f.write_str("invalid operand"),
+			Problem::WrongReturn(opcode) => {
+				write!(f, "{} doesn't match the method's return type", bytecode::mnemonic(*opcode).unwrap_or("???"))
+			}
+			Problem::UninitializedThis => f.write_str("constructor returns without initialising this"),
+			Problem::FallsOffEnd => f.write_str("execution falls off the end of the code"),
+		}
+	}
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Diagnostic {
+	pub location: Location,
+	/// The offset of the failing instruction, if the problem is with one.
+	pub pc: Option<u32>,
+	pub problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.pc {
+			Some(pc) => write!(f, "{}, pc {}: {}", self.location, pc, self.problem),
+			None => write!(f, "{}: {}", self.location, self.problem),
+		}
+	}
+}
+
+pub(crate) struct Handler {
+	pub(crate) start: u32,
+	pub(crate) end: u32,
+	pub(crate) handler: u32,
+	pub(crate) catch_type: Type,
+}
+
+/// The state shared by verifying a method and computing its frames, see `frames`.
+pub(crate) struct Checker<'c, 'a> {
+	cp: &'c ConstantPool<'a>,
+	pub(crate) hierarchy: &'c dyn ClassHierarchy,
+	class_name: String,
+	version: (u16, u16),
+	return_type: Option<Type>,
+	pub(crate) max_stack: u16,
+	max_locals: u16,
+	pub(crate) instructions: BTreeMap<u32, Instruction>,
+	frames: BTreeMap<u32, Frame>,
+	pub(crate) handlers: Vec<Handler>,
+}
+
+impl<'c, 'a> Checker<'c, 'a> {
+	fn is_assignable(&self, from: &Type, to: &Type) -> bool {
+		match (from, to) {
+			(_, Type::Top) => true,
+			(Type::Null, Type::Object(_)) => true,
+			(Type::Object(from), Type::Object(to)) => self.hierarchy.is_assignable(from, to),
+			(from, to) => from == to,
+		}
+	}
+
+	pub(crate) fn pad(&self, mut frame: Frame) -> Result<Frame, Problem> {
+		if frame.locals.len() > self.max_locals as usize {
+			return Err(Problem::InvalidLocal(frame.locals.len() as u16 - 1));
+		}
+		frame.locals.resize(self.max_locals as usize, Type::Top);
+		Ok(frame)
+	}
+
+	/// Checks that `frame` may flow into the recorded frame at `target`.
+	fn check_target(&self, frame: &Frame, target: u32) -> Result<(), Problem> {
+		if !self.instructions.contains_key(&target) {
+			return Err(Problem::InvalidTarget(target));
+		}
+		let expected = self.frames.get(&target)
+			.ok_or(Problem::MissingFrame(target))?;
+		self.check_frame(frame, expected)
+	}
+
+	fn check_frame(&self, frame: &Frame, expected: &Frame) -> Result<(), Problem> {
+		for (i, (actual, expected)) in frame.locals.iter().zip(&expected.locals).enumerate() {
+			if !self.is_assignable(actual, expected) {
+				return Err(Problem::FrameMismatch { slot: Slot::Local(i as u16), expected: expected.clone(), actual: actual.clone() });
+			}
+		}
+		if frame.stack.len() != expected.stack.len() {
+			return Err(Problem::StackSizeMismatch { expected: expected.stack.len(), actual: frame.stack.len() });
+		}
+		for (i, (actual, expected)) in frame.stack.iter().zip(&expected.stack).enumerate() {
+			if !self.is_assignable(actual, expected) {
+				return Err(Problem::FrameMismatch { slot: Slot::Stack(i), expected: expected.clone(), actual: actual.clone() });
+			}
+		}
+		Ok(())
+	}
+
+	/// Handlers covering `pc` are checked against the locals coming into the instruction.
+	fn check_handlers(&self, pc: u32, locals: &[Type]) -> Result<(), Problem> {
+		for handler in &self.handlers {
+			if pc >= handler.start && pc < handler.end {
+				let frame = Frame {
+					locals: locals.to_vec(),
+					stack: vec![handler.catch_type.clone()],
+				};
+				self.check_target(&frame, handler.handler)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn check(&self, initial: Frame) -> Result<(), (Option<u32>, Problem)> {
+		let mut current = Some(initial);
+		for (&pc, instruction) in &self.instructions {
+			let mut frame = match (self.frames.get(&pc), current) {
+				(Some(expected), Some(frame)) => {
+					self.check_frame(&frame, expected).map_err(|problem| (Some(pc), problem))?;
+					expected.clone()
+				}
+				(Some(expected), None) => expected.clone(),
+				(None, Some(frame)) => frame,
+				(None, None) => return Err((Some(pc), Problem::MissingFrame(pc))),
+			};
+
+			let result = self.check_handlers(pc, &frame.locals)
+				.and_then(|_| self.execute(instruction, &mut frame))
+				.and_then(|_| instruction.targets().into_iter().try_for_each(|target| self.check_target(&frame, target)));
+			result.map_err(|problem| (Some(pc), problem))?;
+
+			current = if instruction.is_unconditional() { None } else { Some(frame) };
+		}
+		match current {
+			Some(_) => Err((None, Problem::FallsOffEnd)),
+			None => Ok(()),
+		}
+	}
+
+	fn pop(&self, frame: &mut Frame) -> Result<Type, Problem> {
+		frame.stack.pop()
+			.ok_or(Problem::StackUnderflow)
+	}
+
+	fn pop_expect(&self, frame: &mut Frame, expected: &Type) -> Result<Type, Problem> {
+		let actual = self.pop(frame)?;
+		if !self.is_assignable(&actual, expected) {
+			return Err(Problem::Mismatch { expected: expected.clone(), actual });
+		}
+		Ok(actual)
+	}
+
+	fn pop_reference(&self, frame: &mut Frame) -> Result<Type, Problem> {
+		let actual = self.pop(frame)?;
+		if !actual.is_reference() {
+			return Err(Problem::Mismatch { expected: Type::object(OBJECT), actual });
+		}
+		Ok(actual)
+	}
+
+	fn pop_category(&self, frame: &mut Frame, category: u8) -> Result<Type, Problem> {
+		let actual = self.pop(frame)?;
+		if actual.size() != category as usize {
+			return Err(Problem::Category { expected: category, actual });
+		}
+		Ok(actual)
+	}
+
+	/// Pops an array, handing back the descriptor of its components, or `None` if it's `null`.
+	fn pop_array(&self, frame: &mut Frame, expected: &str) -> Result<Option<String>, Problem> {
+		let actual = self.pop(frame)?;
+		match actual {
+			Type::Null => Ok(None),
+			Type::Object(ref name) if name.starts_with('[') => Ok(Some(name[1..].to_string())),
+			actual => Err(Problem::Mismatch { expected: Type::object(expected), actual }),
+		}
+	}
+
+	fn push(&self, frame: &mut Frame, value: Type) -> Result<(), Problem> {
+		frame.stack.push(value);
+		if frame.stack_size() > self.max_stack as usize {
+			return Err(Problem::StackOverflow(self.max_stack));
+		}
+		Ok(())
+	}
+
+	fn load(&self, frame: &mut Frame, index: u16, expected: &Type) -> Result<(), Problem> {
+		let actual = frame.locals.get(index as usize)
+			.ok_or(Problem::InvalidLocal(index))?
+			.clone();
+		let valid = match expected {
+			Type::Object(_) => actual.is_reference(),
+			expected => actual == *expected,
+		};
+		if !valid {
+			return Err(Problem::Mismatch { expected: expected.clone(), actual });
+		}
+		self.push(frame, actual)
+	}
+
+	fn store(&self, frame: &mut Frame, index: u16, value: Type) -> Result<(), Problem> {
+		let index = index as usize;
+		if index + value.size() > frame.locals.len() {
+			return Err(Problem::InvalidLocal(index as u16));
+		}
+		// Overwriting the second half of a long or double invalidates the first.
+		if index > 0 && frame.locals[index - 1].size() == 2 {
+			frame.locals[index - 1] = Type::Top;
+		}
+		if value.size() == 2 {
+			frame.locals[index + 1] = Type::Top;
+		}
+		frame.locals[index] = value;
+		Ok(())
+	}
+
+	fn constant(&self, index: u16) -> Result<&'c CPEntry<'a>, Problem> {
+		self.cp.entries.get((index as usize).wrapping_sub(1))
+			.ok_or(Problem::InvalidConstant(index))
+	}
+
+	pub(crate) fn class_constant(&self, index: u16) -> Result<String, Problem> {
+		self.cp.class_name(CPIndex::new(index))
+			.map(MStrExt::decoded)
+			.ok_or(Problem::InvalidConstant(index))
+	}
+
+	fn loadable_constant(&self, index: u16, wide: bool) -> Result<Type, Problem> {
+		let value = match self.constant(index)? {
+			CPEntry::Integer(_) if !wide => Type::Integer,
+			CPEntry::Float(_) if !wide => Type::Float,
+			CPEntry::Long(_) if wide => Type::Long,
+			CPEntry::Double(_) if wide => Type::Double,
+			CPEntry::String(_) if !wide => Type::object("java/lang/String"),
+			CPEntry::Class(_) if !wide => Type::object("java/lang/Class"),
+			CPEntry::MethodType(_) if !wide => Type::object("java/lang/invoke/MethodType"),
+			CPEntry::MethodHandle(_) if !wide => Type::object("java/lang/invoke/MethodHandle"),
+			CPEntry::Dynamic(info) => {
+				let resolved = info.resolve(self.cp)
+					.ok_or(Problem::InvalidConstant(index))?;
+				let field_type = FieldType::parse(&utf8::decode(resolved.descriptor.as_bytes()))
+					.ok_or(Problem::InvalidConstant(index))?;
+				let value = Type::from_field_type(&field_type);
+				if (value.size() == 2) != wide {
+					return Err(Problem::InvalidConstant(index));
+				}
+				value
+			}
+			_ => return Err(Problem::InvalidConstant(index)),
+		};
+		Ok(value)
+	}
+
+	fn field(&self, index: u16) -> Result<(String, Type), Problem> {
+		let field = self.cp.resolve(CPIndex::<FieldRefInfo>::new(index))
+			.ok_or(Problem::InvalidConstant(index))?;
+		let field_type = FieldType::parse(&utf8::decode(field.descriptor.as_bytes()))
+			.ok_or(Problem::InvalidConstant(index))?;
+		Ok((field.owner.decoded(), Type::from_field_type(&field_type)))
+	}
+
+	fn method(&self, opcode: u8, index: u16) -> Result<(String, String, MethodDescriptor), Problem> {
+		let (owner, name, descriptor) = if opcode == INVOKEDYNAMIC {
+			let resolved = self.cp.resolve(CPIndex::<InvokeDynamicInfo>::new(index))
+				.ok_or(Problem::InvalidConstant(index))?;
+			(OBJECT.into(), resolved.name, resolved.descriptor)
+		} else {
+			let method: MethodRef = match self.cp.resolve(CPIndex::<MethodRefInfo>::new(index)) {
+				Some(method) if opcode != INVOKEINTERFACE => method,
+				// invokestatic and invokespecial may also call interface methods, as of 52.0.
+				_ if opcode == INVOKEINTERFACE || (self.version >= (52, 0) && (opcode == INVOKESTATIC || opcode == INVOKESPECIAL)) => {
+					self.cp.resolve(CPIndex::<InterfaceMethodRefInfo>::new(index))
+						.ok_or(Problem::InvalidConstant(index))?
+				}
+				_ => return Err(Problem::InvalidConstant(index)),
+			};
+			(method.owner.decoded(), method.name, method.descriptor)
+		};
+		let descriptor = MethodDescriptor::parse(&utf8::decode(descriptor.as_bytes()))
+			.ok_or(Problem::InvalidConstant(index))?;
+		Ok((owner, name.decoded(), descriptor))
+	}
+
+	fn invoke(&self, frame: &mut Frame, opcode: u8, index: u16) -> Result<(), Problem> {
+		let (owner, name, descriptor) = self.method(opcode, index)?;
+		for parameter in descriptor.parameters.iter().rev() {
+			self.pop_expect(frame, &Type::from_field_type(parameter))?;
+		}
+
+		if name == "<init>" {
+			if opcode != INVOKESPECIAL || descriptor.return_type.is_some() {
+				return Err(Problem::InvalidConstant(index));
+			}
+			let receiver = self.pop(frame)?;
+			let initialized = match receiver {
+				Type::UninitializedThis => Type::object(&self.class_name),
+				Type::Uninitialized(offset) => {
+					match self.instructions.get(&(offset as u32)) {
+						Some(Instruction { opcode: NEW, operand: Operand::Constant(index), .. }) => {
+							Type::Object(self.class_constant(*index)?)
+						}
+						_ => return Err(Problem::InvalidStackMap(format!("uninitialized({}) doesn't refer to a new instruction", offset))),
+					}
+				}
+				actual => return Err(Problem::Mismatch { expected: Type::UninitializedThis, actual }),
+			};
+			for value in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+				if *value == receiver {
+					*value = initialized.clone();
+				}
+			}
+			return Ok(());
+		}
+
+		match opcode {
+			INVOKESTATIC | INVOKEDYNAMIC => {}
+			// Calling private methods and super methods, the receiver has to be this class or a subclass.
+			INVOKESPECIAL => {
+				self.pop_expect(frame, &Type::object(&self.class_name))?;
+			}
+			_ => {
+				let receiver = if owner.starts_with('[') { Type::object(OBJECT) } else { Type::Object(owner) };
+				self.pop_expect(frame, &receiver)?;
+			}
+		}
+		if let Some(ref return_type) = descriptor.return_type {
+			self.push(frame, Type::from_field_type(return_type))?;
+		}
+		Ok(())
+	}
+
+	fn array_load(&self, frame: &mut Frame, opcode: u8) -> Result<(), Problem> {
+		self.pop_expect(frame, &Type::Integer)?;
+		let (expected, value) = match opcode {
+			IALOAD => ("[I", Type::Integer),
+			LALOAD => ("[J", Type::Long),
+			FALOAD => ("[F", Type::Float),
+			DALOAD => ("[D", Type::Double),
+			BALOAD => ("[B", Type::Integer),
+			CALOAD => ("[C", Type::Integer),
+			SALOAD => ("[S", Type::Integer),
+			_ => {
+				let value = match self.pop_array(frame, "[Ljava/lang/Object;")? {
+					None => Type::Null,
+					Some(ref component) if component.starts_with('[') => Type::object(component),
+					Some(ref component) if component.starts_with('L') => Type::object(component[1..].trim_end_matches(';')),
+					Some(component) => {
+						return Err(Problem::Mismatch { expected: Type::object("[Ljava/lang/Object;"), actual: Type::Object(format!("[{}", component)) });
+					}
+				};
+				return self.push(frame, value);
+			}
+		};
+		self.check_component(frame, expected)?;
+		self.push(frame, value)
+	}
+
+	/// Pops a primitive array, `baload` and `bastore` being happy with either bytes or booleans.
+	fn check_component(&self, frame: &mut Frame, expected: &str) -> Result<(), Problem> {
+		match self.pop_array(frame, expected)? {
+			None => Ok(()),
+			Some(ref component) if *component == expected[1..] => Ok(()),
+			Some(ref component) if expected == "[B" && component == "Z" => Ok(()),
+			Some(component) => Err(Problem::Mismatch { expected: Type::object(expected), actual: Type::Object(format!("[{}", component)) }),
+		}
+	}
+
+	fn array_store(&self, frame: &mut Frame, opcode: u8) -> Result<(), Problem> {
+		let (expected, value) = match opcode {
+			IASTORE => ("[I", Type::Integer),
+			LASTORE => ("[J", Type::Long),
+			FASTORE => ("[F", Type::Float),
+			DASTORE => ("[D", Type::Double),
+			BASTORE => ("[B", Type::Integer),
+			CASTORE => ("[C", Type::Integer),
+			SASTORE => ("[S", Type::Integer),
+			_ => {
+				// Whether the value fits the array is left to the runtime.
+				self.pop_reference(frame)?;
+				self.pop_expect(frame, &Type::Integer)?;
+				return match self.pop_array(frame, "[Ljava/lang/Object;")? {
+					Some(ref component) if !component.starts_with('[') && !component.starts_with('L') => {
+						Err(Problem::Mismatch { expected: Type::object("[Ljava/lang/Object;"), actual: Type::Object(format!("[{}", component)) })
+					}
+					_ => Ok(()),
+				};
+			}
+		};
+		self.pop_expect(frame, &value)?;
+		self.pop_expect(frame, &Type::Integer)?;
+		self.check_component(frame, expected)
+	}
+
+	fn binary(&self, frame: &mut Frame, operand: Type) -> Result<(), Problem> {
+		self.pop_expect(frame, &operand)?;
+		self.pop_expect(frame, &operand)?;
+		self.push(frame, operand)
+	}
+
+	fn convert(&self, frame: &mut Frame, from: Type, to: Type) -> Result<(), Problem> {
+		self.pop_expect(frame, &from)?;
+		self.push(frame, to)
+	}
+
+	fn ret(&self, frame: &mut Frame, opcode: u8) -> Result<(), Problem> {
+		let expected = match (opcode, &self.return_type) {
+			(RETURN, None) => {
+				if frame.locals.contains(&Type::UninitializedThis) {
+					return Err(Problem::UninitializedThis);
+				}
+				return Ok(());
+			}
+			(IRETURN, Some(Type::Integer)) | (LRETURN, Some(Type::Long)) | (FRETURN, Some(Type::Float))
+			| (DRETURN, Some(Type::Double)) | (ARETURN, Some(Type::Object(_))) => self.return_type.as_ref().unwrap(),
+			_ => return Err(Problem::WrongReturn(opcode)),
+		};
+		self.pop_expect(frame, expected)?;
+		Ok(())
+	}
+
+	/// Applies the instruction to the frame, leaving it to the caller to see where the result flows to.
+	pub(crate) fn execute(&self, instruction: &Instruction, frame: &mut Frame) -> Result<(), Problem> {
+		let opcode = instruction.opcode;
+		let local = || match instruction.operand {
+			Operand::Local(index) => index,
+			_ => 0,
+		};
+		let constant = || match instruction.operand {
+			Operand::Constant(index) | Operand::InvokeInterface { index, .. } | Operand::MultiANewArray { index, .. } => index,
+			_ => 0,
+		};
+
+		match opcode {
+			NOP => {}
+			ACONST_NULL => self.push(frame, Type::Null)?,
+			ICONST_M1..=ICONST_5 | BIPUSH | SIPUSH => self.push(frame, Type::Integer)?,
+			LCONST_0 | LCONST_1 => self.push(frame, Type::Long)?,
+			FCONST_0..=FCONST_2 => self.push(frame, Type::Float)?,
+			DCONST_0 | DCONST_1 => self.push(frame, Type::Double)?,
+			LDC | LDC_W | LDC2_W => {
+				let value = self.loadable_constant(constant(), opcode == LDC2_W)?;
+				self.push(frame, value)?;
+			}
+
+			ILOAD | ILOAD_0..=ILOAD_3 => self.load(frame, local(), &Type::Integer)?,
+			LLOAD | LLOAD_0..=LLOAD_3 => self.load(frame, local(), &Type::Long)?,
+			FLOAD | FLOAD_0..=FLOAD_3 => self.load(frame, local(), &Type::Float)?,
+			DLOAD | DLOAD_0..=DLOAD_3 => self.load(frame, local(), &Type::Double)?,
+			ALOAD | ALOAD_0..=ALOAD_3 => self.load(frame, local(), &Type::object(OBJECT))?,
+			IALOAD..=SALOAD => self.array_load(frame, opcode)?,
+
+			ISTORE | ISTORE_0..=ISTORE_3 => {
+				let value = self.pop_expect(frame, &Type::Integer)?;
+				self.store(frame, local(), value)?;
+			}
+			LSTORE | LSTORE_0..=LSTORE_3 => {
+				let value = self.pop_expect(frame, &Type::Long)?;
+				self.store(frame, local(), value)?;
+			}
+			FSTORE | FSTORE_0..=FSTORE_3 => {
+				let value = self.pop_expect(frame, &Type::Float)?;
+				self.store(frame, local(), value)?;
+			}
+			DSTORE | DSTORE_0..=DSTORE_3 => {
+				let value = self.pop_expect(frame, &Type::Double)?;
+				self.store(frame, local(), value)?;
+			}
+			ASTORE | ASTORE_0..=ASTORE_3 => {
+				let value = self.pop_reference(frame)?;
+				self.store(frame, local(), value)?;
+			}
+			IASTORE..=SASTORE => self.array_store(frame, opcode)?,
+
+			POP => {
+				self.pop_category(frame, 1)?;
+			}
+			POP2 => {
+				if self.pop(frame)?.size() == 1 {
+					self.pop_category(frame, 1)?;
+				}
+			}
+			DUP => {
+				let value = self.pop_category(frame, 1)?;
+				self.push(frame, value.clone())?;
+				self.push(frame, value)?;
+			}
+			DUP_X1 => {
+				let first = self.pop_category(frame, 1)?;
+				let second = self.pop_category(frame, 1)?;
+				for value in [first.clone(), second, first] {
+					self.push(frame, value)?;
+				}
+			}
+			DUP_X2 => {
+				let first = self.pop_category(frame, 1)?;
+				let mut under = vec![self.pop(frame)?];
+				if under[0].size() == 1 {
+					under.insert(0, self.pop_category(frame, 1)?);
+				}
+				for value in iter::once(&first).chain(&under).chain(iter::once(&first)).cloned().collect::<Vec<_>>() {
+					self.push(frame, value)?;
+				}
+			}
+			DUP2 => {
+				let first = self.pop(frame)?;
+				let mut values = vec![first.clone()];
+				if first.size() == 1 {
+					values.insert(0, self.pop_category(frame, 1)?);
+				}
+				for value in values.iter().chain(values.iter()).cloned().collect::<Vec<_>>() {
+					self.push(frame, value)?;
+				}
+			}
+			DUP2_X1 | DUP2_X2 => {
+				let first = self.pop(frame)?;
+				let mut top = vec![first.clone()];
+				if first.size() == 1 {
+					top.insert(0, self.pop_category(frame, 1)?);
+				}
+				let mut under = vec![self.pop(frame)?];
+				if opcode == DUP2_X1 {
+					if under[0].size() != 1 {
+						return Err(Problem::Category { expected: 1, actual: under.remove(0) });
+					}
+				} else if under[0].size() == 1 {
+					under.insert(0, self.pop_category(frame, 1)?);
+				}
+				for value in top.iter().chain(&under).chain(&top).cloned().collect::<Vec<_>>() {
+					self.push(frame, value)?;
+				}
+			}
+			SWAP => {
+				let first = self.pop_category(frame, 1)?;
+				let second = self.pop_category(frame, 1)?;
+				self.push(frame, first)?;
+				self.push(frame, second)?;
+			}
+
+			IADD | ISUB | IMUL | IDIV | IREM | ISHL | ISHR | IUSHR | IAND | IOR | IXOR => self.binary(frame, Type::Integer)?,
+			LADD | LSUB | LMUL | LDIV | LREM | LAND | LOR | LXOR => self.binary(frame, Type::Long)?,
+			FADD | FSUB | FMUL | FDIV | FREM => self.binary(frame, Type::Float)?,
+			DADD | DSUB | DMUL | DDIV | DREM => self.binary(frame, Type::Double)?,
+			LSHL | LSHR | LUSHR => {
+				self.pop_expect(frame, &Type::Integer)?;
+				self.pop_expect(frame, &Type::Long)?;
+				self.push(frame, Type::Long)?;
+			}
+			INEG | I2B | I2C | I2S => self.convert(frame, Type::Integer, Type::Integer)?,
+			LNEG => self.convert(frame, Type::Long, Type::Long)?,
+			FNEG => self.convert(frame, Type::Float, Type::Float)?,
+			DNEG => self.convert(frame, Type::Double, Type::Double)?,
+			IINC => {
+				let index = match instruction.operand {
+					Operand::Iinc { index, .. } => index,
+					_ => 0,
+				};
+				match frame.locals.get(index as usize) {
+					Some(Type::Integer) => {}
+					Some(actual) => return Err(Problem::Mismatch { expected: Type::Integer, actual: actual.clone() }),
+					None => return Err(Problem::InvalidLocal(index)),
+				}
+			}
+			I2L => self.convert(frame, Type::Integer, Type::Long)?,
+			I2F => self.convert(frame, Type::Integer, Type::Float)?,
+			I2D => self.convert(frame, Type::Integer, Type::Double)?,
+			L2I => self.convert(frame, Type::Long, Type::Integer)?,
+			L2F => self.convert(frame, Type::Long, Type::Float)?,
+			L2D => self.convert(frame, Type::Long, Type::Double)?,
+			F2I => self.convert(frame, Type::Float, Type::Integer)?,
+			F2L => self.convert(frame, Type::Float, Type::Long)?,
+			F2D => self.convert(frame, Type::Float, Type::Double)?,
+			D2I => self.convert(frame, Type::Double, Type::Integer)?,
+			D2L => self.convert(frame, Type::Double, Type::Long)?,
+			D2F => self.convert(frame, Type::Double, Type::Float)?,
+			LCMP => {
+				self.binary(frame, Type::Long)?;
+				self.convert(frame, Type::Long, Type::Integer)?;
+			}
+			FCMPL | FCMPG => {
+				self.binary(frame, Type::Float)?;
+				self.convert(frame, Type::Float, Type::Integer)?;
+			}
+			DCMPL | DCMPG => {
+				self.binary(frame, Type::Double)?;
+				self.convert(frame, Type::Double, Type::Integer)?;
+			}
+
+			IFEQ..=IFLE => {
+				self.pop_expect(frame, &Type::Integer)?;
+			}
+			IF_ICMPEQ..=IF_ICMPLE => {
+				self.pop_expect(frame, &Type::Integer)?;
+				self.pop_expect(frame, &Type::Integer)?;
+			}
+			IF_ACMPEQ | IF_ACMPNE => {
+				self.pop_reference(frame)?;
+				self.pop_reference(frame)?;
+			}
+			IFNULL | IFNONNULL => {
+				self.pop_reference(frame)?;
+			}
+			GOTO | GOTO_W => {}
+			JSR | JSR_W | RET => return Err(Problem::IllegalInstruction(opcode)),
+			TABLESWITCH | LOOKUPSWITCH => {
+				self.pop_expect(frame, &Type::Integer)?;
+			}
+			IRETURN..=RETURN => self.ret(frame, opcode)?,
+
+			GETSTATIC => {
+				let (_, value) = self.field(constant())?;
+				self.push(frame, value)?;
+			}
+			PUTSTATIC => {
+				let (_, value) = self.field(constant())?;
+				self.pop_expect(frame, &value)?;
+			}
+			GETFIELD => {
+				let (owner, value) = self.field(constant())?;
+				self.pop_expect(frame, &Type::Object(owner))?;
+				self.push(frame, value)?;
+			}
+			PUTFIELD => {
+				let (owner, value) = self.field(constant())?;
+				self.pop_expect(frame, &value)?;
+				// Constructors may assign their own fields before calling the super constructor.
+				let receiver = self.pop(frame)?;
+				let valid = match receiver {
+					Type::UninitializedThis => owner == self.class_name,
+					ref receiver => self.is_assignable(receiver, &Type::Object(owner.clone())),
+				};
+				if !valid {
+					return Err(Problem::Mismatch { expected: Type::Object(owner), actual: receiver });
+				}
+			}
+			INVOKEVIRTUAL..=INVOKEDYNAMIC => self.invoke(frame, opcode, constant())?,
+
+			NEW => {
+				self.class_constant(constant())?;
+				self.push(frame, Type::Uninitialized(instruction.pc as u16))?;
+			}
+			NEWARRAY => {
+				let component = match instruction.operand {
+					Operand::NewArray(T_BOOLEAN) => "Z",
+					Operand::NewArray(T_CHAR) => "C",
+					Operand::NewArray(T_FLOAT) => "F",
+					Operand::NewArray(T_DOUBLE) => "D",
+					Operand::NewArray(T_BYTE) => "B",
+					Operand::NewArray(T_SHORT) => "S",
+					Operand::NewArray(T_INT) => "I",
+					Operand::NewArray(T_LONG) => "J",
+					_ => return Err(Problem::InvalidOperand),
+				};
+				self.convert(frame, Type::Integer, Type::Object(format!("[{}", component)))?;
+			}
+			ANEWARRAY => {
+				let name = self.class_constant(constant())?;
+				let array = if name.starts_with('[') { format!("[{}", name) } else { format!("[L{};", name) };
+				self.convert(frame, Type::Integer, Type::Object(array))?;
+			}
+			ARRAYLENGTH => {
+				self.pop_array(frame, "[Ljava/lang/Object;")?;
+				self.push(frame, Type::Integer)?;
+			}
+			ATHROW => {
+				self.pop_expect(frame, &Type::object("java/lang/Throwable"))?;
+			}
+			CHECKCAST => {
+				let name = self.class_constant(constant())?;
+				self.pop_reference(frame)?;
+				self.push(frame, Type::Object(name))?;
+			}
+			INSTANCEOF => {
+				self.class_constant(constant())?;
+				self.pop_reference(frame)?;
+				self.push(frame, Type::Integer)?;
+			}
+			MONITORENTER | MONITOREXIT => {
+				self.pop_reference(frame)?;
+			}
+			MULTIANEWARRAY => {
+				let dimensions = match instruction.operand {
+					Operand::MultiANewArray { dimensions, .. } => dimensions,
+					_ => 0,
+				};
+				let name = self.class_constant(constant())?;
+				if dimensions == 0 || FieldType::parse(&name).map_or(0, |array| array.dimensions()) < dimensions as usize {
+					return Err(Problem::InvalidOperand);
+				}
+				for _ in 0..dimensions {
+					self.pop_expect(frame, &Type::Integer)?;
+				}
+				self.push(frame, Type::Object(name))?;
+			}
+			_ => return Err(Problem::IllegalInstruction(opcode)),
+		}
+		Ok(())
+	}
+}
+
+/// Sets up a checker for the code of the method at the given index, without any frames yet,
+/// handing it back along with the initial frame, which still needs to be padded.
+pub(crate) fn checker<'c, 'a>(class_file: &'c ClassFile<'a>, index: usize, code: &Code, hierarchy: &'c dyn ClassHierarchy) -> Result<(Checker<'c, 'a>, Frame), Diagnostic> {
+	let location = Location::Code(index);
+	let error = |pc, problem| Diagnostic { location, pc, problem };
+
+	let cp = &class_file.constant_pool;
+	let method = class_file.methods.get(index)
+		.ok_or_else(|| error(None, Problem::MissingCode))?;
+	let instructions = bytecode::decode(&code.code)
+		.map_err(|e| error(None, Problem::Decode(e)))?;
+
+	let class_name = cp.class_name(class_file.this_class).map(MStrExt::decoded).unwrap_or_default();
+	let name = cp.utf8(method.name_index).map(MStrExt::decoded).unwrap_or_default();
+	let descriptor = cp.utf8(method.descriptor_index).map(MStrExt::decoded).unwrap_or_default();
+	let initial = Frame::initial(&class_name, method.access_flags, &name, &descriptor)
+		.ok_or_else(|| error(None, Problem::InvalidDescriptor))?;
+	let return_type = MethodDescriptor::parse(&descriptor)
+		.and_then(|descriptor| descriptor.return_type)
+		.map(|return_type| Type::from_field_type(&return_type));
+
+	let mut checker = Checker {
+		cp,
+		hierarchy,
+		class_name,
+		version: (class_file.major_version, class_file.minor_version),
+		return_type,
+		max_stack: code.max_stack,
+		max_locals: code.max_locals,
+		instructions: instructions.into_iter().map(|instruction| (instruction.pc, instruction)).collect(),
+		frames: BTreeMap::new(),
+		handlers: vec![],
+	};
+
+	for exception in &code.exception_table {
+		let catch_type = match exception.catch_type.index {
+			0 => Type::object("java/lang/Throwable"),
+			index => Type::Object(checker.class_constant(index).map_err(|problem| error(Some(exception.handler_pc as u32), problem))?),
+		};
+		checker.handlers.push(Handler {
+			start: exception.start_pc as u32,
+			end: exception.end_pc as u32,
+			handler: exception.handler_pc as u32,
+			catch_type,
+		});
+	}
+
+	Ok((checker, initial))
+}
+
+/// Parses the `Code` attribute of the method at the given index, `None` if it's abstract or native.
+pub(crate) fn method_code<'a>(class_file: &ClassFile<'a>, index: usize) -> Result<Option<Code<'a>>, Diagnostic> {
+	let location = Location::Code(index);
+	let cp = &class_file.constant_pool;
+	let method = match class_file.methods.get(index) {
+		Some(method) => method,
+		None => return Ok(None),
+	};
+	if method.access_flags & (ABSTRACT | NATIVE) != 0 {
+		return Ok(None);
+	}
+	let info = method.attributes.named_all(cp, "Code").next()
+		.ok_or(Diagnostic { location, pc: None, problem: Problem::MissingCode })?;
+	<Code as FromBytes<BigEndian>>::from_bytes(&mut Cursor::new(info.info()))
+		.map(Some)
+		.map_err(|_| Diagnostic { location, pc: None, problem: Problem::MalformedCode })
+}
+
+/// Verifies a single method, the one at the given index into `ClassFile::methods`.
+pub fn verify_method(class_file: &ClassFile, index: usize, hierarchy: &dyn ClassHierarchy) -> Option<Diagnostic> {
+	let location = Location::Code(index);
+	let error = |pc, problem| Some(Diagnostic { location, pc, problem });
+
+	let code = match method_code(class_file, index) {
+		Ok(Some(code)) => code,
+		Ok(None) => return None,
+		Err(diagnostic) => return Some(diagnostic),
+	};
+	let (mut checker, initial) = match checker(class_file, index, &code, hierarchy) {
+		Ok(result) => result,
+		Err(diagnostic) => return Some(diagnostic),
+	};
+
+	let cp = &class_file.constant_pool;
+	let table = code.attributes.named_all(cp, "StackMapTable")
+		.next()
+		.map(|info| <StackMapTable as FromBytes<BigEndian>>::from_bytes(&mut Cursor::new(info.info())));
+	let table = match table {
+		Some(Ok(table)) => table,
+		Some(Err(_)) => return error(None, Problem::InvalidStackMap("couldn't be parsed".to_string())),
+		None => StackMapTable { table: vec![] },
+	};
+	let frames = match expand_frames(&initial, &table, cp) {
+		Ok(frames) => frames,
+		Err(message) => return error(None, Problem::InvalidStackMap(message)),
+	};
+	for (pc, frame) in frames {
+		if !checker.instructions.contains_key(&pc) {
+			return error(Some(pc), Problem::InvalidTarget(pc));
+		}
+		match checker.pad(frame) {
+			Ok(frame) => checker.frames.insert(pc, frame),
+			Err(problem) => return error(Some(pc), problem),
+		};
+	}
+
+	let initial = match checker.pad(initial) {
+		Ok(initial) => initial,
+		Err(problem) => return error(None, problem),
+	};
+	checker.check(initial)
+		.err()
+		.map(|(pc, problem)| Diagnostic { location, pc, problem })
+}
+
+/// Verifies every method, reporting the first problem in each of them.
+pub fn validate(class_file: &ClassFile, hierarchy: &dyn ClassHierarchy) -> Vec<Diagnostic> {
+	if class_file.major_version < 50 {
+		return vec![];
+	}
+	(0..class_file.methods.len())
+		.filter_map(|index| verify_method(class_file, index, hierarchy))
+		.collect()
+}
@@ -0,0 +1,503 @@
+//! The parts of the ZIP format jars, jmods and the like need: stored and deflated entries, and Zip64 for large archives,
+//! though only when reading.
+//!
+//! Archives are read from memory, and data before the archive, such as the header of a jmod, is skipped over,
+//! as offsets are taken relative to where the central directory actually is.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Midnight on the 1st of January 1980, the earliest time and date an entry can have,
+/// and what reproducible builds use for every entry.
+pub const EPOCH: (u16, u16) = (0, 1 << 5 | 1);
+
+/// Entries that are stored as-is.
+pub const STORED: u16 = 0;
+pub const DEFLATED: u16 = 8;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const ZIP64_EXTRA: u16 = 0x0001;
+
+/// Entry names are UTF-8, rather than code page 437.
+const UTF8_FLAG: u16 = 1 << 11;
+/// The sizes and checksum follow the data, rather than being in the local header.
+const DATA_DESCRIPTOR_FLAG: u16 = 1 << 3;
+
+const ENCRYPTED_FLAG: u16 = 1;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum ZipError {
+	/// There's no end of central directory record, so it's not an archive at all.
+	NotAnArchive,
+	/// A record runs past the end of the data.
+	Truncated,
+	/// A record doesn't start with the signature it should have.
+	BadSignature(u32),
+	/// Encrypted entries can't be read.
+	Encrypted(String),
+	UnsupportedMethod {
+		name: String,
+		method: u16,
+	},
+	/// The compressed data is corrupt.
+	Inflate(String),
+	/// The data doesn't match the size or checksum the central directory gives.
+	Corrupt(String),
+}
+
+impl fmt::Display for ZipError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ZipError::NotAnArchive => f.write_str("not a zip archive"),
+			ZipError::Truncated => f.write_str("archive is truncated"),
+			ZipError::BadSignature(offset) => write!(f, "bad signature at offset {}", offset),
+			ZipError::Encrypted(name) => write!(f, "{} is encrypted", name),
+			ZipError::UnsupportedMethod { name, method } => write!(f, "{} uses unsupported compression method {}", name, method),
+			ZipError::Inflate(name) => write!(f, "{} can't be decompressed", name),
+			ZipError::Corrupt(name) => write!(f, "{} doesn't match its size or checksum", name),
+		}
+	}
+}
+
+/// What the central directory says about an entry.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct ZipEntry {
+	pub name: String,
+	pub flags: u16,
+	pub method: u16,
+	/// The modification time and date, in MS-DOS format.
+	pub time: u16,
+	pub date: u16,
+	pub crc32: u32,
+	pub compressed_size: u64,
+	pub size: u64,
+	pub extra: Vec<u8>,
+	pub comment: Vec<u8>,
+	pub external_attributes: u32,
+	/// Where the local header is, relative to the start of the archive.
+	pub(crate) offset: u64,
+}
+
+impl ZipEntry {
+	pub fn is_directory(&self) -> bool {
+		self.name.ends_with('/')
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ZipArchive {
+	data: Vec<u8>,
+	/// Where the archive starts within the data.
+	base: u64,
+	entries: Vec<ZipEntry>,
+	names: HashMap<String, usize>,
+	pub comment: Vec<u8>,
+}
+
+struct Reader<'d> {
+	data: &'d [u8],
+	position: usize,
+}
+
+impl<'d> Reader<'d> {
+	fn at(data: &'d [u8], position: u64) -> Result<Self, ZipError> {
+		if position > data.len() as u64 {
+			return Err(ZipError::Truncated);
+		}
+		Ok(Reader {
+			data,
+			position: position as usize,
+		})
+	}
+
+	fn bytes(&mut self, length: usize) -> Result<&'d [u8], ZipError> {
+		let end = self.position.checked_add(length)
+			.filter(|&end| end <= self.data.len())
+			.ok_or(ZipError::Truncated)?;
+		let bytes = &self.data[self.position..end];
+		self.position = end;
+		Ok(bytes)
+	}
+
+	fn u16(&mut self) -> Result<u16, ZipError> {
+		let bytes = self.bytes(2)?;
+		Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+	}
+
+	fn u32(&mut self) -> Result<u32, ZipError> {
+		let bytes = self.bytes(4)?;
+		Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+	}
+
+	fn u64(&mut self) -> Result<u64, ZipError> {
+		Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+	}
+
+	fn signature(&mut self, expected: u32) -> Result<(), ZipError> {
+		let position = self.position;
+		if self.u32()? != expected {
+			return Err(ZipError::BadSignature(position as u32));
+		}
+		Ok(())
+	}
+}
+
+/// Fills in the sizes and offset the Zip64 extra field holds, in place of the ones set to all ones.
+fn zip64(extra: &[u8], entry: &mut ZipEntry) -> Result<(), ZipError> {
+	let mut reader = Reader::at(extra, 0)?;
+	while reader.position + 4 <= extra.len() {
+		let id = reader.u16()?;
+		let length = reader.u16()? as usize;
+		let mut field = Reader::at(reader.bytes(length)?, 0)?;
+		if id != ZIP64_EXTRA {
+			continue;
+		}
+		if entry.size == u32::MAX as u64 {
+			entry.size = field.u64()?;
+		}
+		if entry.compressed_size == u32::MAX as u64 {
+			entry.compressed_size = field.u64()?;
+		}
+		if entry.offset == u32::MAX as u64 {
+			entry.offset = field.u64()?;
+		}
+	}
+	Ok(())
+}
+
+impl ZipArchive {
+	pub fn new(data: Vec<u8>) -> Result<Self, ZipError> {
+		// The end of central directory record is at least 22 bytes, followed by a comment of up to 65535.
+		if data.len() < 22 {
+			return Err(ZipError::NotAnArchive);
+		}
+		let minimum = data.len().saturating_sub(22 + u16::MAX as usize);
+		let end = (minimum..=data.len() - 22).rev()
+			.find(|&position| data[position..position + 4] == END_OF_CENTRAL_DIRECTORY.to_le_bytes())
+			.ok_or(ZipError::NotAnArchive)?;
+
+		let mut reader = Reader::at(&data, end as u64 + 10)?;
+		let mut count = reader.u16()? as u64;
+		let mut size = reader.u32()? as u64;
+		let mut offset = reader.u32()? as u64;
+		let comment_length = reader.u16()? as usize;
+		let comment = reader.bytes(comment_length)?.to_vec();
+		let mut directory = end as u64;
+
+		if end >= 76 && data[end - 20..end - 16] == ZIP64_LOCATOR.to_le_bytes() {
+			// The locator gives the record's offset relative to the start of the archive, which isn't known yet,
+			// but without any extensible data, the record sits right before the locator anyway.
+			let position = end as u64 - 76;
+			let mut reader = Reader::at(&data, position)?;
+			reader.signature(ZIP64_END_OF_CENTRAL_DIRECTORY)?;
+			reader.bytes(20)?;
+			count = reader.u64()?;
+			size = reader.u64()?;
+			offset = reader.u64()?;
+			directory = position;
+		}
+
+		let start = directory.checked_sub(size).ok_or(ZipError::Truncated)?;
+		let base = start.checked_sub(offset).ok_or(ZipError::Truncated)?;
+
+		let mut reader = Reader::at(&data, start)?;
+		let mut entries = Vec::new();
+		for _ in 0..count {
+			reader.signature(CENTRAL_HEADER)?;
+			reader.bytes(4)?;
+			let flags = reader.u16()?;
+			let method = reader.u16()?;
+			let time = reader.u16()?;
+			let date = reader.u16()?;
+			let crc32 = reader.u32()?;
+			let compressed_size = reader.u32()? as u64;
+			let size = reader.u32()? as u64;
+			let name_length = reader.u16()? as usize;
+			let extra_length = reader.u16()? as usize;
+			let comment_length = reader.u16()? as usize;
+			reader.bytes(4)?;
+			let external_attributes = reader.u32()?;
+			let offset = reader.u32()? as u64;
+			// Java reads names as UTF-8 whether or not they're flagged as such, so that's what jars have.
+			let name = String::from_utf8_lossy(reader.bytes(name_length)?).into_owned();
+			let extra = reader.bytes(extra_length)?;
+			let mut entry = ZipEntry {
+				name,
+				flags,
+				method,
+				time,
+				date,
+				crc32,
+				compressed_size,
+				size,
+				extra: extra.to_vec(),
+				comment: reader.bytes(comment_length)?.to_vec(),
+				external_attributes,
+				offset,
+			};
+			zip64(extra, &mut entry)?;
+			entries.push(entry);
+		}
+
+		let names = entries.iter()
+			.enumerate()
+			.map(|(i, entry)| (entry.name.clone(), i))
+			.collect();
+		Ok(ZipArchive {
+			data,
+			base,
+			entries,
+			names,
+			comment,
+		})
+	}
+
+	/// The entries, in the order of the central directory.
+	pub fn entries(&self) -> &[ZipEntry] {
+		&self.entries
+	}
+
+	pub fn entry(&self, name: &str) -> Option<&ZipEntry> {
+		self.names.get(name)
+			.map(|&i| &self.entries[i])
+	}
+
+	/// The entry's data as it's stored in the archive, compressed or not.
+	pub fn raw(&self, entry: &ZipEntry) -> Result<&[u8], ZipError> {
+		let mut reader = Reader::at(&self.data, self.base + entry.offset)?;
+		reader.signature(LOCAL_HEADER)?;
+		reader.bytes(22)?;
+		let name_length = reader.u16()? as usize;
+		let extra_length = reader.u16()? as usize;
+		reader.bytes(name_length + extra_length)?;
+		let length = usize::try_from(entry.compressed_size).map_err(|_| ZipError::Truncated)?;
+		reader.bytes(length)
+	}
+
+	/// The entry's data, decompressed and checked against its checksum.
+	pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>, ZipError> {
+		if entry.flags & ENCRYPTED_FLAG != 0 {
+			return Err(ZipError::Encrypted(entry.name.clone()));
+		}
+		let raw = self.raw(entry)?;
+		let data = match entry.method {
+			STORED => raw.to_vec(),
+			DEFLATED => {
+				let limit = usize::try_from(entry.size).map_err(|_| ZipError::Corrupt(entry.name.clone()))?;
+				miniz_oxide::inflate::decompress_to_vec_with_limit(raw, limit)
+					.map_err(|_| ZipError::Inflate(entry.name.clone()))?
+			}
+			method => return Err(ZipError::UnsupportedMethod {
+				name: entry.name.clone(),
+				method,
+			}),
+		};
+		if data.len() as u64 != entry.size || crc32(&data) != entry.crc32 {
+			return Err(ZipError::Corrupt(entry.name.clone()));
+		}
+		Ok(data)
+	}
+
+	/// Reads the entry with the given name, `None` if there isn't one.
+	pub fn read_named(&self, name: &str) -> Option<Result<Vec<u8>, ZipError>> {
+		self.entry(name)
+			.map(|entry| self.read(entry))
+	}
+}
+
+const CRC_TABLE: [u32; 256] = {
+	let mut table = [0; 256];
+	let mut i = 0;
+	while i < 256 {
+		let mut value = i as u32;
+		let mut bit = 0;
+		while bit < 8 {
+			value = if value & 1 != 0 { 0xEDB8_8320 ^ (value >> 1) } else { value >> 1 };
+			bit += 1;
+		}
+		table[i] = value;
+		i += 1;
+	}
+	table
+};
+
+/// The CRC-32 ZIP uses to check entries.
+pub fn crc32(data: &[u8]) -> u32 {
+	!data.iter().fold(!0, |crc, &byte| CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// The MS-DOS time and date for the given number of seconds since the Unix epoch, in UTC.
+pub fn dos_time(seconds: u64) -> (u16, u16) {
+	let days = (seconds / 86400) as i64;
+	let seconds = seconds % 86400;
+
+	// Howard Hinnant's days_from_civil, the other way around.
+	let days = days + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days - era * 146_097;
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month + 2) / 5 + 1;
+	let month = if month < 10 { month + 3 } else { month - 9 };
+	let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+	if year < 1980 {
+		return EPOCH;
+	}
+	let year = (year - 1980).min(127) as u16;
+	let time = (seconds / 3600) << 11 | (seconds % 3600 / 60) << 5 | (seconds % 60 / 2);
+	(time as u16, year << 9 | (month as u16) << 5 | day as u16)
+}
+
+/// Drops the Zip64 extra field, as the writer doesn't write Zip64 archives.
+fn without_zip64(extra: &[u8]) -> Vec<u8> {
+	let mut kept = Vec::new();
+	let mut position = 0;
+	while position + 4 <= extra.len() {
+		let id = u16::from_le_bytes([extra[position], extra[position + 1]]);
+		let length = u16::from_le_bytes([extra[position + 2], extra[position + 3]]) as usize;
+		let end = (position + 4 + length).min(extra.len());
+		if id != ZIP64_EXTRA {
+			kept.extend_from_slice(&extra[position..end]);
+		}
+		position = end;
+	}
+	kept
+}
+
+fn too_large(what: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidInput, format!("{} needs Zip64, which isn't written", what))
+}
+
+/// Writes an archive entry by entry, followed by the central directory once it's finished.
+///
+/// Sizes and checksums go in the local headers, so there are no data descriptors, and no Zip64 either,
+/// so archives are limited to 65535 entries and 4 GiB.
+#[derive(Debug)]
+pub struct ZipWriter<W: Write> {
+	output: W,
+	offset: u64,
+	count: usize,
+	central_directory: Vec<u8>,
+	pub comment: Vec<u8>,
+}
+
+impl<W: Write> ZipWriter<W> {
+	pub fn new(output: W) -> Self {
+		ZipWriter {
+			output,
+			offset: 0,
+			count: 0,
+			central_directory: Vec::new(),
+			comment: Vec::new(),
+		}
+	}
+
+	/// Writes an entry whose data is already compressed with the entry's method, such as the one `ZipArchive::raw` gives,
+	/// so it's copied byte for byte.
+	///
+	/// The entry's offset is ignored, and so is its Zip64 extra field.
+	pub fn write_raw(&mut self, entry: &ZipEntry, raw: &[u8]) -> io::Result<()> {
+		if self.count == u16::MAX as usize {
+			return Err(too_large("more than 65535 entries"));
+		}
+		let compressed_size = u32::try_from(raw.len()).map_err(|_| too_large(&entry.name))?;
+		let size = u32::try_from(entry.size).ok()
+			.filter(|&size| size != u32::MAX)
+			.ok_or_else(|| too_large(&entry.name))?;
+		let offset = u32::try_from(self.offset).map_err(|_| too_large("an archive over 4 GiB"))?;
+
+		let mut flags = entry.flags & !DATA_DESCRIPTOR_FLAG;
+		if !entry.name.is_ascii() {
+			flags |= UTF8_FLAG;
+		}
+		let extra = without_zip64(&entry.extra);
+		let name = entry.name.as_bytes();
+
+		let mut header = Vec::with_capacity(30 + name.len() + extra.len());
+		header.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+		header.extend_from_slice(&20u16.to_le_bytes());
+		header.extend_from_slice(&flags.to_le_bytes());
+		header.extend_from_slice(&entry.method.to_le_bytes());
+		header.extend_from_slice(&entry.time.to_le_bytes());
+		header.extend_from_slice(&entry.date.to_le_bytes());
+		header.extend_from_slice(&entry.crc32.to_le_bytes());
+		header.extend_from_slice(&compressed_size.to_le_bytes());
+		header.extend_from_slice(&size.to_le_bytes());
+		header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+		header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+		header.extend_from_slice(name);
+		header.extend_from_slice(&extra);
+		self.output.write_all(&header)?;
+		self.output.write_all(raw)?;
+
+		let central = &mut self.central_directory;
+		central.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+		central.extend_from_slice(&20u16.to_le_bytes());
+		// Everything from the version needed to extract up to the extra field's length is the same as in the local header.
+		central.extend_from_slice(&header[4..30]);
+		central.extend_from_slice(&(entry.comment.len() as u16).to_le_bytes());
+		central.extend_from_slice(&[0; 4]);
+		central.extend_from_slice(&entry.external_attributes.to_le_bytes());
+		central.extend_from_slice(&offset.to_le_bytes());
+		central.extend_from_slice(name);
+		central.extend_from_slice(&extra);
+		central.extend_from_slice(&entry.comment);
+
+		self.offset += (header.len() + raw.len()) as u64;
+		self.count += 1;
+		Ok(())
+	}
+
+	/// Writes an entry with the given data, deflating it, unless it's empty.
+	pub fn write(&mut self, name: &str, data: &[u8], (time, date): (u16, u16)) -> io::Result<()> {
+		let (method, raw) = if data.is_empty() {
+			(STORED, Vec::new())
+		} else {
+			(DEFLATED, miniz_oxide::deflate::compress_to_vec(data, 6))
+		};
+		let entry = ZipEntry {
+			name: name.to_string(),
+			flags: 0,
+			method,
+			time,
+			date,
+			crc32: crc32(data),
+			compressed_size: raw.len() as u64,
+			size: data.len() as u64,
+			extra: Vec::new(),
+			comment: Vec::new(),
+			external_attributes: 0,
+			offset: 0,
+		};
+		self.write_raw(&entry, &raw)
+	}
+
+	/// Writes the central directory, and gives back the output.
+	pub fn finish(mut self) -> io::Result<W> {
+		let offset = u32::try_from(self.offset).map_err(|_| too_large("an archive over 4 GiB"))?;
+		let size = u32::try_from(self.central_directory.len()).map_err(|_| too_large("the central directory"))?;
+		let comment_length = u16::try_from(self.comment.len())
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "the comment is longer than 65535 bytes"))?;
+		self.output.write_all(&self.central_directory)?;
+
+		let mut end = Vec::with_capacity(22 + self.comment.len());
+		end.extend_from_slice(&END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+		end.extend_from_slice(&[0; 4]);
+		end.extend_from_slice(&(self.count as u16).to_le_bytes());
+		end.extend_from_slice(&(self.count as u16).to_le_bytes());
+		end.extend_from_slice(&size.to_le_bytes());
+		end.extend_from_slice(&offset.to_le_bytes());
+		end.extend_from_slice(&comment_length.to_le_bytes());
+		end.extend_from_slice(&self.comment);
+		self.output.write_all(&end)?;
+		Ok(self.output)
+	}
+}
@@ -0,0 +1,56 @@
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.annotation.Target;
+import java.util.ArrayList;
+import java.util.List;
+import java.util.function.IntSupplier;
+
+@Attributes.Info(name = "attributes", tags = {"a", "b"}, kind = ElementType.TYPE, nested = @Attributes.Tag(Object.class))
+public class Attributes<T extends Comparable<T>> {
+	@Retention(RetentionPolicy.RUNTIME)
+	@interface Info {
+		String name();
+		String[] tags() default {};
+		ElementType kind();
+		Tag nested();
+		int count() default 3;
+	}
+
+	@Retention(RetentionPolicy.RUNTIME)
+	@interface Tag {
+		Class<?> value();
+	}
+
+	@Retention(RetentionPolicy.RUNTIME)
+	@Target(ElementType.TYPE_USE)
+	@interface Use {
+	}
+
+	public static final int ANSWER = 42;
+	public static final String GREETING = "hello";
+
+	private final List<@Use T> values = new ArrayList<>();
+
+	@Deprecated
+	public int sum(final int limit, @Tag(String.class) String label) throws IllegalStateException {
+		int total = 0;
+		for (int i = 0; i < limit; i++) {
+			if (i % 2 == 0) {
+				total += i;
+			} else {
+				total -= 1;
+			}
+		}
+		try {
+			IntSupplier supplier = () -> limit * 2;
+			total += supplier.getAsInt();
+		} catch (RuntimeException e) {
+			throw new IllegalStateException(label, e);
+		}
+		return total;
+	}
+
+	class Inner {
+	}
+}
@@ -0,0 +1,20 @@
+import java.util.function.LongSupplier;
+
+public class Constants {
+	public static final long BIG = 1234567890123L;
+	public static final double PI = 3.14159;
+	static final String NAME = "constants";
+
+	interface Shape {
+		double area();
+
+		default String describe() {
+			return "area " + area();
+		}
+	}
+
+	static long twice(long value) {
+		LongSupplier supplier = () -> value * BIG;
+		return supplier.getAsLong() + (long) PI;
+	}
+}
@@ -0,0 +1,98 @@
+public class Eval {
+	static int square(int x) {
+		return x * x;
+	}
+
+	static int sumOfSquares(int n) {
+		int sum = 0;
+		for (int i = 1; i <= n; i++) {
+			sum += square(i);
+		}
+		return sum;
+	}
+
+	static long factorial(int n) {
+		return n <= 1 ? 1 : n * factorial(n - 1);
+	}
+
+	static String decode(int[] data, int key) {
+		char[] chars = new char[data.length];
+		for (int i = 0; i < data.length; i++) {
+			chars[i] = (char) (data[i] ^ key);
+		}
+		return new String(chars);
+	}
+
+	static String name(int kind) {
+		switch (kind) {
+			case 1: return "one";
+			case 2: return "two";
+			case 1000: return "thousand";
+			default: return "other";
+		}
+	}
+
+	static int dense(int kind) {
+		switch (kind) {
+			case 0: return 10;
+			case 1: return 11;
+			case 2: return 12;
+			default: return -1;
+		}
+	}
+
+	static String greet(String name) {
+		return new StringBuilder().append("Hello, ").append(name).append('!').toString();
+	}
+
+	static String label(String prefix, int count, double ratio) {
+		return prefix + count + "/" + ratio;
+	}
+
+	static int length(String value) {
+		return value.length();
+	}
+
+	static double average(int[][] grid) {
+		long total = 0;
+		int count = 0;
+		for (int[] row : grid) {
+			for (int value : row) {
+				total += value;
+				count++;
+			}
+		}
+		return (double) total / count;
+	}
+
+	static int[][] table(int size) {
+		int[][] table = new int[size][size];
+		for (int i = 0; i < size; i++) {
+			for (int j = 0; j < size; j++) {
+				table[i][j] = i * j;
+			}
+		}
+		return table;
+	}
+
+	static boolean same() {
+		return "abc" == "abc";
+	}
+
+	static int divide(int a, int b) {
+		return a / b;
+	}
+
+	static int spin() {
+		int i = 0;
+		while (true) {
+			i++;
+		}
+	}
+
+	static int locked(Object lock) {
+		synchronized (lock) {
+			return 1;
+		}
+	}
+}
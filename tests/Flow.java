@@ -0,0 +1,77 @@
+import java.util.ArrayList;
+import java.util.List;
+
+public class Flow {
+	private final int[] values;
+	private long total;
+
+	public Flow(int size) {
+		this.values = new int[size];
+	}
+
+	public int sum() {
+		int sum = 0;
+		for (int value : values) {
+			sum += value;
+		}
+		return sum;
+	}
+
+	public static String describe(int kind) {
+		switch (kind) {
+			case 0: return "zero";
+			case 1: return "one";
+			case 100: return "hundred";
+			default: return "many";
+		}
+	}
+
+	public static int dense(int kind) {
+		switch (kind) {
+			case 1: return 10;
+			case 2: return 20;
+			case 3: return 30;
+			default: return -1;
+		}
+	}
+
+	public double average(long count) {
+		try {
+			return (double) sum() / count;
+		} catch (ArithmeticException e) {
+			return Double.NaN;
+		} finally {
+			total += count;
+		}
+	}
+
+	public static List<String> names(Object[] objects) {
+		List<String> names = new ArrayList<>();
+		for (Object object : objects) {
+			names.add(object == null ? "null" : object.toString());
+		}
+		return names;
+	}
+
+	public static int[][] grid(int width, int height) {
+		int[][] grid = new int[width][height];
+		for (int x = 0; x < width; x++) {
+			for (int y = 0; y < height; y++) {
+				grid[x][y] = x * y;
+			}
+		}
+		return grid;
+	}
+
+	public static Flow create(boolean big) {
+		return new Flow(big ? 100 : 10);
+	}
+
+	public synchronized long fold(long start) {
+		long result = start;
+		while (result < 1000) {
+			result = result * 2 + 1;
+		}
+		return result;
+	}
+}
@@ -0,0 +1,35 @@
+import java.util.AbstractList;
+import java.util.ArrayList;
+import java.util.LinkedList;
+
+public class Frames {
+	public static AbstractList<String> merge(boolean linked) {
+		return linked ? new LinkedList<>() : new ArrayList<>();
+	}
+
+	public static Object[] arrays(boolean strings) {
+		return strings ? new String[0] : new Integer[0];
+	}
+
+	public static int constant() {
+		return 1;
+	}
+
+	public static long wide(long first, double second) {
+		long result = first;
+		if (second > 0) {
+			result += (long) second;
+		}
+		return result;
+	}
+
+	public static void nothing() {
+	}
+
+	public static void ignore() {
+		try {
+			nothing();
+		} catch (Exception e) {
+		}
+	}
+}
@@ -0,0 +1,75 @@
+public class Hierarchy {
+	interface Shape {
+		double area();
+
+		default String describe() {
+			return "shape";
+		}
+	}
+
+	interface Named {
+		String name();
+
+		default String describe() {
+			return "named";
+		}
+	}
+
+	interface Polygon extends Shape {
+		int sides();
+	}
+
+	static abstract class Base implements Shape {
+		public String name() {
+			return "base";
+		}
+
+		void hidden() {
+		}
+
+		protected void grow() {
+		}
+
+		private void secret() {
+		}
+
+		static void helper() {
+		}
+	}
+
+	static class Square extends Base implements Polygon, Named {
+		public double area() {
+			return 1;
+		}
+
+		public int sides() {
+			return 4;
+		}
+
+		public String describe() {
+			return "square";
+		}
+
+		void hidden() {
+		}
+
+		protected void grow() {
+		}
+
+		void secret() {
+		}
+
+		static void helper() {
+		}
+	}
+
+	static class Rectangle extends Base implements Polygon {
+		public double area() {
+			return 2;
+		}
+
+		public int sides() {
+			return 4;
+		}
+	}
+}
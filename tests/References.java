@@ -0,0 +1,15 @@
+import java.util.function.Supplier;
+
+public class References {
+	private String name = "references";
+
+	public Supplier<String> supplier() {
+		return this::toString;
+	}
+
+	public void print() {
+		System.out.println(name);
+		Runnable r = () -> System.out.println(name.length());
+		r.run();
+	}
+}
@@ -0,0 +1,157 @@
+extern crate class_file;
+
+mod common;
+
+use class_file::analysis::*;
+use class_file::analysis::basic::*;
+use class_file::analysis::constant::*;
+use class_file::analysis::nullness::*;
+use class_file::attr::Code;
+use class_file::bytecode;
+use class_file::ops::*;
+use common::*;
+
+/// The offset of the first instruction with the given opcode.
+fn find(code: &Code, opcode: u8) -> u32 {
+	bytecode::decode(&code.code).unwrap()
+		.into_iter()
+		.find(|instruction| instruction.opcode == opcode)
+		.unwrap()
+		.pc
+}
+
+#[test]
+fn everything_reachable() {
+	for data in &[
+		&include_bytes!("Flow.class")[..],
+		&include_bytes!("Frames.class")[..],
+		&include_bytes!("Constants.class")[..],
+		&include_bytes!("Attributes.class")[..],
+		&include_bytes!("References.class")[..],
+		&include_bytes!("Version55.class")[..],
+	] {
+		let class_file = load(data);
+		for index in 0..class_file.methods.len() {
+			if let Some(code) = method_code(&class_file, index) {
+				let instructions = bytecode::decode(&code.code).unwrap().len();
+				assert_eq!(analyse(&BasicInterpreter, &class_file, index, &code).unwrap().len(), instructions);
+				assert_eq!(analyse(&ConstantInterpreter, &class_file, index, &code).unwrap().len(), instructions);
+				assert_eq!(analyse(&NullnessInterpreter, &class_file, index, &code).unwrap().len(), instructions);
+			}
+		}
+	}
+}
+
+#[test]
+fn basic() {
+	let flow = load(include_bytes!("Flow.class"));
+	let index = method_index(&flow, "average");
+	let code = method_code(&flow, index).unwrap();
+	let frames = analyse(&BasicInterpreter, &flow, index, &code).unwrap();
+	for frame in frames.values() {
+		assert_eq!(frame.locals[..3], [BasicValue::Reference, BasicValue::Long, BasicValue::Uninitialised]);
+	}
+	for exception in &code.exception_table {
+		assert_eq!(frames[&(exception.handler_pc as u32)].stack, vec![BasicValue::Reference]);
+	}
+	// Dividing the int from sum() by the long count.
+	assert_eq!(frames[&find(&code, DDIV)].stack, vec![BasicValue::Double, BasicValue::Double]);
+}
+
+#[test]
+fn constants() {
+	let frames = load(include_bytes!("Frames.class"));
+	let index = method_index(&frames, "constant");
+	let mut code = method_code(&frames, index).unwrap();
+	code.max_stack = 2;
+	code.max_locals = 2;
+	code.code = vec![
+		ICONST_3,
+		ISTORE_0,
+		IINC, 0, 2,
+		ILOAD_0,
+		ICONST_4,
+		IMUL,
+		ICONST_1,
+		ISHL,
+		ISTORE_1,
+		ILOAD_0,
+		IFEQ, 0, 5,
+		ICONST_2,
+		ISTORE_1,
+		ILOAD_1,
+		IRETURN,
+	];
+	let result = analyse(&ConstantInterpreter, &frames, index, &code).unwrap();
+	assert_eq!(result[&10].stack, vec![ConstantValue::Int(40)]);
+	assert_eq!(result[&15].locals, vec![ConstantValue::Int(5), ConstantValue::Int(40)]);
+	assert_eq!(result[&17].locals, vec![ConstantValue::Int(5), ConstantValue::Unknown(BasicValue::Int)]);
+
+	// Nothing's folded where it would throw.
+	code.code = vec![ICONST_1, ICONST_0, IDIV, IRETURN];
+	let result = analyse(&ConstantInterpreter, &frames, index, &code).unwrap();
+	assert_eq!(result[&3].stack, vec![ConstantValue::Unknown(BasicValue::Int)]);
+
+	code.max_stack = 3;
+	code.code = vec![LCONST_1, L2F, FCONST_0, FCONST_0, FDIV, FCMPG, IRETURN];
+	let result = analyse(&ConstantInterpreter, &frames, index, &code).unwrap();
+	assert!(matches!(result[&5].stack[1], ConstantValue::Float(value) if value.is_nan()));
+	assert_eq!(result[&6].stack, vec![ConstantValue::Int(1)]);
+
+	// The loop counter changes every time around.
+	let flow = load(include_bytes!("Flow.class"));
+	let index = method_index(&flow, "grid");
+	let code = method_code(&flow, index).unwrap();
+	let result = analyse(&ConstantInterpreter, &flow, index, &code).unwrap();
+	assert_eq!(result[&find(&code, IMUL)].stack[2..], [ConstantValue::Unknown(BasicValue::Int); 2]);
+}
+
+#[test]
+fn nullness() {
+	let flow = load(include_bytes!("Flow.class"));
+	let index = method_index(&flow, "names");
+	let code = method_code(&flow, index).unwrap();
+	let frames = analyse(&NullnessInterpreter, &flow, index, &code).unwrap();
+	// The list is new, but the value added is either a string constant, or whatever toString() returned.
+	assert_eq!(frames[&find(&code, INVOKEINTERFACE)].stack, vec![Nullness::NotNull, Nullness::Unknown]);
+	assert_eq!(frames[&0].locals[0], Nullness::Unknown);
+
+	let index = method_index(&flow, "create");
+	let code = method_code(&flow, index).unwrap();
+	let frames = analyse(&NullnessInterpreter, &flow, index, &code).unwrap();
+	assert_eq!(frames[&find(&code, ARETURN)].stack, vec![Nullness::NotNull]);
+
+	let index = method_index(&flow, "sum");
+	let code = method_code(&flow, index).unwrap();
+	let frames = analyse(&NullnessInterpreter, &flow, index, &code).unwrap();
+	assert_eq!(frames[&0].locals[0], Nullness::NotNull);
+	assert_eq!(frames[&find(&code, IRETURN)].stack, vec![Nullness::Other(BasicValue::Int)]);
+}
+
+#[test]
+fn errors() {
+	let frames = load(include_bytes!("Frames.class"));
+	let index = method_index(&frames, "constant");
+	let mut code = method_code(&frames, index).unwrap();
+	code.max_stack = 2;
+
+	code.code = vec![LCONST_0, POP, RETURN];
+	let error = analyse(&BasicInterpreter, &frames, index, &code).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(1), Problem::WrongCategory(1)));
+
+	code.code = vec![ICONST_0, POP2, RETURN];
+	let error = analyse(&BasicInterpreter, &frames, index, &code).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(1), Problem::StackUnderflow));
+
+	code.code = vec![ICONST_0, ICONST_0, ICONST_0, RETURN];
+	let error = analyse(&BasicInterpreter, &frames, index, &code).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(2), Problem::StackOverflow(2)));
+
+	code.code = vec![ICONST_0, ISTORE_0, RETURN];
+	let error = analyse(&BasicInterpreter, &frames, index, &code).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(1), Problem::InvalidLocal(0)));
+
+	code.code = vec![ICONST_0, IFEQ, 0, 4, ICONST_0, RETURN];
+	let error = analyse(&BasicInterpreter, &frames, index, &code).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(4), Problem::StackHeightMismatch { first: 0, second: 1 }));
+}
@@ -0,0 +1,133 @@
+extern crate class_file;
+
+use std::io::Cursor;
+
+use class_file::*;
+use class_file::attr::Code;
+use class_file::bytecode::*;
+use class_file::ops::*;
+
+fn code(name: &str) -> Vec<Instruction> {
+	let mut input = Cursor::new(&include_bytes!("Flow.class")[..]);
+	let class_file = ClassFile::open(&mut input).unwrap();
+	let cp = &class_file.constant_pool;
+	let method = class_file.methods.iter()
+		.find(|method| cp.index(method.name_index).unwrap() == name)
+		.unwrap();
+	let code: Code = method.attributes.get(cp).unwrap();
+	decode(&code.code).unwrap()
+}
+
+#[test]
+fn switches() {
+	let describe = code("describe");
+	let lookup = describe.iter().find(|insn| insn.opcode == LOOKUPSWITCH).unwrap();
+	match lookup.operand {
+		Operand::LookupSwitch { ref pairs, .. } => {
+			assert_eq!(pairs.iter().map(|&(key, _)| key).collect::<Vec<_>>(), vec![0, 1, 100]);
+		}
+		ref operand => panic!("Unexpected operand: {:?}", operand),
+	}
+	// Every target is the start of an instruction.
+	for target in lookup.targets() {
+		assert!(describe.iter().any(|insn| insn.pc == target));
+	}
+
+	let dense = code("dense");
+	let table = dense.iter().find(|insn| insn.opcode == TABLESWITCH).unwrap();
+	assert_eq!(table.targets().len(), 4);
+	assert!(table.is_unconditional());
+}
+
+#[test]
+fn operands() {
+	let init = code("<init>");
+	assert_eq!(init[0], Instruction { pc: 0, opcode: ALOAD_0, wide: false, operand: Operand::Local(0) });
+	assert_eq!(init.iter().map(ToString::to_string).collect::<Vec<_>>(), vec![
+		"aload_0",
+		"invokespecial #1",
+		"aload_0",
+		"iload_1",
+		"newarray int",
+		"putfield #7",
+		"return",
+	]);
+}
+
+#[test]
+fn errors() {
+	assert_eq!(decode(&[BIPUSH]), Err(DecodeError::Truncated(0)));
+	assert_eq!(decode(&[NOP, 0xFE]), Err(DecodeError::UnknownOpcode { pc: 1, opcode: 0xFE }));
+	assert_eq!(decode(&[WIDE, NOP]), Err(DecodeError::InvalidWide { pc: 0, opcode: NOP }));
+	assert_eq!(decode(&[GOTO, 0xFF, 0xFF]), Err(DecodeError::InvalidTarget { pc: 0, target: -1 }));
+	assert_eq!(decode(&[WIDE, IINC, 1, 0, 0xFF, 0xFF]).unwrap()[0].operand, Operand::Iinc { index: 256, value: -1 });
+}
+
+#[test]
+fn encode_round_trip() {
+	let mut input = Cursor::new(&include_bytes!("Flow.class")[..]);
+	let class_file = ClassFile::open(&mut input).unwrap();
+	let cp = &class_file.constant_pool;
+	for method in &class_file.methods {
+		let code: Code = method.attributes.get(cp).unwrap();
+		let encoded = encode(&decode(&code.code).unwrap()).unwrap();
+		assert_eq!(encoded.code, code.code);
+		assert!(encoded.offsets.iter().all(|(old, new)| old == new));
+	}
+}
+
+#[test]
+fn encode_moves_code() {
+	let instruction = |pc, opcode, operand| Instruction { pc, opcode, wide: false, operand };
+	// The constant no longer fits in an `ldc`, and the local needs a `wide`, so the branch back is further.
+	let instructions = vec![
+		instruction(0, LDC, Operand::Constant(300)),
+		instruction(2, ISTORE, Operand::Local(256)),
+		instruction(4, GOTO, Operand::Branch(0)),
+	];
+	let encoded = encode(&instructions).unwrap();
+	assert_eq!(encoded.code, vec![LDC_W, 1, 44, WIDE, ISTORE, 1, 0, GOTO, 0xFF, 0xF9]);
+	assert_eq!(encoded.offsets.into_iter().collect::<Vec<_>>(), vec![(0, 0), (2, 3), (4, 7), (7, 10)]);
+
+	let narrowed = encode(&[instruction(0, LDC_W, Operand::Constant(3))]).unwrap();
+	assert_eq!(narrowed.code, vec![LDC, 3]);
+
+	assert_eq!(encode(&[instruction(0, GOTO, Operand::Branch(1))]), Err(EncodeError::InvalidTarget { pc: 0, target: 1 }));
+	assert_eq!(encode(&[instruction(0, BIPUSH, Operand::None)]), Err(EncodeError::InvalidOperand { pc: 0, opcode: BIPUSH }));
+}
+
+#[test]
+fn encode_reforms_locals() {
+	let instruction = |pc, opcode, operand| Instruction { pc, opcode, wide: false, operand };
+	// Shorthands whose local has been changed go back to the general form, which may then need a `wide`.
+	let instructions = vec![
+		instruction(0, ASTORE_3, Operand::Local(3)),
+		instruction(1, ILOAD_0, Operand::Local(5)),
+		instruction(2, DSTORE_1, Operand::Local(300)),
+	];
+	assert_eq!(encode(&instructions).unwrap().code, vec![ASTORE_3, ILOAD, 5, WIDE, DSTORE, 1, 44]);
+	let wide = Instruction { pc: 0, opcode: ALOAD_0, wide: true, operand: Operand::Local(0) };
+	assert_eq!(encode(&[wide]).unwrap().code, vec![WIDE, ALOAD, 0, 0]);
+	assert_eq!(encode(&[instruction(0, ILOAD_0, Operand::None)]), Err(EncodeError::InvalidOperand { pc: 0, opcode: ILOAD_0 }));
+}
+
+#[test]
+fn encode_widens_branches() {
+	let instruction = |pc, opcode, operand| Instruction { pc, opcode, wide: false, operand };
+	// The first `goto` only falls out of reach once the second has been widened.
+	let mut instructions = vec![
+		instruction(0, GOTO, Operand::Branch(32767)),
+		instruction(3, GOTO, Operand::Branch(40000)),
+	];
+	instructions.extend((6..40000).map(|pc| instruction(pc, NOP, Operand::None)));
+	instructions.push(instruction(40000, RETURN, Operand::None));
+	let encoded = encode(&instructions).unwrap();
+	assert_eq!(encoded.offsets[&32767], 32771);
+	assert_eq!(encoded.offsets[&40000], 40004);
+	assert_eq!(encoded.code[..10], [GOTO_W, 0, 0, 0x80, 0x03, GOTO_W, 0, 0, 0x9C, 0x3F]);
+	assert_eq!(decode(&encoded.code).unwrap()[1].operand, Operand::Branch(40004));
+
+	// Conditional branches can't be widened.
+	instructions[1] = instruction(3, IFEQ, Operand::Branch(40000));
+	assert_eq!(encode(&instructions), Err(EncodeError::TooFar { pc: 3, target: 40000 }));
+}
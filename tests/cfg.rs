@@ -0,0 +1,141 @@
+extern crate class_file;
+
+mod common;
+
+use class_file::*;
+use class_file::attr::Code;
+use class_file::cfg::*;
+use class_file::ops::*;
+use common::*;
+
+fn graph(class_file: &ClassFile, name: &str) -> ControlFlowGraph {
+	let cp = &class_file.constant_pool;
+	let method = class_file.methods.iter()
+		.find(|method| cp.index(method.name_index).unwrap() == name)
+		.unwrap();
+	let code: Code = method.attributes.get(cp).unwrap();
+	build(&code, cp).unwrap()
+}
+
+fn edges(graph: &ControlFlowGraph) -> Vec<(usize, usize, String)> {
+	graph.edges.iter()
+		.map(|edge| (edge.from, edge.to, edge.kind.to_string()))
+		.collect()
+}
+
+fn edge(from: usize, to: usize, kind: &str) -> (usize, usize, String) {
+	(from, to, kind.to_string())
+}
+
+#[test]
+fn switches() {
+	let flow = load(include_bytes!("Flow.class"));
+	let graph = graph(&flow, "describe");
+	assert_eq!(graph.blocks.len(), 5);
+	assert_eq!(edges(&graph), vec![
+		edge(0, 1, "case 0"),
+		edge(0, 2, "case 1"),
+		edge(0, 3, "case 100"),
+		edge(0, 4, "default"),
+	]);
+	assert_eq!(graph.reverse_postorder()[0], 0);
+	let dominators = graph.dominators();
+	for block in 1..5 {
+		assert_eq!(dominators.immediate_dominator(block), Some(0));
+	}
+	assert_eq!(dominators.children(0), vec![1, 2, 3, 4]);
+	assert!(graph.loops().is_empty());
+}
+
+#[test]
+fn exceptions() {
+	let flow = load(include_bytes!("Flow.class"));
+	let graph = graph(&flow, "average");
+	let starts: Vec<u32> = graph.blocks.iter().map(|block| block.start).collect();
+	assert_eq!(starts, vec![0, 9, 21, 27, 40, 42]);
+	assert_eq!(edges(&graph), vec![
+		edge(0, 1, "fallthrough"),
+		edge(0, 2, "catch java/lang/ArithmeticException"),
+		edge(0, 4, "catch any"),
+		edge(2, 3, "fallthrough"),
+		edge(2, 4, "catch any"),
+		edge(4, 5, "fallthrough"),
+		edge(4, 4, "catch any"),
+	]);
+	assert_eq!(graph.block_at(25), Some(2));
+	assert_eq!(graph.block_at(54), Some(5));
+	assert_eq!(graph.block_at(55), None);
+	assert_eq!(graph.predecessor_map()[4], vec![0, 2, 4]);
+	assert_eq!(graph.successor_map()[0], vec![1, 2, 4]);
+}
+
+#[test]
+fn loops() {
+	let flow = load(include_bytes!("Flow.class"));
+	let graph = graph(&flow, "grid");
+	let loops = graph.loops();
+	assert_eq!(loops.len(), 2);
+	let (outer, inner) = (&loops[0], &loops[1]);
+	assert!(inner.blocks.is_subset(&outer.blocks));
+	assert!(inner.blocks.len() < outer.blocks.len());
+
+	let dominators = graph.dominators();
+	for &block in &inner.blocks {
+		assert!(dominators.dominates(inner.header, block));
+		assert!(dominators.dominates(outer.header, block));
+	}
+	for &latch in &outer.latches {
+		assert!(graph.successors(latch).any(|edge| edge.to == outer.header));
+	}
+
+	// Every block shows up after its dominator.
+	let order = graph.reverse_postorder();
+	for (i, &block) in order.iter().enumerate() {
+		if let Some(dominator) = dominators.immediate_dominator(block) {
+			assert!(order[..i].contains(&dominator));
+		}
+	}
+}
+
+#[test]
+fn subroutines() {
+	let cp = ConstantPool { entries: vec![] };
+	let code = Code {
+		max_stack: 1,
+		max_locals: 2,
+		code: vec![
+			JSR, 0, 7,
+			JSR, 0, 4,
+			RETURN,
+			ASTORE_1,
+			RET, 1,
+		],
+		exception_table: vec![],
+		attributes: Attributes::new(vec![]),
+	};
+	let graph = build(&code, &cp).unwrap();
+	assert_eq!(edges(&graph), vec![
+		edge(0, 3, "jsr"),
+		edge(1, 3, "jsr"),
+		edge(3, 1, "ret"),
+		edge(3, 2, "ret"),
+	]);
+	assert_eq!(graph.reverse_postorder(), vec![0, 3, 2, 1]);
+}
+
+#[test]
+fn errors() {
+	let cp = ConstantPool { entries: vec![] };
+	let mut code = Code {
+		max_stack: 1,
+		max_locals: 0,
+		code: vec![GOTO, 0, 2, RETURN],
+		exception_table: vec![],
+		attributes: Attributes::new(vec![]),
+	};
+	assert_eq!(build(&code, &cp), Err(BuildError::InvalidTarget { pc: 0, target: 2 }));
+
+	code.code = vec![NOP, RETURN];
+	code.exception_table.push(attr::Exception { start_pc: 0, end_pc: 1, handler_pc: 3, catch_type: CPIndex::new(0) });
+	assert_eq!(build(&code, &cp), Err(BuildError::InvalidHandler(0)));
+}
@@ -0,0 +1,139 @@
+extern crate class_file;
+
+mod common;
+
+use class_file::classpath::*;
+use class_file::hierarchy::ClassHierarchy;
+use common::*;
+
+fn class_path() -> ClassPath {
+	let mut class_path = ClassPath::new();
+	class_path.add(load(include_bytes!("Hierarchy.class")))
+		.add(load(include_bytes!("Hierarchy$Shape.class")))
+		.add(load(include_bytes!("Hierarchy$Named.class")))
+		.add(load(include_bytes!("Hierarchy$Polygon.class")))
+		.add(load(include_bytes!("Hierarchy$Base.class")))
+		.add(load(include_bytes!("Hierarchy$Square.class")))
+		.add(load(include_bytes!("Hierarchy$Rectangle.class")));
+	class_path
+}
+
+fn owners(methods: &[ResolvedMethod]) -> Vec<String> {
+	methods.iter()
+		.map(|method| method.owner.to_string())
+		.collect()
+}
+
+#[test]
+fn directories() {
+	let mut class_path = ClassPath::new();
+	class_path.add_directory("tests").unwrap();
+	assert!(class_path.contains("Hierarchy$Square"));
+	assert!(class_path.contains("Eval"));
+	assert!(class_path.get("Hierarchy$Base").is_some());
+	assert!(class_path.names().any(|name| name == "Hierarchy$Polygon"));
+}
+
+#[test]
+fn queries() {
+	let class_path = class_path();
+	assert_eq!(class_path.len(), 7);
+	assert!(class_path.missing().is_empty());
+
+	assert_eq!(class_path.super_classes("Hierarchy$Square").unwrap(), ["Hierarchy$Base", "java/lang/Object"]);
+	assert_eq!(class_path.super_classes("java/lang/Object").unwrap(), Vec::<String>::new());
+	let interfaces: Vec<_> = class_path.interfaces("Hierarchy$Square").unwrap().into_iter().collect();
+	assert_eq!(interfaces, ["Hierarchy$Named", "Hierarchy$Polygon", "Hierarchy$Shape"]);
+
+	let subtypes: Vec<_> = class_path.subtypes("Hierarchy$Shape").into_iter().collect();
+	assert_eq!(subtypes, ["Hierarchy$Base", "Hierarchy$Polygon", "Hierarchy$Rectangle", "Hierarchy$Square"]);
+	let direct: Vec<_> = class_path.direct_subtypes("Hierarchy$Polygon").into_iter().collect();
+	assert_eq!(direct, ["Hierarchy$Rectangle", "Hierarchy$Square"]);
+
+	assert!(class_path.is_assignable("Hierarchy$Square", "Hierarchy$Shape").unwrap());
+	assert!(class_path.is_assignable("Hierarchy$Square", "Hierarchy$Base").unwrap());
+	assert!(!class_path.is_assignable("Hierarchy$Rectangle", "Hierarchy$Named").unwrap());
+	assert!(!class_path.is_assignable("Hierarchy$Base", "Hierarchy$Square").unwrap());
+	assert!(class_path.is_assignable("[LHierarchy$Square;", "[LHierarchy$Shape;").unwrap());
+	assert!(!class_path.is_assignable("[I", "[J").unwrap());
+	// Malformed descriptors are only assignable to themselves.
+	assert!(!class_path.is_assignable("[", "[I").unwrap());
+	assert!(!class_path.is_assignable("[L", "[LHierarchy$Shape;").unwrap());
+
+	assert_eq!(class_path.common_super_class("Hierarchy$Square", "Hierarchy$Rectangle").unwrap(), "Hierarchy$Base");
+	assert_eq!(class_path.common_super_class("Hierarchy$Square", "Hierarchy").unwrap(), "java/lang/Object");
+	assert_eq!(class_path.common_super_class("Hierarchy$Shape", "Hierarchy$Square").unwrap(), "java/lang/Object");
+
+	assert_eq!(ClassHierarchy::super_class(&class_path, "Hierarchy$Square"), Some(Some("Hierarchy$Base".to_string())));
+	assert_eq!(ClassHierarchy::is_interface(&class_path, "Hierarchy$Named"), Some(true));
+}
+
+#[test]
+fn resolution() {
+	let class_path = class_path();
+
+	let method = class_path.resolve_method("Hierarchy$Square", "name", "()Ljava/lang/String;").unwrap();
+	assert_eq!(method.owner, "Hierarchy$Base");
+	assert_eq!(method.name(), "name");
+
+	// Only one of the defaults is reachable from a Rectangle.
+	let method = class_path.resolve_method("Hierarchy$Rectangle", "describe", "()Ljava/lang/String;").unwrap();
+	assert_eq!(method.owner, "Hierarchy$Shape");
+	let method = class_path.resolve_method("Hierarchy$Rectangle", "sides", "()I").unwrap();
+	assert_eq!(method.owner, "Hierarchy$Rectangle");
+	let method = class_path.resolve_interface_method("Hierarchy$Polygon", "area", "()D").unwrap();
+	assert_eq!(method.owner, "Hierarchy$Shape");
+
+	assert_eq!(
+		class_path.resolve_method("Hierarchy$Shape", "area", "()D").unwrap_err(),
+		ResolutionError::IncompatibleClassChange("Hierarchy$Shape".to_string()),
+	);
+	assert_eq!(
+		class_path.resolve_interface_method("Hierarchy$Base", "area", "()D").unwrap_err(),
+		ResolutionError::IncompatibleClassChange("Hierarchy$Base".to_string()),
+	);
+	assert_eq!(
+		class_path.resolve_method("Hierarchy$Square", "missing", "()V").unwrap_err(),
+		ResolutionError::NoSuchMethod("Hierarchy$Square.missing:()V".to_string()),
+	);
+}
+
+#[test]
+fn overriding() {
+	let class_path = class_path();
+
+	let overridden = class_path.overridden_methods("Hierarchy$Square", "describe", "()Ljava/lang/String;").unwrap();
+	assert_eq!(owners(&overridden), ["Hierarchy$Named", "Hierarchy$Shape"]);
+	let overridden = class_path.overridden_methods("Hierarchy$Square", "grow", "()V").unwrap();
+	assert_eq!(owners(&overridden), ["Hierarchy$Base"]);
+	let overridden = class_path.overridden_methods("Hierarchy$Square", "hidden", "()V").unwrap();
+	assert_eq!(owners(&overridden), ["Hierarchy$Base"]);
+
+	// Private and static methods can't be overridden.
+	assert!(class_path.overridden_methods("Hierarchy$Square", "secret", "()V").unwrap().is_empty());
+	assert!(class_path.overridden_methods("Hierarchy$Square", "helper", "()V").unwrap().is_empty());
+
+	let overriding = class_path.overriding_methods("Hierarchy$Shape", "area", "()D").unwrap();
+	assert_eq!(owners(&overriding), ["Hierarchy$Rectangle", "Hierarchy$Square"]);
+	let overriding = class_path.overriding_methods("Hierarchy$Base", "name", "()Ljava/lang/String;").unwrap();
+	assert!(overriding.is_empty());
+}
+
+#[test]
+fn unresolved() {
+	let mut class_path = ClassPath::new();
+	class_path.add(load(include_bytes!("Hierarchy$Square.class")));
+
+	let missing: Vec<_> = class_path.missing().into_iter().collect();
+	assert_eq!(missing, ["Hierarchy$Base", "Hierarchy$Named", "Hierarchy$Polygon"]);
+	assert_eq!(class_path.super_classes("Hierarchy$Square").unwrap_err(), Unresolved("Hierarchy$Base".to_string()));
+	assert_eq!(class_path.is_assignable("Hierarchy$Square", "Hierarchy$Shape").unwrap_err(), Unresolved("Hierarchy$Shape".to_string()));
+	assert_eq!(class_path.is_assignable("Hierarchy$Other", "Hierarchy$Square").unwrap_err(), Unresolved("Hierarchy$Other".to_string()));
+	assert_eq!(
+		class_path.resolve_method("Hierarchy$Square", "name", "()Ljava/lang/String;").unwrap_err(),
+		ResolutionError::Unresolved("Hierarchy$Base".to_string()),
+	);
+
+	// The verifier's view of it is more forgiving.
+	assert!(ClassHierarchy::is_assignable(&class_path, "Hierarchy$Square", "Hierarchy$Shape"));
+}
@@ -0,0 +1,121 @@
+extern crate class_file;
+
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use class_file::*;
+
+fn fixture(name: &str) -> String {
+	format!("{}/tests/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn classfile(args: &[&str]) -> Output {
+	Command::new(env!("CARGO_BIN_EXE_classfile"))
+		.args(args)
+		.output()
+		.unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+	String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+/// A scratch directory holding the given files, removed before it's handed out in case a previous run left it behind.
+fn scratch(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+	let directory = env::temp_dir().join(format!("classfile-{}-{}", name, std::process::id()));
+	let _ = fs::remove_dir_all(&directory);
+	fs::create_dir_all(&directory).unwrap();
+	for (name, data) in files {
+		fs::write(directory.join(name), data).unwrap();
+	}
+	directory
+}
+
+#[test]
+fn dump() {
+	let output = classfile(&["dump", &fixture("References.class")]);
+	assert_eq!(output.status.code(), Some(0));
+	let output = stdout(&output);
+	assert!(output.starts_with("class References\n"));
+	assert!(output.contains("  flags: (0x0021) ACC_PUBLIC, ACC_SUPER\n"));
+	assert!(output.contains("  lambda$print$0()V\n    flags: (0x1002) ACC_PRIVATE, ACC_SYNTHETIC\n"));
+	assert!(output.contains("7: invokevirtual #25 // java/io/PrintStream.println:(Ljava/lang/String;)V\n"));
+	assert!(output.ends_with("SourceFile: \"References.java\"\nBootstrapMethods: 22 bytes\nInnerClasses: 10 bytes\n"));
+}
+
+#[test]
+fn constant_pool() {
+	let output = stdout(&classfile(&["cp", &fixture("References.class")]));
+	let lines: Vec<_> = output.lines().take(4).collect();
+	assert_eq!(lines, [
+		"   #1 = Methodref          #2.#3           // java/lang/Object.<init>:()V",
+		"   #2 = Class              #4              // java/lang/Object",
+		"   #3 = NameAndType        #5:#6           // <init>:()V",
+		"   #4 = Utf8               java/lang/Object",
+	]);
+}
+
+#[test]
+fn strings_and_refs() {
+	let output = stdout(&classfile(&["strings", &fixture("Constants.class")]));
+	assert_eq!(output, "\"constants\"\n");
+	let output = stdout(&classfile(&["strings", "--all", &fixture("Constants.class")]));
+	assert!(output.contains("\"Constants.java\"\n"));
+
+	let output = stdout(&classfile(&["refs", &fixture("References.class")]));
+	let refs: Vec<_> = output.lines().collect();
+	assert!(refs.contains(&"class java/lang/System"));
+	assert!(refs.contains(&"field java/lang/System.out:Ljava/io/PrintStream;"));
+	assert!(refs.contains(&"method java/lang/Runnable.run:()V"));
+	assert!(!refs.contains(&"class References"));
+}
+
+#[test]
+fn verify_and_round_trip() {
+	let good = include_bytes!("Hierarchy.class");
+	let directory = scratch("good", &[("Hierarchy.class", good), ("Flow.class", include_bytes!("Flow.class"))]);
+	let directory = directory.to_str().unwrap();
+	assert_eq!(classfile(&["verify", directory]).status.code(), Some(0));
+	assert_eq!(classfile(&["roundtrip", directory]).status.code(), Some(0));
+
+	// Jars are searched too, and their broken entries count as failures.
+	let output = classfile(&["roundtrip", &fixture("Archive.jar")]);
+	assert_eq!(output.status.code(), Some(1));
+	assert!(String::from_utf8_lossy(&output.stderr).contains("Archive.jar!/Broken.class: invalid class"));
+
+	// Points the super class at a Utf8 constant, which is fine for writing out, but not for verifying.
+	let mut class_file = ClassFile::open(&mut Cursor::new(&good[..])).unwrap();
+	let utf8 = class_file.constant_pool.entries.iter()
+		.position(|entry| matches!(entry, CPEntry::UTF8(_)))
+		.unwrap();
+	class_file.super_class = CPIndex::new(utf8 as u16 + 1);
+	let mut bad = vec![];
+	class_file.to_bytes(&mut bad).unwrap();
+	let directory = scratch("bad", &[("Bad.class", &bad)]);
+	let directory = directory.to_str().unwrap();
+	assert_eq!(classfile(&["roundtrip", directory]).status.code(), Some(0));
+	let output = classfile(&["verify", directory]);
+	assert_eq!(output.status.code(), Some(1));
+	assert!(stdout(&output).contains("Bad.class: class: "));
+}
+
+#[test]
+fn errors() {
+	assert_eq!(classfile(&[]).status.code(), Some(2));
+	assert_eq!(classfile(&["explode", &fixture("Flow.class")]).status.code(), Some(2));
+	assert_eq!(classfile(&["dump", "--frobnicate", &fixture("Flow.class")]).status.code(), Some(2));
+	assert_eq!(classfile(&["dump", &fixture("Missing.class")]).status.code(), Some(2));
+	assert_eq!(classfile(&["dump"]).status.code(), Some(2));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json() {
+	let output = stdout(&classfile(&["json", "--resolved", &fixture("References.class")]));
+	let value: serde_json::Value = serde_json::from_str(output.trim_end()).unwrap();
+	assert!(value["source"].as_str().unwrap().ends_with("References.class"));
+	assert_eq!(value["class"]["name"], "References");
+}
@@ -0,0 +1,43 @@
+// Not every test uses every helper.
+#![allow(dead_code)]
+
+use std::io::Cursor;
+
+use class_file::*;
+use class_file::attr::Code;
+use class_file::resolve::constant;
+
+pub fn load(data: &[u8]) -> ClassFile<'static> {
+	let mut input = Cursor::new(data);
+	ClassFile::open(&mut input)
+		.expect("Failed to parse class")
+}
+
+pub fn method_index(class_file: &ClassFile, name: &str) -> usize {
+	let cp = &class_file.constant_pool;
+	class_file.methods.iter()
+		.position(|method| cp.index(method.name_index).unwrap() == name)
+		.unwrap()
+}
+
+pub fn method_code(class_file: &ClassFile, index: usize) -> Option<Code<'static>> {
+	let info = class_file.methods[index].attributes.named(&class_file.constant_pool, "Code")?;
+	Some(Code::from_bytes(&mut Cursor::new(info.info())).unwrap())
+}
+
+/// The contents of every `CONSTANT_Utf8` entry.
+pub fn strings(class_file: &ClassFile) -> Vec<String> {
+	class_file.constant_pool.entries.iter()
+		.filter_map(|entry| match entry {
+			CPEntry::UTF8(info) => Some(info.to_string()),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Everything the constant pool refers to, the way javap would comment it.
+pub fn constants(class_file: &ClassFile) -> Vec<String> {
+	(1..=class_file.constant_pool.entries.len() as u16)
+		.filter_map(|index| constant(&class_file.constant_pool, index))
+		.collect()
+}
@@ -0,0 +1,157 @@
+extern crate class_file;
+
+mod common;
+
+use std::collections::HashMap;
+
+use class_file::*;
+use class_file::attr::Code;
+use class_file::bytecode::*;
+use class_file::compact::*;
+use class_file::ops::*;
+use class_file::resolve::constant;
+use class_file::verify::format::validate;
+use common::*;
+
+fn classes() -> Vec<ClassFile<'static>> {
+	vec![
+		load(include_bytes!("compact/pool/Pool.class")),
+		load(include_bytes!("compact/pool/Circle.class")),
+		load(include_bytes!("compact/pool/Square.class")),
+		load(include_bytes!("compact/pool/Shape.class")),
+		load(include_bytes!("compact/pool/Note.class")),
+	]
+}
+
+fn pool() -> ClassFile<'static> {
+	classes().remove(0)
+}
+
+fn code(class_file: &ClassFile, name: &str) -> Vec<Instruction> {
+	let cp = &class_file.constant_pool;
+	let method = class_file.methods.iter()
+		.find(|method| cp.utf8(method.name_index).unwrap().to_utf8() == name)
+		.unwrap();
+	let code: Code = method.attributes.get(cp).unwrap();
+	decode(&code.code).unwrap()
+}
+
+/// The instructions of every method, with constants spelled out and branches going by instruction,
+/// so code can be compared across pools.
+fn listing(class_file: &ClassFile) -> Vec<String> {
+	let cp = &class_file.constant_pool;
+	let mut result = vec![];
+	for method in &class_file.methods {
+		let code: Code = match method.attributes.get(cp) {
+			Some(code) => code,
+			None => continue,
+		};
+		let instructions = decode(&code.code).unwrap();
+		let ordinals: HashMap<u32, usize> = instructions.iter()
+			.enumerate()
+			.map(|(i, instruction)| (instruction.pc, i))
+			.collect();
+		for instruction in &instructions {
+			let mut line = match instruction.opcode {
+				LDC_W => "ldc".to_string(),
+				opcode => mnemonic(opcode).unwrap().to_string(),
+			};
+			match instruction.operand {
+				Operand::Constant(index) | Operand::InvokeInterface { index, .. } | Operand::MultiANewArray { index, .. } => {
+					line += &format!(" {}", constant(cp, index).unwrap());
+				}
+				_ => {}
+			}
+			for target in instruction.targets() {
+				line += &format!(" @{}", ordinals[&target]);
+			}
+			result.push(line);
+		}
+	}
+	result
+}
+
+#[test]
+fn nothing_to_drop() {
+	// javac doesn't leave anything unused, so there's nothing to do.
+	for class_file in classes() {
+		assert_eq!(compact(&class_file).unwrap(), class_file);
+	}
+}
+
+#[test]
+fn drops_unused() {
+	let original = pool();
+	let mut class_file = original.clone();
+	class_file.constant_pool.add_utf8("unused");
+	class_file.constant_pool.add_class("pool/Unused");
+	let compacted = compact(&class_file).unwrap();
+	assert_eq!(compacted, original);
+
+	// Without `strings`, its constants go, and what `describe` loads moves back within reach of `ldc`.
+	let cp = class_file.constant_pool.clone();
+	class_file.methods.retain(|method| cp.utf8(method.name_index).unwrap().to_utf8() != "strings");
+	let compacted = compact(&class_file).unwrap();
+	assert_eq!(validate(&compacted), vec![]);
+	assert!(compacted.constant_pool.entries.len() < original.constant_pool.entries.len() - 300);
+	assert!(!strings(&compacted).contains(&"s0".to_string()));
+	assert!(code(&original, "describe").iter().any(|instruction| instruction.opcode == LDC_W));
+	let describe = code(&compacted, "describe");
+	assert!(describe.iter().all(|instruction| instruction.opcode != LDC_W));
+	assert!(describe.last().unwrap().pc < code(&original, "describe").last().unwrap().pc);
+	assert_eq!(listing(&compacted), listing(&class_file));
+}
+
+#[test]
+fn sort_and_deduplicate() {
+	let original = pool();
+	let mut class_file = original.clone();
+	// Another entry for the class itself, which is the one the class now uses.
+	let entries = &mut class_file.constant_pool.entries;
+	entries.push(CPEntry::UTF8(UTF8Info::new("pool/Pool")));
+	entries.push(CPEntry::Class(ClassInfo { name_index: CPIndex::new(entries.len() as u16) }));
+	class_file.this_class = CPIndex::new(entries.len() as u16);
+
+	let mut compactor = Compactor::new();
+	compactor.sort(true);
+	let sorted = compactor.compact(&class_file).unwrap();
+	assert_eq!(sorted.constant_pool.entries.len(), original.constant_pool.entries.len() + 2);
+	compactor.deduplicate(true);
+	let sorted = compactor.compact(&class_file).unwrap();
+	assert_eq!(sorted.constant_pool.entries.len(), original.constant_pool.entries.len());
+	assert_eq!(validate(&sorted), vec![]);
+	assert_eq!(listing(&sorted), listing(&original));
+
+	// Whatever `ldc` loads comes first, then everything else by kind.
+	let cp = &sorted.constant_pool;
+	assert_eq!(constant(cp, 1).unwrap(), "1.5f");
+	let tags: Vec<u8> = cp.entries.iter()
+		.skip_while(|entry| !matches!(entry, CPEntry::UTF8(_)))
+		.map(CPEntry::tag)
+		.filter(|&tag| tag != 0)
+		.collect();
+	assert!(tags.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", tags);
+	// Some strings ended up past 255, and some came back, such as the first one `describe` loads.
+	assert!(code(&sorted, "strings").iter().any(|instruction| instruction.opcode == LDC_W));
+	let first_load = |class_file| code(class_file, "describe").into_iter().find(|instruction| matches!(instruction.opcode, LDC | LDC_W)).unwrap();
+	assert_eq!((first_load(&original).opcode, first_load(&sorted).opcode), (LDC_W, LDC));
+	assert_eq!(compactor.compact(&sorted).unwrap(), sorted);
+
+	for class_file in classes() {
+		let sorted = compactor.compact(&class_file).unwrap();
+		assert_eq!(validate(&sorted), vec![]);
+		assert_eq!(listing(&sorted), listing(&class_file));
+	}
+}
+
+#[test]
+fn errors() {
+	let mut class_file = pool();
+	let name_index = class_file.constant_pool.add_utf8("Custom").unwrap();
+	class_file.attributes.push(AttributeInfo::new(name_index, vec![0, 1]));
+	assert_eq!(compact(&class_file), Err(CompactError::UnknownAttribute("Custom".to_string())));
+
+	let mut class_file = pool();
+	class_file.super_class = CPIndex::new(10_000);
+	assert_eq!(compact(&class_file), Err(CompactError::InvalidIndex(10_000)));
+}
@@ -0,0 +1,67 @@
+extern crate class_file;
+
+use class_file::classpath::ClassPath;
+use class_file::compat::*;
+use class_file::ops::*;
+
+fn class_path(version: &str) -> ClassPath {
+	let mut class_path = ClassPath::new();
+	class_path.add_directory(format!("{}/tests/compat/{}", env!("CARGO_MANIFEST_DIR"), version))
+		.unwrap();
+	class_path
+}
+
+fn problems(class: &str) -> Vec<(Option<String>, Problem)> {
+	check(&class_path("old"), &class_path("new")).into_iter()
+		.filter(|diagnostic| diagnostic.class == class)
+		.map(|diagnostic| (diagnostic.member, diagnostic.problem))
+		.collect()
+}
+
+fn member(member: &str, problem: Problem) -> (Option<String>, Problem) {
+	(Some(member.to_string()), problem)
+}
+
+#[test]
+fn classes() {
+	assert_eq!(problems("lib/Gone"), [(None, Problem::ClassRemoved)]);
+	assert_eq!(problems("lib/Shape"), [(None, Problem::KindChanged { was_interface: false })]);
+	assert_eq!(problems("lib/Sealed"), [(None, Problem::ClassMadeFinal)]);
+	assert_eq!(problems("lib/Listener"), [member("other()V", Problem::AbstractMethodAdded)]);
+	assert!(problems("lib/Internal").is_empty());
+	assert!(problems("lib/Base").is_empty());
+}
+
+#[test]
+fn members() {
+	let problems = problems("lib/Api");
+	assert_eq!(problems, [
+		(None, Problem::SuperTypeRemoved("java/lang/Runnable".to_string())),
+		member("VERSION:I", Problem::ConstantValueChanged { old: Some("1".to_string()), new: Some("2".to_string()) }),
+		member("label:Ljava/lang/String;", Problem::FieldTypeChanged { old: "Ljava/lang/String;".to_string(), new: "J".to_string() }),
+		member("count:I", Problem::StaticChanged { was_static: false }),
+		member("shared:I", Problem::StaticChanged { was_static: true }),
+		member("remove()V", Problem::MethodRemoved),
+		member("narrowed()V", Problem::MemberLessAccessible { old: PUBLIC, new: PROTECTED }),
+		member("size()I", Problem::ReturnTypeChanged { old: "I".to_string(), new: "J".to_string() }),
+		member("close()V", Problem::MemberMadeFinal),
+	]);
+}
+
+#[test]
+fn severities() {
+	let diagnostics = check(&class_path("old"), &class_path("new"));
+	let warnings: Vec<_> = diagnostics.iter()
+		.filter(|diagnostic| diagnostic.severity() == Severity::Warning)
+		.map(ToString::to_string)
+		.collect();
+	assert_eq!(warnings, [
+		"warning: lib/Api.VERSION:I: constant value changed from 1 to 2",
+		"warning: lib/Listener.other()V: abstract method added",
+	]);
+	assert!(diagnostics.iter().any(|diagnostic| diagnostic.to_string() == "error: lib/Api.narrowed()V: access narrowed from public to protected"));
+	assert!(Severity::Error > Severity::Warning);
+
+	// Nothing changes, nothing to report.
+	assert!(check(&class_path("new"), &class_path("new")).is_empty());
+}
@@ -0,0 +1,24 @@
+package lib;
+
+public class Api extends Base {
+	public static final int VERSION = 2;
+	public long label;
+	public static int count;
+	public int shared;
+
+	public void run() {
+	}
+
+	protected void narrowed() {
+	}
+
+	public long size() {
+		return 0;
+	}
+
+	public final void close() {
+	}
+
+	public static final void utility() {
+	}
+}
@@ -0,0 +1,6 @@
+package lib;
+
+public class Base {
+	public void helper() {
+	}
+}
@@ -0,0 +1,7 @@
+package lib;
+
+public interface Listener {
+	void event();
+
+	void other();
+}
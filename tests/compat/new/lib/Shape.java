@@ -0,0 +1,4 @@
+package lib;
+
+public interface Shape {
+}
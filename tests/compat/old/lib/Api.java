@@ -0,0 +1,36 @@
+package lib;
+
+public class Api extends Base implements Runnable {
+	public static final int VERSION = 1;
+	public String label;
+	public int count;
+	public static int shared;
+
+	public void run() {
+	}
+
+	public void remove() {
+	}
+
+	public void narrowed() {
+	}
+
+	public int size() {
+		return 0;
+	}
+
+	// Moves up to Base, which is fine.
+	public void helper() {
+	}
+
+	public void close() {
+	}
+
+	// Made final, which is fine for static methods.
+	public static void utility() {
+	}
+
+	// Removed, which is fine, as nobody outside the package can see it.
+	void internal() {
+	}
+}
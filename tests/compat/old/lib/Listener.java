@@ -0,0 +1,5 @@
+package lib;
+
+public interface Listener {
+	void event();
+}
@@ -0,0 +1,39 @@
+extern crate class_file;
+
+use class_file::descriptor::*;
+
+#[test]
+fn field_types() {
+	assert_eq!(FieldType::parse("I"), Some(FieldType::Int));
+	assert_eq!(FieldType::parse("[[J"), Some(FieldType::Array(Box::new(FieldType::Array(Box::new(FieldType::Long))))));
+	assert_eq!(FieldType::parse("Ljava/lang/String;"), Some(FieldType::Object("java/lang/String".to_string())));
+
+	for invalid in &["", "V", "II", "L;", "Ljava/lang/String", "Ljava//String;", "Ljava.lang.String;", "["] {
+		assert_eq!(FieldType::parse(invalid), None, "{:?}", invalid);
+	}
+	assert!(FieldType::parse(&format!("{}I", "[".repeat(255))).is_some());
+	assert!(FieldType::parse(&format!("{}I", "[".repeat(256))).is_none());
+}
+
+#[test]
+fn method_descriptors() {
+	let descriptor = MethodDescriptor::parse("(IJ[Ljava/lang/Object;D)V").unwrap();
+	assert_eq!(descriptor.parameters.len(), 4);
+	assert_eq!(descriptor.parameter_slots(), 6);
+	assert_eq!(descriptor.return_type, None);
+	assert_eq!(descriptor.to_string(), "(IJ[Ljava/lang/Object;D)V");
+
+	for invalid in &["", "()", "(V)V", "I", "(I", "()VV", "()[V"] {
+		assert_eq!(MethodDescriptor::parse(invalid), None, "{:?}", invalid);
+	}
+}
+
+#[test]
+fn names() {
+	assert!(is_binary_name("java/lang/Object"));
+	assert!(!is_binary_name("java.lang.Object"));
+	assert!(is_class_name("[Ljava/lang/Object;"));
+	assert!(is_method_name("<init>"));
+	assert!(!is_method_name("<foo>"));
+	assert!(is_unqualified_name("$lambda$0"));
+}
@@ -0,0 +1,102 @@
+extern crate class_file;
+
+mod common;
+
+use class_file::*;
+use class_file::diff::*;
+use class_file::ops::*;
+use common::*;
+
+fn old() -> ClassFile<'static> {
+	load(include_bytes!("diff/old/Sample.class"))
+}
+
+fn new() -> ClassFile<'static> {
+	load(include_bytes!("diff/new/Sample.class"))
+}
+
+fn member<'a>(diff: &'a ClassDiff, name: &str) -> &'a MemberStatus {
+	&diff.members.iter()
+		.find(|member| member.name == name)
+		.unwrap()
+		.status
+}
+
+#[test]
+fn layout_only() {
+	// Same members, but in a different order, with debug information, so the constant pool is laid out differently.
+	let layout = load(include_bytes!("diff/layout/Sample.class"));
+	assert_ne!(layout.constant_pool, old().constant_pool);
+	let diff = diff(&old(), &layout).unwrap();
+	assert!(diff.is_empty(), "{:?}", diff);
+	assert_eq!(diff.to_string(), "");
+}
+
+#[test]
+fn class_changes() {
+	let diff = diff(&old(), &new()).unwrap();
+	assert_eq!(diff.name, "Sample");
+	assert!(diff.changes.contains(&Change::InterfaceAdded("java/lang/Comparable".to_string())));
+	assert!(diff.changes.contains(&Change::Signature {
+		old: None,
+		new: Some("Ljava/lang/Object;Ljava/io/Serializable;Ljava/lang/Comparable<LSample;>;".to_string()),
+	}));
+	assert!(diff.changes.contains(&Change::AnnotationRemoved(Annotation {
+		text: "@Ljava/lang/Deprecated;".to_string(),
+		visible: true,
+	})));
+}
+
+#[test]
+fn member_changes() {
+	let diff = diff(&old(), &new()).unwrap();
+
+	assert_eq!(*member(&diff, "LIMIT"), MemberStatus::Changed {
+		changes: vec![Change::ConstantValue { old: Some("10".to_string()), new: Some("20".to_string()) }],
+		code: vec![],
+	});
+	assert_eq!(*member(&diff, "name"), MemberStatus::Changed {
+		changes: vec![Change::Flags { old: PROTECTED, new: PUBLIC }],
+		code: vec![],
+	});
+	assert_eq!(*member(&diff, "unused"), MemberStatus::Removed(vec![]));
+	assert_eq!(*member(&diff, "added"), MemberStatus::Added(vec![]));
+	assert!(diff.members.iter().all(|member| member.name != "reset" && member.name != "<init>"));
+
+	let (changes, code) = match member(&diff, "describe") {
+		MemberStatus::Changed { changes, code } => (changes, code),
+		status => panic!("expected describe to change, got {:?}", status),
+	};
+	assert_eq!(*changes, [Change::AnnotationAdded(Annotation {
+		text: "@Ljava/lang/Deprecated;(since=\"2\")".to_string(),
+		visible: true,
+	})]);
+	// The limit is inlined, so the comparison changes too.
+	let changed: Vec<_> = code.iter()
+		.filter(|line| !matches!(line, Line::Same(_)))
+		.collect();
+	assert_eq!(changed, [
+		&Line::Removed("bipush 10".to_string()),
+		&Line::Added("bipush 20".to_string()),
+		&Line::Removed("ldc \"too many\"".to_string()),
+		&Line::Added("ldc \"far too many\"".to_string()),
+	]);
+	assert!(code.contains(&Line::Same("if_icmple L0".to_string())));
+	assert!(code.contains(&Line::Same("L0:".to_string())));
+
+	match member(&diff, "compareTo") {
+		MemberStatus::Added(code) => assert!(code.contains(&"invokevirtual java/lang/String.compareTo:(Ljava/lang/String;)I".to_string())),
+		status => panic!("expected compareTo to be added, got {:?}", status),
+	}
+}
+
+#[test]
+fn text() {
+	let text = diff(&old(), &new()).unwrap().to_string();
+	assert!(text.starts_with("--- Sample\n+++ Sample\n@@ class @@\n"));
+	assert!(text.contains("\n+implements java/lang/Comparable\n"));
+	assert!(text.contains("\n@@ field LIMIT:I @@\n-value 10\n+value 20\n"));
+	assert!(text.contains("\n@@ -field unused:I @@\n"));
+	assert!(text.contains("\n-  bipush 10\n+  bipush 20\n"));
+	assert!(text.contains("\n@@ +method compareTo(LSample;)I @@\n+  aload_0\n"));
+}
@@ -0,0 +1,20 @@
+import java.io.Serializable;
+
+// The same class as old/Sample.java, with its members in a different order, so its constant pool is laid out differently.
+@Deprecated
+public class Sample implements Serializable {
+	private int unused;
+	protected String name;
+	public static final int LIMIT = 10;
+
+	public void reset() {
+		name = null;
+	}
+
+	public String describe(int count) {
+		if (count > LIMIT) {
+			return "too many";
+		}
+		return name + count;
+	}
+}
@@ -0,0 +1,24 @@
+import java.io.Serializable;
+
+public class Sample implements Serializable, Comparable<Sample> {
+	public static final int LIMIT = 20;
+	public String name;
+	private long added;
+
+	@Deprecated(since = "2")
+	public String describe(int count) {
+		if (count > LIMIT) {
+			return "far too many";
+		}
+		return name + count;
+	}
+
+	public void reset() {
+		name = null;
+	}
+
+	@Override
+	public int compareTo(Sample other) {
+		return name.compareTo(other.name);
+	}
+}
@@ -0,0 +1,19 @@
+import java.io.Serializable;
+
+@Deprecated
+public class Sample implements Serializable {
+	public static final int LIMIT = 10;
+	protected String name;
+	private int unused;
+
+	public String describe(int count) {
+		if (count > LIMIT) {
+			return "too many";
+		}
+		return name + count;
+	}
+
+	public void reset() {
+		name = null;
+	}
+}
@@ -0,0 +1,55 @@
+extern crate class_file;
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use class_file::*;
+use class_file::cfg::BuildError;
+use class_file::dot::*;
+use common::*;
+
+#[test]
+fn control_flow() {
+	let flow = load(include_bytes!("Flow.class"));
+	let dot = method_to_dot(&flow, method_index(&flow, "describe")).unwrap();
+	assert!(dot.starts_with("digraph \"Flow.describe(I)Ljava/lang/String;\" {\n"));
+	assert!(dot.contains("b0 -> b3 [label=\"case 100\"];"));
+	assert!(dot.contains("b0 -> b4 [label=\"default\"];"));
+	assert!(dot.contains("// \\\"hundred\\\"\\l"));
+	assert!(dot.ends_with("}\n"));
+
+	let dot = method_to_dot(&flow, method_index(&flow, "average")).unwrap();
+	assert!(dot.contains("b0 -> b2 [label=\"catch java/lang/ArithmeticException\", style=dashed];"));
+	assert!(dot.contains("b0 -> b1 [label=\"fallthrough\"];"));
+	assert!(dot.contains("// Flow.sum:()I\\l"));
+	assert!(dot.contains("// Flow.total:J\\l"));
+}
+
+#[test]
+fn unreadable_methods() {
+	let mut flow = load(include_bytes!("Flow.class"));
+	let count = flow.methods.len();
+	assert_eq!(method_to_dot(&flow, count), Err(BuildError::MissingMethod(count)));
+
+	let index = method_index(&flow, "describe");
+	let cp = flow.constant_pool.clone();
+	let name_index = flow.methods[index].attributes.named(&cp, "Code").unwrap().name_index();
+	flow.methods[index].attributes.set(&cp, AttributeInfo::new(name_index, vec![0, 1]));
+	assert_eq!(method_to_dot(&flow, index), Err(BuildError::MalformedCode));
+}
+
+#[test]
+fn call_graph() {
+	let flow = load(include_bytes!("Flow.class"));
+	let dot = call_graph_to_dot(&flow);
+	let node = |name: &str| format!("m{}", method_index(&flow, name));
+	assert!(dot.contains(&format!("{} [label=\"create(Z)LFlow;\"];", node("create"))));
+	assert!(dot.contains(&format!("{} -> {} [label=\"invokespecial\"];", node("create"), node("<init>"))));
+	assert!(dot.contains(&format!("{} -> {} [label=\"invokevirtual\"];", node("average"), node("sum"))));
+	assert!(dot.contains("[label=\"java/util/List.add:(Ljava/lang/Object;)Z\", style=dashed];"));
+	// Each call shows up once, however often it's made.
+	let edges: Vec<&str> = dot.lines().filter(|line| line.contains(" -> ")).collect();
+	let unique: BTreeSet<&str> = edges.iter().cloned().collect();
+	assert_eq!(edges.len(), unique.len());
+}
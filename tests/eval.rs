@@ -0,0 +1,147 @@
+extern crate class_file;
+
+mod common;
+
+use binform::BigEndian;
+
+use class_file::*;
+use class_file::attr::Code;
+use class_file::eval::*;
+use class_file::ops::*;
+use common::*;
+
+fn call_string(evaluator: &mut Evaluator, name: &str, descriptor: &str, arguments: &[Value]) -> String {
+	let value = evaluator.invoke("Eval", name, descriptor, arguments).unwrap().unwrap();
+	evaluator.heap.string(value).unwrap().to_string()
+}
+
+#[test]
+fn arithmetic_and_calls() {
+	let class_file = load(include_bytes!("Eval.class"));
+	let mut evaluator = Evaluator::new();
+	evaluator.add(&class_file);
+	assert_eq!(evaluator.invoke("Eval", "square", "(I)I", &[Value::Int(-7)]), Ok(Some(Value::Int(49))));
+	assert_eq!(evaluator.invoke("Eval", "sumOfSquares", "(I)I", &[Value::Int(10)]), Ok(Some(Value::Int(385))));
+	assert_eq!(evaluator.invoke("Eval", "factorial", "(I)J", &[Value::Int(20)]), Ok(Some(Value::Long(2432902008176640000))));
+	assert_eq!(call_string(&mut evaluator, "name", "(I)Ljava/lang/String;", &[Value::Int(1000)]), "thousand");
+	assert_eq!(call_string(&mut evaluator, "name", "(I)Ljava/lang/String;", &[Value::Int(5)]), "other");
+	assert_eq!(evaluator.invoke("Eval", "dense", "(I)I", &[Value::Int(2)]), Ok(Some(Value::Int(12))));
+	assert_eq!(evaluator.invoke("Eval", "dense", "(I)I", &[Value::Int(-5)]), Ok(Some(Value::Int(-1))));
+}
+
+#[test]
+fn strings() {
+	let class_file = load(include_bytes!("Eval.class"));
+	let mut evaluator = Evaluator::new();
+	evaluator.add(&class_file);
+
+	let data: Vec<i32> = "hello".chars().map(|c| c as i32 ^ 42).collect();
+	let data = evaluator.heap.new_int_array(&data);
+	assert_eq!(call_string(&mut evaluator, "decode", "([II)Ljava/lang/String;", &[data, Value::Int(42)]), "hello");
+
+	let name = evaluator.heap.new_string("World");
+	assert_eq!(call_string(&mut evaluator, "greet", "(Ljava/lang/String;)Ljava/lang/String;", &[name]), "Hello, World!");
+
+	let prefix = evaluator.heap.new_string("n");
+	let label = call_string(&mut evaluator, "label", "(Ljava/lang/String;ID)Ljava/lang/String;", &[prefix, Value::Int(3), Value::Double(0.5)]);
+	assert_eq!(label, "n3/0.5");
+	let label = call_string(&mut evaluator, "label", "(Ljava/lang/String;ID)Ljava/lang/String;", &[Value::Null, Value::Int(-1), Value::Double(2.0)]);
+	assert_eq!(label, "null-1/2.0");
+
+	let value = evaluator.heap.new_string("h\u{e9}llo \u{1F600}");
+	assert_eq!(evaluator.invoke("Eval", "length", "(Ljava/lang/String;)I", &[value]), Ok(Some(Value::Int(8))));
+	// String constants are interned.
+	assert_eq!(evaluator.invoke("Eval", "same", "()Z", &[]), Ok(Some(Value::Int(1))));
+}
+
+#[test]
+fn arrays() {
+	let class_file = load(include_bytes!("Eval.class"));
+	let mut evaluator = Evaluator::new();
+	evaluator.add(&class_file);
+	let table = evaluator.invoke("Eval", "table", "(I)[[I", &[Value::Int(3)]).unwrap().unwrap();
+	let rows = match evaluator.heap.get(table) {
+		Some(Object::Array { descriptor, elements }) => {
+			assert_eq!(descriptor, "[[I");
+			elements.clone()
+		}
+		other => panic!("{:?}", other),
+	};
+	assert_eq!(evaluator.heap.int_array(rows[2]), Some(vec![0, 2, 4]));
+	assert_eq!(evaluator.invoke("Eval", "average", "([[I)D", &[table]), Ok(Some(Value::Double(1.0))));
+}
+
+#[test]
+fn natives() {
+	let class_file = load(include_bytes!("Eval.class"));
+	let mut evaluator = Evaluator::new();
+	evaluator.add(&class_file);
+	// Natives take precedence over the classes.
+	evaluator.register("Eval", "square", "(I)I", |_, arguments| match arguments[0] {
+		Value::Int(value) => Ok(Some(Value::Int(value + 1))),
+		_ => Err(Problem::TypeMismatch),
+	});
+	assert_eq!(evaluator.invoke("Eval", "sumOfSquares", "(I)I", &[Value::Int(3)]), Ok(Some(Value::Int(9))));
+
+	evaluator.remove("java/lang/String", "length", "()I");
+	let value = evaluator.heap.new_string("abc");
+	let error = evaluator.invoke("Eval", "length", "(Ljava/lang/String;)I", &[value]).unwrap_err();
+	assert_eq!(error.method, "java/lang/String.length:()I");
+	assert_eq!(error.problem, Problem::UnknownMethod("java/lang/String.length:()I".to_string()));
+}
+
+#[test]
+fn errors() {
+	let class_file = load(include_bytes!("Eval.class"));
+	let mut evaluator = Evaluator::new();
+	evaluator.add(&class_file);
+
+	let error = evaluator.invoke("Eval", "divide", "(II)I", &[Value::Int(1), Value::Int(0)]).unwrap_err();
+	assert_eq!(error, EvalError {
+		method: "Eval.divide:(II)I".to_string(),
+		pc: Some(2),
+		problem: Problem::Exception("java/lang/ArithmeticException".to_string()),
+	});
+	assert_eq!(error.to_string(), "Eval.divide:(II)I, pc 2: threw java/lang/ArithmeticException");
+	assert_eq!(evaluator.invoke("Eval", "divide", "(II)I", &[Value::Int(i32::MIN), Value::Int(-1)]), Ok(Some(Value::Int(i32::MIN))));
+
+	evaluator.set_budget(1000);
+	let error = evaluator.invoke("Eval", "spin", "()I", &[]).unwrap_err();
+	assert_eq!(error.problem, Problem::BudgetExhausted);
+	// Arrays are paid for by the element, so this one is never built.
+	evaluator.set_budget(DEFAULT_BUDGET);
+	let error = evaluator.invoke("Eval", "table", "(I)[[I", &[Value::Int(100_000)]).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(2), Problem::BudgetExhausted));
+	assert!(evaluator.invoke("Eval", "table", "(I)[[I", &[Value::Int(100)]).is_ok());
+	evaluator.set_budget(1000);
+
+	let error = evaluator.invoke("Eval", "locked", "(Ljava/lang/Object;)I", &[Value::Null]).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(3), Problem::Unsupported(MONITORENTER)));
+
+	let error = evaluator.invoke("Eval", "square", "(I)I", &[Value::Long(1)]).unwrap_err();
+	assert_eq!(error.problem, Problem::WrongArguments);
+	let error = evaluator.invoke("Eval", "cube", "(I)I", &[Value::Int(1)]).unwrap_err();
+	assert_eq!(error.problem, Problem::UnknownMethod("Eval.cube:(I)I".to_string()));
+
+	let error = evaluator.invoke("Eval", "length", "(Ljava/lang/String;)I", &[Value::Null]).unwrap_err();
+	assert_eq!(error.problem, Problem::Exception("java/lang/NullPointerException".to_string()));
+}
+
+#[test]
+fn multianewarray_without_dimensions() {
+	let mut class_file = load(include_bytes!("Eval.class"));
+	let index = method_index(&class_file, "table");
+	let mut code = method_code(&class_file, index).unwrap();
+	let [high, low] = class_file.this_class.index.to_be_bytes();
+	code.code = vec![MULTIANEWARRAY, high, low, 0, ARETURN];
+	let mut info = vec![];
+	<Code as ToBytes<BigEndian>>::to_bytes(&code, &mut info).unwrap();
+	let cp = class_file.constant_pool.clone();
+	let name_index = class_file.methods[index].attributes.named(&cp, "Code").unwrap().name_index();
+	class_file.methods[index].attributes.set(&cp, AttributeInfo::new(name_index, info));
+
+	let mut evaluator = Evaluator::new();
+	evaluator.add(&class_file);
+	let error = evaluator.invoke("Eval", "table", "(I)[[I", &[Value::Int(1)]).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(0), Problem::InvalidOperand));
+}
@@ -0,0 +1,116 @@
+extern crate class_file;
+
+mod common;
+
+use class_file::*;
+use class_file::ops::*;
+use class_file::verify::Location;
+use class_file::verify::format::*;
+use common::*;
+
+fn constants() -> ClassFile<'static> {
+	load(include_bytes!("Constants.class"))
+}
+
+fn problems(class_file: &ClassFile) -> Vec<Problem> {
+	validate(class_file).into_iter()
+		.map(|diagnostic| diagnostic.problem)
+		.collect()
+}
+
+#[test]
+fn compiled_classes_are_valid() {
+	for data in &[
+		&include_bytes!("Constants.class")[..],
+		&include_bytes!("Constants$Shape.class")[..],
+		&include_bytes!("Attributes.class")[..],
+		&include_bytes!("References.class")[..],
+	] {
+		let class_file = load(data);
+		assert_eq!(validate(&class_file), vec![]);
+	}
+}
+
+#[test]
+fn wide_constants_round_trip() {
+	let data = &include_bytes!("Constants.class")[..];
+	let class_file = load(data);
+	let cp = &class_file.constant_pool;
+	let wide = cp.entries.iter()
+		.position(CPEntry::is_wide)
+		.unwrap();
+	assert_eq!(cp.entries[wide + 1], CPEntry::Unusable(UnusableInfo {}));
+
+	let mut output = vec![];
+	class_file.to_bytes(&mut output).unwrap();
+	assert_eq!(output, data);
+}
+
+#[test]
+fn wrong_index_kind() {
+	let mut class_file = constants();
+	let utf8 = class_file.fields[0].name_index.index;
+	class_file.this_class = CPIndex::new(utf8);
+
+	assert_eq!(validate(&class_file), vec![Diagnostic {
+		location: Location::Class,
+		problem: Problem::InvalidIndex { index: utf8, expected: "Class" },
+	}]);
+}
+
+#[test]
+fn invalid_flags() {
+	let mut class_file = constants();
+	class_file.access_flags |= FINAL | ABSTRACT;
+	class_file.fields[0].access_flags |= PRIVATE;
+
+	let problems = problems(&class_file);
+	assert_eq!(problems.len(), 2);
+	assert!(problems.iter().all(|problem| matches!(problem, Problem::InvalidFlags { .. })));
+}
+
+#[test]
+fn duplicate_member() {
+	let mut class_file = constants();
+	let field = class_file.fields[0].clone();
+	class_file.fields.push(field);
+
+	let diagnostics = validate(&class_file);
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(diagnostics[0].to_string(), format!("field #{}: duplicate member BIG:J", class_file.fields.len() - 1));
+}
+
+#[test]
+fn code() {
+	let mut class_file = constants();
+	let index = class_file.methods.iter()
+		.position(|method| method.access_flags & STATIC != 0)
+		.unwrap();
+	class_file.methods[index].access_flags |= NATIVE;
+	assert_eq!(problems(&class_file), vec![Problem::UnexpectedCode]);
+
+	class_file.methods[index].attributes = Attributes::new(vec![]);
+	assert_eq!(problems(&class_file), vec![]);
+	class_file.methods[index].access_flags &= !NATIVE;
+	assert_eq!(problems(&class_file), vec![Problem::MissingCode]);
+}
+
+#[test]
+fn super_class() {
+	let mut class_file = constants();
+	class_file.super_class = CPIndex::new(0);
+	assert_eq!(problems(&class_file), vec![Problem::MissingSuperClass]);
+}
+
+#[test]
+fn version_appropriate_constants() {
+	let mut class_file = constants();
+	class_file.major_version = 50;
+	assert!(problems(&class_file).contains(&Problem::UnsupportedConstant(CONSTANT_INVOKE_DYNAMIC_TAG)));
+
+	let mut class_file = constants();
+	class_file.constant_pool.entries.push(CPEntry::Package(PackageInfo {
+		name_index: class_file.fields[0].name_index,
+	}));
+	assert_eq!(problems(&class_file), vec![Problem::UnsupportedConstant(CONSTANT_PACKAGE_TAG)]);
+}
@@ -0,0 +1,154 @@
+extern crate class_file;
+
+mod common;
+
+use std::io::Cursor;
+
+use class_file::*;
+use class_file::attr::{Code, StackMapFrame, StackMapTable, VerificationTypeInfo};
+use class_file::frames::*;
+use class_file::hierarchy::*;
+use class_file::ops::*;
+use class_file::verify::typecheck::{self, Frame, Slot, Type};
+use common::*;
+
+fn stack_map(class_file: &ClassFile, index: usize) -> Option<Vec<u8>> {
+	method_code(class_file, index).unwrap().attributes.named(&class_file.constant_pool, "StackMapTable")
+		.map(|info| info.info().to_vec())
+}
+
+/// Drops the stack maps javac wrote, so there's nothing left to fall back on.
+fn strip(class_file: &mut ClassFile) {
+	let cp = &class_file.constant_pool;
+	for method in &mut class_file.methods {
+		let info = match method.attributes.named(cp, "Code") {
+			Some(info) => info.clone(),
+			None => continue,
+		};
+		let mut code = Code::from_bytes(&mut Cursor::new(info.info())).unwrap();
+		code.attributes.remove(cp, "StackMapTable");
+		let mut data = vec![];
+		code.to_bytes(&mut data).unwrap();
+		method.attributes.set(cp, AttributeInfo::new(info.name_index(), data));
+	}
+}
+
+fn hierarchy(class_file: &ClassFile) -> SimpleHierarchy {
+	let mut hierarchy = SimpleHierarchy::new();
+	hierarchy.add(class_file);
+	hierarchy
+}
+
+#[test]
+fn recomputed_frames_verify() {
+	for data in &[
+		&include_bytes!("Flow.class")[..],
+		&include_bytes!("Frames.class")[..],
+		&include_bytes!("Constants.class")[..],
+		&include_bytes!("Attributes.class")[..],
+		&include_bytes!("References.class")[..],
+	] {
+		let mut class_file = load(data);
+		strip(&mut class_file);
+		let hierarchy = hierarchy(&class_file);
+		for index in 0..class_file.methods.len() {
+			update_frames(&mut class_file, index, &hierarchy).unwrap();
+		}
+
+		let mut output = vec![];
+		class_file.to_bytes(&mut output).unwrap();
+		let class_file = load(&output);
+		assert_eq!(typecheck::validate(&class_file, &hierarchy), vec![]);
+	}
+}
+
+#[test]
+fn same_as_javac() {
+	let flow = &include_bytes!("Flow.class")[..];
+	let frames = &include_bytes!("Frames.class")[..];
+	for &(data, name) in &[(flow, "describe"), (flow, "dense"), (flow, "create"), (frames, "wide"), (frames, "constant")] {
+		let mut class_file = load(data);
+		let index = method_index(&class_file, name);
+		let expected = stack_map(&class_file, index);
+		strip(&mut class_file);
+		assert_eq!(stack_map(&class_file, index), None);
+		let hierarchy = hierarchy(&class_file);
+		update_frames(&mut class_file, index, &hierarchy).unwrap();
+		assert_eq!(stack_map(&class_file, index), expected, "{}", name);
+	}
+}
+
+#[test]
+fn merges_through_hierarchy() {
+	let class_file = load(include_bytes!("Frames.class"));
+	let merge = method_index(&class_file, "merge");
+	let arrays = method_index(&class_file, "arrays");
+
+	let mut jdk = SimpleHierarchy::new();
+	jdk.insert("java/util/AbstractCollection", Some(OBJECT), false)
+		.insert("java/util/AbstractList", Some("java/util/AbstractCollection"), false)
+		.insert("java/util/AbstractSequentialList", Some("java/util/AbstractList"), false)
+		.insert("java/util/LinkedList", Some("java/util/AbstractSequentialList"), false)
+		.insert("java/util/ArrayList", Some("java/util/AbstractList"), false)
+		.insert("java/lang/Number", Some(OBJECT), false)
+		.insert("java/lang/Integer", Some("java/lang/Number"), false)
+		.insert("java/lang/String", Some(OBJECT), false);
+	let frames = compute_frames(&class_file, merge, &method_code(&class_file, merge).unwrap(), &jdk).unwrap();
+	assert_eq!(frames.last().unwrap().1.stack, vec![Type::object("java/util/AbstractList")]);
+	let frames = compute_frames(&class_file, arrays, &method_code(&class_file, arrays).unwrap(), &jdk).unwrap();
+	assert_eq!(frames.last().unwrap().1.stack, vec![Type::object("[Ljava/lang/Object;")]);
+
+	// Knowing nothing, the best it can do is Object.
+	let frames = compute_frames(&class_file, merge, &method_code(&class_file, merge).unwrap(), &SimpleHierarchy::new()).unwrap();
+	assert_eq!(frames.last().unwrap().1.stack, vec![Type::object(OBJECT)]);
+}
+
+#[test]
+fn compression() {
+	let mut cp = ConstantPool { entries: vec![] };
+	let initial = Frame::initial("Test", STATIC, "test", "(I)V").unwrap();
+	let frame = |locals: Vec<Type>, stack: Vec<Type>| Frame { locals, stack };
+	let frames = vec![
+		(5, frame(vec![Type::Integer, Type::Long, Type::Top, Type::object("Test")], vec![])),
+		(6, frame(vec![Type::Integer, Type::Long, Type::Top, Type::object("Test")], vec![])),
+		(10, frame(vec![Type::Integer], vec![Type::Float])),
+		(20, frame(vec![Type::Integer, Type::Top, Type::Float], vec![])),
+		(30, frame(vec![Type::Integer], vec![])),
+		(200, frame(vec![Type::Integer, Type::Top, Type::Top], vec![])),
+		(201, frame(vec![], vec![Type::Null, Type::Null])),
+	];
+	let table = compress(&initial, &frames, &mut cp).unwrap();
+	assert_eq!(table, StackMapTable {
+		table: vec![
+			StackMapFrame::AppendFrame { offset_delta: 5, locals: vec![VerificationTypeInfo::Long, VerificationTypeInfo::ObjectVariable(CPIndex::new(2))] },
+			StackMapFrame::SameFrame(0),
+			StackMapFrame::FullFrame { offset_delta: 3, locals: vec![VerificationTypeInfo::Integer], stack: vec![VerificationTypeInfo::Float] },
+			StackMapFrame::AppendFrame { offset_delta: 9, locals: vec![VerificationTypeInfo::Top, VerificationTypeInfo::Float] },
+			StackMapFrame::ChopFrame { offset_delta: 9, chopped: 2 },
+			StackMapFrame::SameFrameExtended(169),
+			StackMapFrame::FullFrame { offset_delta: 0, locals: vec![], stack: vec![VerificationTypeInfo::Null, VerificationTypeInfo::Null] },
+		],
+	});
+	assert_eq!(cp.entries.len(), 2);
+	assert_eq!(cp.class_name(CPIndex::new(2)).unwrap().as_bytes(), b"Test");
+}
+
+#[test]
+fn errors() {
+	let class_file = load(include_bytes!("Frames.class"));
+	let index = method_index(&class_file, "constant");
+	let mut code = method_code(&class_file, index).unwrap();
+
+	code.code = vec![ICONST_0, IRETURN, ICONST_1, IRETURN];
+	let error = compute_frames(&class_file, index, &code, &SimpleHierarchy::new()).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(2), Problem::Unreachable));
+
+	code.code = vec![ICONST_0, IFEQ, 0, 6, FCONST_0, FRETURN, ICONST_1, IRETURN];
+	let error = compute_frames(&class_file, index, &code, &SimpleHierarchy::new()).unwrap_err();
+	assert_eq!(error.problem, Problem::Check(typecheck::Problem::WrongReturn(FRETURN)));
+
+	// Two paths meeting with an int and a float on the stack.
+	code.code = vec![ICONST_0, IFEQ, 0, 7, ICONST_1, GOTO, 0, 4, FCONST_0, POP, ICONST_0, IRETURN];
+	let error = compute_frames(&class_file, index, &code, &SimpleHierarchy::new()).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(8), Problem::Unmergeable { slot: Slot::Stack(0), first: Type::Integer, second: Type::Float }));
+}
@@ -0,0 +1,223 @@
+extern crate class_file;
+
+use class_file::*;
+use class_file::classpath::ClassPath;
+use class_file::jar::*;
+use class_file::ops::FINAL;
+use class_file::zip::{self, ZipArchive, ZipError};
+
+/// Archive.jar is a multi-release jar with a `Release` class whose `release()` returns "base",
+/// with versions for 11 and 17 returning "11" and "17", along with a resource and a `Broken.class` that isn't a class.
+fn jar() -> Jar {
+	Jar::new(include_bytes!("Archive.jar").to_vec()).unwrap()
+}
+
+/// What `Release.release()` returns, going by the strings in the constant pool.
+fn release(class_file: &ClassFile) -> String {
+	class_file.constant_pool.entries.iter()
+		.filter_map(|entry| match entry {
+			CPEntry::UTF8(info) => Some(utf8::decode(info.as_bytes()).into_owned()),
+			_ => None,
+		})
+		.find(|value| value == "base" || value == "11" || value == "17")
+		.unwrap()
+}
+
+#[test]
+fn entries() {
+	let jar = jar();
+	let names: Vec<_> = jar.names().collect();
+	assert_eq!(names, [
+		"META-INF/",
+		"META-INF/MANIFEST.MF",
+		"Broken.class",
+		"Release.class",
+		"config/",
+		"config/settings.properties",
+		"META-INF/versions/11/",
+		"META-INF/versions/11/Release.class",
+		"META-INF/versions/17/",
+		"META-INF/versions/17/Release.class",
+	]);
+
+	let resources: Vec<_> = jar.resources().map(|entry| entry.name.as_str()).collect();
+	assert_eq!(resources, ["META-INF/MANIFEST.MF", "config/settings.properties"]);
+	assert_eq!(jar.read("config/settings.properties").unwrap().unwrap(), b"colour=blue\n");
+	assert!(jar.read("missing").is_none());
+
+	let archive = jar.archive();
+	let entry = archive.entry("config/settings.properties").unwrap();
+	assert_eq!(entry.method, zip::DEFLATED);
+	assert_eq!(entry.crc32, zip::crc32(b"colour=blue\n"));
+	assert!(archive.raw(entry).unwrap().len() as u64 == entry.compressed_size);
+}
+
+#[test]
+fn manifest() {
+	let manifest = jar().manifest().unwrap().unwrap();
+	assert_eq!(manifest.get("Main-Class"), Some("Release"));
+	assert_eq!(manifest.get("multi-release"), Some("true"));
+	assert_eq!(manifest.main[0], ("Manifest-Version".to_string(), "1.0".to_string()));
+
+	let manifest = Manifest::parse(b"Manifest-Version: 1.0\r\nClass-Path: a.jar\r\n  b.jar\r\n\r\nName: a/B.class\r\nSHA-256-Digest: abc\r\n\r\n");
+	assert_eq!(manifest.get("Class-Path"), Some("a.jar b.jar"));
+	assert_eq!(manifest.section("a/B.class").unwrap(), [("SHA-256-Digest".to_string(), "abc".to_string())]);
+}
+
+#[test]
+fn multi_release() {
+	let jar = jar();
+	assert!(jar.is_multi_release());
+
+	let paths: Vec<_> = jar.class_entries(Some(17)).into_iter()
+		.map(|(path, entry)| (path, entry.name.as_str()))
+		.collect();
+	assert_eq!(paths, [("Broken.class", "Broken.class"), ("Release.class", "META-INF/versions/17/Release.class")]);
+
+	for (target, expected) in [(None, "base"), (Some(8), "base"), (Some(11), "11"), (Some(16), "11"), (Some(21), "17")] {
+		let class_file = jar.class("Release", target).unwrap().unwrap();
+		assert_eq!(release(&class_file), expected, "release {:?}", target);
+	}
+	assert!(jar.class("Missing", Some(17)).is_none());
+}
+
+#[test]
+fn errors() {
+	let jar = jar();
+	let mut ok = Vec::new();
+	let mut failed = Vec::new();
+	for (entry, class_file) in jar.classes(Some(11)) {
+		match class_file {
+			Ok(class_file) => ok.push(release(&class_file)),
+			Err(JarError::Class(_)) => failed.push(entry.name.clone()),
+			Err(error) => panic!("{}", error),
+		}
+	}
+	assert_eq!(ok, ["11"]);
+	assert_eq!(failed, ["Broken.class"]);
+
+	let mut class_path = ClassPath::new();
+	let errors = class_path.add_jar(&jar, None);
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].0, "Broken.class");
+	assert!(class_path.contains("Release"));
+
+	assert_eq!(Jar::new(b"not a jar".to_vec()).unwrap_err(), ZipError::NotAnArchive);
+	assert_eq!(Jar::new(vec![]).unwrap_err(), ZipError::NotAnArchive);
+	assert_eq!(ZipArchive::new(vec![0; 21]).unwrap_err(), ZipError::NotAnArchive);
+
+	// Flipping a byte of the compressed data makes the checksum fail, or the data fail to inflate.
+	let mut data = include_bytes!("Archive.jar").to_vec();
+	let offset = {
+		let archive = ZipArchive::new(data.clone()).unwrap();
+		let entry = archive.entry("config/settings.properties").unwrap();
+		let raw = archive.raw(entry).unwrap();
+		data.windows(raw.len()).position(|window| window == raw).unwrap()
+	};
+	data[offset] ^= 0xFF;
+	let jar = Jar::new(data).unwrap();
+	assert!(matches!(jar.read("config/settings.properties").unwrap(), Err(ZipError::Corrupt(_)) | Err(ZipError::Inflate(_))));
+	assert!(jar.class("Release", Some(11)).unwrap().is_ok());
+}
+
+fn raw(jar: &Jar, name: &str) -> Vec<u8> {
+	let archive = jar.archive();
+	archive.raw(archive.entry(name).unwrap()).unwrap().to_vec()
+}
+
+#[test]
+fn copying() {
+	let jar = jar();
+	let copy = Jar::new(JarWriter::from_jar(&jar).to_vec().unwrap()).unwrap();
+	assert_eq!(copy.names().collect::<Vec<_>>(), jar.names().collect::<Vec<_>>());
+	for name in jar.names() {
+		assert_eq!(raw(&copy, name), raw(&jar, name), "{}", name);
+		assert_eq!(copy.archive().entry(name).unwrap().date, jar.archive().entry(name).unwrap().date);
+	}
+}
+
+#[test]
+fn modifying() {
+	let jar = jar();
+	let mut class_file = jar.class("Release", None).unwrap().unwrap();
+	class_file.access_flags |= FINAL;
+
+	let mut writer = JarWriter::from_jar(&jar);
+	writer.put_class(&class_file).unwrap()
+		.put("config/extra.txt", b"new".to_vec())
+		.remove("Broken.class");
+	let copy = Jar::new(writer.to_vec().unwrap()).unwrap();
+
+	let names: Vec<_> = copy.names().collect();
+	assert!(!names.contains(&"Broken.class"));
+	assert_eq!(names.last(), Some(&"config/extra.txt"));
+	assert_eq!(copy.read("config/extra.txt").unwrap().unwrap(), b"new");
+	assert_ne!(copy.class("Release", None).unwrap().unwrap().access_flags & FINAL, 0);
+	assert_eq!(copy.class("Release", Some(11)).unwrap().unwrap().access_flags & FINAL, 0);
+	assert_eq!(raw(&copy, "META-INF/versions/11/Release.class"), raw(&jar, "META-INF/versions/11/Release.class"));
+	assert!(copy.is_multi_release());
+}
+
+#[test]
+fn deterministic() {
+	let jar = jar();
+	let mut writer = JarWriter::from_jar(&jar);
+	writer.put("a.txt", b"first".to_vec())
+		.deterministic(true);
+	let data = writer.to_vec().unwrap();
+	assert_eq!(writer.to_vec().unwrap(), data);
+
+	let copy = Jar::new(data).unwrap();
+	let names: Vec<_> = copy.names().collect();
+	assert_eq!(&names[..4], ["META-INF/", "META-INF/MANIFEST.MF", "Broken.class", "META-INF/versions/11/"]);
+	assert_eq!(names.last(), Some(&"config/settings.properties"));
+	assert!(copy.archive().entries().iter().all(|entry| (entry.time, entry.date) == zip::EPOCH && entry.extra.is_empty()));
+	assert_eq!(raw(&copy, "Release.class"), raw(&jar, "Release.class"));
+
+	assert_eq!(zip::dos_time(0), zip::EPOCH);
+	assert_eq!(zip::dos_time(1_709_214_330), (0x6daf, 0x585d));
+}
+
+#[test]
+fn signatures() {
+	let manifest = "Manifest-Version: 1.0\r\n\r\nName: a.txt\r\nSHA-256-Digest: stale\r\n\r\n";
+	let mut writer = JarWriter::new();
+	writer.put(MANIFEST, manifest.as_bytes().to_vec())
+		.put("META-INF/SIGNER.SF", b"signature".to_vec())
+		.put("META-INF/SIGNER.RSA", b"block".to_vec())
+		.put("a.txt", b"abc".to_vec());
+	let signed = Jar::new(writer.to_vec().unwrap()).unwrap();
+	assert!(is_signature_file("META-INF/SIGNER.SF"));
+	assert!(!is_signature_file("META-INF/services/SIGNER.SF"));
+
+	let mut writer = JarWriter::from_jar(&signed);
+	writer.signatures(Signatures::Keep);
+	let kept = Jar::new(writer.to_vec().unwrap()).unwrap();
+	assert_eq!(kept.read(MANIFEST).unwrap().unwrap(), manifest.as_bytes());
+	assert!(kept.read("META-INF/SIGNER.SF").is_some());
+
+	writer.signatures(Signatures::Strip);
+	let stripped = Jar::new(writer.to_vec().unwrap()).unwrap();
+	assert_eq!(stripped.names().collect::<Vec<_>>(), [MANIFEST, "a.txt"]);
+	assert_eq!(stripped.manifest().unwrap().unwrap().entries, []);
+
+	writer.signatures(Signatures::Regenerate);
+	let regenerated = Jar::new(writer.to_vec().unwrap()).unwrap();
+	let manifest = regenerated.manifest().unwrap().unwrap();
+	assert_eq!(manifest.section("a.txt").unwrap(), [("SHA-256-Digest".to_string(), "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=".to_string())]);
+	assert!(regenerated.read("META-INF/SIGNER.RSA").is_none());
+}
+
+#[test]
+fn manifest_lines() {
+	let value = "x".repeat(150);
+	let manifest = Manifest {
+		main: vec![("Manifest-Version".to_string(), "1.0".to_string()), ("Class-Path".to_string(), value.clone())],
+		entries: vec![("a/B.class".to_string(), vec![("Sealed".to_string(), "true".to_string())])],
+	};
+	let text = manifest.to_string();
+	assert!(text.split("\r\n").all(|line| line.len() <= 72));
+	assert!(text.starts_with("Manifest-Version: 1.0\r\nClass-Path: xxx"));
+	assert!(text.ends_with("\r\n\r\nName: a/B.class\r\nSealed: true\r\n\r\n"));
+	assert_eq!(Manifest::parse(text.as_bytes()), manifest);
+}
@@ -0,0 +1,417 @@
+extern crate class_file;
+extern crate miniz_oxide;
+
+use std::collections::HashMap;
+
+use class_file::*;
+use class_file::classpath::ClassPath;
+use class_file::jimage::*;
+
+const HASH_MULTIPLIER: i32 = 0x0100_0193;
+
+fn hash(name: &str, seed: i32) -> i32 {
+	let hash = name.bytes()
+		.fold(seed, |hash, byte| hash.wrapping_mul(HASH_MULTIPLIER) ^ byte as i32);
+	hash & 0x7FFF_FFFF
+}
+
+/// The variable length ints string sharing uses.
+fn compressed_int(value: u32) -> Vec<u8> {
+	if value < 1 << 5 {
+		vec![0x80 | 1 << 5 | value as u8]
+	} else if value < 1 << 13 {
+		vec![0x80 | 2 << 5 | (value >> 8) as u8, value as u8]
+	} else if value < 1 << 21 {
+		vec![0x80 | 3 << 5 | (value >> 16) as u8, (value >> 8) as u8, value as u8]
+	} else {
+		value.to_be_bytes().to_vec()
+	}
+}
+
+struct Resource {
+	name: String,
+	content: Vec<u8>,
+	uncompressed_size: usize,
+	compressed: bool,
+}
+
+/// Just enough of jlink to make small images: no sorting, no tree of directories, and no deduplication of content.
+struct ImageBuilder {
+	big_endian: bool,
+	strings: Vec<u8>,
+	offsets: HashMap<Vec<u8>, u32>,
+	resources: Vec<Resource>,
+}
+
+impl ImageBuilder {
+	fn new(big_endian: bool) -> Self {
+		let mut builder = ImageBuilder {
+			big_endian,
+			strings: Vec::new(),
+			offsets: HashMap::new(),
+			resources: Vec::new(),
+		};
+		builder.string(b"");
+		builder
+	}
+
+	fn u32(&self, value: u32) -> [u8; 4] {
+		if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+	}
+
+	fn u64(&self, value: u64) -> [u8; 8] {
+		if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+	}
+
+	fn string(&mut self, value: &[u8]) -> u32 {
+		if let Some(&offset) = self.offsets.get(value) {
+			return offset;
+		}
+		let offset = self.strings.len() as u32;
+		self.strings.extend_from_slice(value);
+		self.strings.push(0);
+		self.offsets.insert(value.to_vec(), offset);
+		offset
+	}
+
+	fn add(&mut self, name: &str, content: Vec<u8>) -> &mut Self {
+		let uncompressed_size = content.len();
+		self.resources.push(Resource { name: name.to_string(), content, uncompressed_size, compressed: false });
+		self
+	}
+
+	/// Adds content already wrapped in compressed resource headers.
+	fn add_compressed(&mut self, name: &str, content: Vec<u8>, uncompressed_size: usize) -> &mut Self {
+		self.resources.push(Resource { name: name.to_string(), content, uncompressed_size, compressed: true });
+		self
+	}
+
+	fn compressed_header(&mut self, decompressor: &str, content: &[u8], uncompressed_size: usize) -> Vec<u8> {
+		let decompressor = self.string(decompressor.as_bytes());
+		let mut header = Vec::new();
+		header.extend_from_slice(&self.u32(COMPRESSED_MAGIC));
+		header.extend_from_slice(&self.u64(content.len() as u64));
+		header.extend_from_slice(&self.u64(uncompressed_size as u64));
+		header.extend_from_slice(&self.u32(decompressor));
+		header.extend_from_slice(&self.u32(0));
+		header.push(1);
+		header.extend_from_slice(content);
+		header
+	}
+
+	fn zip(&mut self, data: &[u8]) -> Vec<u8> {
+		let compressed = miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+		self.compressed_header("zip", &compressed, data.len())
+	}
+
+	/// Moves the class's `Utf8` constants into the string table, the way `--compress=1` does.
+	fn share_strings(&mut self, class: &[u8]) -> Vec<u8> {
+		let u16_at = |position: usize| u16::from_be_bytes([class[position], class[position + 1]]) as usize;
+		let mut output = class[..10].to_vec();
+		let count = u16_at(8);
+		let mut position = 10;
+		let mut i = 1;
+		while i < count {
+			let tag = class[position];
+			position += 1;
+			let size = match tag {
+				1 => {
+					let length = u16_at(position);
+					let value = class[position + 2..position + 2 + length].to_vec();
+					position += 2 + length;
+					if value.starts_with(b"(") && value.contains(&b';') {
+						self.share_descriptor(&value, &mut output);
+					} else {
+						output.push(23);
+						output.extend(compressed_int(self.string(&value)));
+					}
+					i += 1;
+					continue;
+				}
+				7 | 8 | 16 | 19 | 20 => 2,
+				15 => 3,
+				5 | 6 => {
+					i += 1;
+					8
+				}
+				_ => 4,
+			};
+			output.push(tag);
+			output.extend_from_slice(&class[position..position + size]);
+			position += size;
+			i += 1;
+		}
+		output.extend_from_slice(&class[position..]);
+		self.compressed_header("compact-cp", &output, class.len())
+	}
+
+	fn share_descriptor(&mut self, descriptor: &[u8], output: &mut Vec<u8>) {
+		let mut stripped = Vec::new();
+		let mut indices = Vec::new();
+		let mut position = 0;
+		while position < descriptor.len() {
+			let byte = descriptor[position];
+			stripped.push(byte);
+			position += 1;
+			if byte == b'L' {
+				let end = position + descriptor[position..].iter().position(|&byte| byte == b';').unwrap();
+				let class = &descriptor[position..end];
+				let (package, name) = match class.iter().rposition(|&byte| byte == b'/') {
+					Some(slash) => (&class[..slash], &class[slash + 1..]),
+					None => (&class[..0], class),
+				};
+				indices.extend(compressed_int(self.string(package)));
+				indices.extend(compressed_int(self.string(name)));
+				position = end;
+			}
+		}
+		output.push(25);
+		output.extend(compressed_int(self.string(&stripped)));
+		output.extend(compressed_int(indices.len() as u32));
+		output.extend(indices);
+	}
+
+	/// Splits the name the way jlink does, with `/modules/` and `/packages/` names taken as a whole.
+	fn split(name: &str) -> (&str, &str, &str, &str) {
+		for &module in &["modules", "packages"] {
+			let prefix = format!("/{}/", module);
+			if name.starts_with(&prefix) {
+				return (module, "", &name[prefix.len()..], "");
+			}
+		}
+		let rest = &name[1..];
+		let slash = rest.find('/').unwrap();
+		let (module, rest) = (&rest[..slash], &rest[slash + 1..]);
+		let (parent, rest) = match rest.rfind('/') {
+			Some(slash) => (&rest[..slash], &rest[slash + 1..]),
+			None => ("", rest),
+		};
+		let (base, extension) = match rest.rfind('.') {
+			Some(dot) => (&rest[..dot], &rest[dot + 1..]),
+			None => (rest, ""),
+		};
+		(module, parent, base, extension)
+	}
+
+	fn build(&mut self) -> Vec<u8> {
+		let resources = std::mem::take(&mut self.resources);
+		let mut locations = Vec::new();
+		let mut location_offsets = Vec::new();
+		let mut content = Vec::new();
+		for resource in &resources {
+			let (module, parent, base, extension) = ImageBuilder::split(&resource.name);
+			let compressed_size = if resource.compressed { resource.content.len() } else { 0 };
+			let attributes = [
+				self.string(module.as_bytes()) as u64,
+				self.string(parent.as_bytes()) as u64,
+				self.string(base.as_bytes()) as u64,
+				self.string(extension.as_bytes()) as u64,
+				content.len() as u64,
+				compressed_size as u64,
+				resource.uncompressed_size as u64,
+			];
+			location_offsets.push(locations.len() as u32);
+			for (kind, &value) in attributes.iter().enumerate() {
+				if value != 0 {
+					let bytes = value.to_be_bytes();
+					let skip = bytes.iter().take(7).take_while(|&&byte| byte == 0).count();
+					locations.push(((kind as u8 + 1) << 3) | (7 - skip) as u8);
+					locations.extend_from_slice(&bytes[skip..]);
+				}
+			}
+			locations.push(0);
+			content.extend_from_slice(&resource.content);
+		}
+
+		// A perfect hash, placing the buckets with collisions first, each with a seed that spreads them into free slots.
+		let length = resources.len();
+		let mut buckets = vec![Vec::new(); length];
+		for (i, resource) in resources.iter().enumerate() {
+			buckets[hash(&resource.name, HASH_MULTIPLIER) as usize % length].push(i);
+		}
+		let mut order: Vec<_> = (0..length).collect();
+		order.sort_by_key(|&bucket| std::cmp::Reverse(buckets[bucket].len()));
+		let mut redirect = vec![0i32; length];
+		let mut slots: Vec<Option<usize>> = vec![None; length];
+		for bucket in order {
+			match buckets[bucket].len() {
+				0 => {}
+				1 => {
+					let slot = slots.iter().position(Option::is_none).unwrap();
+					slots[slot] = Some(buckets[bucket][0]);
+					redirect[bucket] = -(slot as i32) - 1;
+				}
+				_ => {
+					let mut seed = 1;
+					loop {
+						let mut chosen: Vec<usize> = buckets[bucket].iter()
+							.map(|&i| hash(&resources[i].name, seed) as usize % length)
+							.collect();
+						let free = chosen.iter().all(|&slot| slots[slot].is_none());
+						let count = chosen.len();
+						chosen.sort();
+						chosen.dedup();
+						if free && chosen.len() == count {
+							break;
+						}
+						seed += 1;
+					}
+					for &i in &buckets[bucket] {
+						slots[hash(&resources[i].name, seed) as usize % length] = Some(i);
+					}
+					redirect[bucket] = seed;
+				}
+			}
+		}
+
+		let mut image = Vec::new();
+		for &value in &[MAGIC, 1 << 16, 0, length as u32, length as u32, locations.len() as u32, self.strings.len() as u32] {
+			image.extend_from_slice(&self.u32(value));
+		}
+		for &value in &redirect {
+			image.extend_from_slice(&self.u32(value as u32));
+		}
+		for slot in &slots {
+			image.extend_from_slice(&self.u32(location_offsets[slot.unwrap()]));
+		}
+		image.extend_from_slice(&locations);
+		image.extend_from_slice(&self.strings);
+		image.extend_from_slice(&content);
+		image
+	}
+}
+
+const SHAPE: &[u8] = include_bytes!("Hierarchy$Shape.class");
+const BASE: &[u8] = include_bytes!("Hierarchy$Base.class");
+const SQUARE: &[u8] = include_bytes!("Hierarchy$Square.class");
+const RECTANGLE: &[u8] = include_bytes!("Hierarchy$Rectangle.class");
+const POLYGON: &[u8] = include_bytes!("Hierarchy$Polygon.class");
+const NAMED: &[u8] = include_bytes!("Hierarchy$Named.class");
+const SETTINGS: &[u8] = b"answer=42\n";
+
+/// An image with a module `app` holding the `Hierarchy` classes, stored in every way jlink can store them.
+fn image(big_endian: bool) -> JImage {
+	let mut builder = ImageBuilder::new(big_endian);
+	let zipped = builder.zip(BASE);
+	let shared = builder.share_strings(SQUARE);
+	let inner = builder.share_strings(RECTANGLE);
+	let chained = builder.zip(&inner);
+	let app = builder.string(b"app");
+	let mut packages = builder.u32(0).to_vec();
+	packages.extend_from_slice(&builder.u32(app));
+
+	builder.add("/app/Hierarchy$Shape.class", SHAPE.to_vec())
+		.add_compressed("/app/Hierarchy$Base.class", zipped, BASE.len())
+		.add_compressed("/app/Hierarchy$Square.class", shared, SQUARE.len())
+		.add_compressed("/app/Hierarchy$Rectangle.class", chained, RECTANGLE.len())
+		.add("/app/Hierarchy$Polygon.class", POLYGON.to_vec())
+		.add("/app/Hierarchy$Named.class", NAMED.to_vec())
+		.add("/app/config/settings.properties", SETTINGS.to_vec())
+		.add("/app/module-info.class", b"not read".to_vec())
+		.add("/other/config/Broken.class", b"not a class".to_vec())
+		.add("/packages/config", packages);
+	JImage::new(builder.build()).unwrap()
+}
+
+#[test]
+fn lookup() {
+	for &big_endian in &[false, true] {
+		let image = image(big_endian);
+		assert_eq!(image.is_big_endian(), big_endian);
+		assert_eq!(image.header().major_version, 1);
+		assert_eq!(image.header().resource_count, 10);
+		assert_eq!(image.locations().count(), 10);
+
+		let location = image.find("/app/config/settings.properties").unwrap();
+		assert_eq!(location.module, "app");
+		assert_eq!(location.parent, "config");
+		assert_eq!(location.base, "settings");
+		assert_eq!(location.extension, "properties");
+		assert_eq!(location.name(), "/app/config/settings.properties");
+		assert_eq!(image.read(&location).unwrap(), SETTINGS);
+
+		assert!(image.find("/app/config/missing.properties").is_none());
+		assert!(image.find("/other/config/settings.properties").is_none());
+		assert!(image.find("").is_none());
+
+		assert_eq!(image.modules(), ["app", "other"]);
+		assert_eq!(image.package_module("config").as_deref(), Some("app"));
+		assert_eq!(image.package_module("missing"), None);
+	}
+}
+
+#[test]
+fn decompression() {
+	for &big_endian in &[false, true] {
+		let image = image(big_endian);
+		let base = image.find("/app/Hierarchy$Base.class").unwrap();
+		assert_eq!(base.uncompressed_size, BASE.len() as u64);
+		assert_ne!(base.compressed_size, 0);
+		for (name, data) in &[("Shape", SHAPE), ("Base", BASE), ("Square", SQUARE), ("Rectangle", RECTANGLE)] {
+			let name = format!("/app/Hierarchy${}.class", name);
+			assert_eq!(image.read_named(&name).unwrap().unwrap(), *data, "{}", name);
+		}
+
+		let square = image.class("app", "Hierarchy$Square").unwrap().unwrap();
+		let name = square.constant_pool.class_name(square.this_class).unwrap();
+		assert_eq!(utf8::decode(name.as_bytes()), "Hierarchy$Square");
+		assert!(image.class("app", "Hierarchy$Missing").is_none());
+	}
+}
+
+#[test]
+fn class_path() {
+	let image = image(false);
+	let mut class_path = ClassPath::new();
+	let errors = class_path.add_jimage(&image, None);
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].0, "/other/config/Broken.class");
+	assert!(matches!(errors[0].1, JImageError::Class(_)));
+
+	assert_eq!(class_path.len(), 6);
+	assert!(class_path.missing().is_empty());
+	assert_eq!(class_path.super_classes("Hierarchy$Square").unwrap(), ["Hierarchy$Base", "java/lang/Object"]);
+
+	let mut class_path = ClassPath::new();
+	assert!(class_path.add_jimage(&image, Some("app")).is_empty());
+	assert_eq!(class_path.len(), 6);
+}
+
+#[test]
+fn errors() {
+	assert!(matches!(JImage::new(vec![0; 10]), Err(JImageError::Truncated)));
+	assert!(matches!(JImage::new(vec![0; 64]), Err(JImageError::BadMagic(0))));
+
+	let mut data = ImageBuilder::new(false).add("/app/a.txt", vec![1]).build();
+	data[4..8].copy_from_slice(&(2u32 << 16).to_le_bytes());
+	assert!(matches!(JImage::new(data), Err(JImageError::UnsupportedVersion { major: 2, minor: 0 })));
+
+	let mut builder = ImageBuilder::new(false);
+	let unknown = builder.compressed_header("lz4", b"data", 4);
+	let truncated = builder.zip(b"some data")[..32].to_vec();
+	builder.add_compressed("/app/unknown.txt", unknown, 4)
+		.add_compressed("/app/truncated.txt", truncated, 9)
+		.add("/app/plain.txt", b"plain".to_vec());
+	let image = JImage::new(builder.build()).unwrap();
+	match image.read_named("/app/unknown.txt") {
+		Some(Err(JImageError::UnknownDecompressor(name))) => assert_eq!(name, "lz4"),
+		other => panic!("{:?}", other),
+	}
+	match image.read_named("/app/truncated.txt") {
+		Some(Err(JImageError::Decompression(name))) => assert_eq!(name, "/app/truncated.txt"),
+		other => panic!("{:?}", other),
+	}
+
+	// Inflating stops at the size the header gives, rather than going on to a megabyte.
+	let mut builder = ImageBuilder::new(false);
+	let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&vec![0; 1 << 20], 6);
+	let bomb = builder.compressed_header("zip", &compressed, 16);
+	builder.add_compressed("/app/bomb.txt", bomb, 16);
+	let image = JImage::new(builder.build()).unwrap();
+	assert!(matches!(image.read_named("/app/bomb.txt"), Some(Err(JImageError::Decompression(_)))));
+
+	// A redirect of `i32::MIN` doesn't overflow, and just leads nowhere.
+	let mut data = ImageBuilder::new(false).add("/app/a.txt", vec![1]).build();
+	data[28..32].copy_from_slice(&i32::MIN.to_le_bytes());
+	assert!(JImage::new(data).unwrap().find("/app/a.txt").is_none());
+}
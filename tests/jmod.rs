@@ -0,0 +1,71 @@
+extern crate class_file;
+
+use class_file::*;
+use class_file::classpath::ClassPath;
+use class_file::jmod::*;
+
+/// Modular.jmod is the module in modular/, at version 1.2, with `com.example.modular.Main` as its main class
+/// and a `modular.properties` in its `conf/` section.
+fn jmod() -> Jmod {
+	Jmod::new(include_bytes!("Modular.jmod").to_vec()).unwrap()
+}
+
+#[test]
+fn sections() {
+	let jmod = jmod();
+	let classes: Vec<_> = jmod.entries(Section::Classes).map(|(name, _)| name).collect();
+	assert_eq!(classes, [
+		"module-info.class",
+		"com/example/modular/Main.class",
+		"com/example/modular/internal/Helper.class",
+	]);
+	let config: Vec<_> = jmod.entries(Section::Config).map(|(name, _)| name).collect();
+	assert_eq!(config, ["modular.properties"]);
+	assert_eq!(jmod.entries(Section::NativeLibraries).count(), 0);
+
+	assert_eq!(jmod.read(Section::Config, "modular.properties").unwrap().unwrap(), b"greeting=hello\n");
+	assert!(jmod.read(Section::Config, "missing.properties").is_none());
+	assert_eq!(Section::of("bin/java"), Some((Section::NativeCommands, "java")));
+	assert_eq!(Section::of("other/file"), None);
+}
+
+#[test]
+fn classes() {
+	let jmod = jmod();
+	let names: Vec<_> = jmod.classes()
+		.map(|(_, class_file)| {
+			let class_file = class_file.unwrap();
+			let name = class_file.constant_pool.class_name(class_file.this_class).unwrap();
+			utf8::decode(name.as_bytes()).into_owned()
+		})
+		.collect();
+	assert_eq!(names, ["com/example/modular/Main", "com/example/modular/internal/Helper"]);
+	assert!(jmod.class("com/example/modular/Main").unwrap().is_ok());
+	assert!(jmod.class("com/example/modular/Missing").is_none());
+
+	let mut class_path = ClassPath::new();
+	assert!(class_path.add_jmod(&jmod).is_empty());
+	assert_eq!(class_path.len(), 2);
+	assert_eq!(class_path.missing().into_iter().collect::<Vec<_>>(), ["java/sql/Driver"]);
+}
+
+#[test]
+fn module_info() {
+	let module_info = jmod().module_info().unwrap();
+	assert_eq!(module_info.name().as_deref(), Some("com.example.modular"));
+	assert_eq!(module_info.package_names(), ["com/example/modular", "com/example/modular/internal"]);
+	assert_eq!(module_info.main_class_name().as_deref(), Some("com/example/modular/Main"));
+
+	let module = &module_info.module;
+	assert_eq!(module.requires.len(), 4);
+	assert_eq!(module.exports.len(), 1);
+	assert_eq!(module.opens.len(), 1);
+	assert_eq!(module.uses.len(), 1);
+	assert_eq!(module.provides.len(), 1);
+}
+
+#[test]
+fn errors() {
+	assert!(matches!(Jmod::new(b"PK\x03\x04".to_vec()), Err(JmodError::NotAJmod)));
+	assert!(matches!(Jmod::new(MAGIC.to_vec()), Err(JmodError::Zip(_))));
+}
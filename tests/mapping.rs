@@ -0,0 +1,163 @@
+extern crate class_file;
+
+mod common;
+
+use class_file::*;
+use class_file::classpath::ClassPath;
+use class_file::diff::diff;
+use class_file::mapping::*;
+use class_file::remap::*;
+use class_file::verify::format::validate;
+use common::*;
+
+fn proguard() -> Mappings {
+	Mappings::parse_proguard(include_str!("mapping/mapping.txt")).unwrap()
+}
+
+fn class_path() -> ClassPath {
+	package("app")
+}
+
+fn package(name: &str) -> ClassPath {
+	let mut class_path = ClassPath::new();
+	class_path.add_directory(format!("{}/tests/mapping/{}", env!("CARGO_MANIFEST_DIR"), name))
+		.unwrap();
+	class_path
+}
+
+/// Remaps every class in the class path, reading them back in, so they can go in a class path of their own.
+fn apply(class_path: &ClassPath, mappings: &Mappings) -> ClassPath {
+	let mapper = HierarchyMapper::new(mappings, class_path);
+	let mut result = ClassPath::new();
+	for name in class_path.names() {
+		let remapped = remap(class_path.get(name).unwrap(), &mapper).unwrap();
+		assert_eq!(validate(&remapped), vec![], "{}", name);
+		let mut data = vec![];
+		remapped.to_bytes(&mut data).unwrap();
+		result.add(load(&data));
+	}
+	result
+}
+
+fn methods(class_file: &ClassFile) -> Vec<String> {
+	let cp = &class_file.constant_pool;
+	class_file.methods.iter()
+		.map(|method| format!("{}{}", cp.utf8(method.name_index).unwrap().to_utf8(), cp.utf8(method.descriptor_index).unwrap().to_utf8()))
+		.collect()
+}
+
+#[test]
+fn parse_proguard() {
+	let mappings = proguard();
+	assert_eq!(mappings.map_class("app/Shape"), Some("app/a".to_string()));
+	assert_eq!(mappings.map_class("app/Main"), Some("app/Main".to_string()));
+	assert_eq!(mappings.map_field("app/Base", "sides", "I"), Some("b".to_string()));
+	assert_eq!(mappings.map_field("app/Base", "sides", "J"), None);
+	assert_eq!(mappings.map_method("app/Square", "area", "()D"), Some("a".to_string()));
+	assert_eq!(mappings.map_method("app/Main", "main", "([Ljava/lang/String;)V"), Some("main".to_string()));
+	// Only inlined into area, so it's not a method of the obfuscated class.
+	assert_eq!(mappings.map_method("app/Square", "square", "(D)D"), None);
+
+	let error = Mappings::parse_proguard("app.Main -> a:\n    int broken\n").unwrap_err();
+	assert_eq!(error.to_string(), "line 2: expected ->");
+	assert_eq!(Mappings::parse_proguard("    int count -> a\n").unwrap_err().line, 1);
+}
+
+#[test]
+fn parse_tiny() {
+	// The descriptors are in terms of the first namespace, the obfuscated one here.
+	let v2 = "tiny\t2\t0\tofficial\tnamed\n\
+		c\ta/a\tapp/Shape\n\
+		\tm\t()D\ta\tarea\n\
+		\t\tc\tThe area.\n\
+		c\ta/b\tapp/Base\n\
+		\tf\tI\tb\tsides\n\
+		\tm\t(La/a;)La/b;\tc\tcopy\n";
+	let mut expected = Mappings::new();
+	expected.class("app/Shape", "a/a")
+		.class("app/Base", "a/b")
+		.method("app/Shape", "area", "()D", "a")
+		.field("app/Base", "sides", Some("I"), "b")
+		.method("app/Base", "copy", "(Lapp/Shape;)Lapp/Base;", "c");
+	assert_eq!(Mappings::parse_tiny(v2, "named", "official").unwrap(), expected);
+	assert_eq!(Mappings::parse_tiny(v2, "official", "named").unwrap(), expected.reversed());
+
+	let v1 = "v1\tofficial\tnamed\n\
+		CLASS\ta/a\tapp/Shape\n\
+		CLASS\ta/b\tapp/Base\n\
+		METHOD\ta/a\t()D\ta\tarea\n\
+		FIELD\ta/b\tI\tb\tsides\n\
+		METHOD\ta/b\t(La/a;)La/b;\tc\tcopy\n";
+	assert_eq!(Mappings::parse_tiny(v1, "named", "official").unwrap(), expected);
+
+	assert_eq!(Mappings::parse_tiny(v1, "named", "intermediary").unwrap_err().to_string(), "line 1: no namespace intermediary");
+	assert!(Mappings::parse_tiny("tiny\t3\t0\ta\tb\n", "a", "b").is_err());
+}
+
+#[test]
+fn parse_srg() {
+	let srg = "PK: . net/example\n\
+		CL: a net/example/Widget\n\
+		FD: a/b net/example/Widget/size\n\
+		MD: a/c (La;)V net/example/Widget/copy (Lnet/example/Widget;)V\n";
+	let mappings = Mappings::parse_srg(srg).unwrap();
+	assert_eq!(mappings.map_class("a"), Some("net/example/Widget".to_string()));
+	// Unlisted classes in the default package move with it.
+	assert_eq!(mappings.map_class("b"), Some("net/example/b".to_string()));
+	assert_eq!(mappings.map_field("a", "b", "I"), Some("size".to_string()));
+	assert_eq!(mappings.map_field("a", "b", "J"), Some("size".to_string()));
+	assert_eq!(mappings.map_method("a", "c", "(La;)V"), Some("copy".to_string()));
+
+	let reversed = mappings.reversed();
+	assert_eq!(reversed.map_class("net/example/b"), Some("b".to_string()));
+	assert_eq!(reversed.map_method("net/example/Widget", "copy", "(Lnet/example/Widget;)V"), Some("c".to_string()));
+
+	assert_eq!(Mappings::parse_srg("CL: a\n").unwrap_err().line, 1);
+}
+
+#[test]
+fn obfuscate() {
+	let obfuscated = apply(&class_path(), &proguard());
+	assert_eq!(obfuscated.names().collect::<Vec<_>>(), ["app/Main", "app/a", "app/b", "app/c"]);
+
+	// Only the interface methods are in the mappings, everything else follows along.
+	assert_eq!(methods(obfuscated.get("app/a").unwrap()), ["a()D", "b()Ljava/lang/String;"]);
+	assert_eq!(methods(obfuscated.get("app/b").unwrap()), ["<init>()V", "b()Ljava/lang/String;", "c()I"]);
+	assert_eq!(methods(obfuscated.get("app/c").unwrap()), ["<init>()V", "a()D", "b()Ljava/lang/String;"]);
+
+	// Inherited members are referred to through the subclass.
+	let main = constants(obfuscated.get("app/Main").unwrap());
+	assert!(main.contains(&"app/c.c:()I".to_string()), "{:?}", main);
+	assert!(main.contains(&"app/c.b:I".to_string()), "{:?}", main);
+	assert!(main.contains(&"app/a.b:()Ljava/lang/String;".to_string()), "{:?}", main);
+}
+
+#[test]
+fn deobfuscate() {
+	let original = class_path();
+	let obfuscated = apply(&original, &proguard());
+	let deobfuscated = apply(&obfuscated, &proguard().reversed());
+	assert_eq!(deobfuscated.names().collect::<Vec<_>>(), original.names().collect::<Vec<_>>());
+	for name in original.names() {
+		let diff = diff(original.get(name).unwrap(), deobfuscated.get(name).unwrap()).unwrap();
+		assert!(diff.is_empty(), "{}", diff);
+	}
+}
+
+#[test]
+fn field_lookup() {
+	let class_path = package("lookup");
+	let mut mappings = Mappings::new();
+	mappings.field("lookup/Named", "NAME", None, "n")
+		.field("lookup/Other", "NAME", None, "o")
+		.field("lookup/Middle", "NAME", None, "m");
+	let mapper = HierarchyMapper::new(&mappings, &class_path);
+	let string = "Ljava/lang/String;";
+
+	// The super class's own field comes before the interface of a class further up.
+	assert_eq!(mapper.map_field("lookup/Lookup", "NAME", string), Some("m".to_string()));
+	// But a class's direct interfaces come before its super class.
+	assert_eq!(mapper.map_field("lookup/Both", "NAME", string), Some("o".to_string()));
+	assert_eq!(mapper.map_field("lookup/Top", "NAME", string), Some("n".to_string()));
+	assert_eq!(mapper.map_field("lookup/Lookup", "missing", string), None);
+}
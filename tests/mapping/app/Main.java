@@ -0,0 +1,46 @@
+package app;
+
+interface Shape {
+	double area();
+
+	String name();
+}
+
+abstract class Base implements Shape {
+	static int count;
+
+	protected int sides = 4;
+
+	Base() {
+		count++;
+	}
+
+	public String name() {
+		return "base";
+	}
+
+	static int total() {
+		return count;
+	}
+}
+
+class Square extends Base {
+	double side = 2;
+
+	public double area() {
+		return side * side;
+	}
+
+	@Override
+	public String name() {
+		return "square";
+	}
+}
+
+public class Main {
+	public static void main(String[] args) {
+		Shape shape = new Square();
+		Square square = new Square();
+		System.out.println(shape.name() + " " + shape.area() + " " + Square.total() + " " + square.sides);
+	}
+}
@@ -0,0 +1,25 @@
+package lookup;
+
+interface Named {
+	String NAME = "named";
+}
+
+interface Other {
+	String NAME = "other";
+}
+
+class Top implements Named {
+}
+
+class Middle extends Top {
+	String NAME = "middle";
+}
+
+class Both extends Middle implements Other {
+}
+
+public class Lookup extends Middle {
+	String name() {
+		return NAME;
+	}
+}
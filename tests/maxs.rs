@@ -0,0 +1,76 @@
+extern crate class_file;
+
+mod common;
+
+use class_file::maxs::*;
+use class_file::ops::*;
+use common::*;
+
+#[test]
+fn same_as_javac() {
+	for data in &[
+		&include_bytes!("Flow.class")[..],
+		&include_bytes!("Frames.class")[..],
+		&include_bytes!("Constants.class")[..],
+		&include_bytes!("Attributes.class")[..],
+		&include_bytes!("References.class")[..],
+		&include_bytes!("Version55.class")[..],
+	] {
+		let class_file = load(data);
+		for index in 0..class_file.methods.len() {
+			if let Some(mut code) = method_code(&class_file, index) {
+				let expected = (code.max_stack, code.max_locals);
+				assert_eq!(compute_maxs(&class_file, index, &code), Ok(expected));
+
+				code.max_stack = 0;
+				code.max_locals = 0;
+				update_maxs(&class_file, index, &mut code).unwrap();
+				assert_eq!((code.max_stack, code.max_locals), expected);
+			}
+		}
+	}
+}
+
+#[test]
+fn parameters_and_subroutines() {
+	let flow = load(include_bytes!("Flow.class"));
+	// `this` and the long parameter, even though only the latter is used.
+	let fold = method_index(&flow, "fold");
+	let mut fold_code = method_code(&flow, fold).unwrap();
+	fold_code.code = vec![LLOAD_1, LRETURN];
+	assert_eq!(compute_maxs(&flow, fold, &fold_code), Ok((2, 3)));
+
+	// The subroutine gets the return address on the stack, but the caller carries on without it.
+	let frames = load(include_bytes!("Frames.class"));
+	let constant = method_index(&frames, "constant");
+	let mut code = method_code(&frames, constant).unwrap();
+	code.code = vec![JSR, 0, 4, RETURN, ASTORE_0, RET, 0];
+	assert_eq!(compute_maxs(&frames, constant, &code), Ok((1, 1)));
+	code.code = vec![JSR, 0, 4, RETURN, DUP, ASTORE_1, POP, RET, 1];
+	assert_eq!(compute_maxs(&frames, constant, &code), Ok((2, 2)));
+
+	// Nothing is ever pushed, but the handler starts with the exception on the stack.
+	let ignore = method_index(&frames, "ignore");
+	let code = method_code(&frames, ignore).unwrap();
+	assert_eq!(compute_maxs(&frames, ignore, &code), Ok((1, 1)));
+}
+
+#[test]
+fn errors() {
+	let class_file = load(include_bytes!("Frames.class"));
+	let index = method_index(&class_file, "constant");
+	let mut code = method_code(&class_file, index).unwrap();
+
+	code.code = vec![POP, RETURN];
+	let error = compute_maxs(&class_file, index, &code).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(0), Problem::StackUnderflow));
+
+	// A loop that pushes a value every time around.
+	code.code = vec![ICONST_0, GOTO, 0xFF, 0xFF];
+	let error = compute_maxs(&class_file, index, &code).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(1), Problem::StackHeightMismatch { first: 0, second: 1 }));
+
+	code.code = vec![ICONST_0];
+	let error = compute_maxs(&class_file, index, &code).unwrap_err();
+	assert_eq!((error.pc, error.problem), (Some(0), Problem::FallsOffEnd));
+}
@@ -0,0 +1,7 @@
+package com.example.modular;
+
+public class Main {
+	public static void main(String[] args) {
+		System.out.println(com.example.modular.internal.Helper.greeting());
+	}
+}
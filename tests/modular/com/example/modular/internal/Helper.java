@@ -0,0 +1,19 @@
+package com.example.modular.internal;
+
+import java.sql.*;
+import java.util.Properties;
+import java.util.logging.Logger;
+
+public class Helper implements Driver {
+	public static String greeting() {
+		return "Hello from a module";
+	}
+
+	public Connection connect(String url, Properties info) { return null; }
+	public boolean acceptsURL(String url) { return false; }
+	public DriverPropertyInfo[] getPropertyInfo(String url, Properties info) { return new DriverPropertyInfo[0]; }
+	public int getMajorVersion() { return 1; }
+	public int getMinorVersion() { return 0; }
+	public boolean jdbcCompliant() { return false; }
+	public Logger getParentLogger() { return null; }
+}
@@ -0,0 +1,11 @@
+module com.example.modular {
+	requires java.logging;
+	requires transitive java.sql;
+	requires static java.desktop;
+
+	exports com.example.modular;
+	opens com.example.modular.internal to java.logging;
+
+	uses java.sql.Driver;
+	provides java.sql.Driver with com.example.modular.internal.Helper;
+}
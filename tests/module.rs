@@ -0,0 +1,98 @@
+extern crate class_file;
+
+use std::io::Cursor;
+
+use class_file::*;
+use class_file::jmod::Jmod;
+use class_file::module::*;
+use class_file::ops::MODULE;
+
+/// The descriptor of the module in modular/, which is in Modular.jmod.
+fn modular() -> ModuleDescriptor {
+	let jmod = Jmod::new(include_bytes!("Modular.jmod").to_vec()).unwrap();
+	jmod.module_info().unwrap().descriptor().unwrap()
+}
+
+fn reparse(class_file: &ClassFile) -> ClassFile<'static> {
+	let mut data = Vec::new();
+	class_file.to_bytes(&mut data).unwrap();
+	ClassFile::open(&mut Cursor::new(data)).unwrap()
+}
+
+#[test]
+fn parsing() {
+	let descriptor = modular();
+	assert_eq!(descriptor.name, "com.example.modular");
+	assert_eq!(descriptor.version.as_deref(), Some("1.2"));
+	assert!(!descriptor.open);
+
+	let requires: Vec<_> = descriptor.requires.iter()
+		.map(|requires| (requires.module.as_str(), requires.transitive, requires.static_phase, requires.mandated))
+		.collect();
+	assert_eq!(requires, [
+		("java.base", false, false, true),
+		("java.logging", false, false, false),
+		("java.sql", true, false, false),
+		("java.desktop", false, true, false),
+	]);
+
+	assert_eq!(descriptor.exports, [Exports::new("com/example/modular", &[])]);
+	assert_eq!(descriptor.opens, [Exports::new("com/example/modular/internal", &["java.logging"])]);
+	assert!(descriptor.opens[0].is_qualified());
+	assert_eq!(descriptor.uses, ["java/sql/Driver"]);
+	assert_eq!(descriptor.provides, [Provides {
+		service: "java/sql/Driver".to_string(),
+		with: vec!["com/example/modular/internal/Helper".to_string()],
+	}]);
+	assert_eq!(descriptor.packages, ["com/example/modular", "com/example/modular/internal"]);
+	assert_eq!(descriptor.main_class.as_deref(), Some("com/example/modular/Main"));
+}
+
+#[test]
+fn round_trip() {
+	let descriptor = modular();
+	let class_file = reparse(&descriptor.to_class_file().unwrap());
+	assert_eq!(ModuleDescriptor::from_class_file(&class_file).unwrap(), descriptor);
+}
+
+#[test]
+fn generating() {
+	let mut descriptor = ModuleDescriptor::new("org.legacy");
+	let mut logging = Requires::new("java.logging");
+	logging.transitive = true;
+	descriptor.set_version("3.1")
+		.set_open(true)
+		.add_requires(logging)
+		.add_exports("org/legacy/api", &[])
+		.add_exports("org/legacy/spi", &["org.legacy.plugins", "org.legacy.tests"])
+		.add_uses("org/legacy/spi/Plugin")
+		.add_provides("org/legacy/spi/Plugin", &["org/legacy/impl/DefaultPlugin"])
+		.add_package("org/legacy/util")
+		.set_main_class("org/legacy/cli/Main");
+	assert_eq!(descriptor.flags(), 0x0020);
+	assert_eq!(descriptor.requires[0].module, JAVA_BASE);
+
+	let class_file = reparse(&descriptor.to_class_file().unwrap());
+	assert_eq!(class_file.access_flags, MODULE);
+	assert_eq!(class_file.major_version, 53);
+	let name = class_file.constant_pool.class_name(class_file.this_class).unwrap();
+	assert_eq!(utf8::decode(name.as_bytes()), MODULE_INFO);
+
+	let parsed = ModuleDescriptor::from_class_file(&class_file).unwrap();
+	let packages: Vec<_> = descriptor.all_packages().into_iter().collect();
+	assert_eq!(packages, ["org/legacy/api", "org/legacy/cli", "org/legacy/impl", "org/legacy/spi", "org/legacy/util"]);
+	assert_eq!(parsed.packages, packages);
+	assert_eq!(ModuleDescriptor { packages: descriptor.all_packages().into_iter().collect(), ..descriptor }, parsed);
+
+	let base = ModuleDescriptor::new(JAVA_BASE);
+	assert!(base.requires.is_empty());
+	let class_file = reparse(&base.to_class_file().unwrap());
+	assert_eq!(class_file.attributes.len(), 1);
+	assert_eq!(ModuleDescriptor::from_class_file(&class_file).unwrap(), base);
+}
+
+#[test]
+fn not_a_module() {
+	let class_file = ClassFile::open(&mut Cursor::new(&include_bytes!("Hierarchy.class")[..])).unwrap();
+	assert!(ModuleDescriptor::from_class_file(&class_file).is_none());
+}
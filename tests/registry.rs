@@ -0,0 +1,159 @@
+extern crate class_file;
+
+mod common;
+
+use class_file::*;
+use class_file::registry::*;
+use common::*;
+
+/// Decodes every attribute, asserts that they're all understood, and that they encode back to the same bytes.
+fn check_all<'a>(registry: &AttributeRegistry, attributes: &Attributes<'a>, cp: &ConstantPool<'a>, names: &mut Vec<&'static str>) {
+	for decoded in registry.decode_all(attributes, cp) {
+		if let KnownAttribute::Code(ref code) = decoded.attribute {
+			check_all(registry, &code.attributes, cp, names);
+		}
+		let name = decoded.attribute.standard_name()
+			.unwrap_or_else(|| panic!("Failed to decode: {:?}", decoded.attribute));
+		names.push(name);
+
+		let original = attributes.iter()
+			.find(|info| info.name_index() == decoded.name_index)
+			.unwrap();
+		let encoded = registry.encode(&decoded, cp).unwrap();
+		assert_eq!(original, &encoded, "{} changed after encoding", name);
+	}
+}
+
+#[test]
+fn decode_standard_attributes() {
+	let registry = AttributeRegistry::new();
+	let mut names = vec![];
+
+	for data in &[&include_bytes!("Attributes.class")[..], &include_bytes!("Attributes$Info.class")[..]] {
+		let class_file = load(data);
+		let cp = &class_file.constant_pool;
+
+		check_all(&registry, &class_file.attributes, cp, &mut names);
+		for field in &class_file.fields {
+			check_all(&registry, &field.attributes, cp, &mut names);
+		}
+		for method in &class_file.methods {
+			check_all(&registry, &method.attributes, cp, &mut names);
+		}
+	}
+
+	for expected in &[
+		"BootstrapMethods",
+		"Code",
+		"ConstantValue",
+		"Deprecated",
+		"Exceptions",
+		"InnerClasses",
+		"LineNumberTable",
+		"LocalVariableTable",
+		"LocalVariableTypeTable",
+		"MethodParameters",
+		"NestMembers",
+		"RuntimeVisibleAnnotations",
+		"RuntimeVisibleParameterAnnotations",
+		"RuntimeVisibleTypeAnnotations",
+		"Signature",
+		"SourceFile",
+		"StackMapTable",
+		"AnnotationDefault",
+	] {
+		assert!(names.contains(expected), "{} wasn't decoded", expected);
+	}
+}
+
+#[test]
+fn decode_records_and_sealed_classes() {
+	let registry = AttributeRegistry::new();
+	let mut names = vec![];
+
+	let shape = load(include_bytes!("compact/pool/Shape.class"));
+	check_all(&registry, &shape.attributes, &shape.constant_pool, &mut names);
+	assert!(names.contains(&"PermittedSubclasses"));
+
+	let circle = load(include_bytes!("compact/pool/Circle.class"));
+	let cp = &circle.constant_pool;
+	check_all(&registry, &circle.attributes, cp, &mut names);
+	let record = registry.decode_all(&circle.attributes, cp).into_iter()
+		.find_map(|decoded| match decoded.attribute {
+			KnownAttribute::Record(record) => Some(record),
+			_ => None,
+		})
+		.unwrap();
+	assert_eq!(record.components.len(), 1);
+	assert_eq!(cp.index(record.components[0].name_index).unwrap(), "radius");
+}
+
+#[derive(Debug, PartialEq)]
+struct ScalaSig {
+	major: u8,
+	minor: u8,
+	entries: u8,
+}
+
+impl CustomAttribute for ScalaSig {
+	fn decode(data: &[u8], _cp: &ConstantPool) -> Option<Self> {
+		match *data {
+			[major, minor, entries] => Some(ScalaSig {
+				major,
+				minor,
+				entries,
+			}),
+			_ => None,
+		}
+	}
+
+	fn encode(&self) -> Vec<u8> {
+		vec![self.major, self.minor, self.entries]
+	}
+}
+
+#[test]
+fn custom_and_unknown_attributes() {
+	let mut class_file = load(include_bytes!("Version55.class"));
+	let cp = &mut class_file.constant_pool;
+	cp.entries.push(CPEntry::UTF8(UTF8Info::new("ScalaSig")));
+	let scala_sig = CPIndex::new(cp.entries.len() as u16);
+	cp.entries.push(CPEntry::UTF8(UTF8Info::new("GroovySomething")));
+	let groovy = CPIndex::new(cp.entries.len() as u16);
+	let cp = &class_file.constant_pool;
+
+	let scala_sig = AttributeInfo::new(scala_sig, vec![5, 0, 0]);
+	let groovy = AttributeInfo::new(groovy, vec![1, 2, 3, 4]);
+
+	let mut registry = AttributeRegistry::new();
+	registry.register::<ScalaSig>("ScalaSig");
+
+	let decoded = registry.decode(&scala_sig, cp);
+	match decoded.attribute {
+		KnownAttribute::Custom(ref value) => {
+			let value = value.downcast_ref::<ScalaSig>().unwrap();
+			assert_eq!(value, &ScalaSig {
+				major: 5,
+				minor: 0,
+				entries: 0,
+			});
+		}
+		ref other => panic!("Expected a custom attribute, found {:?}", other),
+	}
+	assert_eq!(registry.encode(&decoded, cp).unwrap(), scala_sig);
+
+	// Nobody registered this one, so it's kept exactly as it was.
+	let decoded = registry.decode(&groovy, cp);
+	match decoded.attribute {
+		KnownAttribute::Unknown(ref info) => assert_eq!(info, &groovy),
+		ref other => panic!("Expected an unknown attribute, found {:?}", other),
+	}
+	assert_eq!(registry.encode(&decoded, cp).unwrap(), groovy);
+
+	// A custom attribute that doesn't decode is also kept as is.
+	let broken = AttributeInfo::new(scala_sig.name_index(), vec![5]);
+	match registry.decode(&broken, cp).attribute {
+		KnownAttribute::Unknown(ref info) => assert_eq!(info, &broken),
+		ref other => panic!("Expected an unknown attribute, found {:?}", other),
+	}
+}
@@ -0,0 +1,177 @@
+extern crate class_file;
+
+mod common;
+
+use std::io::Cursor;
+
+use binform::BigEndian;
+
+use class_file::*;
+use class_file::attr::*;
+use class_file::remap::*;
+use class_file::verify::format::validate;
+use common::*;
+
+fn classes() -> Vec<ClassFile<'static>> {
+	vec![
+		load(include_bytes!("remap/lib/Widget.class")),
+		load(include_bytes!("remap/lib/Widget$Inner.class")),
+		load(include_bytes!("remap/lib/Widget$1.class")),
+		load(include_bytes!("remap/lib/Helper.class")),
+		load(include_bytes!("remap/lib/Kind.class")),
+		load(include_bytes!("remap/lib/Tag.class")),
+	]
+}
+
+fn utf8<'a>(cp: &'a ConstantPool<'a>, index: CPIndex<'a, UTF8Info<'a>>) -> String {
+	cp.utf8(index).unwrap().to_utf8().into_owned()
+}
+
+fn name(class_file: &ClassFile) -> String {
+	class_file.constant_pool.class_name(class_file.this_class).unwrap().to_utf8().into_owned()
+}
+
+fn decode<'a, T: FromBytes<BigEndian, Output = T> + 'a>(attributes: &Attributes<'a>, cp: &ConstantPool<'a>, name: &str) -> T {
+	T::from_bytes(&mut Cursor::new(attributes.named(cp, name).unwrap().info())).unwrap()
+}
+
+fn remap_all<M: Mapper>(mapper: &M) -> Vec<ClassFile<'static>> {
+	classes().iter()
+		.map(|class_file| remap(class_file, mapper).unwrap())
+		.collect()
+}
+
+#[test]
+fn relocate() {
+	let mut relocator = Relocator::new();
+	relocator.package("lib", "shaded.lib");
+	let remapped = remap_all(&relocator);
+	let names: Vec<_> = remapped.iter().map(name).collect();
+	assert_eq!(names, ["shaded/lib/Widget", "shaded/lib/Widget$Inner", "shaded/lib/Widget$1", "shaded/lib/Helper", "shaded/lib/Kind", "shaded/lib/Tag"]);
+
+	for (old, new) in classes().iter().zip(&remapped) {
+		assert_eq!(validate(new), vec![], "{}", name(new));
+		// The code, and the layout of the pool, stays the same, but for the string shared by a constant, see below.
+		let split = if name(new) == "shaded/lib/Widget" { 1 } else { 0 };
+		assert_eq!(old.constant_pool.entries.len() + split, new.constant_pool.entries.len());
+		let code = |class_file: &ClassFile| -> Vec<Vec<u8>> {
+			class_file.methods.iter()
+				.filter_map(|method| method.attributes.get::<Code>(&class_file.constant_pool))
+				.map(|code| code.code)
+				.collect()
+		};
+		assert_eq!(code(old), code(new));
+		// Only the constants are left naming the old package.
+		for string in strings(new) {
+			assert!(!string.contains("Llib/") && !string.starts_with("lib/") || string == "lib/Helper", "{} in {}", string, name(new));
+		}
+	}
+
+	let widget = &remapped[0];
+	let cp = &widget.constant_pool;
+	let signature: Signature = widget.attributes.get(cp).unwrap();
+	assert_eq!(utf8(cp, signature.class_index), "<T:Lshaded/lib/Helper;>Ljava/lang/Object;");
+	let annotations: RuntimeVisibleAnnotations = decode(&widget.attributes, cp, "RuntimeVisibleAnnotations");
+	assert_eq!(utf8(cp, annotations.table[0].type_index), "Lshaded/lib/Tag;");
+	let constants = self::constants(widget);
+	assert!(constants.contains(&"shaded/lib/Kind.LARGE:Lshaded/lib/Kind;".to_string()));
+	assert!(constants.contains(&"\"lib.Helper\"".to_string()));
+}
+
+#[test]
+fn strings_are_split() {
+	// The constant and the class name share a string, so relocating one but not the other needs another entry.
+	let mut relocator = Relocator::new();
+	relocator.package("lib", "shaded/lib");
+	let widget = &classes()[0];
+	let remapped = remap(widget, &relocator).unwrap();
+	assert_eq!(remapped.constant_pool.entries.len(), widget.constant_pool.entries.len() + 1);
+	let constants = self::constants(&remapped);
+	assert!(constants.contains(&"\"lib/Helper\"".to_string()));
+	assert!(constants.contains(&"shaded/lib/Helper".to_string()));
+	assert_eq!(validate(&remapped), vec![]);
+
+	relocator.strings(true);
+	let remapped = remap(widget, &relocator).unwrap();
+	assert_eq!(remapped.constant_pool.entries.len(), widget.constant_pool.entries.len());
+	let constants = self::constants(&remapped);
+	assert!(constants.contains(&"\"shaded/lib/Helper\"".to_string()));
+	assert!(constants.contains(&"\"shaded.lib.Helper\"".to_string()));
+}
+
+struct Renames;
+
+impl Mapper for Renames {
+	fn map_class(&self, name: &str) -> Option<String> {
+		match name {
+			"lib/Widget$Inner" => Some("lib/Widget$Nested".to_string()),
+			_ => None,
+		}
+	}
+
+	fn map_field(&self, owner: &str, name: &str, _descriptor: &str) -> Option<String> {
+		match (owner, name) {
+			("lib/Kind", "LARGE") => Some("BIG".to_string()),
+			_ => None,
+		}
+	}
+
+	fn map_method(&self, owner: &str, name: &str, descriptor: &str) -> Option<String> {
+		match (owner, name, descriptor) {
+			("lib/Helper", "describe", "()Ljava/lang/String;") => Some("d".to_string()),
+			_ => None,
+		}
+	}
+}
+
+#[test]
+fn members() {
+	let classes = remap_all(&Renames);
+	for class_file in &classes {
+		assert_eq!(validate(class_file), vec![], "{}", name(class_file));
+	}
+
+	let widget = &classes[0];
+	let cp = &widget.constant_pool;
+	let constants = self::constants(widget);
+	assert!(constants.contains(&"lib/Kind.BIG:Llib/Kind;".to_string()));
+	assert!(constants.contains(&"lib/Widget$Nested".to_string()));
+	let inner_classes: InnerClasses = decode(&widget.attributes, cp, "InnerClasses");
+	let inner_names: Vec<_> = inner_classes.table.iter()
+		.filter_map(|inner| cp.utf8(inner.inner_name_index?))
+		.map(|name| name.to_utf8().into_owned())
+		.collect();
+	assert!(inner_names.contains(&"Nested".to_string()));
+	let inner = widget.methods.iter()
+		.find(|method| utf8(cp, method.name_index) == "inner")
+		.unwrap();
+	let signature: Signature = inner.attributes.get(cp).unwrap();
+	assert_eq!(utf8(cp, signature.class_index), "()Llib/Widget<TT;>.Nested;");
+	let annotations: RuntimeVisibleAnnotations = decode(&widget.attributes, cp, "RuntimeVisibleAnnotations");
+	match &annotations.table[0].element_value_pairs[0].element_value {
+		ElementValue::Enum { const_name_index, .. } => assert_eq!(utf8(cp, *const_name_index), "BIG"),
+		value => panic!("expected an enum, got {:?}", value),
+	}
+
+	assert!(self::constants(&classes[2]).contains(&"lib/Helper.d:()Ljava/lang/String;".to_string()));
+
+	// The declarations follow, but the enum's own name for the constant doesn't.
+	let helper = &classes[3];
+	assert!(strings(helper).contains(&"d".to_string()));
+	assert!(!strings(helper).contains(&"describe".to_string()));
+	let kind = &classes[4];
+	assert!(kind.fields.iter().any(|field| utf8(&kind.constant_pool, field.name_index) == "BIG"));
+	assert!(self::constants(kind).contains(&"\"LARGE\"".to_string()));
+}
+
+#[test]
+fn signatures() {
+	assert_eq!(descriptor(&Renames, "(Llib/Widget$Inner;[Llib/Widget$Inner;I)V"), "(Llib/Widget$Nested;[Llib/Widget$Nested;I)V");
+	assert_eq!(class_name(&Renames, "[[Llib/Widget$Inner;"), "[[Llib/Widget$Nested;");
+	assert_eq!(
+		signature(&Renames, "<T::Ljava/lang/Comparable<-TT;>;>(Ljava/util/Map<*+Llib/Widget$Inner;>;)Llib/Widget<TT;>.Inner;^TE;"),
+		"<T::Ljava/lang/Comparable<-TT;>;>(Ljava/util/Map<*+Llib/Widget$Nested;>;)Llib/Widget<TT;>.Nested;^TE;",
+	);
+	// Not a signature, so left alone.
+	assert_eq!(signature(&Renames, "Llib/Widget$Inner"), "Llib/Widget$Inner");
+}
@@ -0,0 +1,63 @@
+package lib;
+
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.util.ArrayList;
+import java.util.List;
+import java.util.function.Supplier;
+
+@Tag(kind = Kind.LARGE, type = Helper.class)
+public class Widget<T extends Helper> {
+	public static final String NAME = "lib.Helper";
+	public static final String PATH = "lib/Helper";
+
+	private final List<T> helpers = new ArrayList<>();
+
+	public class Inner {
+		Helper helper() {
+			return new Helper();
+		}
+	}
+
+	public Widget<T>.Inner inner() {
+		return new Inner();
+	}
+
+	public void add(T helper) {
+		helpers.add(helper);
+	}
+
+	public static String run() {
+		Supplier<Helper> supplier = Helper::new;
+		Object local = new Object() {
+			@Override
+			public String toString() {
+				return supplier.get().describe();
+			}
+		};
+		Helper[] helpers = { new Helper() };
+		return local + " " + Helper.class.getName() + " " + helpers.length + " " + NAME + " " + PATH.length() + " " + Kind.LARGE;
+	}
+
+	public static void main(String[] args) {
+		System.out.println(run());
+	}
+}
+
+@Retention(RetentionPolicy.RUNTIME)
+@interface Tag {
+	Kind kind() default Kind.SMALL;
+
+	Class<?> type() default Widget.class;
+}
+
+enum Kind {
+	SMALL,
+	LARGE,
+}
+
+class Helper {
+	String describe() {
+		return "helper";
+	}
+}
@@ -0,0 +1,80 @@
+extern crate class_file;
+
+use std::collections::HashSet;
+use std::io::Cursor;
+
+use class_file::*;
+use class_file::resolve::*;
+
+fn load() -> ClassFile<'static> {
+	let data = include_bytes!("References.class");
+	let mut input = Cursor::new(&data[..]);
+	ClassFile::open(&mut input)
+		.expect("Failed to parse \"References.class\"")
+}
+
+#[test]
+fn resolve_member_refs() {
+	let class_file = load();
+	let cp = &class_file.constant_pool;
+
+	let mut fields = vec![];
+	let mut methods = vec![];
+	let mut indys = vec![];
+	let mut handles = vec![];
+	for entry in cp {
+		match entry {
+			CPEntry::FieldRef(info) => fields.push(info.resolve(cp).unwrap().to_string()),
+			CPEntry::MethodRef(info) => methods.push(info.resolve(cp).unwrap().to_string()),
+			CPEntry::InterfaceMethodRef(info) => {
+				let method = info.resolve(cp).unwrap();
+				assert!(method.interface);
+				methods.push(method.to_string());
+			}
+			CPEntry::InvokeDynamic(info) => indys.push(info.resolve(cp).unwrap().to_string()),
+			CPEntry::MethodHandle(info) => handles.push(info.resolve(cp).unwrap().to_string()),
+			_ => {}
+		}
+	}
+
+	assert_eq!(fields, vec![
+		"References.name:Ljava/lang/String;",
+		"java/lang/System.out:Ljava/io/PrintStream;",
+	]);
+	assert!(methods.contains(&"java/lang/Object.<init>:()V".to_string()));
+	assert!(methods.contains(&"java/lang/Runnable.run:()V".to_string()));
+	assert!(indys.contains(&"#1:run:(LReferences;)Ljava/lang/Runnable;".to_string()));
+	assert!(handles.contains(&"REF_invokeVirtual java/lang/Object.toString:()Ljava/lang/String;".to_string()));
+}
+
+#[test]
+fn resolved_refs_as_keys() {
+	let class_file = load();
+	let cp = &class_file.constant_pool;
+
+	let mut owners = HashSet::new();
+	for entry in cp {
+		if let CPEntry::MethodRef(info) = entry {
+			let method = info.resolve(cp).unwrap();
+			owners.insert(method.owner);
+			// Resolving twice gives back an equal value.
+			assert_eq!(Some(method), info.resolve(cp));
+		}
+	}
+	let owners: HashSet<_> = owners.into_iter()
+		.map(|owner| owner.to_utf8().into_owned())
+		.collect();
+	assert!(owners.contains("java/io/PrintStream"));
+	assert!(owners.contains("java/lang/String"));
+}
+
+#[test]
+fn method_handles_passthrough() {
+	let data = include_bytes!("References.class");
+	let class_file = load();
+
+	let mut output = vec![];
+	class_file.to_bytes(&mut Cursor::new(&mut output)).unwrap();
+
+	assert_eq!(&data[..], &output[..]);
+}
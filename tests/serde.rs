@@ -0,0 +1,99 @@
+#![cfg(feature = "serde")]
+
+extern crate class_file;
+
+mod common;
+
+use std::io::Cursor;
+
+use class_file::*;
+use class_file::serialize::*;
+use common::*;
+
+const FIXTURES: [&[u8]; 8] = [
+	include_bytes!("Attributes.class"),
+	include_bytes!("Constants.class"),
+	include_bytes!("Eval.class"),
+	include_bytes!("Flow.class"),
+	include_bytes!("Frames.class"),
+	include_bytes!("Hierarchy.class"),
+	include_bytes!("References.class"),
+	include_bytes!("Version55.class"),
+];
+
+fn round_trip(class_file: &ClassFile) -> Vec<u8> {
+	let json = serde_json::to_string(class_file).unwrap();
+	let class_file: ClassFile = serde_json::from_str(&json).unwrap();
+	let mut output = vec![];
+	class_file.to_bytes(&mut output).unwrap();
+	output
+}
+
+fn method<'a>(class: &'a ResolvedClass, name: &str) -> &'a ResolvedMember {
+	class.methods.iter()
+		.find(|method| method.name == name)
+		.unwrap()
+}
+
+#[test]
+fn raw() {
+	for &data in FIXTURES.iter() {
+		assert_eq!(round_trip(&load(data)), data);
+	}
+
+	// Strings are written as strings.
+	let json = serde_json::to_value(load(include_bytes!("Hierarchy.class"))).unwrap();
+	let entries = json["constant_pool"]["entries"].as_array().unwrap();
+	assert!(entries.iter().any(|entry| entry["UTF8"] == "Hierarchy.java"));
+}
+
+#[test]
+fn raw_invalid_utf8() {
+	let mut data = include_bytes!("Version55.class").to_vec();
+	let needle = b"Version55.java";
+	let offset = data.windows(needle.len())
+		.position(|window| window == needle)
+		.unwrap();
+	data[offset] = 0xFF;
+
+	// Strings that aren't valid are written as their bytes, so they come back the same.
+	let class_file = ClassFile::open_lenient(&mut Cursor::new(&data)).unwrap();
+	assert_eq!(round_trip(&class_file), data);
+}
+
+#[test]
+fn resolved() {
+	let class = ResolvedClass::new(&load(include_bytes!("References.class"))).unwrap();
+	assert_eq!(class.name, "References");
+	assert_eq!(class.super_class.as_deref(), Some("java/lang/Object"));
+	assert_eq!(class.fields[0].name, "name");
+	assert_eq!(class.fields[0].descriptor, "Ljava/lang/String;");
+	assert!(class.attributes.contains(&ResolvedAttribute::SourceFile("References.java".to_string())));
+
+	let print = method(&class, "print");
+	assert_eq!(print.descriptor, "()V");
+	let instructions = match &print.attributes[0] {
+		ResolvedAttribute::Code { instructions, .. } => instructions,
+		attribute => panic!("expected code, got {:?}", attribute),
+	};
+	assert_eq!(instructions[3].pc, 7);
+	assert!(instructions[3].text.starts_with("invokevirtual"));
+	assert_eq!(instructions[3].constant.as_deref(), Some("java/io/PrintStream.println:(Ljava/lang/String;)V"));
+	assert_eq!(instructions[4].constant, None);
+
+	let json = serde_json::to_string(&class).unwrap();
+	assert_eq!(serde_json::from_str::<ResolvedClass>(&json).unwrap(), class);
+}
+
+#[test]
+fn resolved_constants() {
+	let class = ResolvedClass::new(&load(include_bytes!("Constants.class"))).unwrap();
+	let big = class.fields.iter()
+		.find(|field| field.name == "BIG")
+		.unwrap();
+	assert_eq!(big.attributes, [ResolvedAttribute::ConstantValue("1234567890123l".to_string())]);
+	let name = class.fields.iter()
+		.find(|field| field.name == "NAME")
+		.unwrap();
+	assert_eq!(name.attributes, [ResolvedAttribute::ConstantValue("\"constants\"".to_string())]);
+}
@@ -0,0 +1,149 @@
+extern crate class_file;
+
+mod common;
+
+use std::io::Cursor;
+
+use class_file::*;
+use class_file::attr::Code;
+use class_file::hierarchy::*;
+use class_file::ops::*;
+use class_file::verify::Location;
+use class_file::verify::typecheck::*;
+use common::*;
+
+fn flow() -> ClassFile<'static> {
+	load(include_bytes!("Flow.class"))
+}
+
+/// Decodes the `Code` attribute of the named method, hands it to `f`, and puts it back.
+fn patch<F: FnOnce(&mut Code)>(class_file: &mut ClassFile, name: &str, f: F) -> usize {
+	let index = method_index(class_file, name);
+	let method = &mut class_file.methods[index];
+	let info = method.attributes.named(&class_file.constant_pool, "Code").unwrap().clone();
+	let mut code = Code::from_bytes(&mut Cursor::new(info.info())).unwrap();
+	f(&mut code);
+
+	let mut data = vec![];
+	code.to_bytes(&mut data).unwrap();
+	let attributes = method.attributes.iter()
+		.map(|attribute| if *attribute == info { AttributeInfo::new(info.name_index(), data.clone()) } else { attribute.clone() })
+		.collect();
+	method.attributes = Attributes::new(attributes);
+	index
+}
+
+fn verify(class_file: &ClassFile) -> Vec<Diagnostic> {
+	let mut hierarchy = SimpleHierarchy::new();
+	hierarchy.add(class_file);
+	validate(class_file, &hierarchy)
+}
+
+#[test]
+fn compiled_classes_are_valid() {
+	for data in &[
+		&include_bytes!("Flow.class")[..],
+		&include_bytes!("Constants.class")[..],
+		&include_bytes!("Constants$Shape.class")[..],
+		&include_bytes!("Attributes.class")[..],
+		&include_bytes!("Attributes$Info.class")[..],
+		&include_bytes!("References.class")[..],
+	] {
+		let class_file = load(data);
+		assert_eq!(verify(&class_file), vec![]);
+	}
+}
+
+#[test]
+fn type_mismatch() {
+	let mut class_file = flow();
+	// iload_0 becomes aload_0.
+	let index = patch(&mut class_file, "describe", |code| code.code[0] = ALOAD_0);
+
+	let diagnostics = verify(&class_file);
+	assert_eq!(diagnostics, vec![Diagnostic {
+		location: Location::Code(index),
+		pc: Some(0),
+		problem: Problem::Mismatch { expected: Type::object("java/lang/Object"), actual: Type::Integer },
+	}]);
+	assert_eq!(diagnostics[0].to_string(), format!("code of method #{}, pc 0: expected java/lang/Object, found int", index));
+}
+
+#[test]
+fn uninitialized_this() {
+	let mut class_file = flow();
+	// Replaces the call to the super constructor.
+	let index = patch(&mut class_file, "<init>", |code| {
+		assert_eq!(code.code[1], INVOKESPECIAL);
+		code.code[0] = NOP;
+		code.code[1..4].copy_from_slice(&[NOP, NOP, NOP]);
+	});
+
+	let diagnostics = verify(&class_file);
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(diagnostics[0].location, Location::Code(index));
+	assert_eq!(diagnostics[0].problem, Problem::UninitializedThis);
+}
+
+#[test]
+fn uninitialized_new() {
+	let mut class_file = flow();
+	// Drops the constructor call on the freshly created Flow, leaving an uninitialized value on the stack.
+	patch(&mut class_file, "create", |code| {
+		let call = code.code.iter().rposition(|&op| op == INVOKESPECIAL).unwrap();
+		code.code[call..call + 3].copy_from_slice(&[POP, NOP, NOP]);
+	});
+
+	let diagnostics = verify(&class_file);
+	assert_eq!(diagnostics.len(), 1);
+	match diagnostics[0].problem {
+		Problem::Mismatch { ref expected, actual: Type::Uninitialized(0) } => assert_eq!(*expected, Type::object("Flow")),
+		ref problem => panic!("Unexpected problem: {:?}", problem),
+	}
+}
+
+#[test]
+fn missing_frame() {
+	let mut class_file = flow();
+	let cp = class_file.constant_pool.clone();
+	patch(&mut class_file, "sum", |code| {
+		let attributes = code.attributes.iter()
+			.filter(|attribute| cp.index(attribute.name_index()).unwrap() != "StackMapTable")
+			.cloned()
+			.collect();
+		code.attributes = Attributes::new(attributes);
+	});
+
+	let diagnostics = verify(&class_file);
+	assert_eq!(diagnostics.len(), 1);
+	assert!(matches!(diagnostics[0].problem, Problem::MissingFrame(_)), "{}", diagnostics[0]);
+}
+
+#[test]
+fn hierarchy() {
+	let mut hierarchy = SimpleHierarchy::new();
+	hierarchy.insert("java/lang/Number", Some(OBJECT), false)
+		.insert("java/lang/Integer", Some("java/lang/Number"), false)
+		.insert("java/lang/String", Some(OBJECT), false)
+		.insert("java/lang/Comparable", Some(OBJECT), true);
+
+	assert!(hierarchy.is_assignable("java/lang/Integer", "java/lang/Number"));
+	assert!(!hierarchy.is_assignable("java/lang/String", "java/lang/Number"));
+	assert!(hierarchy.is_assignable("java/lang/String", "java/lang/Comparable"));
+	assert!(hierarchy.is_assignable("[Ljava/lang/Integer;", "[Ljava/lang/Number;"));
+	assert!(!hierarchy.is_assignable("[I", "[J"));
+	assert!(hierarchy.is_assignable("[I", "java/lang/Cloneable"));
+	// Unknown classes get the benefit of the doubt.
+	assert!(hierarchy.is_assignable("com/example/Unknown", "java/lang/Number"));
+	assert_eq!(hierarchy.common_super_class("java/lang/Integer", "java/lang/String"), OBJECT);
+	// Malformed descriptors are only assignable to themselves.
+	assert!(!hierarchy.is_assignable("[L", "[Ljava/lang/Number;"));
+	assert!(!hierarchy.is_assignable("[Ljava/lang/Integer;", "[L"));
+
+	// Super classes going round in circles don't lead anywhere.
+	hierarchy.insert("com/example/A", Some("com/example/B"), false)
+		.insert("com/example/B", Some("com/example/A"), false);
+	assert!(!hierarchy.is_assignable("com/example/A", "java/lang/Number"));
+	assert_eq!(hierarchy.common_super_class("com/example/A", "java/lang/Integer"), OBJECT);
+	assert_eq!(hierarchy.common_super_class("java/lang/Integer", "com/example/B"), OBJECT);
+}
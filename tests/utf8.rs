@@ -0,0 +1,83 @@
+extern crate class_file;
+
+use std::io::Cursor;
+
+use class_file::*;
+use class_file::utf8::*;
+
+#[test]
+fn encode_special_characters() {
+	// The null character takes two bytes, so there's never a zero byte in the output.
+	assert_eq!(encode("a\0b"), vec![b'a', 0xC0, 0x80, b'b']);
+	// Supplementary characters are written as a surrogate pair, three bytes each.
+	assert_eq!(encode("\u{1F600}"), vec![0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+	// Everything else matches standard UTF-8.
+	assert_eq!(encode("h\u{E9}llo \u{20AC}"), "h\u{E9}llo \u{20AC}".as_bytes().to_vec());
+}
+
+#[test]
+fn validate_sequences() {
+	assert!(validate(&encode("a\0\u{1F600}\u{7FF}\u{800}")).is_ok());
+	// Unpaired surrogates are fine.
+	assert!(validate(&[0xED, 0xA0, 0x80]).is_ok());
+
+	assert_eq!(validate(&[b'a', 0x00]).unwrap_err().valid_up_to(), 1);
+	assert_eq!(validate(&[0xF0, 0x9F, 0x98, 0x80]).unwrap_err().valid_up_to(), 0);
+	assert_eq!(validate(&[b'a', b'b', 0xC1, 0x81]).unwrap_err().valid_up_to(), 2);
+	assert_eq!(validate(&[0xE0, 0x80, 0x80]).unwrap_err().valid_up_to(), 0);
+	assert_eq!(validate(&[0xE2, 0x82]).unwrap_err().valid_up_to(), 0);
+}
+
+#[test]
+fn compare_with_str() {
+	let info = UTF8Info::new("java/lang/\0Object\u{1F600}");
+	assert!(info == "java/lang/\0Object\u{1F600}");
+	assert!(info != "java/lang/\0Object");
+	assert!(info != "java/lang/\0Object\u{1F600}!");
+	assert!(info.data.eq_str("java/lang/\0Object\u{1F600}"));
+
+	assert_eq!(info.to_string(), "java/lang/\0Object\u{1F600}");
+	assert_eq!(format!("{}", UTF8Info::new("Main").data.display()), "Main");
+}
+
+#[test]
+fn lenient_keeps_raw_bytes() {
+	let data = include_bytes!("Version55.class");
+	let mut data = data.to_vec();
+
+	// Corrupt the "Version55.java" string used by the SourceFile attribute.
+	let needle = b"Version55.java";
+	let offset = data.windows(needle.len())
+		.position(|window| window == needle)
+		.unwrap();
+	data[offset] = 0xFF;
+
+	assert!(ClassFile::open(&mut Cursor::new(&data)).is_err());
+
+	let class_file = ClassFile::open_lenient(&mut Cursor::new(&data))
+		.expect("Failed to parse the corrupted class leniently.");
+
+	let invalid: Vec<_> = class_file.constant_pool.entries.iter()
+		.filter_map(|entry| match entry {
+			CPEntry::UTF8(info) if !info.is_valid() => Some(info),
+			_ => None,
+		})
+		.collect();
+	assert_eq!(invalid.len(), 1);
+	assert_eq!(invalid[0].raw().unwrap()[0], 0xFF);
+	assert_eq!(invalid[0].to_string(), "\u{FFFD}ersion55.java");
+
+	// The original bytes are written back out.
+	let mut output = vec![];
+	class_file.to_bytes(&mut Cursor::new(&mut output)).unwrap();
+	assert_eq!(data, output);
+}
+
+#[test]
+fn decode_surrogates() {
+	assert_eq!(decode(&encode("a\0\u{1F600}")), "a\0\u{1F600}");
+	// An unpaired surrogate has no standard equivalent.
+	assert_eq!(decode(&[b'a', 0xED, 0xA0, 0x80, b'b']), "a\u{FFFD}b");
+	// Neither does a four byte sequence, even though it's valid standard UTF-8.
+	assert_eq!(decode(&[b'a', 0xF0, 0x9F, 0x98, 0x80]), "a\u{FFFD}\u{FFFD}\u{FFFD}\u{FFFD}");
+}
@@ -0,0 +1,107 @@
+extern crate class_file;
+
+mod common;
+
+use class_file::*;
+use class_file::verify::Location;
+use class_file::verify::attributes::*;
+use common::*;
+
+fn attributes() -> ClassFile<'static> {
+	load(include_bytes!("Attributes.class"))
+}
+
+#[test]
+fn compiled_classes_are_valid() {
+	for data in &[&include_bytes!("Attributes.class")[..], &include_bytes!("Attributes$Info.class")[..], &include_bytes!("References.class")[..]] {
+		let class_file = load(data);
+		assert_eq!(validate(&class_file), vec![]);
+	}
+}
+
+#[test]
+fn misplaced() {
+	let mut class_file = attributes();
+	let code = class_file.methods[0].attributes.named(&class_file.constant_pool, "Code")
+		.unwrap()
+		.clone();
+	class_file.fields[0].attributes.push(code);
+
+	assert_eq!(validate(&class_file), vec![Diagnostic {
+		location: Location::Field(0),
+		attribute: "Code".to_string(),
+		problem: Problem::Misplaced(Context::FieldInfo),
+	}]);
+}
+
+#[test]
+fn misplaced_in_record() {
+	let mut class_file = load(include_bytes!("compact/pool/Circle.class"));
+	assert_eq!(validate(&class_file), vec![]);
+
+	// A lone `radius` component that's deprecated, which only classes and members can be.
+	let record = class_file.attributes.named(&class_file.constant_pool, "Record").unwrap().clone();
+	let deprecated = class_file.constant_pool.add_utf8("Deprecated").unwrap();
+	let mut info = vec![0, 1];
+	info.extend_from_slice(&record.info()[2..6]);
+	info.extend_from_slice(&[0, 1]);
+	info.extend_from_slice(&deprecated.index.to_be_bytes());
+	info.extend_from_slice(&[0, 0, 0, 0]);
+	class_file.attributes.set(&class_file.constant_pool.clone(), AttributeInfo::new(record.name_index(), info));
+
+	let diagnostics = validate(&class_file);
+	assert_eq!(diagnostics, vec![Diagnostic {
+		location: Location::RecordComponent(0),
+		attribute: "Deprecated".to_string(),
+		problem: Problem::Misplaced(Context::RecordComponent),
+	}]);
+	assert_eq!(diagnostics[0].to_string(), "record component #0: Deprecated is not allowed in record_component_info");
+}
+
+#[test]
+fn duplicated() {
+	let mut class_file = attributes();
+	let source_file = class_file.attributes.named(&class_file.constant_pool, "SourceFile")
+		.unwrap()
+		.clone();
+	class_file.attributes.push(source_file);
+
+	let diagnostics = validate(&class_file);
+	assert_eq!(diagnostics, vec![Diagnostic {
+		location: Location::Class,
+		attribute: "SourceFile".to_string(),
+		problem: Problem::Duplicated(2),
+	}]);
+	assert_eq!(diagnostics[0].to_string(), "class: SourceFile may appear at most once, found 2");
+}
+
+#[test]
+fn unsupported() {
+	let mut class_file = attributes();
+	class_file.major_version = 50;
+
+	let diagnostics = validate(&class_file);
+	assert!(!diagnostics.is_empty());
+	assert!(diagnostics.iter().all(|d| match d.problem {
+		Problem::Unsupported(since) => since > (50, 0),
+		_ => false,
+	}));
+	assert!(diagnostics.iter().any(|d| d.attribute == "BootstrapMethods"));
+	assert!(diagnostics.iter().any(|d| d.attribute == "MethodParameters"));
+}
+
+#[test]
+fn table() {
+	let names: Vec<&str> = ATTRIBUTES.iter().map(|spec| spec.name).collect();
+	assert_eq!(&names[..3], &["ConstantValue", "Code", "StackMapTable"]);
+	assert_eq!(&names[names.len() - 4..], &["NestHost", "NestMembers", "Record", "PermittedSubclasses"]);
+	assert!(names.contains(&"BootstrapMethods"));
+	assert_eq!(spec("Record").unwrap().contexts, &[Context::ClassFile]);
+	assert_eq!(spec("PermittedSubclasses").unwrap().since, (61, 0));
+	assert!(spec("Signature").unwrap().contexts.contains(&Context::RecordComponent));
+	assert!(!spec("Synthetic").unwrap().contexts.contains(&Context::RecordComponent));
+	assert_eq!(spec("StackMapTable").unwrap().contexts, &[Context::Code]);
+	assert_eq!(spec("NestHost").unwrap().since, (55, 0));
+	assert!(!spec("LineNumberTable").unwrap().at_most_one);
+	assert!(spec("ScalaSig").is_none());
+}